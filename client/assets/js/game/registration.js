@@ -8,6 +8,15 @@ clearButton.addEventListener('click', () => {
   localStorage.clear("player_uuid");
 });
 
+// Guests need it to get their seat back after a refresh
+function storeReconnectToken(token) {
+  if (token) {
+    localStorage.setItem("reconnect_token", token);
+  } else {
+    localStorage.removeItem("reconnect_token");
+  }
+}
+
 function clearGameList() {
   while (playerList.firstChild) {
     playerList.removeChild(playerList.firstChild);
@@ -35,11 +44,13 @@ registerButton.addEventListener('click', async () => {
     console.log(`Registering ${username}`)
     socket.emit("register_request", {
       game_uuid: GAME_UUID,
-      username
+      username,
+      invite_code: urlParams.get('invite')
     }, ({data, error}) => { // TODO: Implement error handling
       if (data) {
-        localStorage.setItem("player_uuid", data);
-        console.info(`User ${username} successfully registered with UUID ${data}`);
+        localStorage.setItem("player_uuid", data.id);
+        storeReconnectToken(data.reconnect_token);
+        console.info(`User ${username} successfully registered with UUID ${data.id}`);
       } else if (error) {
         console.error(error);
       }
@@ -2,14 +2,19 @@ const urlParams = new URLSearchParams(window.location.search);
 const GAME_UUID = urlParams.get('uuid');
 
 let CONNECTED = false;
-const socket = io(`localhost:3000/game/${GAME_UUID}`);
+const socket = io(`localhost:3000/game/${GAME_UUID}`, {
+    auth: {protocol: 1, token: localStorage.getItem("session_token"), locale: navigator.language}
+});
 
 function whoami() {
     let player_uuid = localStorage.getItem("player_uuid");
+    // Only guests have one, the session token vouches for everyone else
+    let reconnect_token = localStorage.getItem("reconnect_token");
 
     if (player_uuid) {
         socket.emit("whoami", {
-            player_uuid
+            player_uuid,
+            reconnect_token
         }, ({data, error}) => {
             if (data) {
                 console.log({data});
@@ -1,36 +1,146 @@
 const gameList = document.getElementById("game-list");
 
 function joinGame(gameUuid) {
-    const socketNamespace = io(`localhost:3000/game/${gameUuid}`);
+    const socketNamespace = io(`localhost:3000/game/${gameUuid}`, {
+        auth: {protocol: 1, token: localStorage.getItem("session_token"), locale: navigator.language}
+    });
     socketNamespace.emit("test");
 }
 
 /**
  * Creates a game node element.
- * 
- * @param {string} game - The name of the game.
+ *
+ * @param {object} game - The summary of the game, as sent by the server.
  * @returns {HTMLElement} The created game node element.
  */
 function createGameNode(game) {
     const gameNode = document.createElement("li");
     gameNode.className = "game";
+    gameNode.dataset.uuid = game.uuid;
 
     const gameLink = document.createElement("a");
-    gameLink.textContent = game;
-    gameLink.href = `/game.html?uuid=${game}`;
+    gameLink.textContent = `${game.name} (${game.players}/${game.max_players}) - ${game.status}`;
+    gameLink.href = `/game.html?uuid=${game.uuid}`;
 
     gameNode.appendChild(gameLink);
 
     return gameNode;
 }
 
-function loadGameList() {
-    socket.emit("list-games", null, ({data, error}) => {
+function findGameNode(gameUuid) {
+    return gameList.querySelector(`[data-uuid="${gameUuid}"]`);
+}
+
+function loadGameList(filter = {}, pagination = {}) {
+    socket.emit("list-games", {filter, pagination}, ({data, error}) => {
         if (data) {
-            data.forEach(game => gameList.appendChild(createGameNode(game)));
+            gameList.replaceChildren(...data.games.map(createGameNode));
             console.debug({data});
         } else if (error) {
             console.error(error)
         }
     });
-}
\ No newline at end of file
+}
+
+socket.on("game-added", ({data}) => {
+    if (data && !findGameNode(data.uuid)) {
+        gameList.prepend(createGameNode(data));
+    }
+});
+
+socket.on("game-updated", ({data}) => {
+    const node = data && findGameNode(data.uuid);
+
+    if (node) {
+        node.replaceWith(createGameNode(data));
+    }
+});
+
+socket.on("game-removed", ({data}) => {
+    findGameNode(data)?.remove();
+});
+
+/**
+ * Waits in the matchmaking queue for a quick game.
+ *
+ * @param {object} preferences - Any of variant, time_control, rated and opponents.
+ * @param {string} username - Name used by guests, ignored for logged in players.
+ */
+function playNow(preferences = {}, username = undefined) {
+    socket.emit("join-queue", {preferences, username}, ({data, error}) => {
+        if (error) {
+            console.error(error);
+        } else {
+            console.debug({data});
+        }
+    });
+}
+
+function leaveQueue() {
+    socket.emit("leave-queue", ({error}) => error && console.error(error));
+}
+
+// Guests need it to get their seat back once in the game
+function storeReconnectToken(token) {
+    if (token) {
+        localStorage.setItem("reconnect_token", token);
+    } else {
+        localStorage.removeItem("reconnect_token");
+    }
+}
+
+// The server already registered us in the game it created
+socket.on("match-found", ({data}) => {
+    if (data) {
+        localStorage.setItem("player_uuid", data.player.id);
+        storeReconnectToken(data.reconnect_token);
+        window.location.href = `/game.html?uuid=${data.game_uuid}`;
+    }
+});
+
+/**
+ * Sets up a tournament, whoever creates it is the director who starts it.
+ *
+ * @param {object} tournament - Name, pairing ("round-robin" or "swiss"), rounds,
+ * king_of_the_hill and the settings of the games.
+ */
+function createTournament(tournament, username = undefined) {
+    socket.emit("create-tournament", {tournament, username}, ({data, error}) => {
+        if (error) {
+            console.error(error);
+        } else {
+            console.debug({data});
+        }
+    });
+}
+
+/**
+ * @param {string} event - Either "join-tournament", "start-tournament" or "tournament".
+ * @param {string} tournament_uuid - The tournament to act on.
+ */
+function tournamentAction(event, tournament_uuid, username = undefined) {
+    socket.emit(event, {tournament_uuid, username}, ({data, error}) => {
+        if (error) {
+            console.error(error);
+        } else {
+            console.debug({data});
+        }
+    });
+}
+
+socket.on("tournament-updated", ({data}) => console.debug({data}));
+
+// Rounds start on their own, the server already seated us at our table
+socket.on("tournament-game", ({data}) => {
+    if (data) {
+        storeReconnectToken(data.reconnect_token);
+        window.location.href = `/game.html?uuid=${data.game_uuid}`;
+    }
+});
+
+// Games already going are played to the end, new ones are refused until it's over
+socket.on("maintenance", ({data}) => {
+    if (data) {
+        console.warn(data.message);
+    }
+});
@@ -1,12 +1,51 @@
 let CONNECTED = false;
-const socket = io('localhost:3000');
+// Logged in players send their session token on connect, guests send nothing.
+// Messages from the server come back in the language of the browser. `protocol`
+// is the version of /protocol this client was written against
+const socket = io('localhost:3000', {
+    auth: {protocol: 1, token: localStorage.getItem("session_token"), locale: navigator.language}
+});
+
+/**
+ * Creates an account, or logs into an existing one, and remembers the session.
+ *
+ * @param {string} event - Either "sign-up" or "log-in".
+ * @param {string} username - The account username.
+ * @param {string} password - The account password.
+ */
+function authenticate(event, username, password) {
+    socket.emit(event, {username, password}, ({data, error}) => {
+        if (data) {
+            localStorage.setItem("session_token", data.token);
+        } else {
+            console.error(error);
+        }
+    });
+}
+
+function logOut() {
+    socket.emit("log-out", () => localStorage.removeItem("session_token"));
+}
+
+socket.on('connect_error', (error) => {
+    // The session expired, carry on as a guest
+    if (localStorage.getItem("session_token")) {
+        console.error(error.message);
+        localStorage.removeItem("session_token");
+        socket.auth = {protocol: 1, locale: navigator.language};
+        socket.connect();
+    }
+});
 
 function whoami() {
     let player_uuid = localStorage.getItem("player_uuid");
+    // Only guests have one, the session token vouches for everyone else
+    let reconnect_token = localStorage.getItem("reconnect_token");
 
     if (player_uuid) {
         socket.emit("whoami", {
-            player_uuid
+            player_uuid,
+            reconnect_token
         }, ({data, error}) => {
             if (data) {
                 console.log({data});
@@ -0,0 +1,243 @@
+// Generated by `scrabble --export-protocol`, do not edit
+
+export const PROTOCOL_VERSION = 1;
+
+/**
+ * Sent by clients as the socket.io `auth` payload of every namespace
+ */
+export type Handshake = { 
+/**
+ * The version of the protocol the client speaks, it must be `PROTOCOL_VERSION`
+ */
+protocol: number, 
+/**
+ * Session token of a logged in player, guests send none
+ */
+token?: string | null, 
+/**
+ * Language tag such as `fr-CA`, the `Accept-Language` header is used otherwise
+ */
+locale?: string | null, };
+
+/**
+ * Sent as "welcome" once a socket is connected
+ */
+export type Welcome = { protocol: number, 
+/**
+ * Language errors and notices are sent in
+ */
+locale: Locale, };
+
+/**
+ * Shape of every acknowledgement and server event, see `Response`
+ */
+export type Reply = { data: unknown, 
+/**
+ * Error text in the locale of the socket
+ */
+error: string | null, 
+/**
+ * Stable name of the error
+ */
+code: string | null, 
+/**
+ * What `error`, or `data` for notices, was rendered from
+ */
+message: Message | null, };
+
+/**
+ * Text for a player, sent as a catalog key and parameters so clients can
+ * render it themselves, along with the text in the locale of the socket
+ */
+export type Message = { key: string, params: { [key in string]?: Param }, };
+
+/**
+ * Value filled in a message, statuses are translated as well
+ */
+export type Param = GameStatus | Array<string>;
+
+/**
+ * Languages players can be answered in
+ */
+export type Locale = "en" | "fr" | "es";
+
+/**
+ * Every event a client can send, `event` is the socket.io event name and
+ * `data` its payload, events without one take no payload at all
+ *
+ * Sockets get each payload on its own, this ties them to their event names for
+ * the JSON Schema and the TypeScript definitions in `/protocol`.
+ */
+export type ClientMessage = { "event": "list-games", "data": ListGamesRequest } | { "event": "create-game", "data": CreateGameRequest } | { "event": "ratings", "data": RatingsRequest } | { "event": "player-stats", "data": PlayerStatsRequest } | { "event": "leaderboard", "data": LeaderboardRequest } | { "event": "sign-up", "data": CredentialsRequest } | { "event": "log-in", "data": CredentialsRequest } | { "event": "log-out" } | { "event": "join-queue", "data": JoinQueueRequest } | { "event": "leave-queue" } | { "event": "create-tournament", "data": CreateTournamentRequest } | { "event": "join-tournament", "data": TournamentRequest } | { "event": "start-tournament", "data": TournamentRequest } | { "event": "tournament", "data": TournamentRequest } | { "event": "list-tournaments" } | { "event": "register_request", "data": RegisterRequest } | { "event": "logout", "data": LogoutRequest } | { "event": "whoami", "data": WhoAmIRequest } | { "event": "player-list" } | { "event": "start" } | { "event": "pause" } | { "event": "resume" } | { "event": "invite", "data": InviteRequest } | { "event": "close", "data": CloseRequest } | { "event": "pass" } | { "event": "play", "data": PlayRequest };
+
+export type ListGamesRequest = { filter: GameFilter, pagination: Pagination, };
+
+export type CreateGameRequest = { settings: GameSettings, };
+
+export type RatingsRequest = { account_id: string, 
+/**
+ * Every variant the player is rated in if omitted
+ */
+variant: Variant | null, };
+
+export type PlayerStatsRequest = { account_id: string, variant: Variant | null, period: Period, };
+
+export type LeaderboardRequest = { leaderboard: LeaderboardKind, limit: number | null, variant: Variant | null, period: Period, };
+
+/**
+ * Sent to sign up as well as to log in
+ */
+export type CredentialsRequest = { username: string, password: string, };
+
+export type JoinQueueRequest = { preferences: QueuePreferences, 
+/**
+ * Guests pick a name, logged in players play under their username
+ */
+username: string | null, };
+
+export type CreateTournamentRequest = { tournament: TournamentSettings, username: string | null, };
+
+/**
+ * Sent to join, start or look at a tournament
+ */
+export type TournamentRequest = { tournament_uuid: string, username: string | null, };
+
+export type RegisterRequest = { game_uuid: string, username: string, invite_code: string | null, };
+
+export type LogoutRequest = { game_uuid: string, player_uuid: string, };
+
+export type WhoAmIRequest = { player_uuid: string, 
+/**
+ * Given to guests with their seat, players with an account are known by
+ * their session
+ */
+reconnect_token: string | null, };
+
+/**
+ * What the host of a private game wants to do with its invite code
+ */
+export type InviteAction = "show" | { "regenerate": { 
+/**
+ * Lifetime of the new code in seconds, never expires if omitted
+ */
+expires_in: number | null, } } | "revoke";
+
+export type InviteRequest = { game_uuid: string, action: InviteAction, };
+
+export type CloseRequest = { game_uuid: string, };
+
+export type PlayRequest = { tiles: Array<Play>, };
+
+/**
+ * A tile put on the board at column `x`, row `y`
+ */
+export type Play = { 
+/**
+ * The tile, as it is on the rack
+ */
+tile: Tile, 
+/**
+ * Column, from 0 on the left
+ */
+x: number, 
+/**
+ * Row, from 0 at the top
+ */
+y: number, };
+
+/**
+ * A letter and what it is worth
+ */
+export type Tile = [string, number];
+
+/**
+ * Criteria a lobby client can use to narrow down the games list
+ */
+export type GameFilter = { status: GameStatus | null, variant: Variant | null, rated: boolean | null, 
+/**
+ * Only keep games with at least one free seat
+ */
+joinable: boolean, 
+/**
+ * Case-insensitive search on the game name
+ */
+search: string | null, };
+
+export type Pagination = { offset: number, limit: number, };
+
+/**
+ * Lifecycle of a game
+ *
+ * ```text
+ * Waiting -> Starting -> InProgress <-> Paused
+ *    ^          |            |            |
+ *    +----------+            v            v
+ *                     Finished / Abandoned
+ * ```
+ *
+ * A waiting game can also be abandoned, `Finished` and `Abandoned` are final.
+ */
+export type GameStatus = "waiting" | "starting" | "in-progress" | "paused" | "finished" | "abandoned";
+
+/**
+ * Options chosen by whoever creates a game
+ */
+export type GameSettings = { name: string, max_players: number, variant: Variant, time_control: TimeControl | null, 
+/**
+ * Private games are hidden from the lobby and need an invite code to join
+ */
+private: boolean, 
+/**
+ * Lifetime of invite codes in seconds, `None` for codes that never expire
+ */
+invite_expires_in: number | null, 
+/**
+ * Seconds a disconnected player's seat is held before their turns are passed
+ */
+disconnect_grace: number, 
+/**
+ * Seconds after which a disconnected player is dealt with `on_disconnect`
+ */
+disconnect_timeout: number, on_disconnect: DisconnectPolicy, 
+/**
+ * Rated games are for logged in players only and update their ratings at game over
+ */
+rated: boolean, };
+
+export type Variant = "classic";
+
+export type TimeControl = { initial_seconds: number, increment_seconds: number, };
+
+/**
+ * What happens to a player who stays disconnected past the timeout
+ */
+export type DisconnectPolicy = "forfeit" | "remove";
+
+/**
+ * What a player is looking for, only players with the very same preferences are matched
+ */
+export type QueuePreferences = { variant: Variant, time_control: TimeControl | null, rated: boolean, opponents: number, };
+
+/**
+ * Options chosen by whoever runs a tournament
+ */
+export type TournamentSettings = { name: string, pairing: PairingSystem, 
+/**
+ * Number of Swiss rounds, round robins take as many as needed
+ */
+rounds: number, 
+/**
+ * Adds a final round pairing first against second, third against fourth and so on
+ */
+king_of_the_hill: boolean, variant: Variant, time_control: TimeControl | null, rated: boolean, };
+
+export type PairingSystem = "round-robin" | "swiss";
+
+export type StatsFilter = { variant: Variant | null, period: Period, };
+
+/**
+ * How far back statistics go
+ */
+export type Period = "day" | "week" | "month" | "year" | "all-time";
+
+export type LeaderboardKind = "rating" | "win-rate" | "best-move";
@@ -0,0 +1,312 @@
+use crate::game::{FinalScore, Game, Turn, TurnAction};
+use crate::player::Player;
+use crate::tile::Tile;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt::Formatter;
+use uuid::Uuid;
+
+/// Hash of a seed published before anyone draws from it: the SHA-256 of its
+/// decimal digits, a colon and the salt, in lowercase hexadecimal
+///
+/// `echo -n 42:f00d | sha256sum` gives the commitment of the seed 42 salted with
+/// `f00d`. The salt is random and revealed with the seed, so nobody can tell the
+/// seed from the commitment by hashing every likely one.
+///
+/// The commitment only shows the seed was not changed once the game started. It
+/// says nothing of how the seed was picked: a server choosing it to favour
+/// someone would go unnoticed, the draws are only as fair as the server.
+pub fn commit_seed(seed: u64, salt: &str) -> String {
+    Sha256::digest(format!("{seed}:{salt}"))
+        .iter()
+        .map(|x| format!("{x:02x}"))
+        .collect()
+}
+
+/// A player who left their seat, or forfeited it, once the game had started
+///
+/// Their rack goes back to the bag and the bag is shuffled again.
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize, Debug)]
+pub struct Departure {
+    /// Who left
+    pub player_uuid: Uuid,
+    /// How many turns had been played by then
+    pub after_turns: usize,
+    /// The player lost by forfeit and kept their seat, rather than leaving it
+    pub forfeited: bool,
+}
+
+/// Everything it takes to deal a game again from its seed, only known once the
+/// game is over
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
+pub struct BagRecord {
+    /// Published when the game started, see [`commit_seed`]
+    pub commitment: String,
+    /// Seed the bag was shuffled from, as a string since it may not fit in a
+    /// JavaScript number
+    #[serde(with = "seed_string")]
+    pub seed: u64,
+    /// Hashed with the seed into the commitment
+    pub salt: String,
+    /// Players in turn order when the game started
+    pub seating: Vec<Uuid>,
+    /// Every turn played, oldest first
+    pub turns: Vec<Turn>,
+    /// Every departure, oldest first
+    pub departures: Vec<Departure>,
+    /// Final scores of a finished game, `None` if it was abandoned
+    pub final_scores: Option<Vec<FinalScore>>,
+}
+
+/// Tiles that came out of the bag, as dealt again from the seed
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug, Default)]
+pub struct Draws {
+    /// Racks dealt at the start, in seating order
+    pub racks: Vec<Vec<Tile>>,
+    /// Tiles drawn after each turn, none after a pass
+    pub turns: Vec<Vec<Tile>>,
+}
+
+/// Why a game could not be dealt again the way it was recorded
+#[derive(Debug, PartialEq)]
+pub enum BagError {
+    /// The seed and salt don't hash to the commitment
+    WrongSeed,
+    /// No game can start with these players
+    InvalidSeating,
+    /// The racks dealt from the seed could not have played the turn of this
+    /// index, or it scored otherwise
+    TurnMismatch(usize),
+    /// The departure of this index could not have happened
+    DepartureMismatch(usize),
+    /// Everything was played the same but the final scores differ
+    FinalScoresMismatch,
+}
+
+impl std::fmt::Display for BagError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BagError::WrongSeed => write!(f, "The seed and salt do not match the commitment"),
+            BagError::InvalidSeating => write!(f, "No game can start with these players"),
+            BagError::TurnMismatch(index) => {
+                write!(f, "Turn {index} could not be played from the seeded bag")
+            }
+            BagError::DepartureMismatch(index) => {
+                write!(f, "Departure {index} could not happen in the replayed game")
+            }
+            BagError::FinalScoresMismatch => {
+                write!(f, "The replayed game ends with other final scores")
+            }
+        }
+    }
+}
+
+impl std::error::Error for BagError {}
+
+impl BagRecord {
+    /// Deals the game again from the seed and plays every recorded action on it,
+    /// giving back every draw if they all go the same way
+    ///
+    /// A player who played tiles the seeded bag never gave them, or went out with
+    /// another rack than the seeded one, makes the check fail.
+    pub fn verify(&self) -> Result<Draws, BagError> {
+        if commit_seed(self.seed, &self.salt) != self.commitment {
+            return Err(BagError::WrongSeed);
+        }
+
+        let mut draws = Draws::default();
+
+        // Abandoned before it started, nothing was ever drawn
+        if self.seating.is_empty() {
+            return Ok(draws);
+        }
+
+        let mut game = Game::with_seed(self.seed);
+        for player_uuid in &self.seating {
+            game.register_player(Player::new(player_uuid, ""))
+                .map_err(|_| BagError::InvalidSeating)?;
+        }
+
+        let racks = game.start().map_err(|_| BagError::InvalidSeating)?;
+        draws.racks = self.seating.iter().map(|x| racks[x].clone()).collect();
+
+        let mut departures = self.departures.iter().enumerate().peekable();
+
+        for index in 0..=self.turns.len() {
+            while let Some((i, departure)) = departures.next_if(|(_, x)| x.after_turns <= index) {
+                let result = match departure.forfeited {
+                    true => game.forfeit(&departure.player_uuid),
+                    false => game.remove_player(&departure.player_uuid),
+                };
+                result.map_err(|_| BagError::DepartureMismatch(i))?;
+            }
+
+            if let Some(turn) = self.turns.get(index) {
+                let drawn = replay_turn(&mut game, turn).ok_or(BagError::TurnMismatch(index))?;
+                draws.turns.push(drawn);
+            }
+        }
+
+        if self.final_scores.is_some() && game.get_final_scores() != self.final_scores {
+            return Err(BagError::FinalScoresMismatch);
+        }
+
+        Ok(draws)
+    }
+}
+
+/// Plays the turn again and gives back what it drew, `None` if it can't be
+/// played or doesn't score the same
+fn replay_turn(game: &mut Game, turn: &Turn) -> Option<Vec<Tile>> {
+    let player_uuid = &turn.player_uuid;
+
+    match &turn.action {
+        TurnAction::Play { tiles, .. } => {
+            let kept = game
+                .get_player_tiles(player_uuid)
+                .ok()?
+                .len()
+                .saturating_sub(tiles.len());
+            let replayed = game.play(player_uuid, tiles).ok()?;
+
+            if replayed.action != turn.action || replayed.score != turn.score {
+                return None;
+            }
+
+            // Drawn tiles go after the ones left on the rack
+            let rack = game.get_player_tiles(player_uuid).ok()?;
+            Some(rack.get(kept..).unwrap_or_default().to_vec())
+        }
+        TurnAction::Pass => {
+            game.pass(player_uuid).ok()?;
+            Some(Vec::new())
+        }
+    }
+}
+
+/// Seeds as decimal strings, JavaScript numbers lose the low digits of large ones
+mod seed_string {
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(seed: &u64, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(seed)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::CENTER;
+    use crate::tile::Play;
+
+    /// Two tiles of the current player's rack across the center
+    fn opening(game: &Game) -> (Uuid, Vec<Play>) {
+        let player_uuid = *game.get_current_player().unwrap().get_id();
+        let rack = game.get_player_tiles(&player_uuid).unwrap();
+        let (x, y) = CENTER;
+        let plays = (0..2)
+            .map(|i| Play {
+                tile: rack[i],
+                x: x + i,
+                y,
+            })
+            .collect();
+
+        (player_uuid, plays)
+    }
+
+    /// Three players, one move, a forfeit and then passes until it ends
+    fn finished_game(tamper: bool) -> Game {
+        let uuids: Vec<Uuid> = (0..3).map(|_| Uuid::new_v4()).collect();
+        let mut game = Game::with_seed(1234);
+        for uuid in &uuids {
+            game.register_player(Player::new(uuid, "Player")).unwrap();
+        }
+        game.start().unwrap();
+
+        if tamper {
+            let rack = vec![Tile('Z', 10); 7];
+            game.set_rack(&uuids[0], rack);
+        }
+
+        let (player_uuid, plays) = opening(&game);
+        game.play(&player_uuid, &plays).unwrap();
+        game.forfeit(&uuids[2]).unwrap();
+
+        while !game.get_status().is_over() {
+            let current = *game.get_current_player().unwrap().get_id();
+            game.pass(&current).unwrap();
+        }
+
+        game
+    }
+
+    #[test]
+    fn commitment_is_the_hash_of_the_digits_and_salt() {
+        assert_eq!(
+            commit_seed(42, "f00d"),
+            "7ff72578dff153c5b2e70065ce04ea7fd54a39a454e9e3d07be4e4a1d0efc05a"
+        );
+        assert_ne!(commit_seed(42, "f00d"), commit_seed(42, "f00e"));
+    }
+
+    #[test]
+    fn seed_is_revealed_once_the_game_is_over() {
+        let mut game = Game::with_seed(1234);
+        assert!(game.get_bag_record().is_none());
+
+        game.abandon().unwrap();
+        let record = game.get_bag_record().unwrap();
+        assert_eq!(record.seed, 1234);
+        assert_eq!(record.verify(), Ok(Draws::default()));
+    }
+
+    #[test]
+    fn honest_game_is_dealt_again() {
+        let game = finished_game(false);
+        let record = game.get_bag_record().unwrap();
+        let draws = record.verify().unwrap();
+
+        assert_eq!(draws.racks.len(), 3);
+        assert_eq!(draws.racks.iter().map(Vec::len).sum::<usize>(), 21);
+        assert_eq!(draws.turns.len(), record.turns.len());
+        assert_eq!(draws.turns[0].len(), 2);
+        assert!(draws.turns[1..].iter().all(Vec::is_empty));
+        assert_eq!(record.departures.len(), 1);
+    }
+
+    #[test]
+    fn tampered_rack_is_caught() {
+        let record = finished_game(true).get_bag_record().unwrap();
+        assert_eq!(record.verify(), Err(BagError::TurnMismatch(0)));
+    }
+
+    #[test]
+    fn seed_and_salt_must_match_the_commitment() {
+        let record = finished_game(false).get_bag_record().unwrap();
+
+        let mut wrong_seed = record.clone();
+        wrong_seed.seed += 1;
+        assert_eq!(wrong_seed.verify(), Err(BagError::WrongSeed));
+
+        let mut wrong_salt = record;
+        wrong_salt.salt.push('0');
+        assert_eq!(wrong_salt.verify(), Err(BagError::WrongSeed));
+    }
+
+    #[test]
+    fn seed_goes_over_the_wire_as_a_string() {
+        let mut record = finished_game(false).get_bag_record().unwrap();
+        record.seed = u64::MAX;
+
+        let json = serde_json::to_value(&record).unwrap();
+        assert_eq!(json["seed"], "18446744073709551615");
+        assert_eq!(serde_json::from_value::<BagRecord>(json).unwrap(), record);
+    }
+}
@@ -0,0 +1,541 @@
+use crate::error::Error;
+use crate::tile::{Play, Tile};
+use serde::{Deserialize, Serialize};
+use std::fmt::Formatter;
+
+/// Squares along each side of the board
+pub const BOARD_SIZE: usize = 15;
+/// Tiles on a full rack
+pub const RACK_SIZE: usize = 7;
+
+/// Playing a whole rack at once
+pub const BINGO_BONUS: u32 = 50;
+
+/// The square the first move must cover, as `(x, y)`
+pub const CENTER: (usize, usize) = (7, 7);
+
+// T: triple word, D: double word, t: triple letter, d: double letter
+const PREMIUMS: [&str; BOARD_SIZE] = [
+    "T..d...T...d..T",
+    ".D...t...t...D.",
+    "..D...d.d...D..",
+    "d..D...d...D..d",
+    "....D.....D....",
+    ".t...t...t...t.",
+    "..d...d.d...d..",
+    "T..d...D...d..T",
+    "..d...d.d...d..",
+    ".t...t...t...t.",
+    "....D.....D....",
+    "d..D...d...D..d",
+    "..D...d.d...D..",
+    ".D...t...t...D.",
+    "T..d...T...d..T",
+];
+
+/// What an empty square multiplies, only the move that covers it benefits
+#[derive(Copy, Clone, PartialEq, Eq, Serialize, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum Premium {
+    /// Doubles the letter on the square
+    DoubleLetter,
+    /// Triples the letter on the square
+    TripleLetter,
+    /// Doubles every word going through the square
+    DoubleWord,
+    /// Triples every word going through the square
+    TripleWord,
+}
+
+impl Premium {
+    /// Premium of the square at column `x`, row `y`
+    pub fn at(x: usize, y: usize) -> Option<Premium> {
+        match PREMIUMS.get(y)?.as_bytes().get(x)? {
+            b'd' => Some(Premium::DoubleLetter),
+            b't' => Some(Premium::TripleLetter),
+            b'D' => Some(Premium::DoubleWord),
+            b'T' => Some(Premium::TripleWord),
+            _ => None,
+        }
+    }
+
+    fn letter_multiplier(premium: Option<Premium>) -> u32 {
+        match premium {
+            Some(Premium::DoubleLetter) => 2,
+            Some(Premium::TripleLetter) => 3,
+            _ => 1,
+        }
+    }
+
+    fn word_multiplier(premium: Option<Premium>) -> u32 {
+        match premium {
+            Some(Premium::DoubleWord) => 2,
+            Some(Premium::TripleWord) => 3,
+            _ => 1,
+        }
+    }
+}
+
+/// A word formed by a move, with what it scored
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct Word {
+    /// Letters of the word, in reading order
+    pub word: String,
+    /// Letter values with the premiums the move covered
+    pub score: u32,
+}
+
+/// Everything a move would form if it were played
+#[derive(Clone, Serialize, Debug, PartialEq)]
+pub struct ScoredMove {
+    /// The word along the placed tiles comes first
+    pub words: Vec<Word>,
+    /// The whole rack was played, worth `BINGO_BONUS` more
+    pub bingo: bool,
+    /// Every word and the bingo bonus together
+    pub score: u32,
+}
+
+/// Tiles are addressed as `cells[y][x]`, `x` being the column
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct Board {
+    cells: [[Option<Tile>; BOARD_SIZE]; BOARD_SIZE],
+}
+
+impl Board {
+    /// A board without any tile
+    pub fn new() -> Self {
+        Board {
+            cells: [[None; BOARD_SIZE]; BOARD_SIZE],
+        }
+    }
+
+    /// A board with tiles already on it, `rows[y][x]` as `rows` gives them
+    pub fn from_rows(rows: [[Option<Tile>; BOARD_SIZE]; BOARD_SIZE]) -> Self {
+        Board { cells: rows }
+    }
+
+    /// Tile at column `x`, row `y`, `None` for empty squares and off the board
+    pub fn get(&self, x: usize, y: usize) -> Option<Tile> {
+        self.cells.get(y)?.get(x).copied().flatten()
+    }
+
+    /// No tile was played yet
+    pub fn is_empty(&self) -> bool {
+        self.cells.iter().flatten().all(Option::is_none)
+    }
+
+    /// Every square, row by row
+    pub fn rows(&self) -> &[[Option<Tile>; BOARD_SIZE]; BOARD_SIZE] {
+        &self.cells
+    }
+
+    /// Checks where the tiles go and scores the words they would form, the board
+    /// is left untouched
+    ///
+    /// Tiles go on empty squares in a single line without gaps, and must cover the
+    /// center on the first move or touch a tile already on the board afterwards.
+    /// Words are not checked against any dictionary.
+    pub fn evaluate(&self, plays: &[Play]) -> Result<ScoredMove, Error> {
+        if plays.is_empty() || plays.len() > RACK_SIZE {
+            return Err(Error::InvalidPlacement);
+        }
+
+        for (i, play) in plays.iter().enumerate() {
+            if play.x >= BOARD_SIZE || play.y >= BOARD_SIZE || self.get(play.x, play.y).is_some() {
+                return Err(Error::InvalidPlacement);
+            }
+
+            if plays[..i].iter().any(|x| (x.x, x.y) == (play.x, play.y)) {
+                return Err(Error::InvalidPlacement);
+            }
+        }
+
+        let horizontal = plays.iter().all(|x| x.y == plays[0].y);
+        let vertical = plays.iter().all(|x| x.x == plays[0].x);
+
+        if !horizontal && !vertical {
+            return Err(Error::InvalidPlacement);
+        }
+
+        let placed = |x: usize, y: usize| plays.iter().find(|p| (p.x, p.y) == (x, y));
+        let tile_at = |x: usize, y: usize| placed(x, y).map(|p| p.tile).or(self.get(x, y));
+
+        // No gaps between the first and the last tile placed
+        let (first, last) = match horizontal {
+            true => (
+                plays.iter().map(|x| x.x).min().unwrap(),
+                plays.iter().map(|x| x.x).max().unwrap(),
+            ),
+            false => (
+                plays.iter().map(|x| x.y).min().unwrap(),
+                plays.iter().map(|x| x.y).max().unwrap(),
+            ),
+        };
+        let line = |i: usize| match horizontal {
+            true => (i, plays[0].y),
+            false => (plays[0].x, i),
+        };
+
+        if (first..=last).any(|i| {
+            let (x, y) = line(i);
+            tile_at(x, y).is_none()
+        }) {
+            return Err(Error::InvalidPlacement);
+        }
+
+        if self.is_empty() {
+            if plays.len() < 2 || placed(CENTER.0, CENTER.1).is_none() {
+                return Err(Error::InvalidPlacement);
+            }
+        } else if !plays
+            .iter()
+            .any(|play| neighbours(play.x, play.y).any(|(x, y)| self.get(x, y).is_some()))
+        {
+            return Err(Error::InvalidPlacement);
+        }
+
+        // Reads the word going through (x, y) along an axis, scoring only the new tiles' premiums
+        let word_through = |x: usize, y: usize, along_x: bool| -> Option<Word> {
+            let step = |(x, y): (usize, usize), forward: bool| -> Option<(usize, usize)> {
+                let (x, y) = match (along_x, forward) {
+                    (true, true) => (x + 1, y),
+                    (true, false) => (x.checked_sub(1)?, y),
+                    (false, true) => (x, y + 1),
+                    (false, false) => (x, y.checked_sub(1)?),
+                };
+
+                (x < BOARD_SIZE && y < BOARD_SIZE).then_some((x, y))
+            };
+
+            let mut start = (x, y);
+            while let Some(previous) = step(start, false).filter(|&(x, y)| tile_at(x, y).is_some())
+            {
+                start = previous;
+            }
+
+            let mut word = String::new();
+            let mut letters = 0;
+            let mut multiplier = 1;
+            let mut cell = Some(start);
+
+            while let Some((x, y)) = cell {
+                let Some(Tile(letter, value)) = tile_at(x, y) else {
+                    break;
+                };
+
+                let premium = placed(x, y).and(Premium::at(x, y));
+                letters += value as u32 * Premium::letter_multiplier(premium);
+                multiplier *= Premium::word_multiplier(premium);
+                word.push(letter);
+                cell = step((x, y), true);
+            }
+
+            (word.chars().count() > 1).then_some(Word {
+                word,
+                score: letters * multiplier,
+            })
+        };
+
+        // A single tile forms its main word along whichever axis it touches something
+        let main_along_x =
+            horizontal && (plays.len() > 1 || word_through(plays[0].x, plays[0].y, true).is_some());
+        let mut words: Vec<Word> = word_through(plays[0].x, plays[0].y, main_along_x)
+            .into_iter()
+            .collect();
+        words.extend(
+            plays
+                .iter()
+                .filter_map(|play| word_through(play.x, play.y, !main_along_x)),
+        );
+
+        if words.is_empty() {
+            return Err(Error::InvalidPlacement);
+        }
+
+        let bingo = plays.len() == RACK_SIZE;
+        let score =
+            words.iter().map(|x| x.score).sum::<u32>() + if bingo { BINGO_BONUS } else { 0 };
+
+        Ok(ScoredMove {
+            words,
+            bingo,
+            score,
+        })
+    }
+
+    /// First placement `evaluate` accepts, playing as many tiles of the rack as
+    /// possible in a straight line, `None` when nothing fits
+    ///
+    /// Tiles are laid in rack order, so this finds a legal move rather than the
+    /// best one, and words are not checked against any dictionary.
+    pub fn find_move(&self, rack: &[Tile]) -> Option<Vec<Play>> {
+        // Squares next to the tiles already down, or the center on the first move
+        let anchors: Vec<(usize, usize)> = match self.is_empty() {
+            true => vec![CENTER],
+            false => (0..BOARD_SIZE)
+                .flat_map(|y| (0..BOARD_SIZE).map(move |x| (x, y)))
+                .filter(|&(x, y)| {
+                    self.get(x, y).is_none()
+                        && neighbours(x, y).any(|(x, y)| self.get(x, y).is_some())
+                })
+                .collect(),
+        };
+
+        for count in (1..=rack.len().min(RACK_SIZE)).rev() {
+            for &anchor in &anchors {
+                for along_x in [true, false] {
+                    // Lines starting before the anchor, up to `count` empty squares back
+                    for back in 0..count {
+                        let plays = self.line(&rack[..count], anchor, along_x, back);
+                        if let Some(plays) = plays.filter(|x| self.evaluate(x).is_ok()) {
+                            return Some(plays);
+                        }
+                    }
+                }
+            }
+        }
+
+        None
+    }
+
+    /// `tiles` on the empty squares of a line through `anchor`, the first one
+    /// `back` empty squares before it, `None` if they run off the board
+    fn line(
+        &self,
+        tiles: &[Tile],
+        anchor: (usize, usize),
+        along_x: bool,
+        back: usize,
+    ) -> Option<Vec<Play>> {
+        let (dx, dy) = if along_x { (1, 0) } else { (0, 1) };
+        let (mut x, mut y) = anchor;
+        let mut skipped = 0;
+        while skipped < back {
+            x = x.checked_sub(dx)?;
+            y = y.checked_sub(dy)?;
+            if self.get(x, y).is_none() {
+                skipped += 1;
+            }
+        }
+
+        let mut plays = Vec::with_capacity(tiles.len());
+        for &tile in tiles {
+            while self.get(x, y).is_some() {
+                (x, y) = (x + dx, y + dy);
+            }
+            if x >= BOARD_SIZE || y >= BOARD_SIZE {
+                return None;
+            }
+            plays.push(Play { tile, x, y });
+            (x, y) = (x + dx, y + dy);
+        }
+
+        Some(plays)
+    }
+
+    /// Puts tiles on the board, they must have gone through `evaluate` first
+    pub fn apply(&mut self, plays: &[Play]) {
+        for play in plays {
+            self.cells[play.y][play.x] = Some(play.tile);
+        }
+    }
+}
+
+impl Default for Board {
+    fn default() -> Self {
+        Board::new()
+    }
+}
+
+/// One line per row, empty squares are dots
+impl std::fmt::Display for Board {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for row in &self.cells {
+            let line: String = row
+                .iter()
+                .map(|x| x.map_or('.', |Tile(letter, _)| letter))
+                .collect();
+            writeln!(f, "{line}")?;
+        }
+
+        Ok(())
+    }
+}
+
+fn neighbours(x: usize, y: usize) -> impl Iterator<Item = (usize, usize)> {
+    [
+        (x.checked_sub(1), Some(y)),
+        (Some(x + 1), Some(y)),
+        (Some(x), y.checked_sub(1)),
+        (Some(x), Some(y + 1)),
+    ]
+    .into_iter()
+    .filter_map(|(x, y)| Some((x?, y?)))
+    .filter(|&(x, y)| x < BOARD_SIZE && y < BOARD_SIZE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word(word: &str, x: usize, y: usize, horizontal: bool) -> Vec<Play> {
+        word.chars()
+            .enumerate()
+            .map(|(i, letter)| Play {
+                tile: Tile(letter, 1),
+                x: if horizontal { x + i } else { x },
+                y: if horizontal { y } else { y + i },
+            })
+            .collect()
+    }
+
+    #[test]
+    fn premium_squares_are_symmetric() {
+        for y in 0..BOARD_SIZE {
+            for x in 0..BOARD_SIZE {
+                assert_eq!(Premium::at(x, y), Premium::at(y, x));
+                assert_eq!(Premium::at(x, y), Premium::at(BOARD_SIZE - 1 - x, y));
+            }
+        }
+
+        assert_eq!(Premium::at(0, 0), Some(Premium::TripleWord));
+        assert_eq!(Premium::at(7, 7), Some(Premium::DoubleWord));
+        assert_eq!(Premium::at(5, 5), Some(Premium::TripleLetter));
+        assert_eq!(Premium::at(3, 0), Some(Premium::DoubleLetter));
+    }
+
+    #[test]
+    fn first_move_must_cover_the_center() {
+        let board = Board::new();
+
+        assert_eq!(
+            board.evaluate(&word("CAT", 0, 0, true)),
+            Err(Error::InvalidPlacement)
+        );
+        assert_eq!(
+            board.evaluate(&word("A", 7, 7, true)),
+            Err(Error::InvalidPlacement)
+        );
+
+        // The center doubles the word
+        let scored = board.evaluate(&word("CAT", 6, 7, true)).unwrap();
+        assert_eq!(
+            scored.words,
+            vec![Word {
+                word: "CAT".to_string(),
+                score: 6
+            }]
+        );
+        assert_eq!(scored.score, 6);
+    }
+
+    #[test]
+    fn tiles_must_be_in_one_line_without_gaps() {
+        let board = Board::new();
+
+        let mut plays = word("CAT", 6, 7, true);
+        plays[2].y = 8;
+        assert_eq!(board.evaluate(&plays), Err(Error::InvalidPlacement));
+
+        let mut plays = word("CAT", 6, 7, true);
+        plays[2].x = 10;
+        assert_eq!(board.evaluate(&plays), Err(Error::InvalidPlacement));
+    }
+
+    #[test]
+    fn later_moves_must_connect() {
+        let mut board = Board::new();
+        board.apply(&word("CAT", 6, 7, true));
+
+        assert_eq!(
+            board.evaluate(&word("DOG", 0, 0, true)),
+            Err(Error::InvalidPlacement)
+        );
+        assert_eq!(
+            board.evaluate(&word("CAT", 6, 7, true)),
+            Err(Error::InvalidPlacement)
+        );
+
+        // Extending CAT into CATS, the S sitting on no premium
+        let scored = board.evaluate(&word("S", 9, 7, true)).unwrap();
+        assert_eq!(
+            scored.words,
+            vec![Word {
+                word: "CATS".to_string(),
+                score: 4
+            }]
+        );
+    }
+
+    #[test]
+    fn cross_words_are_scored() {
+        let mut board = Board::new();
+        board.apply(&word("CAT", 6, 7, true));
+
+        // AN right under AT also forms AA and TN downwards
+        let scored = board.evaluate(&word("AN", 7, 8, true)).unwrap();
+        let words: Vec<_> = scored.words.iter().map(|x| x.word.as_str()).collect();
+
+        assert_eq!(words, vec!["AN", "AA", "TN"]);
+    }
+
+    #[test]
+    fn found_first_move_covers_the_center() {
+        let board = Board::new();
+        let rack = vec![Tile('A', 1); RACK_SIZE];
+
+        let plays = board.find_move(&rack).unwrap();
+        assert_eq!(plays.len(), RACK_SIZE);
+        assert!(plays.iter().any(|x| (x.x, x.y) == CENTER));
+        assert!(board.evaluate(&plays).unwrap().bingo);
+    }
+
+    #[test]
+    fn found_later_moves_connect_to_the_board() {
+        let mut board = Board::new();
+        board.apply(&word("CAT", 6, 7, true));
+
+        let rack = vec![Tile('B', 3), Tile('C', 3)];
+        let plays = board.find_move(&rack).unwrap();
+        assert_eq!(plays.len(), 2);
+        assert!(board.evaluate(&plays).is_ok());
+    }
+
+    #[test]
+    fn found_moves_play_around_the_tiles_down() {
+        let mut board = Board::new();
+        board.apply(&word("CAT", 6, 7, true));
+        board.apply(&word("OW", 6, 8, false));
+
+        // The whole rack still fits, on empty squares only
+        let rack = vec![Tile('E', 1); RACK_SIZE];
+        let plays = board.find_move(&rack).unwrap();
+        assert_eq!(plays.len(), RACK_SIZE);
+        assert!(plays.iter().all(|x| board.get(x.x, x.y).is_none()));
+        assert!(board.evaluate(&plays).is_ok());
+    }
+
+    #[test]
+    fn nothing_is_found_when_nothing_fits() {
+        let board = Board::new();
+
+        // A single tile can't open the game
+        assert_eq!(board.find_move(&[Tile('A', 1)]), None);
+        assert_eq!(board.find_move(&[]), None);
+
+        // Every square is taken
+        let full = Board::from_rows([[Some(Tile('A', 1)); BOARD_SIZE]; BOARD_SIZE]);
+        assert_eq!(full.find_move(&[Tile('B', 3), Tile('C', 3)]), None);
+    }
+
+    #[test]
+    fn playing_the_whole_rack_is_a_bingo() {
+        let board = Board::new();
+
+        let scored = board.evaluate(&word("RETAINS", 1, 7, true)).unwrap();
+        assert!(scored.bingo);
+        // Seven letters, the E on a double letter, the S on the center star
+        assert_eq!(scored.score, (7 + 1) * 2 + BINGO_BONUS);
+    }
+}
@@ -0,0 +1,58 @@
+use crate::status::GameStatus;
+use std::fmt::Formatter;
+
+/// Why the rules turned down an action, worded like the server's English messages
+#[derive(Debug, PartialEq)]
+pub enum Error {
+    /// Fewer than `MIN_PLAYERS` to start with
+    NotEnoughPlayers,
+    /// Someone with the same id is already seated
+    DuplicatePlayerId,
+    /// Nobody with this id is seated
+    PlayerNotRegistered,
+    /// The bag ran out while dealing
+    NoMoreTiles,
+    /// The rack is already full
+    PlayerHas7Tiles,
+    /// The action is not possible in the current status
+    ActionNotAllowed(GameStatus),
+    /// The lifecycle doesn't go from the first status to the second
+    InvalidTransition(GameStatus, GameStatus),
+    /// Someone else is due to play
+    NotYourTurn,
+    /// See `Board::evaluate` for where tiles may go
+    InvalidPlacement,
+    /// Some of the tiles are not on the player's rack
+    TileNotInRack,
+    /// Words the move forms that are not in the word list
+    UnknownWords(Vec<String>),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::NotEnoughPlayers => write!(f, "Not enough players"),
+            Error::DuplicatePlayerId => write!(f, "Duplicate player UUID"),
+            Error::PlayerNotRegistered => write!(f, "Player is not registered in this game"),
+            Error::NoMoreTiles => write!(f, "No more tiles in the bag"),
+            Error::PlayerHas7Tiles => write!(f, "Player already has 7 tiles"),
+            Error::ActionNotAllowed(status) => {
+                write!(f, "Action not allowed while the game is {status}")
+            }
+            Error::InvalidTransition(from, to) => {
+                write!(f, "Game cannot go from {from} to {to}")
+            }
+            Error::NotYourTurn => write!(f, "It is not your turn"),
+            Error::InvalidPlacement => write!(
+                f,
+                "Tiles must form a single line connected to the tiles on the board"
+            ),
+            Error::TileNotInRack => write!(f, "These tiles are not in your rack"),
+            Error::UnknownWords(words) => {
+                write!(f, "Not in the word list: {}", words.join(", "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
@@ -0,0 +1,1093 @@
+use crate::bag::{commit_seed, BagRecord, Departure};
+use crate::board::{Board, Word, RACK_SIZE};
+use crate::error::Error;
+use crate::lexicon::Lexicon;
+use crate::player::Player;
+use crate::status::GameStatus;
+use crate::tile::{Play, Tile, TILE_BAG};
+use rand::prelude::SliceRandom;
+use rand::SeedableRng;
+use rand_chacha::ChaCha12Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+use uuid::Uuid;
+
+/// Fewest players a game can start with, or go on with
+pub const MIN_PLAYERS: usize = 2;
+
+/// A game from the first registration to the final scores
+///
+/// How many players may sit at the game is up to whoever hosts it, the rules
+/// only need two of them to start.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Game {
+    board: Board,
+    /// Every shuffle of the bag draws from it, in the order they happen
+    rng: BagRng,
+    tile_bag: Vec<Tile>,
+    racks: HashMap<Uuid, Vec<Tile>>,
+    players: Vec<Player>,
+    current_player_index: usize,
+    status: GameStatus,
+    /// Players who lost by forfeit, they keep their seat but never play again
+    forfeited: Vec<Uuid>,
+    /// Turns in a row where nobody scored, the game ends once everyone passed twice
+    scoreless_turns: usize,
+    scores: HashMap<Uuid, i32>,
+    /// Every turn played so far, oldest first
+    turns: Vec<Turn>,
+    /// Players in turn order when the game started
+    seating: Vec<Uuid>,
+    /// Players who left or forfeited once the game started, oldest first
+    departures: Vec<Departure>,
+}
+
+/// Generator the bag is shuffled with, see [`Game::with_seed`]
+///
+/// Saved as its seed and how far along it is, snapshots can't hold the 128-bit
+/// position of the generator itself.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(from = "BagRngState", into = "BagRngState")]
+struct BagRng {
+    seed: u64,
+    /// Hashed along with the seed, see [`commit_seed`]
+    salt: String,
+    rng: ChaCha12Rng,
+}
+
+#[derive(Serialize, Deserialize)]
+struct BagRngState {
+    seed: u64,
+    #[serde(default)]
+    salt: String,
+    word_pos: u64,
+}
+
+impl BagRng {
+    fn new(seed: u64) -> Self {
+        BagRng {
+            seed,
+            salt: format!("{:032x}", rand::random::<u128>()),
+            rng: ChaCha12Rng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl From<BagRngState> for BagRng {
+    fn from(state: BagRngState) -> Self {
+        let mut rng = ChaCha12Rng::seed_from_u64(state.seed);
+        rng.set_word_pos(state.word_pos.into());
+
+        BagRng {
+            seed: state.seed,
+            salt: state.salt,
+            rng,
+        }
+    }
+}
+
+impl From<BagRng> for BagRngState {
+    fn from(bag_rng: BagRng) -> Self {
+        BagRngState {
+            seed: bag_rng.seed,
+            salt: bag_rng.salt,
+            word_pos: bag_rng.rng.get_word_pos() as u64,
+        }
+    }
+}
+
+/// What a player did on their turn
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum TurnAction {
+    /// Tiles went from the rack to the board
+    Play {
+        /// Tiles in the order they were sent
+        tiles: Vec<Play>,
+        /// The word along the placed tiles comes first
+        words: Vec<Word>,
+        /// The whole rack was played
+        bingo: bool,
+    },
+    /// The turn went by without a move
+    Pass,
+}
+
+/// A turn as it was played, and what it scored
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct Turn {
+    /// Who played the turn
+    pub player_uuid: Uuid,
+    /// What they did
+    #[serde(flatten)]
+    pub action: TurnAction,
+    /// Points the turn earned
+    pub score: i32,
+    /// Seconds since the UNIX epoch
+    pub played_at: u64,
+}
+
+/// Where a player stands once the game is over
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize, Debug)]
+pub struct FinalScore {
+    /// Whose score it is
+    pub player_uuid: Uuid,
+    /// Points at the end, after what was left on the racks was counted
+    pub score: i32,
+    /// The player lost by forfeit, whatever their score
+    pub forfeited: bool,
+}
+
+impl Game {
+    /// A game waiting for its players, with a full bag shuffled from a seed
+    /// drawn from the OS
+    pub fn new() -> Self {
+        Self::with_seed(rand::random())
+    }
+
+    /// A game waiting for its players, with a full bag shuffled from `seed`
+    ///
+    /// Tiles going back to the bag are shuffled from the same seed, so two games
+    /// with the same seed and the same actions draw the same tiles.
+    pub fn with_seed(seed: u64) -> Self {
+        let mut game = Game {
+            board: Board::new(),
+            rng: BagRng::new(seed),
+            tile_bag: Vec::new(),
+            racks: HashMap::new(),
+            players: Vec::new(),
+            current_player_index: 0,
+            status: GameStatus::Waiting,
+            forfeited: Vec::new(),
+            scoreless_turns: 0,
+            scores: HashMap::new(),
+            turns: Vec::new(),
+            seating: Vec::new(),
+            departures: Vec::new(),
+        };
+
+        game.init_tile_bag();
+        game
+    }
+
+    fn init_tile_bag(&mut self) {
+        for &(tile, amount) in &TILE_BAG {
+            for _ in 0..amount {
+                self.tile_bag.push(tile);
+            }
+        }
+
+        self.tile_bag.shuffle(&mut self.rng.rng);
+    }
+
+    fn transition(&mut self, next: GameStatus) -> Result<(), Error> {
+        if !self.status.can_transition_to(next) {
+            return Err(Error::InvalidTransition(self.status, next));
+        }
+
+        self.status = next;
+        Ok(())
+    }
+
+    fn require_status(&self, allowed: &[GameStatus]) -> Result<(), Error> {
+        if allowed.contains(&self.status) {
+            Ok(())
+        } else {
+            Err(Error::ActionNotAllowed(self.status))
+        }
+    }
+
+    fn require_not_over(&self) -> Result<(), Error> {
+        if self.status.is_over() {
+            Err(Error::ActionNotAllowed(self.status))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Seats a player, until the game starts
+    pub fn register_player(&mut self, player: Player) -> Result<&Player, Error> {
+        self.require_status(&[GameStatus::Waiting])?;
+
+        if self.players.iter().any(|x| x.get_id() == player.get_id()) {
+            return Err(Error::DuplicatePlayerId);
+        }
+
+        self.racks.insert(*player.get_id(), Vec::new());
+        self.scores.insert(*player.get_id(), 0);
+        self.players.push(player);
+
+        Ok(self.players.last().unwrap())
+    }
+
+    /// Players leaving mid-game give their tiles back to the bag, and the game ends
+    /// if not enough players remain
+    pub fn remove_player(&mut self, player_uuid: &Uuid) -> Result<(), Error> {
+        self.require_status(&[
+            GameStatus::Waiting,
+            GameStatus::InProgress,
+            GameStatus::Paused,
+        ])?;
+
+        let index = self
+            .players
+            .iter()
+            .position(|x| x.get_id() == player_uuid)
+            .ok_or(Error::PlayerNotRegistered)?;
+
+        self.players.remove(index);
+        self.scores.remove(player_uuid);
+        self.forfeited.retain(|x| x != player_uuid);
+
+        if let Some(rack) = self.racks.remove(player_uuid) {
+            if !rack.is_empty() {
+                self.tile_bag.extend(rack);
+                self.tile_bag.shuffle(&mut self.rng.rng);
+            }
+        }
+
+        // The turn stays with whoever was due to play
+        if index < self.current_player_index {
+            self.current_player_index -= 1;
+        }
+        if self.current_player_index >= self.players.len() {
+            self.current_player_index = 0;
+        }
+
+        if self.status == GameStatus::Waiting {
+            return Ok(());
+        }
+
+        self.departures.push(Departure {
+            player_uuid: *player_uuid,
+            after_turns: self.turns.len(),
+            forfeited: false,
+        });
+
+        if self.get_active_player_count() < MIN_PLAYERS {
+            self.transition(GameStatus::Finished)?;
+        } else if self.is_forfeited_index(self.current_player_index) {
+            self.advance_turn();
+        }
+
+        Ok(())
+    }
+
+    /// The player loses and their tiles go back to the bag, the game ends if
+    /// not enough players remain
+    pub fn forfeit(&mut self, player_uuid: &Uuid) -> Result<(), Error> {
+        self.require_status(&[GameStatus::InProgress, GameStatus::Paused])?;
+        self.get_player(player_uuid)?;
+
+        if self.forfeited.contains(player_uuid) {
+            return Ok(());
+        }
+
+        self.forfeited.push(*player_uuid);
+        self.departures.push(Departure {
+            player_uuid: *player_uuid,
+            after_turns: self.turns.len(),
+            forfeited: true,
+        });
+
+        if let Some(rack) = self.racks.get_mut(player_uuid) {
+            self.tile_bag.append(rack);
+            self.tile_bag.shuffle(&mut self.rng.rng);
+        }
+
+        if self.get_active_player_count() < MIN_PLAYERS {
+            self.transition(GameStatus::Finished)?;
+        } else if self.get_current_player().map(|x| x.get_id()) == Some(player_uuid) {
+            self.advance_turn();
+        }
+
+        Ok(())
+    }
+
+    /// The player forfeited and won't play again
+    pub fn is_forfeited(&self, player_uuid: &Uuid) -> bool {
+        self.forfeited.contains(player_uuid)
+    }
+
+    fn is_forfeited_index(&self, index: usize) -> bool {
+        self.players
+            .get(index)
+            .is_some_and(|x| self.forfeited.contains(x.get_id()))
+    }
+
+    /// Players still in the running
+    fn get_active_player_count(&self) -> usize {
+        self.players.len() - self.forfeited.len()
+    }
+
+    /// The current player gives up their turn
+    pub fn pass(&mut self, player_uuid: &Uuid) -> Result<(), Error> {
+        self.require_status(&[GameStatus::InProgress])?;
+        self.get_player(player_uuid)?;
+
+        if self.get_current_player().map(|x| x.get_id()) != Some(player_uuid) {
+            return Err(Error::NotYourTurn);
+        }
+
+        self.scoreless_turns += 1;
+        self.record_turn(player_uuid, TurnAction::Pass, 0);
+
+        if self.scoreless_turns >= 2 * self.get_active_player_count() {
+            return self.finish(None);
+        }
+
+        self.next_turn()?;
+        Ok(())
+    }
+
+    /// The current player puts tiles from their rack on the board and draws new ones
+    ///
+    /// The game ends when a player empties their rack once the bag is empty.
+    pub fn play(&mut self, player_uuid: &Uuid, plays: &[Play]) -> Result<&Turn, Error> {
+        self.play_checked(player_uuid, plays, None)
+    }
+
+    /// Same as `play`, every word the move forms must be in `lexicon` as well
+    pub fn play_checked(
+        &mut self,
+        player_uuid: &Uuid,
+        plays: &[Play],
+        lexicon: Option<&Lexicon>,
+    ) -> Result<&Turn, Error> {
+        self.require_status(&[GameStatus::InProgress])?;
+        self.get_player(player_uuid)?;
+
+        if self.get_current_player().map(|x| x.get_id()) != Some(player_uuid) {
+            return Err(Error::NotYourTurn);
+        }
+
+        // Every tile must come from the rack, as many times as it is in there
+        let mut rack = self.get_player_tiles(player_uuid)?.clone();
+        for play in plays {
+            match rack.iter().position(|x| *x == play.tile) {
+                Some(index) => rack.swap_remove(index),
+                None => return Err(Error::TileNotInRack),
+            };
+        }
+
+        let scored = self.board.evaluate(plays)?;
+        if let Some(lexicon) = lexicon {
+            let unknown = lexicon.unknown_words(&scored.words);
+            if !unknown.is_empty() {
+                return Err(Error::UnknownWords(
+                    unknown.into_iter().map(String::from).collect(),
+                ));
+            }
+        }
+        self.board.apply(plays);
+
+        while rack.len() < RACK_SIZE {
+            match self.tile_bag.pop() {
+                Some(tile) => rack.push(tile),
+                None => break,
+            }
+        }
+
+        let went_out = rack.is_empty();
+        self.racks.insert(*player_uuid, rack);
+        *self.scores.entry(*player_uuid).or_default() += scored.score as i32;
+        self.scoreless_turns = 0;
+
+        let action = TurnAction::Play {
+            tiles: plays.to_vec(),
+            words: scored.words,
+            bingo: scored.bingo,
+        };
+        self.record_turn(player_uuid, action, scored.score as i32);
+
+        if went_out {
+            self.finish(Some(*player_uuid))?;
+        } else {
+            self.next_turn()?;
+        }
+
+        Ok(self.turns.last().unwrap())
+    }
+
+    fn record_turn(&mut self, player_uuid: &Uuid, action: TurnAction, score: i32) {
+        let played_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+
+        self.turns.push(Turn {
+            player_uuid: *player_uuid,
+            action,
+            score,
+            played_at,
+        });
+    }
+
+    /// Everyone loses what is left on their rack, and whoever went out gets all of it
+    fn finish(&mut self, went_out: Option<Uuid>) -> Result<(), Error> {
+        let mut leftovers = 0;
+
+        for (player_uuid, rack) in &self.racks {
+            let value: i32 = rack.iter().map(|Tile(_, value)| *value as i32).sum();
+
+            *self.scores.entry(*player_uuid).or_default() -= value;
+            leftovers += value;
+        }
+
+        if let Some(player_uuid) = went_out {
+            *self.scores.entry(player_uuid).or_default() += leftovers;
+        }
+
+        self.transition(GameStatus::Finished)
+    }
+
+    fn get_player_ids(&self) -> Vec<Uuid> {
+        self.players.iter().map(|x| *x.get_id()).collect()
+    }
+
+    /// Everyone seated, in turn order
+    pub fn get_players(&self) -> &[Player] {
+        &self.players
+    }
+
+    /// The host is the earliest registered player still in the game
+    pub fn get_host(&self) -> Option<&Player> {
+        self.players.first()
+    }
+
+    /// Where the game is in its lifecycle
+    pub fn get_status(&self) -> GameStatus {
+        self.status
+    }
+
+    /// Whose turn it is, the first registered player until the game starts
+    pub fn get_current_player(&self) -> Option<&Player> {
+        self.players.get(self.current_player_index)
+    }
+
+    /// Scores of everyone who played until the end or forfeited, `None` until the
+    /// game is finished
+    pub fn get_final_scores(&self) -> Option<Vec<FinalScore>> {
+        if self.status != GameStatus::Finished {
+            return None;
+        }
+
+        let final_scores = self
+            .players
+            .iter()
+            .map(|x| FinalScore {
+                player_uuid: *x.get_id(),
+                score: self.scores.get(x.get_id()).copied().unwrap_or_default(),
+                forfeited: self.is_forfeited(x.get_id()),
+            })
+            .collect();
+
+        Some(final_scores)
+    }
+
+    fn give_tile(&mut self, player_id: &Uuid) -> Result<(), Error> {
+        let tile = self.tile_bag.pop().ok_or(Error::NoMoreTiles)?;
+
+        match self.racks.get_mut(player_id) {
+            Some(rack) => {
+                if rack.len() == RACK_SIZE {
+                    return Err(Error::PlayerHas7Tiles);
+                }
+
+                rack.push(tile);
+                Ok(())
+            }
+            None => {
+                self.tile_bag.push(tile);
+                Err(Error::PlayerNotRegistered)
+            }
+        }
+    }
+
+    /// The player seated under `player_id`
+    pub fn get_player(&self, player_id: &Uuid) -> Result<&Player, Error> {
+        self.players
+            .iter()
+            .find(|x| x.get_id() == player_id)
+            .ok_or(Error::PlayerNotRegistered)
+    }
+
+    /// Seed the bag was shuffled from, to be kept secret until the game is over
+    pub fn get_seed(&self) -> u64 {
+        self.rng.seed
+    }
+
+    /// Drawn from the OS along with the game, and kept secret with the seed
+    pub fn get_seed_salt(&self) -> &str {
+        &self.rng.salt
+    }
+
+    /// Hash of the seed and its salt, to be published when the game starts, see
+    /// [`commit_seed`]
+    pub fn get_seed_commitment(&self) -> String {
+        commit_seed(self.rng.seed, &self.rng.salt)
+    }
+
+    /// What it takes to check the bag was not tampered with, `None` until the game
+    /// is over since it reveals the seed
+    pub fn get_bag_record(&self) -> Option<BagRecord> {
+        if !self.status.is_over() {
+            return None;
+        }
+
+        Some(BagRecord {
+            commitment: self.get_seed_commitment(),
+            seed: self.rng.seed,
+            salt: self.rng.salt.clone(),
+            seating: self.seating.clone(),
+            turns: self.turns.clone(),
+            departures: self.departures.clone(),
+            final_scores: self.get_final_scores(),
+        })
+    }
+
+    /// What is on the player's rack
+    pub fn get_player_tiles(&self, player_uuid: &Uuid) -> Result<&Vec<Tile>, Error> {
+        match self.racks.get(player_uuid) {
+            Some(rack) => Ok(rack),
+            None => Err(Error::PlayerNotRegistered),
+        }
+    }
+
+    /// Deals a full rack to everyone, the first registered player plays first
+    pub fn start(&mut self) -> Result<HashMap<Uuid, Vec<Tile>>, Error> {
+        self.require_status(&[GameStatus::Waiting])?;
+
+        if self.players.len() < MIN_PLAYERS {
+            return Err(Error::NotEnoughPlayers);
+        }
+
+        self.transition(GameStatus::Starting)?;
+
+        // Dealing is all or nothing, a failure puts the tiles back where they were
+        let tile_bag = self.tile_bag.clone();
+        let racks = self.racks.clone();
+
+        if let Err(error) = self.deal() {
+            self.tile_bag = tile_bag;
+            self.racks = racks;
+            self.transition(GameStatus::Waiting)?;
+
+            return Err(error);
+        }
+
+        self.transition(GameStatus::InProgress)?;
+        self.seating = self.get_player_ids();
+
+        Ok(self.racks.clone())
+    }
+
+    fn deal(&mut self) -> Result<(), Error> {
+        let player_ids = self.get_player_ids();
+
+        for player_id in player_ids {
+            for _ in 0..RACK_SIZE {
+                self.give_tile(&player_id)?
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Stops the game, nobody can play until it resumes
+    pub fn pause(&mut self) -> Result<(), Error> {
+        self.require_status(&[GameStatus::InProgress])?;
+        self.transition(GameStatus::Paused)
+    }
+
+    /// Goes on with a paused game, with the same player to play
+    pub fn resume(&mut self) -> Result<(), Error> {
+        self.require_status(&[GameStatus::Paused])?;
+        self.transition(GameStatus::InProgress)
+    }
+
+    /// Gives up on a game that won't be played to the end
+    pub fn abandon(&mut self) -> Result<(), Error> {
+        self.require_not_over()?;
+        self.transition(GameStatus::Abandoned)
+    }
+
+    /// Replaces what is on a player's rack, to set up a position, the bag is left as is
+    ///
+    /// Only for tests, the racks no longer add up with the bag afterwards.
+    #[cfg(any(test, feature = "testing"))]
+    pub fn set_rack(&mut self, player_uuid: &Uuid, rack: Vec<Tile>) {
+        self.racks.insert(*player_uuid, rack);
+    }
+
+    /// Points of every player so far
+    pub fn get_scores(&self) -> &HashMap<Uuid, i32> {
+        &self.scores
+    }
+
+    /// Tiles played so far
+    pub fn get_board(&self) -> &Board {
+        &self.board
+    }
+
+    /// Tiles still in the bag
+    pub fn get_tiles_left(&self) -> usize {
+        self.tile_bag.len()
+    }
+
+    /// Every turn played so far, oldest first
+    pub fn get_turns(&self) -> &[Turn] {
+        &self.turns
+    }
+
+    /// Hands the turn to the next player, forfeited players are skipped
+    pub fn next_turn(&mut self) -> Result<usize, Error> {
+        self.require_status(&[GameStatus::InProgress])?;
+        self.advance_turn();
+
+        Ok(self.current_player_index)
+    }
+
+    fn advance_turn(&mut self) {
+        for _ in 0..self.players.len() {
+            self.current_player_index = (self.current_player_index + 1) % self.players.len();
+
+            if !self.is_forfeited_index(self.current_player_index) {
+                break;
+            }
+        }
+    }
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Game::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // === Game.new()
+
+    #[test]
+    fn test_new_game_tile_bag_initialized() {
+        let game = Game::new();
+        assert_eq!(game.tile_bag.len(), 98); // Official rules
+    }
+
+    // ===
+
+    #[test]
+    fn game_cannot_have_multiple_players_with_same_id() {
+        let mut game = Game::new();
+
+        let uuid = Uuid::new_v4();
+        assert!(game.register_player(Player::new(&uuid, "Player")).is_ok());
+
+        let uuid = Uuid::new_v4();
+        assert!(game.register_player(Player::new(&uuid, "Player")).is_ok());
+        assert!(game.register_player(Player::new(&uuid, "Player")).is_err());
+    }
+
+    #[test]
+    fn game_cannot_start_without_at_least_2_players() {
+        {
+            let mut game = Game::new();
+
+            // Zero players
+            assert!(game.start().is_err());
+        }
+
+        {
+            let mut game = Game::new();
+
+            // One player
+            let uuid = Uuid::new_v4();
+            game.register_player(Player::new(&uuid, "Player")).unwrap();
+            assert!(game.start().is_err());
+        }
+
+        // >= 2 players
+        for n_players in 2..4 {
+            let mut game = Game::new();
+
+            let uuid = Uuid::new_v4();
+            game.register_player(Player::new(&uuid, "Player")).unwrap();
+
+            for _ in 1..n_players {
+                let uuid = Uuid::new_v4();
+                game.register_player(Player::new(&uuid, "Player")).unwrap();
+            }
+
+            assert!(game.start().is_ok());
+        }
+    }
+
+    #[test]
+    fn game_can_give_a_registered_player() {
+        let mut game = Game::new();
+
+        let uuid_0 = Uuid::new_v4();
+        let uuid_1 = Uuid::new_v4();
+
+        assert!(game
+            .register_player(Player::new(&uuid_0, "Player0"))
+            .is_ok());
+        assert!(game
+            .register_player(Player::new(&uuid_1, "Player1"))
+            .is_ok());
+
+        assert!(game.get_player(&uuid_0).is_ok());
+        assert_eq!(*game.get_player(&uuid_0).unwrap().get_id(), uuid_0);
+        assert_eq!(game.get_player(&uuid_0).unwrap().get_name(), "Player0");
+
+        assert!(game.get_player(&uuid_1).is_ok());
+        assert_eq!(*game.get_player(&uuid_1).unwrap().get_id(), uuid_1);
+        assert_eq!(game.get_player(&uuid_1).unwrap().get_name(), "Player1");
+    }
+
+    #[test]
+    fn game_cannot_give_player_not_registered() {
+        let mut game = Game::new();
+
+        let uuid = Uuid::new_v4();
+        assert!(game.register_player(Player::new(&uuid, "Player0")).is_ok());
+
+        let uuid = Uuid::new_v4();
+        assert!(game.register_player(Player::new(&uuid, "Player1")).is_ok());
+
+        let uuid = Uuid::new_v4();
+        assert!(game.get_player(&uuid).is_err()); // Unknown player ID
+    }
+
+    #[test]
+    fn game_must_give_7_tiles_on_start() {
+        let mut game = Game::new();
+
+        let uuid_0 = Uuid::new_v4();
+        let uuid_1 = Uuid::new_v4();
+
+        game.register_player(Player::new(&uuid_0, "Player0"))
+            .unwrap();
+        game.register_player(Player::new(&uuid_1, "Player1"))
+            .unwrap();
+
+        assert_eq!(game.get_status(), GameStatus::Waiting);
+
+        game.start().unwrap();
+
+        assert_eq!(game.get_status(), GameStatus::InProgress);
+        assert_eq!(game.get_player_tiles(&uuid_0).unwrap().len(), 7);
+        assert_eq!(game.get_player_tiles(&uuid_1).unwrap().len(), 7);
+    }
+
+    #[test]
+    fn next_turn_works() {
+        let mut game = Game::new();
+
+        let uuid_0 = Uuid::new_v4();
+        let uuid_1 = Uuid::new_v4();
+
+        game.register_player(Player::new(&uuid_0, "Player0"))
+            .unwrap();
+        game.register_player(Player::new(&uuid_1, "Player1"))
+            .unwrap();
+
+        assert_eq!(
+            game.next_turn(),
+            Err(Error::ActionNotAllowed(GameStatus::Waiting))
+        );
+
+        game.start().unwrap();
+
+        assert_eq!(game.current_player_index, 0);
+        assert_eq!(game.next_turn(), Ok(1));
+        assert_eq!(game.next_turn(), Ok(0));
+
+        game.pause().unwrap();
+        assert_eq!(
+            game.next_turn(),
+            Err(Error::ActionNotAllowed(GameStatus::Paused))
+        );
+    }
+
+    fn started_game(n_players: usize) -> (Game, Vec<Uuid>) {
+        let mut game = Game::new();
+        let mut uuids = Vec::new();
+
+        for i in 0..n_players {
+            let uuid = Uuid::new_v4();
+            game.register_player(Player::new(&uuid, &format!("Player{i}")))
+                .unwrap();
+            uuids.push(uuid);
+        }
+
+        game.start().unwrap();
+        (game, uuids)
+    }
+
+    #[test]
+    fn game_cannot_start_twice() {
+        let (mut game, uuids) = started_game(2);
+        let bag_size = game.tile_bag.len();
+
+        assert_eq!(
+            game.start().unwrap_err(),
+            Error::ActionNotAllowed(GameStatus::InProgress)
+        );
+        assert_eq!(game.tile_bag.len(), bag_size);
+        assert_eq!(game.get_player_tiles(&uuids[0]).unwrap().len(), 7);
+        assert_eq!(game.get_player_tiles(&uuids[1]).unwrap().len(), 7);
+    }
+
+    #[test]
+    fn failed_deal_is_rolled_back() {
+        let mut game = Game::new();
+
+        for i in 0..2 {
+            let uuid = Uuid::new_v4();
+            game.register_player(Player::new(&uuid, &format!("Player{i}")))
+                .unwrap();
+        }
+
+        game.tile_bag.truncate(10);
+
+        assert_eq!(game.start().unwrap_err(), Error::NoMoreTiles);
+        assert_eq!(game.get_status(), GameStatus::Waiting);
+        assert_eq!(game.tile_bag.len(), 10);
+        assert!(game.racks.values().all(|x| x.is_empty()));
+    }
+
+    #[test]
+    fn players_cannot_register_once_started() {
+        let (mut game, _) = started_game(2);
+
+        let uuid = Uuid::new_v4();
+        assert_eq!(
+            game.register_player(Player::new(&uuid, "Late"))
+                .unwrap_err(),
+            Error::ActionNotAllowed(GameStatus::InProgress)
+        );
+    }
+
+    #[test]
+    fn leaving_mid_game_returns_tiles_to_bag() {
+        let (mut game, uuids) = started_game(3);
+        let bag_size = game.tile_bag.len();
+
+        game.next_turn().unwrap();
+        game.next_turn().unwrap();
+        assert_eq!(game.get_current_player().unwrap().get_id(), &uuids[2]);
+
+        game.remove_player(&uuids[0]).unwrap();
+
+        assert_eq!(game.tile_bag.len(), bag_size + 7);
+        assert_eq!(game.get_status(), GameStatus::InProgress);
+        assert_eq!(game.get_current_player().unwrap().get_id(), &uuids[2]);
+    }
+
+    #[test]
+    fn game_finishes_when_too_few_players_remain() {
+        let (mut game, uuids) = started_game(2);
+
+        game.remove_player(&uuids[0]).unwrap();
+
+        assert_eq!(game.get_status(), GameStatus::Finished);
+        assert_eq!(
+            game.remove_player(&uuids[1]).unwrap_err(),
+            Error::ActionNotAllowed(GameStatus::Finished)
+        );
+        assert_eq!(
+            game.pause().unwrap_err(),
+            Error::ActionNotAllowed(GameStatus::Finished)
+        );
+    }
+
+    #[test]
+    fn only_current_player_can_pass() {
+        let (mut game, uuids) = started_game(2);
+
+        assert_eq!(game.pass(&uuids[1]), Err(Error::NotYourTurn));
+        assert!(game.pass(&uuids[0]).is_ok());
+        assert_eq!(game.get_current_player().unwrap().get_id(), &uuids[1]);
+    }
+
+    #[test]
+    fn game_finishes_after_everyone_passed_twice() {
+        let (mut game, uuids) = started_game(2);
+
+        for uuid in uuids.iter().cycle().take(3) {
+            game.pass(uuid).unwrap();
+        }
+        assert_eq!(game.get_status(), GameStatus::InProgress);
+
+        game.pass(&uuids[1]).unwrap();
+        assert_eq!(game.get_status(), GameStatus::Finished);
+    }
+
+    #[test]
+    fn forfeited_players_are_skipped() {
+        let (mut game, uuids) = started_game(3);
+        let bag_size = game.tile_bag.len();
+
+        game.forfeit(&uuids[1]).unwrap();
+
+        assert!(game.is_forfeited(&uuids[1]));
+        assert_eq!(game.tile_bag.len(), bag_size + 7);
+        assert_eq!(game.get_status(), GameStatus::InProgress);
+
+        game.pass(&uuids[0]).unwrap();
+        assert_eq!(game.get_current_player().unwrap().get_id(), &uuids[2]);
+
+        game.forfeit(&uuids[2]).unwrap();
+        assert_eq!(game.get_status(), GameStatus::Finished);
+    }
+
+    #[test]
+    fn forfeiting_current_player_moves_the_turn() {
+        let (mut game, uuids) = started_game(3);
+
+        game.forfeit(&uuids[0]).unwrap();
+
+        assert_eq!(game.get_current_player().unwrap().get_id(), &uuids[1]);
+    }
+
+    #[test]
+    fn abandoned_game_cannot_be_resumed() {
+        let (mut game, _) = started_game(2);
+
+        game.pause().unwrap();
+        game.abandon().unwrap();
+
+        assert_eq!(game.get_status(), GameStatus::Abandoned);
+        assert_eq!(
+            game.resume().unwrap_err(),
+            Error::ActionNotAllowed(GameStatus::Abandoned)
+        );
+        assert_eq!(
+            game.abandon().unwrap_err(),
+            Error::ActionNotAllowed(GameStatus::Abandoned)
+        );
+    }
+
+    fn cat(x: usize, y: usize) -> Vec<Play> {
+        [Tile('C', 3), Tile('A', 1), Tile('T', 1)]
+            .into_iter()
+            .enumerate()
+            .map(|(i, tile)| Play { tile, x: x + i, y })
+            .collect()
+    }
+
+    #[test]
+    fn play_scores_and_refills_the_rack() {
+        let (mut game, uuids) = started_game(2);
+        game.set_rack(&uuids[0], cat(0, 0).iter().map(|x| x.tile).collect());
+
+        assert_eq!(
+            game.play(&uuids[1], &cat(6, 7)).unwrap_err(),
+            Error::NotYourTurn
+        );
+
+        let turn = game.play(&uuids[0], &cat(6, 7)).unwrap().clone();
+        assert_eq!(turn.score, 10);
+        assert!(matches!(turn.action, TurnAction::Play { bingo: false, .. }));
+        assert_eq!(game.get_scores()[&uuids[0]], 10);
+        assert_eq!(game.get_player_tiles(&uuids[0]).unwrap().len(), 7);
+        assert_eq!(game.get_current_player().unwrap().get_id(), &uuids[1]);
+        assert_eq!(game.get_turns().len(), 1);
+    }
+
+    #[test]
+    fn play_needs_the_tiles_in_the_rack() {
+        let (mut game, uuids) = started_game(2);
+        game.set_rack(&uuids[0], vec![Tile('C', 3), Tile('A', 1)]);
+
+        assert_eq!(
+            game.play(&uuids[0], &cat(6, 7)).unwrap_err(),
+            Error::TileNotInRack
+        );
+        assert!(game.get_turns().is_empty());
+    }
+
+    #[test]
+    fn play_checked_needs_words_of_the_lexicon() {
+        let (mut game, uuids) = started_game(2);
+        game.set_rack(&uuids[0], cat(0, 0).iter().map(|x| x.tile).collect());
+
+        let lexicon = Lexicon::parse("DOG");
+        assert_eq!(
+            game.play_checked(&uuids[0], &cat(6, 7), Some(&lexicon))
+                .unwrap_err(),
+            Error::UnknownWords(vec![String::from("CAT")])
+        );
+        assert!(game.get_board().is_empty());
+        assert!(game.get_turns().is_empty());
+
+        let lexicon = Lexicon::parse("CAT\nDOG");
+        game.play_checked(&uuids[0], &cat(6, 7), Some(&lexicon))
+            .unwrap();
+        assert_eq!(game.get_turns().len(), 1);
+    }
+
+    #[test]
+    fn going_out_ends_the_game() {
+        let (mut game, uuids) = started_game(2);
+        game.tile_bag.clear();
+        game.set_rack(&uuids[0], cat(0, 0).iter().map(|x| x.tile).collect());
+        game.set_rack(&uuids[1], vec![Tile('Q', 10)]);
+
+        game.play(&uuids[0], &cat(6, 7)).unwrap();
+
+        assert_eq!(game.get_status(), GameStatus::Finished);
+        assert_eq!(game.get_scores()[&uuids[0]], 10 + 10);
+        assert_eq!(game.get_scores()[&uuids[1]], -10);
+    }
+
+    fn seeded_game(seed: u64, uuids: &[Uuid]) -> Game {
+        let mut game = Game::with_seed(seed);
+
+        for (i, uuid) in uuids.iter().enumerate() {
+            game.register_player(Player::new(uuid, &format!("Player{i}")))
+                .unwrap();
+        }
+
+        game
+    }
+
+    #[test]
+    fn same_seed_and_actions_draw_the_same_tiles() {
+        let uuids: Vec<Uuid> = (0..3).map(|_| Uuid::new_v4()).collect();
+        let mut games = [seeded_game(42, &uuids), seeded_game(42, &uuids)];
+
+        for game in &mut games {
+            game.start().unwrap();
+            game.remove_player(&uuids[2]).unwrap();
+        }
+
+        assert_eq!(games[0].get_seed(), 42);
+        assert_eq!(games[0].racks, games[1].racks);
+        assert_eq!(games[0].tile_bag, games[1].tile_bag);
+
+        let other = seeded_game(43, &uuids);
+        assert_ne!(games[0].tile_bag[..10], other.tile_bag[..10]);
+    }
+
+    #[test]
+    fn restored_game_shuffles_like_the_original() {
+        let uuids: Vec<Uuid> = (0..3).map(|_| Uuid::new_v4()).collect();
+        let mut game = seeded_game(7, &uuids);
+        game.start().unwrap();
+        game.remove_player(&uuids[2]).unwrap();
+
+        let json = serde_json::to_string(&game).unwrap();
+        let mut restored: Game = serde_json::from_str(&json).unwrap();
+
+        game.forfeit(&uuids[1]).unwrap();
+        restored.forfeit(&uuids[1]).unwrap();
+
+        assert_eq!(game.tile_bag, restored.tile_bag);
+    }
+}
@@ -0,0 +1,78 @@
+use crate::board::Word;
+use std::collections::HashSet;
+
+/// Words a move may form, read from a word list with one word per line
+///
+/// Case is ignored, and so are blank lines.
+#[derive(Clone, Debug, Default)]
+pub struct Lexicon {
+    words: HashSet<String>,
+}
+
+impl Lexicon {
+    /// Reads a word list, such as the `[lexicons]` files of the server
+    pub fn parse(text: &str) -> Lexicon {
+        let words = text
+            .lines()
+            .map(str::trim)
+            .filter(|x| !x.is_empty())
+            .map(str::to_uppercase)
+            .collect();
+
+        Lexicon { words }
+    }
+
+    /// Whether `word` is in the list, whatever its case
+    pub fn contains(&self, word: &str) -> bool {
+        self.words.contains(&word.to_uppercase())
+    }
+
+    /// Words formed by a move that are not in the list, in the order the move formed them
+    pub fn unknown_words<'a>(&self, words: &'a [Word]) -> Vec<&'a str> {
+        words
+            .iter()
+            .map(|x| x.word.as_str())
+            .filter(|x| !self.contains(x))
+            .collect()
+    }
+
+    /// Number of words in the list
+    pub fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    /// The list has no word at all
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn words_are_read_one_per_line() {
+        let lexicon = Lexicon::parse("cat\r\n\n  Dog \nZEST\n");
+
+        assert_eq!(lexicon.len(), 3);
+        assert!(lexicon.contains("CAT"));
+        assert!(lexicon.contains("dog"));
+        assert!(lexicon.contains("Zest"));
+        assert!(!lexicon.contains("CATS"));
+    }
+
+    #[test]
+    fn unknown_words_keep_their_order() {
+        let lexicon = Lexicon::parse("CAT\nAT");
+        let words: Vec<Word> = ["CAT", "XAT", "AT", "QI"]
+            .into_iter()
+            .map(|x| Word {
+                word: x.to_string(),
+                score: 0,
+            })
+            .collect();
+
+        assert_eq!(lexicon.unknown_words(&words), ["XAT", "QI"]);
+    }
+}
@@ -0,0 +1,49 @@
+//! Rules of Scrabble, without anything about serving them
+//!
+//! [`Board`] checks where tiles go, scores the words they form and finds a move
+//! for a rack, [`Game`] runs a game from the first registration to the final
+//! scores: the bag, the racks, whose turn it is and when it ends. Words are only
+//! checked when a [`Lexicon`] is handed to [`Game::play_checked`], any word goes
+//! otherwise.
+//!
+//! Bags are shuffled from a seed whose salted hash is known from the start, the
+//! [`BagRecord`] of a game that is over deals it again from that seed to show
+//! the draws were not changed along the way. How the seed was picked is beyond
+//! what it can show, see [`commit_seed`].
+//!
+//! ```
+//! use scrabble_core::{Game, GameStatus, Player};
+//! use uuid::Uuid;
+//!
+//! let mut game = Game::new();
+//! let (alice, bob) = (Uuid::new_v4(), Uuid::new_v4());
+//! game.register_player(Player::new(&alice, "Alice")).unwrap();
+//! game.register_player(Player::new(&bob, "Bob")).unwrap();
+//!
+//! let racks = game.start().unwrap();
+//! assert_eq!(racks[&alice].len(), 7);
+//! assert_eq!(game.get_status(), GameStatus::InProgress);
+//!
+//! game.pass(&alice).unwrap();
+//! assert_eq!(game.get_current_player().unwrap().get_id(), &bob);
+//! ```
+
+#![warn(missing_docs)]
+
+mod bag;
+mod board;
+mod error;
+mod game;
+mod lexicon;
+mod player;
+mod status;
+mod tile;
+
+pub use bag::{commit_seed, BagError, BagRecord, Departure, Draws};
+pub use board::{Board, Premium, ScoredMove, Word, BINGO_BONUS, BOARD_SIZE, CENTER, RACK_SIZE};
+pub use error::Error;
+pub use game::{FinalScore, Game, Turn, TurnAction, MIN_PLAYERS};
+pub use lexicon::Lexicon;
+pub use player::Player;
+pub use status::GameStatus;
+pub use tile::{Play, Tile, TILE_BAG};
@@ -0,0 +1,52 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Someone sitting at a game, two players are the same if both their id and name are
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct Player {
+    id: Uuid,
+    name: String,
+    /// Logged in players play under their account, the others are guests
+    #[serde(default)]
+    registered: bool,
+}
+
+impl PartialEq for Player {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id && self.name == other.name
+    }
+}
+
+impl Player {
+    /// A guest, playing without an account
+    pub fn new(id: &Uuid, name: &str) -> Player {
+        Player {
+            id: *id,
+            name: name.to_string(),
+            registered: false,
+        }
+    }
+
+    /// A player logged into the account `id`
+    pub fn with_account(id: &Uuid, name: &str) -> Player {
+        Player {
+            registered: true,
+            ..Player::new(id, name)
+        }
+    }
+
+    /// Name shown to the other players
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    /// Identifies the player in the game, the account id for registered players
+    pub fn get_id(&self) -> &Uuid {
+        &self.id
+    }
+
+    /// The player has an account, as opposed to a guest
+    pub fn is_registered(&self) -> bool {
+        self.registered
+    }
+}
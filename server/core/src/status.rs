@@ -0,0 +1,101 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::Formatter;
+
+/// Lifecycle of a game
+///
+/// ```text
+/// Waiting -> Starting -> InProgress <-> Paused
+///    ^          |            |            |
+///    +----------+            v            v
+///                     Finished / Abandoned
+/// ```
+///
+/// A waiting game can also be abandoned, `Finished` and `Abandoned` are final.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema, ts_rs::TS))]
+#[serde(rename_all = "kebab-case")]
+pub enum GameStatus {
+    /// Players can join
+    Waiting,
+    /// Racks are being dealt
+    Starting,
+    /// Players take turns
+    InProgress,
+    /// Nobody can play until the game resumes
+    Paused,
+    /// Played to the end, the final scores stand
+    Finished,
+    /// Given up on before the end
+    Abandoned,
+}
+
+impl GameStatus {
+    /// Whether the lifecycle allows going from this status to `next`
+    pub fn can_transition_to(self, next: GameStatus) -> bool {
+        use GameStatus::*;
+
+        matches!(
+            (self, next),
+            (Waiting, Starting)
+                | (Waiting, Abandoned)
+                | (Starting, InProgress)
+                | (Starting, Waiting)
+                | (InProgress, Paused)
+                | (InProgress, Finished)
+                | (InProgress, Abandoned)
+                | (Paused, InProgress)
+                | (Paused, Finished)
+                | (Paused, Abandoned)
+        )
+    }
+
+    /// `Finished` or `Abandoned`
+    pub fn is_over(self) -> bool {
+        matches!(self, GameStatus::Finished | GameStatus::Abandoned)
+    }
+}
+
+impl std::fmt::Display for GameStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GameStatus::Waiting => write!(f, "waiting"),
+            GameStatus::Starting => write!(f, "starting"),
+            GameStatus::InProgress => write!(f, "in progress"),
+            GameStatus::Paused => write!(f, "paused"),
+            GameStatus::Finished => write!(f, "finished"),
+            GameStatus::Abandoned => write!(f, "abandoned"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GameStatus::*;
+
+    #[test]
+    fn final_states_cannot_be_left() {
+        for status in [Finished, Abandoned] {
+            assert!(status.is_over());
+
+            for next in [Waiting, Starting, InProgress, Paused, Finished, Abandoned] {
+                assert!(!status.can_transition_to(next));
+            }
+        }
+    }
+
+    #[test]
+    fn games_cannot_skip_starting() {
+        assert!(!Waiting.can_transition_to(InProgress));
+        assert!(!Waiting.can_transition_to(Paused));
+        assert!(!Waiting.can_transition_to(Finished));
+        assert!(Waiting.can_transition_to(Starting));
+        assert!(Starting.can_transition_to(InProgress));
+    }
+
+    #[test]
+    fn games_can_be_paused_and_resumed() {
+        assert!(InProgress.can_transition_to(Paused));
+        assert!(Paused.can_transition_to(InProgress));
+        assert!(!Paused.can_transition_to(Paused));
+    }
+}
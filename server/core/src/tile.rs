@@ -0,0 +1,50 @@
+use serde::{Deserialize, Serialize};
+
+/// A letter and what it is worth
+#[derive(Copy, Clone, PartialEq, Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema, ts_rs::TS))]
+pub struct Tile(pub char, pub usize);
+
+/// A tile put on the board at column `x`, row `y`
+#[derive(Copy, Clone, PartialEq, Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema, ts_rs::TS))]
+pub struct Play {
+    /// The tile, as it is on the rack
+    pub tile: Tile,
+    /// Column, from 0 on the left
+    pub x: usize,
+    /// Row, from 0 at the top
+    pub y: usize,
+}
+
+/// Every tile of the English set, and how many of them are in the bag
+///
+/// The two blanks are left out, 98 tiles in all.
+pub const TILE_BAG: [(Tile, usize); 26] = [
+    (Tile('A', 1), 9),
+    (Tile('B', 3), 2),
+    (Tile('C', 3), 2),
+    (Tile('D', 2), 4),
+    (Tile('E', 1), 12),
+    (Tile('F', 4), 2),
+    (Tile('G', 2), 3),
+    (Tile('H', 4), 2),
+    (Tile('I', 1), 9),
+    (Tile('J', 8), 1),
+    (Tile('K', 5), 1),
+    (Tile('L', 1), 4),
+    (Tile('M', 3), 2),
+    (Tile('N', 1), 6),
+    (Tile('O', 1), 8),
+    (Tile('P', 3), 2),
+    (Tile('Q', 10), 1),
+    (Tile('R', 1), 6),
+    (Tile('S', 1), 4),
+    (Tile('T', 1), 6),
+    (Tile('U', 1), 4),
+    (Tile('V', 4), 2),
+    (Tile('W', 4), 2),
+    (Tile('X', 8), 1),
+    (Tile('Y', 4), 2),
+    (Tile('Z', 10), 1),
+];
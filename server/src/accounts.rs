@@ -0,0 +1,365 @@
+use crate::protocol::Handshake;
+use crate::storage;
+use crate::summary::unix_timestamp;
+use crate::Error;
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::SaltString;
+use argon2::{Argon2, PasswordHash, PasswordHasher, PasswordVerifier};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use socketioxide::extract::{SocketRef, TryData};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock, PoisonError};
+use std::time::{Duration, SystemTime};
+use uuid::Uuid;
+
+/// Where accounts are kept between restarts
+pub const ACCOUNTS_PATH: &str = "accounts.json";
+
+/// How long a session token stays valid after logging in
+pub const SESSION_LIFETIME: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+const USERNAME_LENGTH: std::ops::RangeInclusive<usize> = 3..=20;
+const MIN_PASSWORD_LENGTH: usize = 8;
+
+/// Every socket handler and connect middleware shares the same accounts
+pub type SharedAccounts = Arc<Mutex<Accounts>>;
+
+/// A registered player, its UUID is the one used as `Player.id` in every game
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct Account {
+    pub id: Uuid,
+    pub username: String,
+    /// PHC string, salt and parameters included
+    password_hash: String,
+    /// Seconds since the UNIX epoch
+    pub created_at: u64,
+}
+
+/// Proof that a socket logged in, handed back to the client as `token`
+#[derive(Clone, Serialize, Debug, PartialEq)]
+pub struct Session {
+    pub token: String,
+    pub account_id: Uuid,
+    pub username: String,
+    /// Seconds since the UNIX epoch
+    pub expires_at: u64,
+}
+
+#[derive(Default)]
+pub struct Accounts {
+    accounts: HashMap<Uuid, Account>,
+    sessions: HashMap<String, Session>,
+    /// In memory only when `None`
+    path: Option<PathBuf>,
+}
+
+impl Accounts {
+    /// Reads the accounts saved at `path`, starts from scratch if there is no file yet
+    pub fn load(path: &Path) -> std::io::Result<Self> {
+        let accounts: Vec<Account> = storage::load(path)?;
+
+        Ok(Accounts {
+            accounts: accounts.into_iter().map(|x| (x.id, x)).collect(),
+            sessions: HashMap::new(),
+            path: Some(path.to_path_buf()),
+        })
+    }
+
+    fn save(&self) -> Result<(), Error> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        let accounts: Vec<&Account> = self.accounts.values().collect();
+        storage::save(path, &accounts)
+    }
+
+    pub fn get_account(&self, account_id: &Uuid) -> Option<&Account> {
+        self.accounts.get(account_id)
+    }
+
+    fn find_by_username(&self, username: &str) -> Option<&Account> {
+        self.accounts
+            .values()
+            .find(|x| x.username.eq_ignore_ascii_case(username))
+    }
+
+    /// Adds an account whose password was hashed beforehand, and logs it in
+    ///
+    /// The username is looked up again, someone may have taken it while the
+    /// password was being hashed.
+    fn add_account(&mut self, username: &str, password_hash: String) -> Result<Session, Error> {
+        if self.find_by_username(username).is_some() {
+            return Err(Error::UsernameTaken);
+        }
+
+        let account = Account {
+            id: Uuid::new_v4(),
+            username: username.to_string(),
+            password_hash,
+            created_at: unix_timestamp(SystemTime::now()),
+        };
+        let account_id = account.id;
+
+        self.accounts.insert(account_id, account);
+
+        if let Err(error) = self.save() {
+            self.accounts.remove(&account_id);
+            return Err(error);
+        }
+
+        Ok(self.open_session(&account_id, SystemTime::now()))
+    }
+
+    pub fn log_out(&mut self, token: &str) {
+        self.sessions.remove(token);
+    }
+
+    /// Finds the session behind a token, expired sessions are dropped on the way
+    pub fn authenticate(&mut self, token: &str, now: SystemTime) -> Result<Session, Error> {
+        let session = self.sessions.get(token).ok_or(Error::InvalidSession)?;
+
+        if unix_timestamp(now) >= session.expires_at {
+            self.sessions.remove(token);
+            return Err(Error::InvalidSession);
+        }
+
+        Ok(session.clone())
+    }
+
+    fn open_session(&mut self, account_id: &Uuid, now: SystemTime) -> Session {
+        let session = Session {
+            token: random_token(),
+            account_id: *account_id,
+            username: self.accounts[account_id].username.clone(),
+            expires_at: unix_timestamp(now + SESSION_LIFETIME),
+        };
+
+        self.sessions.insert(session.token.clone(), session.clone());
+
+        session
+    }
+}
+
+/// Hashing passwords is slow on purpose, the accounts are only locked to look
+/// them up and to write to them, never while a password is hashed
+fn lock(accounts: &SharedAccounts) -> MutexGuard<'_, Accounts> {
+    accounts.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Creates an account and logs it in right away
+pub fn sign_up(
+    accounts: &SharedAccounts,
+    username: &str,
+    password: &str,
+) -> Result<Session, Error> {
+    let username = username.trim();
+
+    if !USERNAME_LENGTH.contains(&username.chars().count())
+        || !username
+            .chars()
+            .all(|x| x.is_alphanumeric() || x == '_' || x == '-')
+    {
+        return Err(Error::InvalidUsername);
+    }
+
+    if password.chars().count() < MIN_PASSWORD_LENGTH {
+        return Err(Error::WeakPassword);
+    }
+
+    // Spares the hashing when the answer is already known
+    if lock(accounts).find_by_username(username).is_some() {
+        return Err(Error::UsernameTaken);
+    }
+
+    let salt = SaltString::generate(&mut OsRng);
+    let password_hash = Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map_err(|_| Error::Storage)?
+        .to_string();
+
+    lock(accounts).add_account(username, password_hash)
+}
+
+/// Usernames are case-insensitive, the same error is returned whether the
+/// username or the password is wrong
+///
+/// Unknown usernames are checked against a made up hash, so they take as long
+/// to turn down as a wrong password and don't give away who has an account.
+pub fn log_in(accounts: &SharedAccounts, username: &str, password: &str) -> Result<Session, Error> {
+    let account = lock(accounts)
+        .find_by_username(username.trim())
+        .map(|x| (x.id, x.password_hash.clone()));
+
+    let password_hash = account.as_ref().map_or(dummy_hash(), |(_, x)| x.as_str());
+    let verified = PasswordHash::new(password_hash).is_ok_and(|x| {
+        Argon2::default()
+            .verify_password(password.as_bytes(), &x)
+            .is_ok()
+    });
+
+    match account {
+        Some((account_id, _)) if verified => {
+            Ok(lock(accounts).open_session(&account_id, SystemTime::now()))
+        }
+        _ => Err(Error::InvalidCredentials),
+    }
+}
+
+/// Hash of a password nobody has, with the same parameters as real ones
+fn dummy_hash() -> &'static str {
+    static DUMMY_HASH: OnceLock<String> = OnceLock::new();
+
+    DUMMY_HASH.get_or_init(|| {
+        let salt = SaltString::generate(&mut OsRng);
+        Argon2::default()
+            .hash_password(random_token().as_bytes(), &salt)
+            .map(|x| x.to_string())
+            .unwrap_or_default()
+    })
+}
+
+/// 32 random bytes, hex encoded, too many to guess
+pub fn random_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+
+    bytes.iter().map(|x| format!("{x:02x}")).collect()
+}
+
+/// Connect middleware binding the session behind the handshake token to the socket
+///
+/// Sockets that come without a token play as guests, the ones with a bad or expired
+/// token are turned away so the client knows it has to log in again.
+pub fn authenticate_socket(
+    accounts: SharedAccounts,
+) -> impl FnOnce(SocketRef, TryData<Handshake>) -> Result<(), Error> + Clone + Send + Sync + 'static
+{
+    move |socket_ref: SocketRef, TryData(handshake): TryData<Handshake>| {
+        let Some(token) = handshake.ok().and_then(|x| x.token) else {
+            return Ok(());
+        };
+
+        let session = lock(&accounts).authenticate(&token, SystemTime::now())?;
+        socket_ref.extensions.insert::<Session>(session);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PASSWORD: &str = "correct horse";
+
+    #[test]
+    fn sign_up_then_log_in() {
+        let accounts = SharedAccounts::default();
+
+        let session = sign_up(&accounts, "alice", PASSWORD).unwrap();
+        assert_eq!(session.username, "alice");
+
+        let other_session = log_in(&accounts, "Alice", PASSWORD).unwrap();
+        assert_eq!(other_session.account_id, session.account_id);
+        assert_ne!(other_session.token, session.token);
+
+        assert_eq!(
+            log_in(&accounts, "alice", "wrong password").unwrap_err(),
+            Error::InvalidCredentials
+        );
+        assert_eq!(
+            log_in(&accounts, "bob", PASSWORD).unwrap_err(),
+            Error::InvalidCredentials
+        );
+    }
+
+    #[test]
+    fn unknown_usernames_go_through_a_real_verification() {
+        let dummy = PasswordHash::new(dummy_hash()).unwrap();
+        assert_eq!(dummy.algorithm, argon2::Algorithm::default().ident());
+    }
+
+    #[test]
+    fn sign_up_rejects_bad_input() {
+        let accounts = SharedAccounts::default();
+
+        assert_eq!(
+            sign_up(&accounts, "al", PASSWORD).unwrap_err(),
+            Error::InvalidUsername
+        );
+        assert_eq!(
+            sign_up(&accounts, "alice smith", PASSWORD).unwrap_err(),
+            Error::InvalidUsername
+        );
+        assert_eq!(
+            sign_up(&accounts, "alice", "short").unwrap_err(),
+            Error::WeakPassword
+        );
+
+        sign_up(&accounts, "alice", PASSWORD).unwrap();
+        assert_eq!(
+            sign_up(&accounts, "ALICE", PASSWORD).unwrap_err(),
+            Error::UsernameTaken
+        );
+    }
+
+    #[test]
+    fn password_is_not_stored_in_clear() {
+        let accounts = SharedAccounts::default();
+
+        let session = sign_up(&accounts, "alice", PASSWORD).unwrap();
+        let accounts = accounts.lock().unwrap();
+        let account = &accounts.accounts[&session.account_id];
+
+        assert!(account.password_hash.starts_with("$argon2"));
+        assert!(!account.password_hash.contains(PASSWORD));
+    }
+
+    #[test]
+    fn sessions_expire_and_can_be_revoked() {
+        let shared = SharedAccounts::default();
+
+        let session = sign_up(&shared, "alice", PASSWORD).unwrap();
+        let mut accounts = shared.lock().unwrap();
+        assert_eq!(
+            accounts.authenticate(&session.token, SystemTime::now()),
+            Ok(session.clone())
+        );
+
+        let later = SystemTime::now() + SESSION_LIFETIME;
+        assert_eq!(
+            accounts.authenticate(&session.token, later),
+            Err(Error::InvalidSession)
+        );
+
+        drop(accounts);
+        let session = log_in(&shared, "alice", PASSWORD).unwrap();
+        let mut accounts = shared.lock().unwrap();
+        accounts.log_out(&session.token);
+        assert_eq!(
+            accounts.authenticate(&session.token, SystemTime::now()),
+            Err(Error::InvalidSession)
+        );
+    }
+
+    #[test]
+    fn accounts_survive_a_restart() {
+        let path = std::env::temp_dir().join(format!("accounts-{}.json", Uuid::new_v4()));
+
+        let account_id = {
+            let accounts = Arc::new(Mutex::new(Accounts::load(&path).unwrap()));
+            sign_up(&accounts, "alice", PASSWORD).unwrap().account_id
+        };
+
+        let accounts = Arc::new(Mutex::new(Accounts::load(&path).unwrap()));
+        assert_eq!(
+            log_in(&accounts, "alice", PASSWORD).unwrap().account_id,
+            account_id
+        );
+
+        std::fs::remove_file(&path).ok();
+    }
+}
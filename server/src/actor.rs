@@ -0,0 +1,257 @@
+use crate::config;
+use crate::events::Event::Report;
+use crate::events::{Event, EventSender};
+use crate::game::{self, GameEvent};
+use crate::manager::Manager;
+use crate::metrics;
+use crate::presence::{self, PRESENCE_INTERVAL};
+use crate::reaper;
+use crate::registry::{GameHandle, Registry};
+use crate::room::{Reaped, Room};
+use crate::scrabble::Scrabble;
+use crate::summary::GameSummary;
+use socketioxide::SocketIo;
+use std::collections::HashMap;
+use std::time::{Instant, SystemTime};
+use tokio::sync::{mpsc, oneshot, watch};
+use tokio::time::interval_at;
+use tracing::debug;
+use uuid::Uuid;
+
+/// Commands a game holds before whoever sends one has to wait
+const COMMAND_CAPACITY: usize = 32;
+
+pub enum GameCommand {
+    /// Something a socket of the game asked for
+    Socket(GameEvent),
+    /// Looks at the game as it is right now, for the API
+    Inspect(Box<dyn FnOnce(&Room) + Send>),
+    /// Ends the task once the commands queued before are handled, and hands the
+    /// game over so it can be saved
+    Stop(oneshot::Sender<Scrabble>),
+}
+
+/// What a game tells the lobby loop, which keeps everything that outlives it
+pub enum GameReport {
+    /// Sent once, when the game is finished or abandoned
+    Over {
+        game_uuid: Uuid,
+        game: Box<Scrabble>,
+    },
+    /// The task is done, archived games are kept by the lobby loop
+    Removed {
+        game_uuid: Uuid,
+        /// The game as it ended, `None` if it expired and is gone for good
+        archived: Option<Box<Scrabble>>,
+    },
+}
+
+/// A game running in a task of its own, it alone touches its `Room`
+pub struct GameActor {
+    room: Room,
+    commands: mpsc::Receiver<(Instant, GameCommand)>,
+    summary: watch::Sender<GameSummary>,
+    /// The lobby loop was told the game is over
+    reported_over: bool,
+}
+
+enum Wake {
+    Command(Instant, GameCommand),
+    Presence,
+    Reaper,
+    /// Every handle is gone, nobody can reach the game anymore
+    Unreachable,
+}
+
+/// Sets a game up without running it yet, so it can be registered first
+pub fn new(room: Room) -> (GameHandle, GameActor) {
+    let (commands_tx, commands) = mpsc::channel(COMMAND_CAPACITY);
+    let (summary, summary_rx) = watch::channel(room.get_summary());
+    // Games restored over are not reported twice
+    let reported_over = room.get_game().get_status().is_over();
+
+    (
+        GameHandle::new(commands_tx, summary_rx),
+        GameActor {
+            room,
+            commands,
+            summary,
+            reported_over,
+        },
+    )
+}
+
+impl GameActor {
+    #[cfg(test)]
+    pub fn get_room_mut(&mut self) -> &mut Room {
+        &mut self.room
+    }
+
+    pub async fn run(mut self, socket_io: SocketIo, lobby: EventSender) {
+        let config = config::get().limits.reaper();
+        let start = tokio::time::Instant::now();
+        let mut presence_interval = interval_at(start + PRESENCE_INTERVAL, PRESENCE_INTERVAL);
+        let mut reaper_interval = interval_at(start + config.interval, config.interval);
+
+        loop {
+            let wake = tokio::select! {
+                command = self.commands.recv() => match command {
+                    Some((sent_at, command)) => Wake::Command(sent_at, command),
+                    None => Wake::Unreachable,
+                },
+                _ = presence_interval.tick() => Wake::Presence,
+                _ = reaper_interval.tick() => Wake::Reaper,
+            };
+
+            let reaped = match wake {
+                Wake::Command(_, GameCommand::Stop(reply)) => {
+                    reply.send(self.room.into_game()).ok();
+                    return;
+                }
+                Wake::Command(sent_at, command) => {
+                    let received_at = Instant::now();
+
+                    match command {
+                        GameCommand::Socket(event) => {
+                            game::handle_event(event, &socket_io, &mut self.room)
+                        }
+                        GameCommand::Inspect(inspect) => inspect(&self.room),
+                        // Taken care of above
+                        GameCommand::Stop(_) => {}
+                    }
+
+                    metrics::observe_event(received_at - sent_at, received_at.elapsed());
+
+                    // A closed waiting room goes away like an expired one
+                    self.room.is_closed().then_some(Reaped::Expired)
+                }
+                Wake::Presence => {
+                    presence::enforce(&socket_io, &mut self.room);
+                    None
+                }
+                Wake::Reaper => reaper::reap(&socket_io, &mut self.room, &config),
+                Wake::Unreachable => return,
+            };
+
+            self.publish(&lobby).await;
+
+            if let Some(reaped @ (Reaped::Expired | Reaped::Archived)) = reaped {
+                let report = GameReport::Removed {
+                    game_uuid: *self.room.get_uuid(),
+                    archived: (reaped == Reaped::Archived).then(|| Box::new(self.room.into_game())),
+                };
+                lobby.send(Report(report)).await.ok();
+
+                return;
+            }
+        }
+    }
+
+    /// Keeps the summary seen by the lobby up to date, and tells the lobby loop
+    /// once the game is over
+    async fn publish(&mut self, lobby: &EventSender) {
+        let summary = self.room.get_summary();
+        self.summary.send_if_modified(|x| {
+            let modified = *x != summary;
+            *x = summary;
+            modified
+        });
+
+        let game = self.room.get_game();
+        if self.reported_over || !game.get_status().is_over() || self.room.is_closed() {
+            return;
+        }
+
+        self.reported_over = true;
+        let report = GameReport::Over {
+            game_uuid: *self.room.get_uuid(),
+            game: Box::new(game.clone()),
+        };
+        lobby.send(Report(report)).await.ok();
+    }
+}
+
+/// Starts the tasks of the games the manager just created
+pub fn spawn_new_games(socket_io: &SocketIo, manager: &mut Manager, lobby: &EventSender) {
+    for actor in manager.take_new_games() {
+        tokio::spawn(actor.run(socket_io.clone(), lobby.clone()));
+    }
+}
+
+/// Stops every game once the commands already queued are handled, and gives the
+/// games back
+pub async fn stop_all(registry: &Registry) -> HashMap<Uuid, Scrabble> {
+    let mut replies = Vec::new();
+
+    for (game_uuid, handle) in registry.get_handles() {
+        let (reply, game) = oneshot::channel();
+
+        if handle.send(GameCommand::Stop(reply)).await.is_ok() {
+            replies.push((game_uuid, game));
+        }
+    }
+
+    let mut games = HashMap::new();
+    for (game_uuid, game) in replies {
+        if let Ok(game) = game.await {
+            games.insert(game_uuid, game);
+        }
+    }
+
+    games
+}
+
+pub fn handle_events(event: Event, socket_io: &SocketIo, manager: &mut Manager) {
+    if let Report(report) = event {
+        match report {
+            // A game ended, whatever outlives it is settled
+            GameReport::Over { game_uuid, game } => {
+                if let Some(changes) = manager.game_over(&game_uuid, &game, SystemTime::now()) {
+                    debug!(%game_uuid, "Ratings updated");
+
+                    game::notify_ratings(socket_io, &game_uuid, &changes);
+                }
+            }
+
+            // A game task ended, the game is only kept if it was archived
+            GameReport::Removed {
+                game_uuid,
+                archived,
+            } => {
+                manager.game_removed(&game_uuid, archived.map(|x| *x));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::GameSettings;
+
+    #[tokio::test]
+    async fn stopped_games_are_handed_back() {
+        let (_, socket_io) = SocketIo::new_layer();
+        let (sender, _receiver) = crate::events::channel(8);
+        let mut manager = Manager::new();
+
+        let created = manager.create_game(GameSettings::default()).unwrap();
+        spawn_new_games(&socket_io, &mut manager, &sender);
+
+        let handle = manager.get_registry().get(&created.uuid).unwrap();
+        let (reply, response) = oneshot::channel();
+        let inspect = GameCommand::Inspect(Box::new(move |room: &Room| {
+            reply.send(*room.get_uuid()).ok();
+        }));
+        assert!(handle.send(inspect).await.is_ok());
+        assert_eq!(response.await.unwrap(), created.uuid);
+
+        let games = stop_all(manager.get_registry()).await;
+        assert_eq!(games.len(), 1);
+        assert!(games.contains_key(&created.uuid));
+
+        // Nobody is left to answer
+        let (reply, _) = oneshot::channel();
+        assert!(handle.send(GameCommand::Stop(reply)).await.is_err());
+    }
+}
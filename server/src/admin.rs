@@ -0,0 +1,183 @@
+use crate::events::Event::Admin;
+use crate::events::{Event, EventSender};
+use crate::maintenance::{self, MaintenanceNotice};
+use crate::manager::Manager;
+use crate::response::Response;
+use axum::extract::State;
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::IntoResponse;
+use axum::routing::get;
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use socketioxide::SocketIo;
+use tokio::sync::oneshot;
+use tracing::info;
+
+#[derive(Serialize, Debug)]
+pub struct MaintenanceStatus {
+    enabled: bool,
+    /// Games that are not over yet, the server can be restarted once there are none
+    running_games: usize,
+}
+
+#[derive(Deserialize, Debug)]
+struct MaintenanceRequest {
+    enabled: bool,
+    /// Shown to the players instead of the default notice
+    #[serde(default)]
+    message: Option<String>,
+}
+
+pub enum AdminEvent {
+    Maintenance {
+        /// `None` to only look at the current state
+        change: Option<(bool, Option<String>)>,
+        reply: oneshot::Sender<MaintenanceStatus>,
+    },
+}
+
+#[derive(Clone)]
+struct AdminState {
+    sender: EventSender,
+    token: String,
+}
+
+/// Routes for whoever runs the server, every request needs `Authorization: Bearer <token>`
+pub fn router(sender: EventSender, token: String) -> Router {
+    Router::new()
+        .route("/maintenance", get(get_maintenance).put(set_maintenance))
+        .with_state(AdminState { sender, token })
+}
+
+fn authorize(state: &AdminState, headers: &HeaderMap) -> Result<(), StatusCode> {
+    let token = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|x| x.to_str().ok())
+        .and_then(|x| x.strip_prefix("Bearer "));
+
+    match token {
+        Some(token) if token == state.token => Ok(()),
+        _ => Err(StatusCode::UNAUTHORIZED),
+    }
+}
+
+async fn maintenance(
+    state: &AdminState,
+    change: Option<(bool, Option<String>)>,
+) -> axum::response::Response {
+    let (reply, response) = oneshot::channel();
+
+    if state
+        .sender
+        .send(Admin(AdminEvent::Maintenance { change, reply }))
+        .await
+        .is_err()
+    {
+        return StatusCode::SERVICE_UNAVAILABLE.into_response();
+    }
+
+    match response.await {
+        Ok(status) => Json(Response::from_data(status)).into_response(),
+        Err(_) => StatusCode::SERVICE_UNAVAILABLE.into_response(),
+    }
+}
+
+async fn get_maintenance(
+    State(state): State<AdminState>,
+    headers: HeaderMap,
+) -> axum::response::Response {
+    if let Err(status) = authorize(&state, &headers) {
+        return status.into_response();
+    }
+
+    maintenance(&state, None).await
+}
+
+/// Games already there are played to the end, only new ones are refused
+async fn set_maintenance(
+    State(state): State<AdminState>,
+    headers: HeaderMap,
+    Json(request): Json<MaintenanceRequest>,
+) -> axum::response::Response {
+    if let Err(status) = authorize(&state, &headers) {
+        return status.into_response();
+    }
+
+    maintenance(&state, Some((request.enabled, request.message))).await
+}
+
+pub fn handle_events(event: Event, socket_io: &SocketIo, manager: &mut Manager) {
+    if let Admin(event) = event {
+        match event {
+            // Maintenance is looked at or switched, players hear about any change
+            AdminEvent::Maintenance { change, reply } => {
+                if let Some((enabled, message)) = change {
+                    if enabled != manager.is_in_maintenance() {
+                        info!(enabled, "Maintenance mode changed");
+
+                        manager.set_maintenance(enabled);
+                        maintenance::notify_all(
+                            socket_io,
+                            manager,
+                            &MaintenanceNotice {
+                                enabled,
+                                shutdown: false,
+                                message,
+                            },
+                        );
+                    }
+                }
+
+                reply
+                    .send(MaintenanceStatus {
+                        enabled: manager.is_in_maintenance(),
+                        running_games: manager.count_running_games(),
+                    })
+                    .ok();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::Request;
+    use http_body_util::BodyExt;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn maintenance_needs_the_token() {
+        let (sender, mut receiver) = crate::events::channel(8);
+        let app = router(sender, String::from("secret-token"));
+
+        tokio::spawn(async move {
+            let (_, socket_io) = SocketIo::new_layer();
+            socket_io.ns("/", || ());
+            let mut manager = Manager::new();
+
+            while let Some((_, event)) = receiver.recv().await {
+                handle_events(event, &socket_io, &mut manager);
+            }
+        });
+
+        let request = |token: &str| {
+            Request::put("/maintenance")
+                .header(header::AUTHORIZATION, format!("Bearer {token}"))
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(r#"{"enabled": true}"#))
+                .unwrap()
+        };
+
+        let response = app.clone().oneshot(request("guess")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let response = app.oneshot(request("secret-token")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["data"]["enabled"], true);
+    }
+}
@@ -0,0 +1,608 @@
+use crate::accounts::SharedAccounts;
+use crate::actor::GameCommand;
+use crate::events::Event::Api;
+use crate::events::{Event, EventSender};
+use crate::manager::{CreatedGame, Manager};
+use crate::ratings::Rating;
+use crate::registry::Registry;
+use crate::response::Response;
+use crate::room::Room;
+use crate::scrabble::Scrabble;
+use crate::settings::{GameSettings, Variant};
+use crate::stats::{PlayerStats, StatsFilter};
+use crate::summary::{GameFilter, GamePage, GameSummary, Pagination};
+use crate::Error;
+use crate::{config, lobby};
+use axum::extract::{Path, Query, State};
+use axum::http::{header, StatusCode};
+use axum::response::IntoResponse;
+use axum::routing::get;
+use axum::{Json, Router};
+use scrabble_core::{BagRecord, Draws, FinalScore, Player, Tile, Turn, BOARD_SIZE};
+use serde::Serialize;
+use socketioxide::SocketIo;
+use std::collections::HashMap;
+use std::sync::PoisonError;
+use tokio::sync::oneshot;
+use uuid::Uuid;
+
+/// What a script or a dashboard can see of a game, racks stay hidden
+#[derive(Serialize, Debug)]
+pub struct GameState {
+    #[serde(flatten)]
+    summary: GameSummary,
+    players: Vec<Player>,
+    current_player: Option<Uuid>,
+    scores: HashMap<Uuid, i32>,
+    /// Rows from top to bottom
+    board: [[Option<Tile>; BOARD_SIZE]; BOARD_SIZE],
+    tiles_left: usize,
+    /// Hash of the bag's seed, from the start on
+    seed_commitment: Option<String>,
+    /// The seed itself, as a string, once the game is over
+    seed: Option<String>,
+    /// Hashed with the seed into the commitment, revealed along with it
+    seed_salt: Option<String>,
+}
+
+impl GameState {
+    fn new(uuid: &Uuid, game: &Scrabble) -> Self {
+        GameState {
+            summary: GameSummary::new(uuid, game),
+            players: game.get_players(),
+            current_player: game.get_current_player().map(|x| *x.get_id()),
+            scores: game.get_scores().clone(),
+            board: *game.get_board().rows(),
+            tiles_left: game.get_tiles_left(),
+            seed_commitment: game.get_seed_commitment(),
+            seed: game.get_revealed_seed().map(|(x, _)| x.to_string()),
+            seed_salt: game.get_revealed_seed().map(|(_, x)| x.to_string()),
+        }
+    }
+}
+
+/// A game that is over, with everything needed to replay it
+#[derive(Serialize, Debug)]
+pub struct GameRecord {
+    #[serde(flatten)]
+    state: GameState,
+    final_scores: Option<Vec<FinalScore>>,
+    turns: Vec<Turn>,
+}
+
+/// The bag of a game that is over, dealt again from its revealed seed
+#[derive(Serialize, Debug)]
+pub struct BagAudit {
+    #[serde(flatten)]
+    record: BagRecord,
+    /// Why the bag doesn't deal the game again, `None` when it does
+    mismatch: Option<String>,
+    /// Every draw as dealt again from the seed, if the bag checks out
+    draws: Option<Draws>,
+}
+
+impl BagAudit {
+    fn new(record: BagRecord) -> Self {
+        let (draws, mismatch) = match record.verify() {
+            Ok(draws) => (Some(draws), None),
+            Err(error) => (None, Some(error.to_string())),
+        };
+
+        BagAudit {
+            record,
+            mismatch,
+            draws,
+        }
+    }
+}
+
+/// An account as anyone can see it
+#[derive(Serialize, Debug)]
+pub struct PlayerProfile {
+    id: Uuid,
+    username: String,
+    /// Seconds since the UNIX epoch
+    created_at: u64,
+    ratings: Vec<Rating>,
+    stats: PlayerStats,
+}
+
+pub enum ApiEvent {
+    ListGames {
+        filter: GameFilter,
+        pagination: Pagination,
+        reply: oneshot::Sender<GamePage>,
+    },
+    CreateGame {
+        settings: GameSettings,
+        reply: oneshot::Sender<Result<CreatedGame, Error>>,
+    },
+    Ratings {
+        account_id: Uuid,
+        filter: StatsFilter,
+        reply: oneshot::Sender<(Vec<Rating>, PlayerStats)>,
+    },
+    ArchivedGame {
+        game_uuid: Uuid,
+        reply: oneshot::Sender<Option<Box<Scrabble>>>,
+    },
+}
+
+#[derive(Clone)]
+struct ApiState {
+    sender: EventSender,
+    registry: Registry,
+    accounts: SharedAccounts,
+}
+
+/// Routes of the JSON API, to be nested under its version prefix
+///
+/// Requests go to the same places as the socket ones, so both see the very same
+/// games: the event channel for the lobby, the task of a game for the game itself.
+pub fn router(sender: EventSender, registry: Registry, accounts: SharedAccounts) -> Router {
+    Router::new()
+        .route("/games", get(list_games).post(create_game))
+        .route("/games/:game_uuid", get(game_state))
+        .route("/games/:game_uuid/moves", get(moves))
+        .route("/games/:game_uuid/download", get(download))
+        .route("/games/:game_uuid/bag", get(bag))
+        .route("/players/:account_id", get(player_profile))
+        .route("/players/:account_id/ratings", get(player_ratings))
+        .route("/lexicons/:variant", get(lexicon))
+        .with_state(ApiState {
+            sender,
+            registry,
+            accounts,
+        })
+}
+
+/// Hands an event to the manager and waits for its reply, which only fails if
+/// the event loop is gone
+async fn ask<T>(
+    state: &ApiState,
+    event: impl FnOnce(oneshot::Sender<T>) -> ApiEvent,
+) -> Result<T, StatusCode> {
+    let (reply, response) = oneshot::channel();
+
+    state
+        .sender
+        .send(Api(event(reply)))
+        .await
+        .map_err(|_| StatusCode::SERVICE_UNAVAILABLE)?;
+
+    response.await.map_err(|_| StatusCode::SERVICE_UNAVAILABLE)
+}
+
+/// Looks at a game from within its task, without going through the manager
+async fn inspect<T: Send + 'static>(
+    state: &ApiState,
+    game_uuid: &Uuid,
+    look: impl FnOnce(&Room) -> Result<T, Error> + Send + 'static,
+) -> Result<T, Error> {
+    let handle = state.registry.get(game_uuid).ok_or(Error::GameNotFound)?;
+    let (reply, response) = oneshot::channel();
+    let command = GameCommand::Inspect(Box::new(move |room| {
+        reply.send(look(room)).ok();
+    }));
+
+    // A game whose task just ended is as good as gone
+    handle
+        .send(command)
+        .await
+        .map_err(|_| Error::GameNotFound)?;
+
+    response.await.unwrap_or(Err(Error::GameNotFound))
+}
+
+/// Looks at a game from within its task, or in the archive once the reaper
+/// ended its task
+///
+/// Archived games are handed over as a copy, so they are looked at outside of
+/// the event loop.
+async fn inspect_game<T: Send + 'static>(
+    state: &ApiState,
+    game_uuid: &Uuid,
+    look: fn(&Uuid, &Scrabble) -> Result<T, Error>,
+) -> Result<T, Error> {
+    let game_uuid = *game_uuid;
+
+    match inspect(state, &game_uuid, move |room| {
+        look(&game_uuid, room.get_game())
+    })
+    .await
+    {
+        Err(Error::GameNotFound) => {}
+        result => return result,
+    }
+
+    ask(state, |reply| ApiEvent::ArchivedGame { game_uuid, reply })
+        .await
+        .map_err(|_| Error::ServerUnavailable)?
+        .ok_or(Error::GameNotFound)
+        .and_then(|game| look(&game_uuid, &game))
+}
+
+fn status_code(error: &Error) -> StatusCode {
+    match error {
+        Error::GameNotFound | Error::AccountNotFound => StatusCode::NOT_FOUND,
+        Error::ActionNotAllowed(_) => StatusCode::CONFLICT,
+        Error::TooManyGames | Error::Maintenance | Error::ServerUnavailable => {
+            StatusCode::SERVICE_UNAVAILABLE
+        }
+        Error::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+        _ => StatusCode::BAD_REQUEST,
+    }
+}
+
+/// Same body as the socket acknowledgements, with a matching status code
+fn respond<T: Serialize>(result: Result<T, Error>) -> axum::response::Response {
+    match result {
+        Ok(data) => Json(Response::from_data(data)).into_response(),
+        Err(error) => {
+            (status_code(&error), Json(Response::<()>::from_error(error))).into_response()
+        }
+    }
+}
+
+async fn list_games(
+    State(state): State<ApiState>,
+    Query(filter): Query<GameFilter>,
+    Query(pagination): Query<Pagination>,
+) -> axum::response::Response {
+    match ask(&state, |reply| ApiEvent::ListGames {
+        filter,
+        pagination,
+        reply,
+    })
+    .await
+    {
+        Ok(page) => respond(Ok(page)),
+        Err(status) => status.into_response(),
+    }
+}
+
+async fn create_game(
+    State(state): State<ApiState>,
+    Json(settings): Json<GameSettings>,
+) -> axum::response::Response {
+    match ask(&state, |reply| ApiEvent::CreateGame { settings, reply }).await {
+        Ok(Ok(created)) => (StatusCode::CREATED, respond(Ok(created))).into_response(),
+        Ok(Err(error)) => respond::<()>(Err(error)),
+        Err(status) => status.into_response(),
+    }
+}
+
+async fn game_state(
+    State(state): State<ApiState>,
+    Path(game_uuid): Path<Uuid>,
+) -> axum::response::Response {
+    respond(
+        inspect(&state, &game_uuid, |room| {
+            Ok(GameState::new(room.get_uuid(), room.get_game()))
+        })
+        .await,
+    )
+}
+
+async fn moves(
+    State(state): State<ApiState>,
+    Path(game_uuid): Path<Uuid>,
+) -> axum::response::Response {
+    respond(
+        inspect(&state, &game_uuid, |room| {
+            Ok(room.get_game().get_turns().to_vec())
+        })
+        .await,
+    )
+}
+
+/// Served as a file, so browsers save it instead of showing it
+async fn download(
+    State(state): State<ApiState>,
+    Path(game_uuid): Path<Uuid>,
+) -> axum::response::Response {
+    // Someone wants to keep a game for later, only once it's over
+    let record = inspect_game(&state, &game_uuid, |game_uuid, game| {
+        if !game.get_status().is_over() {
+            return Err(Error::ActionNotAllowed(game.get_status()));
+        }
+
+        Ok(GameRecord {
+            state: GameState::new(game_uuid, game),
+            final_scores: game.get_final_scores(),
+            turns: game.get_turns().to_vec(),
+        })
+    })
+    .await;
+
+    match record {
+        Ok(record) => (
+            [(
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"game-{game_uuid}.json\""),
+            )],
+            Json(record),
+        )
+            .into_response(),
+        Err(error) => respond::<()>(Err(error)),
+    }
+}
+
+/// Anyone can check the draws once the seed is revealed, the server does it too
+async fn bag(
+    State(state): State<ApiState>,
+    Path(game_uuid): Path<Uuid>,
+) -> axum::response::Response {
+    respond(
+        inspect_game(&state, &game_uuid, |_, game| {
+            game.get_bag_record()
+                .map(BagAudit::new)
+                .ok_or(Error::ActionNotAllowed(game.get_status()))
+        })
+        .await,
+    )
+}
+
+async fn player_profile(
+    State(state): State<ApiState>,
+    Path(account_id): Path<Uuid>,
+    Query(filter): Query<StatsFilter>,
+) -> axum::response::Response {
+    // Accounts are not part of any game, they never go through the manager
+    let Some(account) = state
+        .accounts
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get_account(&account_id)
+        .cloned()
+    else {
+        return respond::<()>(Err(Error::AccountNotFound));
+    };
+
+    match ask(&state, |reply| ApiEvent::Ratings {
+        account_id,
+        filter,
+        reply,
+    })
+    .await
+    {
+        Ok((ratings, stats)) => respond(Ok(PlayerProfile {
+            id: account.id,
+            username: account.username,
+            created_at: account.created_at,
+            ratings,
+            stats,
+        })),
+        Err(status) => status.into_response(),
+    }
+}
+
+async fn player_ratings(
+    State(state): State<ApiState>,
+    Path(account_id): Path<Uuid>,
+    Query(filter): Query<StatsFilter>,
+) -> axum::response::Response {
+    if state
+        .accounts
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get_account(&account_id)
+        .is_none()
+    {
+        return respond::<()>(Err(Error::AccountNotFound));
+    }
+
+    match ask(&state, |reply| ApiEvent::Ratings {
+        account_id,
+        filter,
+        reply,
+    })
+    .await
+    {
+        Ok((ratings, _)) => respond(Ok(ratings)),
+        Err(status) => status.into_response(),
+    }
+}
+
+/// Word list of a variant as the server checks moves against it, for clients
+/// to check words before playing them
+async fn lexicon(Path(variant): Path<Variant>) -> axum::response::Response {
+    let Some(path) = config::get().lexicons.get(&variant) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    match tokio::fs::read_to_string(path).await {
+        Ok(text) => ([(header::CONTENT_TYPE, "text/plain; charset=utf-8")], text).into_response(),
+        Err(_) => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+pub fn handle_events(event: Event, socket_io: &SocketIo, manager: &mut Manager) {
+    if let Api(event) = event {
+        // Replies are dropped when the HTTP client went away, nobody is waiting then
+        match event {
+            // Same list as the lobby's, private games are left out
+            ApiEvent::ListGames {
+                filter,
+                pagination,
+                reply,
+            } => {
+                reply.send(manager.get_game_list(&filter, pagination)).ok();
+            }
+
+            // A script wants to host a game, players join it from the lobby
+            ApiEvent::CreateGame { settings, reply } => {
+                reply
+                    .send(lobby::create_game(socket_io, manager, settings))
+                    .ok();
+            }
+
+            // How good a player is, and how they usually do
+            ApiEvent::Ratings {
+                account_id,
+                filter,
+                reply,
+            } => {
+                let ratings = manager.get_ratings(&account_id, filter.variant);
+                let stats = manager.get_player_stats(&account_id, &filter);
+
+                reply.send((ratings, stats)).ok();
+            }
+
+            // The reaper ended the task of a game that is over, it lives on here
+            ApiEvent::ArchivedGame { game_uuid, reply } => {
+                let game = manager.get_archived_game(&game_uuid).cloned();
+                reply.send(game.map(Box::new)).ok();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actor::GameReport;
+    use axum::body::Body;
+    use axum::http::Request;
+    use http_body_util::BodyExt;
+    use tower::ServiceExt;
+
+    /// The API with a manager of its own, answering from a spawned event loop
+    fn app() -> Router {
+        app_with_sender().0
+    }
+
+    /// Also gives the event channel, to send what the games would
+    fn app_with_sender() -> (Router, EventSender) {
+        let (sender, mut receiver) = crate::events::channel(8);
+        let (_, socket_io) = SocketIo::new_layer();
+        // Lobby notifications go to the default namespace
+        socket_io.ns("/", || ());
+        let mut manager = Manager::new();
+        let registry = manager.get_registry().clone();
+
+        tokio::spawn({
+            let sender = sender.clone();
+
+            async move {
+                while let Some((_, event)) = receiver.recv().await {
+                    match event {
+                        event @ Event::Report(_) => {
+                            crate::actor::handle_events(event, &socket_io, &mut manager)
+                        }
+                        event => handle_events(event, &socket_io, &mut manager),
+                    }
+                    crate::actor::spawn_new_games(&socket_io, &mut manager, &sender);
+                }
+            }
+        });
+
+        (
+            router(sender.clone(), registry, SharedAccounts::default()),
+            sender,
+        )
+    }
+
+    async fn call(app: &Router, request: Request<Body>) -> (StatusCode, serde_json::Value) {
+        let response = app.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    fn get(uri: &str) -> Request<Body> {
+        Request::get(uri).body(Body::empty()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn games_can_be_created_and_looked_at() {
+        let app = app();
+
+        let (status, body) = call(
+            &app,
+            Request::post("/games")
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(r#"{"name": "From a script"}"#))
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+        let game_uuid = body["data"]["uuid"].as_str().unwrap().to_string();
+
+        let (status, body) = call(&app, get("/games?search=script&limit=5")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"]["total"], 1);
+
+        let (status, body) = call(&app, get(&format!("/games/{game_uuid}"))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"]["name"], "From a script");
+        assert_eq!(body["data"]["tiles_left"], 98);
+        assert!(body["data"]["seed_commitment"].is_null());
+        assert!(body["data"]["seed"].is_null());
+        assert!(body["data"]["seed_salt"].is_null());
+
+        let (status, body) = call(&app, get(&format!("/games/{game_uuid}/moves"))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"], serde_json::json!([]));
+
+        // Not over yet
+        let (status, _) = call(&app, get(&format!("/games/{game_uuid}/download"))).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        let (status, _) = call(&app, get(&format!("/games/{game_uuid}/bag"))).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+    }
+
+    #[tokio::test]
+    async fn archived_games_can_still_be_downloaded() {
+        let (app, sender) = app_with_sender();
+        let game_uuid = Uuid::new_v4();
+        let mut game = Scrabble::new();
+        game.abandon().unwrap();
+
+        let report = GameReport::Removed {
+            game_uuid,
+            archived: Some(Box::new(game)),
+        };
+        assert!(sender.send(Event::Report(report)).await.is_ok());
+
+        let (status, body) = call(&app, get(&format!("/games/{game_uuid}/download"))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["uuid"], game_uuid.to_string());
+        assert_eq!(body["status"], "abandoned");
+    }
+
+    #[tokio::test]
+    async fn archived_games_can_still_be_audited() {
+        let (app, sender) = app_with_sender();
+        let game_uuid = Uuid::new_v4();
+        let mut game = Scrabble::new();
+        game.abandon().unwrap();
+        let (seed, _) = game.get_revealed_seed().unwrap();
+
+        // What the task of a game sends once the reaper archived it
+        let report = GameReport::Removed {
+            game_uuid,
+            archived: Some(Box::new(game)),
+        };
+        assert!(sender.send(Event::Report(report)).await.is_ok());
+
+        let (status, body) = call(&app, get(&format!("/games/{game_uuid}/bag"))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"]["seed"], seed.to_string());
+        assert!(body["data"]["mismatch"].is_null());
+    }
+
+    #[tokio::test]
+    async fn unknown_things_are_not_found() {
+        let app = app();
+
+        let (status, body) = call(&app, get(&format!("/games/{}", Uuid::new_v4()))).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert!(body["error"].is_string());
+        assert_eq!(body["code"], "game_not_found");
+
+        let (status, _) = call(&app, get(&format!("/players/{}", Uuid::new_v4()))).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+}
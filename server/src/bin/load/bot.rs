@@ -0,0 +1,319 @@
+use crate::report::Recorder;
+use futures_util::future::join_all;
+use futures_util::FutureExt;
+use rust_socketio::asynchronous::{Client, ClientBuilder};
+use rust_socketio::Payload;
+use scrabble::protocol::PROTOCOL_VERSION;
+use scrabble_core::{Board, GameStatus, Play, Tile};
+use serde_json::{json, Value};
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, oneshot};
+
+/// How long the server has to answer a request
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How a game is played by the bots
+#[derive(Clone, Debug)]
+pub struct Plan {
+    /// Address of the server, `http://host:port`
+    pub server: String,
+    /// Bots seated at each game
+    pub players: usize,
+    /// Pause before each move, as a person would take
+    pub think_time: Duration,
+    /// A game still going after this long counts as failed
+    pub game_timeout: Duration,
+}
+
+/// Why a game could not be played to the end
+#[derive(Debug)]
+enum Failure {
+    /// The socket could not connect, or was let go of
+    Connection,
+    /// A request went unanswered
+    Timeout,
+    /// The server refused something the game can't go on without
+    Refused(String),
+    /// The game went on past the game timeout
+    Stalled,
+}
+
+impl Failure {
+    fn code(&self) -> &str {
+        match self {
+            Failure::Connection => "connection_failed",
+            Failure::Timeout => "timeout",
+            Failure::Refused(code) => code,
+            Failure::Stalled => "game_timeout",
+        }
+    }
+}
+
+/// A socket to one namespace of the server, timing every request it sends
+struct Socket {
+    client: Client,
+    events: mpsc::UnboundedReceiver<(String, Value)>,
+    recorder: Recorder,
+}
+
+impl Socket {
+    /// Connects to `namespace`, once the server welcomed the socket
+    async fn connect(plan: &Plan, namespace: &str, recorder: &Recorder) -> Result<Self, Failure> {
+        let (sender, events) = mpsc::unbounded_channel();
+        let started_at = Instant::now();
+
+        let client = ClientBuilder::new(plan.server.as_str())
+            .namespace(namespace)
+            .auth(json!({ "protocol": PROTOCOL_VERSION }))
+            .reconnect(false)
+            .on_any(move |event, payload, _| {
+                sender
+                    .send((event.as_str().to_string(), first_value(payload)))
+                    .ok();
+                async {}.boxed()
+            })
+            .connect()
+            .await
+            .map_err(|_| Failure::Connection)?;
+
+        let mut socket = Socket {
+            client,
+            events,
+            recorder: recorder.clone(),
+        };
+        match tokio::time::timeout(REQUEST_TIMEOUT, socket.expect("welcome")).await {
+            Ok(welcome) => welcome?,
+            Err(_) => {
+                recorder.error(Failure::Timeout.code());
+                return Err(Failure::Timeout);
+            }
+        };
+        recorder.request("connect", started_at.elapsed());
+
+        Ok(socket)
+    }
+
+    /// Sends a request and waits for its acknowledgement, refusals are counted
+    /// by their code
+    async fn request(&self, event: &str, data: Value) -> Result<Value, Failure> {
+        let (reply, answer) = oneshot::channel();
+        let reply = Mutex::new(Some(reply));
+        let payload = match data {
+            Value::Null => Payload::Text(Vec::new()),
+            data => Payload::from(data),
+        };
+        let started_at = Instant::now();
+
+        self.client
+            .emit_with_ack(event, payload, REQUEST_TIMEOUT, move |payload, _| {
+                // Acknowledgements come as the list of their arguments
+                let value = match first_value(payload) {
+                    Value::Array(mut values) if !values.is_empty() => values.swap_remove(0),
+                    value => value,
+                };
+                let sender = reply.lock().unwrap_or_else(PoisonError::into_inner).take();
+                if let Some(sender) = sender {
+                    sender.send(value).ok();
+                }
+                async {}.boxed()
+            })
+            .await
+            .map_err(|_| Failure::Connection)?;
+
+        let value = match tokio::time::timeout(REQUEST_TIMEOUT, answer).await {
+            Ok(Ok(value)) => value,
+            _ => {
+                self.recorder.error(Failure::Timeout.code());
+                return Err(Failure::Timeout);
+            }
+        };
+        self.recorder.request(event, started_at.elapsed());
+
+        match value["code"].as_str() {
+            Some(code) => {
+                self.recorder.error(code);
+                Err(Failure::Refused(code.to_string()))
+            }
+            None => Ok(value),
+        }
+    }
+
+    /// Payload of the next `event`, the events before it are dropped
+    async fn expect(&mut self, event: &str) -> Result<Value, Failure> {
+        loop {
+            match self.events.recv().await {
+                Some((name, data)) if name == event => return Ok(data),
+                Some(_) => continue,
+                None => return Err(Failure::Connection),
+            }
+        }
+    }
+
+    async fn close(self) {
+        self.client.disconnect().await.ok();
+    }
+}
+
+/// Server events and acknowledgements carry a single argument
+fn first_value(payload: Payload) -> Value {
+    match payload {
+        Payload::Text(mut values) if !values.is_empty() => values.swap_remove(0),
+        _ => Value::Null,
+    }
+}
+
+/// A seated player, playing whatever its rack allows whenever it's its turn
+struct Bot {
+    socket: Socket,
+    player_uuid: String,
+    rack: Vec<Tile>,
+    board: Board,
+}
+
+impl Bot {
+    async fn join(
+        plan: &Plan,
+        game_uuid: &str,
+        number: usize,
+        recorder: &Recorder,
+    ) -> Result<Self, Failure> {
+        let socket = Socket::connect(plan, &format!("/game/{game_uuid}"), recorder).await?;
+        let player = socket
+            .request(
+                "register_request",
+                json!({ "game_uuid": game_uuid, "username": format!("Bot {number}") }),
+            )
+            .await?;
+        let player_uuid = player["data"]["id"]
+            .as_str()
+            .ok_or(Failure::Connection)?
+            .to_string();
+
+        Ok(Bot {
+            socket,
+            player_uuid,
+            rack: Vec::new(),
+            board: Board::new(),
+        })
+    }
+
+    /// Follows the game until it's over, true if it was played to the end
+    async fn play(&mut self, plan: &Plan) -> Result<bool, Failure> {
+        loop {
+            let (name, data) = self.socket.events.recv().await.ok_or(Failure::Connection)?;
+
+            match name.as_str() {
+                "get-tiles" => self.rack = serde_json::from_value(data).unwrap_or_default(),
+                "move-played" => {
+                    let turn = &data["data"];
+                    if let Ok(tiles) = serde_json::from_value::<Vec<Play>>(turn["tiles"].clone()) {
+                        self.board.apply(&tiles);
+                    }
+                }
+                "game-status" => {
+                    let status = &data["data"];
+                    match serde_json::from_value(status["status"].clone()) {
+                        Ok(GameStatus::Finished) => return Ok(true),
+                        Ok(GameStatus::Abandoned) => return Ok(false),
+                        Ok(GameStatus::InProgress)
+                            if status["current_player"]["id"] == self.player_uuid.as_str() =>
+                        {
+                            tokio::time::sleep(plan.think_time).await;
+                            self.take_turn().await?;
+                        }
+                        _ => {}
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Plays the longest placement the rack allows, or passes
+    async fn take_turn(&mut self) -> Result<(), Failure> {
+        if let Some(tiles) = self.board.find_move(&self.rack) {
+            match self.socket.request("play", json!({ "tiles": tiles })).await {
+                Ok(_) => {
+                    self.socket.recorder.moved();
+                    return Ok(());
+                }
+                // Counted, the turn is passed instead
+                Err(Failure::Refused(_)) => {}
+                Err(failure) => return Err(failure),
+            }
+        }
+
+        self.socket.request("pass", Value::Null).await?;
+        self.socket.recorder.moved();
+        Ok(())
+    }
+}
+
+/// Creates a game from the lobby, seats a bot for each player and plays it to
+/// the end, counting it as finished or failed
+pub async fn run_game(plan: Plan, number: usize, recorder: Recorder) {
+    let result = tokio::time::timeout(plan.game_timeout, play_game(&plan, number, &recorder))
+        .await
+        .unwrap_or(Err(Failure::Stalled));
+
+    match result {
+        Ok(finished) => recorder.game_over(finished),
+        Err(failure) => {
+            // Refusals and timeouts were counted as they happened
+            if matches!(failure, Failure::Connection | Failure::Stalled) {
+                recorder.error(failure.code());
+            }
+            recorder.game_over(false);
+        }
+    }
+}
+
+async fn play_game(plan: &Plan, number: usize, recorder: &Recorder) -> Result<bool, Failure> {
+    let lobby = Socket::connect(plan, "/", recorder).await?;
+    let created = lobby
+        .request(
+            "create-game",
+            json!({ "settings": { "name": format!("Load {number}") } }),
+        )
+        .await;
+    lobby.close().await;
+
+    let created = created?;
+    let game_uuid = created["data"]["uuid"]
+        .as_str()
+        .ok_or(Failure::Connection)?
+        .to_string();
+
+    let mut bots = Vec::with_capacity(plan.players);
+    for seat in 0..plan.players {
+        match Bot::join(plan, &game_uuid, seat + 1, recorder).await {
+            Ok(bot) => bots.push(bot),
+            Err(failure) => {
+                close_all(bots).await;
+                return Err(failure);
+            }
+        }
+    }
+
+    if let Err(failure) = bots[0].socket.request("start", Value::Null).await {
+        close_all(bots).await;
+        return Err(failure);
+    }
+
+    let results = join_all(bots.iter_mut().map(|x| x.play(plan))).await;
+    close_all(bots).await;
+
+    // Everyone saw the same end, the first failure wins otherwise
+    results
+        .into_iter()
+        .reduce(|a, b| match (a, b) {
+            (Ok(a), Ok(b)) => Ok(a && b),
+            (Err(a), _) | (_, Err(a)) => Err(a),
+        })
+        .unwrap_or(Ok(false))
+}
+
+async fn close_all(bots: Vec<Bot>) {
+    join_all(bots.into_iter().map(|x| x.socket.close())).await;
+}
@@ -0,0 +1,148 @@
+mod bot;
+mod report;
+
+use crate::bot::Plan;
+use crate::report::Recorder;
+use clap::Parser;
+use scrabble::accounts::SharedAccounts;
+use scrabble::config::{Config, StorageBackend};
+use scrabble::manager::Manager;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+/// How often the progress line is printed
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Plays bot games against a server and reports how it held up
+///
+/// Without `--server`, the whole server runs in this process with nothing
+/// saved, so the figures are those of the event loop and the sockets alone.
+#[derive(Parser, Debug)]
+#[command(version)]
+struct Args {
+    /// Address of the server, one is started in-process if left out
+    #[arg(long, env = "SCRABBLE_SERVER")]
+    server: Option<String>,
+    /// Games to play in all
+    #[arg(long, default_value_t = 100)]
+    games: usize,
+    /// Most games played at the same time
+    #[arg(long, default_value_t = 50)]
+    concurrency: usize,
+    /// Bots seated at each game
+    #[arg(long, default_value_t = 2)]
+    players: usize,
+    /// Games started per second, 0 to start them as fast as they are let in
+    #[arg(long, default_value_t = 0.0)]
+    rate: f64,
+    /// Milliseconds each bot waits before moving
+    #[arg(long, default_value_t = 0)]
+    think_time: u64,
+    /// Seconds after which a game still going counts as failed
+    #[arg(long, default_value_t = 300)]
+    game_timeout: u64,
+    /// Size of the in-process server's event queue, see `limits.event_channel_capacity`
+    #[arg(long)]
+    event_channel_capacity: Option<usize>,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+    if args.concurrency == 0 || args.players == 0 {
+        eprintln!("--concurrency and --players must be at least 1");
+        std::process::exit(2);
+    }
+
+    let server = match &args.server {
+        Some(server) => server.clone(),
+        None => serve(&args).await?,
+    };
+    eprintln!(
+        "Playing {} games of {} bots against {server}, {} at a time",
+        args.games, args.players, args.concurrency
+    );
+
+    let plan = Plan {
+        server,
+        players: args.players,
+        think_time: Duration::from_millis(args.think_time),
+        game_timeout: Duration::from_secs(args.game_timeout),
+    };
+    let recorder = Recorder::new();
+
+    let progress = tokio::spawn({
+        let recorder = recorder.clone();
+        async move {
+            let mut interval = tokio::time::interval(PROGRESS_INTERVAL);
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                eprintln!("{}", recorder.progress());
+            }
+        }
+    });
+
+    let slots = Arc::new(Semaphore::new(args.concurrency));
+    let mut pace =
+        (args.rate > 0.0).then(|| tokio::time::interval(Duration::from_secs_f64(1.0 / args.rate)));
+    let mut games = JoinSet::new();
+
+    for number in 1..=args.games {
+        if let Some(pace) = &mut pace {
+            pace.tick().await;
+        }
+        let slot = slots.clone().acquire_owned().await?;
+        let game = bot::run_game(plan.clone(), number, recorder.clone());
+
+        games.spawn(async move {
+            game.await;
+            drop(slot);
+        });
+    }
+    while games.join_next().await.is_some() {}
+    progress.abort();
+
+    let report = recorder.report();
+    println!("{report}");
+
+    if report.has_failures() {
+        std::process::exit(1);
+    }
+
+    Ok(())
+}
+
+/// The whole server on an ephemeral port, with nothing saved anywhere
+async fn serve(args: &Args) -> Result<String, Box<dyn std::error::Error>> {
+    let mut config = Config::default();
+    config.storage.backend = StorageBackend::Memory;
+    config.limits.max_games = config.limits.max_games.max(args.concurrency);
+    if let Some(capacity) = args.event_channel_capacity {
+        config.limits.event_channel_capacity = capacity;
+    }
+    scrabble::config::init(config);
+    let config = scrabble::config::get();
+
+    let manager = Manager::new();
+    let registry = manager.get_registry().clone();
+    let (tx, rx) = scrabble::events::channel(config.limits.event_channel_capacity);
+    let (app, io) = scrabble::server::app(config, &tx, &registry, &SharedAccounts::default())?;
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let address = listener.local_addr()?;
+
+    tokio::spawn(async move { axum::serve(listener, app).await });
+    tokio::spawn(scrabble::server::run(
+        io,
+        manager,
+        rx,
+        tx,
+        registry,
+        std::future::pending(),
+    ));
+
+    Ok(format!("http://{address}"))
+}
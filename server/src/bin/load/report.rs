@@ -0,0 +1,221 @@
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+/// Everything the bots measured, shared by all of them
+#[derive(Clone)]
+pub struct Recorder(Arc<Mutex<Stats>>);
+
+struct Stats {
+    started_at: Instant,
+    /// Time to the acknowledgement, by request
+    latencies: BTreeMap<String, Vec<Duration>>,
+    /// Refused requests by error code, and failures of the bots themselves
+    errors: BTreeMap<String, usize>,
+    games_finished: usize,
+    games_failed: usize,
+    moves: usize,
+}
+
+impl Recorder {
+    pub fn new() -> Self {
+        Recorder(Arc::new(Mutex::new(Stats {
+            started_at: Instant::now(),
+            latencies: BTreeMap::new(),
+            errors: BTreeMap::new(),
+            games_finished: 0,
+            games_failed: 0,
+            moves: 0,
+        })))
+    }
+
+    fn with<T>(&self, f: impl FnOnce(&mut Stats) -> T) -> T {
+        f(&mut self.0.lock().unwrap_or_else(PoisonError::into_inner))
+    }
+
+    pub fn request(&self, event: &str, latency: Duration) {
+        self.with(|x| {
+            x.latencies
+                .entry(event.to_string())
+                .or_default()
+                .push(latency)
+        });
+    }
+
+    pub fn error(&self, code: &str) {
+        self.with(|x| *x.errors.entry(code.to_string()).or_default() += 1);
+    }
+
+    pub fn moved(&self) {
+        self.with(|x| x.moves += 1);
+    }
+
+    pub fn game_over(&self, finished: bool) {
+        self.with(|x| match finished {
+            true => x.games_finished += 1,
+            false => x.games_failed += 1,
+        });
+    }
+
+    /// One line of how far along the run is
+    pub fn progress(&self) -> String {
+        self.with(|x| {
+            let elapsed = x.started_at.elapsed().as_secs_f64();
+            format!(
+                "{:>6.1}s  {} games finished, {} failed, {:.0} moves/s",
+                elapsed,
+                x.games_finished,
+                x.games_failed,
+                x.moves as f64 / elapsed
+            )
+        })
+    }
+
+    pub fn report(&self) -> Report {
+        self.with(|x| {
+            let elapsed = x.started_at.elapsed();
+            let requests = x
+                .latencies
+                .iter()
+                .map(|(event, latencies)| (event.clone(), Percentiles::of(latencies)))
+                .collect();
+
+            Report {
+                elapsed,
+                games_finished: x.games_finished,
+                games_failed: x.games_failed,
+                moves: x.moves,
+                requests,
+                errors: x.errors.clone(),
+            }
+        })
+    }
+}
+
+/// How long requests took to be acknowledged
+pub struct Percentiles {
+    count: usize,
+    p50: Duration,
+    p90: Duration,
+    p99: Duration,
+    max: Duration,
+}
+
+impl Percentiles {
+    fn of(latencies: &[Duration]) -> Self {
+        let mut sorted = latencies.to_vec();
+        sorted.sort_unstable();
+
+        // Nearest rank, so every percentile is a latency that was measured
+        let at = |percent: usize| {
+            let rank = (sorted.len() * percent).div_ceil(100).max(1);
+            sorted.get(rank - 1).copied().unwrap_or_default()
+        };
+
+        Percentiles {
+            count: sorted.len(),
+            p50: at(50),
+            p90: at(90),
+            p99: at(99),
+            max: sorted.last().copied().unwrap_or_default(),
+        }
+    }
+}
+
+/// What the run amounted to, printed once it's over
+pub struct Report {
+    elapsed: Duration,
+    games_finished: usize,
+    games_failed: usize,
+    moves: usize,
+    requests: BTreeMap<String, Percentiles>,
+    errors: BTreeMap<String, usize>,
+}
+
+impl Report {
+    /// Something went wrong for the bots, the exit code says so
+    pub fn has_failures(&self) -> bool {
+        self.games_failed > 0
+    }
+}
+
+impl Display for Report {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let seconds = self.elapsed.as_secs_f64();
+        let requests: usize = self.requests.values().map(|x| x.count).sum();
+
+        writeln!(f, "Ran for {seconds:.1}s")?;
+        writeln!(
+            f,
+            "Games     {} finished, {} failed, {:.1}/s",
+            self.games_finished,
+            self.games_failed,
+            self.games_finished as f64 / seconds
+        )?;
+        writeln!(
+            f,
+            "Moves     {}, {:.1}/s",
+            self.moves,
+            self.moves as f64 / seconds
+        )?;
+        writeln!(
+            f,
+            "Requests  {requests}, {:.1}/s",
+            requests as f64 / seconds
+        )?;
+        writeln!(f)?;
+        writeln!(
+            f,
+            "{:<18}{:>8}{:>10}{:>10}{:>10}{:>10}",
+            "request", "count", "p50", "p90", "p99", "max"
+        )?;
+        for (event, x) in &self.requests {
+            writeln!(
+                f,
+                "{:<18}{:>8}{:>10}{:>10}{:>10}{:>10}",
+                event,
+                x.count,
+                millis(x.p50),
+                millis(x.p90),
+                millis(x.p99),
+                millis(x.max)
+            )?;
+        }
+
+        if !self.errors.is_empty() {
+            writeln!(f)?;
+            writeln!(f, "{:<26}{:>8}", "error", "count")?;
+            for (code, count) in &self.errors {
+                writeln!(f, "{code:<26}{count:>8}")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn millis(duration: Duration) -> String {
+    format!("{:.2}ms", duration.as_secs_f64() * 1000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percentiles_are_measured_latencies() {
+        let latencies: Vec<Duration> = (1..=100).rev().map(Duration::from_millis).collect();
+        let percentiles = Percentiles::of(&latencies);
+
+        assert_eq!(percentiles.count, 100);
+        assert_eq!(percentiles.p50, Duration::from_millis(50));
+        assert_eq!(percentiles.p90, Duration::from_millis(90));
+        assert_eq!(percentiles.p99, Duration::from_millis(99));
+        assert_eq!(percentiles.max, Duration::from_millis(100));
+
+        let single = Percentiles::of(&[Duration::from_millis(3)]);
+        assert_eq!(single.p50, Duration::from_millis(3));
+        assert_eq!(single.p99, Duration::from_millis(3));
+    }
+}
@@ -0,0 +1,803 @@
+use crate::connection::{Incoming, Source};
+use crate::notation::{self, Direction};
+use crate::wire::{
+    CreatedGame, GamePage, GameSummary, Maintenance, Player, Presence, Reply, StatusUpdate,
+    TimeControl, Turn,
+};
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use scrabble_core::{Board, Play, Tile, BOARD_SIZE, CENTER};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// What the main loop does on behalf of the app
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Request {
+        source: Source,
+        event: &'static str,
+        data: Value,
+    },
+    /// Opens a socket to the namespace of a game
+    Join(Uuid),
+    /// Closes the socket of the game, the server holds the seat for a while
+    Leave,
+    Quit,
+}
+
+impl Command {
+    fn lobby(event: &'static str, data: Value) -> Self {
+        Command::Request {
+            source: Source::Lobby,
+            event,
+            data,
+        }
+    }
+
+    fn game(game_uuid: Uuid, event: &'static str, data: Value) -> Self {
+        Command::Request {
+            source: Source::Game(game_uuid),
+            event,
+            data,
+        }
+    }
+}
+
+/// Thinking time of each player, kept by the client since the server has no clock
+#[derive(Debug, Default)]
+pub struct Clock {
+    /// Who is thinking, and since when
+    turn: Option<(Uuid, Instant)>,
+    used: HashMap<Uuid, Duration>,
+    /// Turns each player is done with, for the increments
+    turns: HashMap<Uuid, u32>,
+}
+
+impl Clock {
+    /// Starts the clock of `player`, or stops every clock if there is none
+    pub fn switch(&mut self, player: Option<Uuid>, now: Instant) {
+        let previous = self.turn.map(|x| x.0);
+        if previous == player {
+            return;
+        }
+
+        if let Some((previous, since)) = self.turn.take() {
+            *self.used.entry(previous).or_default() += now - since;
+
+            // A paused game stops the clock without ending the turn
+            if player.is_some() {
+                *self.turns.entry(previous).or_default() += 1;
+            }
+        }
+
+        self.turn = player.map(|x| (x, now));
+    }
+
+    /// How long the current turn has lasted
+    pub fn turn_time(&self, now: Instant) -> Option<Duration> {
+        self.turn.map(|(_, since)| now - since)
+    }
+
+    pub fn used(&self, player: &Uuid, now: Instant) -> Duration {
+        let running = match self.turn {
+            Some((x, since)) if x == *player => now - since,
+            _ => Duration::ZERO,
+        };
+
+        self.used.get(player).copied().unwrap_or_default() + running
+    }
+
+    /// Time left under a time control, which the server does not enforce
+    pub fn remaining(&self, player: &Uuid, time_control: &TimeControl, now: Instant) -> Duration {
+        let turns = self.turns.get(player).copied().unwrap_or_default();
+        let allowed = Duration::from_secs(
+            time_control.initial_seconds + time_control.increment_seconds * turns as u64,
+        );
+
+        allowed.saturating_sub(self.used(player, now))
+    }
+}
+
+/// The game the player sits at
+#[derive(Debug)]
+pub struct GameView {
+    pub uuid: Uuid,
+    pub name: String,
+    pub time_control: Option<TimeControl>,
+    /// Known once registered
+    pub me: Option<Uuid>,
+    pub players: Vec<Player>,
+    pub status: String,
+    pub current_player: Option<Uuid>,
+    pub scores: HashMap<Uuid, i32>,
+    pub board: Board,
+    pub rack: Vec<Tile>,
+    /// Tiles put down with the keyboard and not sent yet
+    pub pending: Vec<Play>,
+    pub cursor: (usize, usize),
+    pub direction: Direction,
+    pub clock: Clock,
+}
+
+impl GameView {
+    fn new(uuid: Uuid, name: String, time_control: Option<TimeControl>) -> Self {
+        GameView {
+            uuid,
+            name,
+            time_control,
+            me: None,
+            players: Vec::new(),
+            status: String::from("waiting"),
+            current_player: None,
+            scores: HashMap::new(),
+            board: Board::default(),
+            rack: Vec::new(),
+            pending: Vec::new(),
+            cursor: CENTER,
+            direction: Direction::default(),
+            clock: Clock::default(),
+        }
+    }
+
+    pub fn player_name(&self, player_uuid: &Uuid) -> &str {
+        self.players
+            .iter()
+            .find(|x| x.id == *player_uuid)
+            .map_or("Someone", |x| &x.name)
+    }
+
+    pub fn pending_at(&self, x: usize, y: usize) -> Option<Tile> {
+        self.pending
+            .iter()
+            .find(|p| (p.x, p.y) == (x, y))
+            .map(|p| p.tile)
+    }
+
+    /// The rack without the tiles already put down
+    pub fn available(&self) -> Vec<Tile> {
+        let mut rack = self.rack.clone();
+
+        for play in &self.pending {
+            if let Some(index) = rack.iter().position(|x| *x == play.tile) {
+                rack.remove(index);
+            }
+        }
+
+        rack
+    }
+
+    fn is_free(&self, (x, y): (usize, usize)) -> bool {
+        self.board.get(x, y).is_none() && self.pending_at(x, y).is_none()
+    }
+
+    fn put_tile(&mut self, letter: char) -> Result<(), String> {
+        if !self.is_free(self.cursor) {
+            return Err(String::from("This square is taken"));
+        }
+
+        let tile = self
+            .available()
+            .into_iter()
+            .find(|x| x.0 == letter)
+            .ok_or_else(|| notation::NotationError::NotInRack(letter).to_string())?;
+
+        let (x, y) = self.cursor;
+        self.pending.push(Play { tile, x, y });
+
+        // Skips over the tiles the word goes through
+        let mut next = self.direction.next(self.cursor);
+        while let Some(square) = next.filter(|x| !self.is_free(*x)) {
+            next = self.direction.next(square);
+        }
+        if let Some(square) = next {
+            self.cursor = square;
+        }
+
+        Ok(())
+    }
+
+    fn take_back_tile(&mut self) {
+        if let Some(play) = self.pending.pop() {
+            self.cursor = (play.x, play.y);
+        }
+    }
+
+    fn move_cursor(&mut self, dx: isize, dy: isize) {
+        let (x, y) = self.cursor;
+        let clamp =
+            |value: usize, delta: isize| value.saturating_add_signed(delta).min(BOARD_SIZE - 1);
+
+        self.cursor = (clamp(x, dx), clamp(y, dy));
+    }
+}
+
+pub struct App {
+    /// Name the player registers under
+    pub name: String,
+    pub games: Vec<GameSummary>,
+    pub selected: usize,
+    pub game: Option<GameView>,
+    /// Command line, opened with `:`
+    pub input: Option<String>,
+    /// Last thing worth telling the player
+    pub notice: String,
+}
+
+impl App {
+    pub fn new(name: String) -> Self {
+        App {
+            name,
+            games: Vec::new(),
+            selected: 0,
+            game: None,
+            input: None,
+            notice: String::from("Connected, press Enter to join a game or c to create one"),
+        }
+    }
+
+    /// What to ask for once the lobby is connected
+    pub fn start(&self) -> Vec<Command> {
+        vec![Command::lobby("list-games", json!({}))]
+    }
+
+    pub fn handle_incoming(&mut self, incoming: Incoming, now: Instant) -> Vec<Command> {
+        let source = match &incoming {
+            Incoming::Event { source, .. }
+            | Incoming::Reply { source, .. }
+            | Incoming::Closed { source, .. } => *source,
+        };
+
+        // Late news from a game the player already left
+        if let Source::Game(game_uuid) = source {
+            if self.game.as_ref().map(|x| x.uuid) != Some(game_uuid) {
+                return Vec::new();
+            }
+        }
+
+        match incoming {
+            Incoming::Event { source, name, data } => self.handle_event(source, &name, data, now),
+            Incoming::Reply {
+                source,
+                request,
+                reply,
+            } => self.handle_reply(source, &request, reply),
+            Incoming::Closed {
+                source: Source::Lobby,
+                reason,
+            } => {
+                self.notice = format!("Lobby: {reason}");
+                Vec::new()
+            }
+            Incoming::Closed {
+                source: Source::Game(_),
+                reason,
+            } => {
+                self.game = None;
+                self.notice = reason;
+                vec![Command::Leave]
+            }
+        }
+    }
+
+    fn handle_event(
+        &mut self,
+        source: Source,
+        name: &str,
+        data: Value,
+        now: Instant,
+    ) -> Vec<Command> {
+        if name == "maintenance" {
+            if let Ok(maintenance) = reply(data).into_result::<Maintenance>() {
+                self.notice = maintenance.message;
+            }
+            return Vec::new();
+        }
+
+        match source {
+            Source::Lobby => {
+                self.handle_lobby_event(name, data);
+                Vec::new()
+            }
+            Source::Game(_) => self.handle_game_event(name, data, now),
+        }
+    }
+
+    fn handle_lobby_event(&mut self, name: &str, data: Value) {
+        match name {
+            "game-added" | "game-updated" => {
+                if let Ok(summary) = reply(data).into_result::<GameSummary>() {
+                    match self.games.iter_mut().find(|x| x.uuid == summary.uuid) {
+                        Some(game) => *game = summary,
+                        None => self.games.insert(0, summary),
+                    }
+                }
+            }
+            "game-removed" => {
+                if let Ok(uuid) = reply(data).into_result::<Uuid>() {
+                    self.games.retain(|x| x.uuid != uuid);
+                    self.selected = self.selected.min(self.games.len().saturating_sub(1));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_game_event(&mut self, name: &str, data: Value, now: Instant) -> Vec<Command> {
+        let Some(game) = self.game.as_mut() else {
+            return Vec::new();
+        };
+
+        match name {
+            // The socket was let in, it can take a seat
+            "welcome" => {
+                let data = json!({"game_uuid": game.uuid, "username": self.name});
+                return vec![Command::game(game.uuid, "register_request", data)];
+            }
+            "players-list" => {
+                if let Ok(players) = reply(data).into_result() {
+                    game.players = players;
+                }
+            }
+            "game-status" => {
+                if let Ok(update) = reply(data).into_result::<StatusUpdate>() {
+                    let running = update.status == "in-progress";
+                    game.current_player = update.current_player.map(|x| x.id);
+                    game.clock
+                        .switch(game.current_player.filter(|_| running), now);
+
+                    if game.status != update.status {
+                        self.notice = format!("The game is {}", update.status.replace('-', " "));
+                    }
+                    game.status = update.status;
+                    game.scores = update.scores;
+                }
+            }
+            // Sent as is, without the usual envelope
+            "get-tiles" => {
+                if let Ok(rack) = serde_json::from_value(data) {
+                    game.rack = rack;
+                    game.pending.clear();
+                }
+            }
+            "move-played" => {
+                if let Ok(turn) = reply(data).into_result::<Turn>() {
+                    game.board.apply(&turn.tiles);
+
+                    if Some(turn.player_uuid) == game.me {
+                        game.pending.clear();
+                    }
+                    self.notice = format!(
+                        "{} scored {}",
+                        game.player_name(&turn.player_uuid),
+                        turn.score
+                    );
+                }
+            }
+            "turn-passed" | "player-forfeited" => {
+                if let Ok(player_uuid) = reply(data).into_result::<Uuid>() {
+                    let player = game.player_name(&player_uuid);
+                    self.notice = match name {
+                        "turn-passed" => format!("{player} is away, their turn was passed"),
+                        _ => format!("{player} stayed away too long and forfeited"),
+                    };
+                }
+            }
+            "presence" => {
+                if let Ok(presence) = reply(data).into_result::<Presence>() {
+                    let verb = match presence.connected {
+                        true => "came back",
+                        false => "lost their connection",
+                    };
+                    self.notice = format!("{} {verb}", presence.player.name);
+                }
+            }
+            "ratings-updated" => self.notice = String::from("Ratings were updated"),
+            "game-closed" => {
+                self.game = None;
+                self.notice = String::from("The game was closed");
+                return vec![Command::Leave, Command::lobby("list-games", json!({}))];
+            }
+            _ => {}
+        }
+
+        Vec::new()
+    }
+
+    fn handle_reply(&mut self, source: Source, request: &str, reply: Reply) -> Vec<Command> {
+        match (source, request) {
+            (Source::Lobby, "list-games") => match reply.into_result::<GamePage>() {
+                Ok(page) => {
+                    self.games = page.games;
+                    self.selected = self.selected.min(self.games.len().saturating_sub(1));
+                }
+                Err(error) => self.notice = error,
+            },
+            (Source::Lobby, "create-game") => match reply.into_result::<CreatedGame>() {
+                Ok(created) => return self.join(created.uuid, self.game_name(), None),
+                Err(error) => self.notice = error,
+            },
+            (Source::Game(_), "register_request") => match reply.into_result::<Player>() {
+                Ok(player) => {
+                    if let Some(game) = self.game.as_mut() {
+                        game.me = Some(player.id);
+                        self.notice =
+                            format!("Seated in {}, type :start once everyone is in", game.name);
+                    }
+                }
+                Err(error) => {
+                    self.game = None;
+                    self.notice = error;
+                    return vec![Command::Leave];
+                }
+            },
+            // Moves are shown when the server tells everyone about them
+            (Source::Game(_), "play") => {
+                if let Some(error) = reply.error {
+                    self.notice = error;
+                }
+            }
+            // Notices are sent as text
+            _ => match (reply.error, reply.data) {
+                (Some(error), _) => self.notice = error,
+                (None, Value::String(text)) => self.notice = text,
+                _ => {}
+            },
+        }
+
+        Vec::new()
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> Vec<Command> {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            return vec![Command::Quit];
+        }
+
+        if let Some(input) = self.input.as_mut() {
+            match key.code {
+                KeyCode::Enter => {
+                    let line = self.input.take().unwrap_or_default();
+                    return self.run(&line);
+                }
+                KeyCode::Esc => self.input = None,
+                KeyCode::Backspace => {
+                    input.pop();
+                }
+                KeyCode::Char(x) => input.push(x),
+                _ => {}
+            }
+            return Vec::new();
+        }
+
+        if key.code == KeyCode::Char(':') {
+            self.input = Some(String::new());
+            return Vec::new();
+        }
+
+        match self.game.is_some() {
+            true => self.handle_game_key(key),
+            false => self.handle_lobby_key(key),
+        }
+    }
+
+    fn handle_lobby_key(&mut self, key: KeyEvent) -> Vec<Command> {
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.selected = (self.selected + 1).min(self.games.len().saturating_sub(1))
+            }
+            KeyCode::Enter => {
+                if let Some(summary) = self.games.get(self.selected) {
+                    let (uuid, name) = (summary.uuid, summary.name.clone());
+                    return self.join(uuid, name, summary.time_control);
+                }
+            }
+            KeyCode::Char('c') => return self.create_game(),
+            KeyCode::Char('r') => return self.start(),
+            KeyCode::Char('q') => return vec![Command::Quit],
+            _ => {}
+        }
+
+        Vec::new()
+    }
+
+    fn handle_game_key(&mut self, key: KeyEvent) -> Vec<Command> {
+        let Some(game) = self.game.as_mut() else {
+            return Vec::new();
+        };
+
+        match key.code {
+            KeyCode::Up => game.move_cursor(0, -1),
+            KeyCode::Down => game.move_cursor(0, 1),
+            KeyCode::Left => game.move_cursor(-1, 0),
+            KeyCode::Right => game.move_cursor(1, 0),
+            KeyCode::Tab => game.direction = game.direction.toggle(),
+            KeyCode::Backspace => game.take_back_tile(),
+            KeyCode::Esc => game.pending.clear(),
+            KeyCode::Enter if !game.pending.is_empty() => {
+                let data = json!({"tiles": game.pending});
+                return vec![Command::game(game.uuid, "play", data)];
+            }
+            KeyCode::Char(x) if x.is_ascii_alphabetic() => {
+                if let Err(error) = game.put_tile(x.to_ascii_uppercase()) {
+                    self.notice = error;
+                }
+            }
+            _ => {}
+        }
+
+        Vec::new()
+    }
+
+    /// Runs what was typed on the command line
+    fn run(&mut self, line: &str) -> Vec<Command> {
+        let line = line.trim();
+
+        match (line, self.game.as_ref()) {
+            ("", _) => Vec::new(),
+            ("q" | "quit", _) => vec![Command::Quit],
+            ("refresh", None) => self.start(),
+            ("create", None) => self.create_game(),
+            ("leave", Some(_)) => {
+                self.game = None;
+                self.notice = String::from("Back in the lobby, your seat is held for a while");
+                vec![Command::Leave, Command::lobby("list-games", json!({}))]
+            }
+            ("start", Some(game)) => vec![Command::game(game.uuid, "start", Value::Null)],
+            ("pass", Some(game)) => vec![Command::game(game.uuid, "pass", Value::Null)],
+            ("pause", Some(game)) => vec![Command::game(game.uuid, "pause", Value::Null)],
+            ("resume", Some(game)) => vec![Command::game(game.uuid, "resume", Value::Null)],
+            (line, Some(game)) => match notation::parse_move(line, &game.board, &game.rack) {
+                Ok(tiles) => {
+                    vec![Command::game(game.uuid, "play", json!({"tiles": tiles}))]
+                }
+                Err(error) => {
+                    self.notice = error.to_string();
+                    Vec::new()
+                }
+            },
+            (line, None) => {
+                self.notice = format!("Unknown command: {line}");
+                Vec::new()
+            }
+        }
+    }
+
+    fn join(
+        &mut self,
+        uuid: Uuid,
+        name: String,
+        time_control: Option<TimeControl>,
+    ) -> Vec<Command> {
+        self.notice = format!("Joining {name}…");
+        self.game = Some(GameView::new(uuid, name, time_control));
+
+        vec![Command::Join(uuid)]
+    }
+
+    fn create_game(&self) -> Vec<Command> {
+        let data = json!({"settings": {"name": self.game_name()}});
+        vec![Command::lobby("create-game", data)]
+    }
+
+    fn game_name(&self) -> String {
+        format!("{}'s game", self.name)
+    }
+}
+
+/// Events without the usual envelope are read as empty replies
+fn reply(data: Value) -> Reply {
+    serde_json::from_value(data).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::from(code)
+    }
+
+    fn event(app: &App, name: &str, data: Value) -> Incoming {
+        Incoming::Event {
+            source: Source::Game(app.game.as_ref().unwrap().uuid),
+            name: name.to_string(),
+            data,
+        }
+    }
+
+    fn seated_app() -> (App, Uuid) {
+        let mut app = App::new(String::from("alice"));
+        let me = Uuid::new_v4();
+        app.join(Uuid::new_v4(), String::from("Test"), None);
+        app.game.as_mut().unwrap().me = Some(me);
+
+        (app, me)
+    }
+
+    fn current_game(app: &App) -> Uuid {
+        app.game.as_ref().unwrap().uuid
+    }
+
+    #[test]
+    fn welcome_takes_a_seat() {
+        let mut app = App::new(String::from("alice"));
+        let game_uuid = Uuid::new_v4();
+        assert_eq!(
+            app.join(game_uuid, String::from("Test"), None),
+            [Command::Join(game_uuid)]
+        );
+
+        let commands = app.handle_incoming(event(&app, "welcome", json!({})), Instant::now());
+        assert_eq!(
+            commands,
+            [Command::game(
+                game_uuid,
+                "register_request",
+                json!({"game_uuid": game_uuid, "username": "alice"})
+            )]
+        );
+
+        // A game left earlier has nothing more to say
+        let stale = Incoming::Closed {
+            source: Source::Game(Uuid::new_v4()),
+            reason: String::from("Disconnected from the server"),
+        };
+        assert!(app.handle_incoming(stale, Instant::now()).is_empty());
+        assert!(app.game.is_some());
+    }
+
+    #[test]
+    fn tiles_are_put_down_with_the_keyboard() {
+        let (mut app, _) = seated_app();
+        let now = Instant::now();
+        app.handle_incoming(
+            event(&app, "get-tiles", json!([["C", 3], ["A", 1], ["T", 1]])),
+            now,
+        );
+
+        for letter in ['c', 'a', 't'] {
+            app.handle_key(key(KeyCode::Char(letter)));
+        }
+        // Not on the rack anymore
+        app.handle_key(key(KeyCode::Char('a')));
+        assert_eq!(app.notice, "No A left on your rack");
+
+        let game = app.game.as_ref().unwrap();
+        assert!(game.available().is_empty());
+        assert_eq!(game.pending_at(9, 7), Some(Tile('T', 1)));
+        assert_eq!(game.cursor, (10, 7));
+
+        app.handle_key(key(KeyCode::Backspace));
+        assert_eq!(app.game.as_ref().unwrap().cursor, (9, 7));
+
+        let commands = app.handle_key(key(KeyCode::Enter));
+        assert_eq!(
+            commands,
+            [Command::game(
+                current_game(&app),
+                "play",
+                json!({"tiles": [
+                    {"tile": ["C", 3], "x": 7, "y": 7},
+                    {"tile": ["A", 1], "x": 8, "y": 7},
+                ]})
+            )]
+        );
+    }
+
+    #[test]
+    fn played_moves_land_on_the_board() {
+        let (mut app, me) = seated_app();
+        let now = Instant::now();
+        app.handle_incoming(event(&app, "get-tiles", json!([["A", 1]])), now);
+        app.handle_key(key(KeyCode::Char('a')));
+
+        let turn = json!({"data": {
+            "player_uuid": me,
+            "type": "play",
+            "tiles": [{"tile": ["A", 1], "x": 7, "y": 7}],
+            "words": [],
+            "bingo": false,
+            "score": 2,
+            "played_at": 0,
+        }});
+        app.handle_incoming(event(&app, "move-played", turn), now);
+
+        let game = app.game.as_ref().unwrap();
+        assert_eq!(game.board.get(7, 7), Some(Tile('A', 1)));
+        assert!(game.pending.is_empty());
+        assert_eq!(app.notice, "Someone scored 2");
+    }
+
+    #[test]
+    fn moves_can_be_typed_in_notation() {
+        let (mut app, _) = seated_app();
+        app.handle_incoming(
+            event(&app, "get-tiles", json!([["O", 1], ["X", 8]])),
+            Instant::now(),
+        );
+
+        app.handle_key(key(KeyCode::Char(':')));
+        for letter in "h8 ox".chars() {
+            app.handle_key(key(KeyCode::Char(letter)));
+        }
+        let commands = app.handle_key(key(KeyCode::Enter));
+
+        assert_eq!(
+            commands,
+            [Command::game(
+                current_game(&app),
+                "play",
+                json!({"tiles": [
+                    {"tile": ["O", 1], "x": 7, "y": 7},
+                    {"tile": ["X", 8], "x": 7, "y": 8},
+                ]})
+            )]
+        );
+        assert!(app.input.is_none());
+    }
+
+    #[test]
+    fn clocks_run_for_the_current_player_only() {
+        let (alice, bob) = (Uuid::new_v4(), Uuid::new_v4());
+        let time_control = TimeControl {
+            initial_seconds: 60,
+            increment_seconds: 5,
+        };
+        let start = Instant::now();
+        let mut clock = Clock::default();
+
+        clock.switch(Some(alice), start);
+        clock.switch(Some(bob), start + Duration::from_secs(10));
+        // Paused for a while
+        clock.switch(None, start + Duration::from_secs(15));
+        clock.switch(Some(bob), start + Duration::from_secs(100));
+
+        let now = start + Duration::from_secs(102);
+        assert_eq!(clock.used(&alice, now), Duration::from_secs(10));
+        assert_eq!(clock.used(&bob, now), Duration::from_secs(7));
+        assert_eq!(clock.turn_time(now), Some(Duration::from_secs(2)));
+        assert_eq!(
+            clock.remaining(&alice, &time_control, now),
+            Duration::from_secs(55)
+        );
+        assert_eq!(
+            clock.remaining(&bob, &time_control, now),
+            Duration::from_secs(53)
+        );
+    }
+
+    #[test]
+    fn the_lobby_list_follows_the_server() {
+        let mut app = App::new(String::from("alice"));
+        let summary = |players| {
+            json!({"data": {
+                "uuid": Uuid::nil(),
+                "name": "Test",
+                "host": "bob",
+                "players": players,
+                "max_players": 4,
+                "status": "waiting",
+                "variant": "classic",
+                "time_control": null,
+                "private": false,
+                "rated": false,
+                "created_at": 0,
+            }})
+        };
+        let lobby_event = |name: &str, data| Incoming::Event {
+            source: Source::Lobby,
+            name: name.to_string(),
+            data,
+        };
+
+        app.handle_incoming(lobby_event("game-added", summary(1)), Instant::now());
+        app.handle_incoming(lobby_event("game-updated", summary(2)), Instant::now());
+        assert_eq!(app.games.len(), 1);
+        assert_eq!(app.games[0].players, 2);
+
+        let removed = json!({"data": Uuid::nil()});
+        app.handle_incoming(lobby_event("game-removed", removed), Instant::now());
+        assert!(app.games.is_empty());
+    }
+}
@@ -0,0 +1,196 @@
+use crate::wire::{Reply, PROTOCOL_VERSION};
+use futures_util::FutureExt;
+use rust_socketio::asynchronous::{Client, ClientBuilder};
+use rust_socketio::{Event, Payload};
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+use tokio::sync::mpsc::UnboundedSender;
+use tokio::sync::oneshot;
+use uuid::Uuid;
+
+/// How long the server has to answer a request
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Which socket something came from, or goes to
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Source {
+    Lobby,
+    Game(Uuid),
+}
+
+/// What the server sent, handed over to the app as is
+#[derive(Debug)]
+pub enum Incoming {
+    Event {
+        source: Source,
+        name: String,
+        data: Value,
+    },
+    Reply {
+        source: Source,
+        request: String,
+        reply: Reply,
+    },
+    /// The socket went away, or the server turned it down
+    Closed { source: Source, reason: String },
+}
+
+/// A socket to one namespace of the server
+pub struct Connection {
+    client: Client,
+    source: Source,
+    sender: UnboundedSender<Incoming>,
+}
+
+impl Connection {
+    /// Connects to the namespace of `source`, once the server welcomed the socket
+    ///
+    /// Requests sent before that would be lost, and a server speaking another
+    /// version of the protocol turns the socket down instead.
+    pub async fn open(
+        server: &str,
+        source: Source,
+        sender: UnboundedSender<Incoming>,
+    ) -> Result<Connection, String> {
+        let namespace = match source {
+            Source::Lobby => String::from("/"),
+            Source::Game(game_uuid) => format!("/game/{game_uuid}"),
+        };
+        let (welcomed, welcome) = oneshot::channel();
+        let welcomed = Arc::new(Mutex::new(Some(welcomed)));
+        let refused = welcomed.clone();
+        let events = sender.clone();
+        let errors = sender.clone();
+        let closes = sender.clone();
+
+        let client = ClientBuilder::new(server)
+            .namespace(namespace)
+            .auth(json!({ "protocol": PROTOCOL_VERSION }))
+            .reconnect(false)
+            .on("welcome", move |_, _| {
+                settle(&welcomed, Ok(()));
+                async {}.boxed()
+            })
+            .on_any(move |event, payload, _| {
+                let incoming = Incoming::Event {
+                    source,
+                    name: event.as_str().to_string(),
+                    data: first_value(payload),
+                };
+                events.send(incoming).ok();
+                async {}.boxed()
+            })
+            // Also what the server sends a socket it lets go of
+            .on(Event::Error, move |payload, _| {
+                let reason = error_reason(first_value(payload));
+
+                if !settle(&refused, Err(reason.clone())) {
+                    errors.send(Incoming::Closed { source, reason }).ok();
+                }
+                async {}.boxed()
+            })
+            .on(Event::Close, move |_, _| {
+                let reason = String::from("Disconnected from the server");
+                closes.send(Incoming::Closed { source, reason }).ok();
+                async {}.boxed()
+            })
+            .connect()
+            .await
+            .map_err(|x| x.to_string())?;
+
+        match tokio::time::timeout(REQUEST_TIMEOUT, welcome).await {
+            Ok(Ok(Ok(()))) => Ok(Connection {
+                client,
+                source,
+                sender,
+            }),
+            Ok(Ok(Err(reason))) => Err(reason),
+            _ => Err(String::from("The server did not answer")),
+        }
+    }
+
+    /// Sends a request, its answer comes back as an `Incoming::Reply`
+    pub async fn request(&self, event: &str, data: Value) {
+        let sender = self.sender.clone();
+        let source = self.source;
+        let request = event.to_string();
+
+        let on_reply = move |payload, _| {
+            // Acknowledgements come as the list of their arguments
+            let reply = match first_value(payload) {
+                Value::Array(mut values) if !values.is_empty() => values.swap_remove(0),
+                value => value,
+            };
+            let reply = serde_json::from_value(reply).unwrap_or_default();
+            let incoming = Incoming::Reply {
+                source,
+                request: request.clone(),
+                reply,
+            };
+            sender.send(incoming).ok();
+            async {}.boxed()
+        };
+
+        let result = self
+            .client
+            .emit_with_ack(event, payload_of(data), REQUEST_TIMEOUT, on_reply)
+            .await;
+
+        if let Err(error) = result {
+            let reason = error.to_string();
+            self.sender.send(Incoming::Closed { source, reason }).ok();
+        }
+    }
+
+    pub async fn close(self) {
+        self.client.disconnect().await.ok();
+    }
+}
+
+type Welcome = Arc<Mutex<Option<oneshot::Sender<Result<(), String>>>>>;
+
+/// Tells `open` how connecting went, false if it was already told
+fn settle(welcome: &Welcome, result: Result<(), String>) -> bool {
+    let sender = welcome
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .take();
+
+    match sender {
+        Some(sender) => sender.send(result).is_ok(),
+        None => false,
+    }
+}
+
+/// Text of an error event, or of the reason a connection was refused
+fn error_reason(value: Value) -> String {
+    match value {
+        // Refusals are written `Received an ConnectError frame: {"message": ...}`
+        Value::String(text) => text
+            .split_once(": ")
+            .and_then(|(_, x)| serde_json::from_str::<Value>(x).ok())
+            .and_then(|x| x.get("message")?.as_str().map(String::from))
+            .unwrap_or(text),
+        value => serde_json::from_value::<Reply>(value)
+            .ok()
+            .and_then(|x| x.error)
+            .unwrap_or_default(),
+    }
+}
+
+/// Requests without a payload are sent without any argument
+fn payload_of(data: Value) -> Payload {
+    match data {
+        Value::Null => Payload::Text(Vec::new()),
+        data => Payload::from(data),
+    }
+}
+
+/// Server events and acknowledgements carry a single argument
+fn first_value(payload: Payload) -> Value {
+    match payload {
+        Payload::Text(mut values) if !values.is_empty() => values.swap_remove(0),
+        _ => Value::Null,
+    }
+}
@@ -0,0 +1,132 @@
+mod app;
+mod connection;
+mod notation;
+mod ui;
+mod wire;
+
+use crate::app::{App, Command};
+use crate::connection::{Connection, Incoming, Source};
+use clap::Parser;
+use ratatui::crossterm::event::{self, Event, KeyEventKind};
+use ratatui::DefaultTerminal;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+
+/// Plays Scrabble from the terminal
+#[derive(Parser, Debug)]
+#[command(version)]
+struct Args {
+    /// Address of the server
+    #[arg(long, env = "SCRABBLE_SERVER", default_value = "http://localhost:3000")]
+    server: String,
+    /// Name to play under
+    #[arg(short, long, env = "USER", default_value = "Guest")]
+    name: String,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+    let (sender, incoming) = mpsc::unbounded_channel();
+
+    // Connected before taking over the terminal, so failures read like any other
+    let lobby = Connection::open(&args.server, Source::Lobby, sender.clone()).await?;
+
+    let mut terminal = ratatui::init();
+    let result = run(
+        &mut terminal,
+        App::new(args.name),
+        &args.server,
+        lobby,
+        sender,
+        incoming,
+    )
+    .await;
+    ratatui::restore();
+
+    Ok(result?)
+}
+
+async fn run(
+    terminal: &mut DefaultTerminal,
+    mut app: App,
+    server: &str,
+    lobby: Connection,
+    sender: UnboundedSender<Incoming>,
+    mut incoming: UnboundedReceiver<Incoming>,
+) -> std::io::Result<()> {
+    // Reading the terminal blocks, it gets a thread of its own
+    let (keys_sender, mut keys) = mpsc::unbounded_channel();
+    std::thread::spawn(move || {
+        while let Ok(event) = event::read() {
+            if keys_sender.send(event).is_err() {
+                break;
+            }
+        }
+    });
+
+    // Redraws the clocks
+    let mut tick = tokio::time::interval(Duration::from_secs(1));
+    let mut game: Option<Connection> = None;
+    let mut commands = VecDeque::from(app.start());
+
+    loop {
+        while let Some(command) = commands.pop_front() {
+            match command {
+                Command::Quit => {
+                    if let Some(game) = game.take() {
+                        game.close().await;
+                    }
+                    lobby.close().await;
+                    return Ok(());
+                }
+                Command::Join(game_uuid) => {
+                    if let Some(game) = game.take() {
+                        game.close().await;
+                    }
+
+                    let source = Source::Game(game_uuid);
+                    match Connection::open(server, source, sender.clone()).await {
+                        Ok(connection) => game = Some(connection),
+                        Err(reason) => {
+                            let failed = Incoming::Closed { source, reason };
+                            commands.extend(app.handle_incoming(failed, Instant::now()));
+                        }
+                    }
+                }
+                Command::Leave => {
+                    if let Some(game) = game.take() {
+                        game.close().await;
+                    }
+                }
+                Command::Request {
+                    source: Source::Lobby,
+                    event,
+                    data,
+                } => lobby.request(event, data).await,
+                Command::Request {
+                    source: Source::Game(_),
+                    event,
+                    data,
+                } => {
+                    if let Some(game) = &game {
+                        game.request(event, data).await;
+                    }
+                }
+            }
+        }
+
+        terminal.draw(|frame| ui::draw(frame, &app, Instant::now()))?;
+
+        let new_commands = tokio::select! {
+            Some(incoming) = incoming.recv() => app.handle_incoming(incoming, Instant::now()),
+            Some(event) = keys.recv() => match event {
+                Event::Key(key) if key.kind == KeyEventKind::Press => app.handle_key(key),
+                _ => Vec::new(),
+            },
+            _ = tick.tick() => Vec::new(),
+        };
+        commands.extend(new_commands);
+    }
+}
@@ -0,0 +1,219 @@
+use scrabble_core::{Board, Play, Tile, BOARD_SIZE};
+use std::fmt::Formatter;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub enum Direction {
+    #[default]
+    Across,
+    Down,
+}
+
+impl Direction {
+    pub fn toggle(self) -> Self {
+        match self {
+            Direction::Across => Direction::Down,
+            Direction::Down => Direction::Across,
+        }
+    }
+
+    /// The square after `(x, y)`, if it is still on the board
+    pub fn next(self, (x, y): (usize, usize)) -> Option<(usize, usize)> {
+        let (x, y) = match self {
+            Direction::Across => (x + 1, y),
+            Direction::Down => (x, y + 1),
+        };
+
+        (x < BOARD_SIZE && y < BOARD_SIZE).then_some((x, y))
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum NotationError {
+    /// Neither `8H` nor `H8`
+    InvalidSquare,
+    OffTheBoard,
+    /// The letter is not on the rack, or not as many times as the word needs
+    NotInRack(char),
+    /// The word goes through a tile that is not the letter written
+    Mismatch(char, char),
+    /// Every letter of the word is already on the board
+    NothingPlaced,
+}
+
+impl std::fmt::Display for NotationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NotationError::InvalidSquare => {
+                write!(f, "Squares are written 8H to play across, H8 to play down")
+            }
+            NotationError::OffTheBoard => write!(f, "The word does not fit on the board"),
+            NotationError::NotInRack(letter) => write!(f, "No {letter} left on your rack"),
+            NotationError::Mismatch(written, found) => {
+                write!(f, "The word needs a {written} where there is a {found}")
+            }
+            NotationError::NothingPlaced => write!(f, "The word is already on the board"),
+        }
+    }
+}
+
+/// Column and row of a square such as `H8`, and the direction it implies
+///
+/// As on paper, the row comes first for words played across (`8H`) and the column
+/// first for words played down (`H8`).
+pub fn parse_square(text: &str) -> Result<((usize, usize), Direction), NotationError> {
+    let text = text.trim().to_ascii_uppercase();
+    let digits = |x: &str| {
+        x.parse::<usize>()
+            .ok()
+            .filter(|x| (1..=BOARD_SIZE).contains(x))
+    };
+    let column = |x: &str| match x.as_bytes() {
+        [letter @ b'A'..=b'Z'] => Some((letter - b'A') as usize).filter(|x| *x < BOARD_SIZE),
+        _ => None,
+    };
+
+    let split = text
+        .find(|x: char| x.is_ascii_alphabetic())
+        .ok_or(NotationError::InvalidSquare)?;
+
+    let (square, direction) = match split {
+        0 => ((column(&text[..1]), digits(&text[1..])), Direction::Down),
+        _ => (
+            (column(&text[split..]), digits(&text[..split])),
+            Direction::Across,
+        ),
+    };
+
+    match square {
+        (Some(x), Some(row)) => Ok(((x, row - 1), direction)),
+        _ => Err(NotationError::InvalidSquare),
+    }
+}
+
+/// Written name of a square, the way `parse_square` reads it
+pub fn square_name((x, y): (usize, usize), direction: Direction) -> String {
+    let column = (b'A' + x as u8) as char;
+
+    match direction {
+        Direction::Across => format!("{}{column}", y + 1),
+        Direction::Down => format!("{column}{}", y + 1),
+    }
+}
+
+/// Tiles to place for a move written like `8H WORD`
+///
+/// The word is written in full, letters already on the board are skipped over
+/// and the others are taken from the rack.
+pub fn parse_move(text: &str, board: &Board, rack: &[Tile]) -> Result<Vec<Play>, NotationError> {
+    let (square, word) = text
+        .trim()
+        .split_once(char::is_whitespace)
+        .ok_or(NotationError::InvalidSquare)?;
+    let (mut position, direction) = parse_square(square)?;
+
+    let mut rack = rack.to_vec();
+    let mut plays = Vec::new();
+    let mut letters = word
+        .trim()
+        .chars()
+        .map(|x| x.to_ascii_uppercase())
+        .peekable();
+
+    while let Some(letter) = letters.next() {
+        let (x, y) = position;
+
+        match board.get(x, y) {
+            Some(Tile(found, _)) if found != letter => {
+                return Err(NotationError::Mismatch(letter, found))
+            }
+            Some(_) => {}
+            None => {
+                let index = rack
+                    .iter()
+                    .position(|x| x.0 == letter)
+                    .ok_or(NotationError::NotInRack(letter))?;
+
+                plays.push(Play {
+                    tile: rack.remove(index),
+                    x,
+                    y,
+                });
+            }
+        }
+
+        if letters.peek().is_some() {
+            position = direction.next(position).ok_or(NotationError::OffTheBoard)?;
+        }
+    }
+
+    match plays.is_empty() {
+        true => Err(NotationError::NothingPlaced),
+        false => Ok(plays),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rack(letters: &str) -> Vec<Tile> {
+        letters.chars().map(|x| Tile(x, 1)).collect()
+    }
+
+    #[test]
+    fn squares_follow_the_usual_notation() {
+        assert_eq!(parse_square("8H"), Ok(((7, 7), Direction::Across)));
+        assert_eq!(parse_square("h8"), Ok(((7, 7), Direction::Down)));
+        assert_eq!(parse_square("15A"), Ok(((0, 14), Direction::Across)));
+        assert_eq!(parse_square("O1"), Ok(((14, 0), Direction::Down)));
+
+        assert_eq!(parse_square("16A"), Err(NotationError::InvalidSquare));
+        assert_eq!(parse_square("P1"), Err(NotationError::InvalidSquare));
+        assert_eq!(parse_square("H"), Err(NotationError::InvalidSquare));
+        assert_eq!(parse_square("88"), Err(NotationError::InvalidSquare));
+
+        assert_eq!(square_name((7, 7), Direction::Across), "8H");
+        assert_eq!(square_name((14, 0), Direction::Down), "O1");
+    }
+
+    #[test]
+    fn words_take_their_tiles_from_the_rack() {
+        let board = Board::default();
+        let plays = parse_move("8g cat", &board, &rack("TACXYZ")).unwrap();
+
+        let placed: Vec<_> = plays.iter().map(|x| (x.tile.0, x.x, x.y)).collect();
+        assert_eq!(placed, [('C', 6, 7), ('A', 7, 7), ('T', 8, 7)]);
+
+        assert_eq!(
+            parse_move("8G CAT", &board, &rack("CAXYZ")),
+            Err(NotationError::NotInRack('T'))
+        );
+        assert_eq!(
+            parse_move("8N CAT", &board, &rack("CAT")),
+            Err(NotationError::OffTheBoard)
+        );
+    }
+
+    #[test]
+    fn tiles_on_the_board_are_played_through() {
+        let mut board = Board::default();
+        board.apply(&[Play {
+            tile: Tile('A', 1),
+            x: 7,
+            y: 7,
+        }]);
+
+        let plays = parse_move("H7 CAT", &board, &rack("CT")).unwrap();
+        let placed: Vec<_> = plays.iter().map(|x| (x.tile.0, x.x, x.y)).collect();
+        assert_eq!(placed, [('C', 7, 6), ('T', 7, 8)]);
+
+        assert_eq!(
+            parse_move("H7 COT", &board, &rack("COT")),
+            Err(NotationError::Mismatch('O', 'A'))
+        );
+        assert_eq!(
+            parse_move("8H A", &board, &rack("A")),
+            Err(NotationError::NothingPlaced)
+        );
+    }
+}
@@ -0,0 +1,220 @@
+use crate::app::{App, GameView};
+use crate::notation::{self, Direction};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Paragraph, Row, Table, TableState};
+use ratatui::Frame;
+use scrabble_core::{Premium, Tile, BOARD_SIZE, CENTER};
+use std::time::{Duration, Instant};
+
+/// Row labels, then three characters a square
+const BOARD_WIDTH: u16 = 3 + 3 * BOARD_SIZE as u16 + 2;
+const BOARD_HEIGHT: u16 = 1 + BOARD_SIZE as u16 + 2;
+
+const LOBBY_HELP: &str = "↑↓ select · Enter join · c create · r refresh · q quit";
+const GAME_HELP: &str = "arrows move · letters put tiles down · Tab direction · Enter play · \
+    Esc take back · :start :pass :pause :resume :leave · :8H WORD";
+
+pub fn draw(frame: &mut Frame, app: &App, now: Instant) {
+    let [main, notice, help] = Layout::vertical([
+        Constraint::Min(0),
+        Constraint::Length(1),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+
+    match &app.game {
+        Some(game) => draw_game(frame, main, game, now),
+        None => draw_lobby(frame, main, app),
+    }
+
+    frame.render_widget(Paragraph::new(app.notice.as_str()).yellow(), notice);
+
+    let help_line = match (&app.input, &app.game) {
+        (Some(input), _) => Line::from(vec![Span::raw(":"), Span::raw(input), "█".into()]),
+        (None, Some(_)) => Line::from(GAME_HELP).dark_gray(),
+        (None, None) => Line::from(LOBBY_HELP).dark_gray(),
+    };
+    frame.render_widget(Paragraph::new(help_line), help);
+}
+
+fn draw_lobby(frame: &mut Frame, area: Rect, app: &App) {
+    let rows = app.games.iter().map(|game| {
+        let time_control = game.time_control.map_or(String::from("-"), |x| {
+            format!(
+                "{}+{}",
+                format_duration(Duration::from_secs(x.initial_seconds)),
+                x.increment_seconds
+            )
+        });
+
+        Row::new([
+            game.name.clone(),
+            game.host.clone().unwrap_or_default(),
+            format!("{}/{}", game.players, game.max_players),
+            game.status.replace('-', " "),
+            time_control,
+        ])
+    });
+
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Fill(3),
+            Constraint::Fill(2),
+            Constraint::Length(8),
+            Constraint::Length(12),
+            Constraint::Length(8),
+        ],
+    )
+    .header(Row::new(["Game", "Host", "Players", "Status", "Clock"]).bold())
+    .row_highlight_style(Style::new().reversed())
+    .block(Block::bordered().title(format!(" Scrabble · playing as {} ", app.name)));
+
+    let mut state = TableState::default().with_selected(Some(app.selected));
+    frame.render_stateful_widget(table, area, &mut state);
+}
+
+fn draw_game(frame: &mut Frame, area: Rect, game: &GameView, now: Instant) {
+    let [board, side] =
+        Layout::horizontal([Constraint::Length(BOARD_WIDTH), Constraint::Min(24)]).areas(area);
+    let [board, _] =
+        Layout::vertical([Constraint::Length(BOARD_HEIGHT), Constraint::Min(0)]).areas(board);
+    let [players, rack, clock] = Layout::vertical([
+        Constraint::Length(game.players.len().max(1) as u16 + 2),
+        Constraint::Length(3),
+        Constraint::Min(3),
+    ])
+    .areas(side);
+
+    let title = format!(" {} · {} ", game.name, game.status.replace('-', " "));
+    let board_lines = board_lines(game);
+    frame.render_widget(
+        Paragraph::new(board_lines).block(Block::bordered().title(title)),
+        board,
+    );
+
+    let score_lines: Vec<Line> = game
+        .players
+        .iter()
+        .map(|player| {
+            let marker = match game.current_player == Some(player.id) {
+                true => "▶ ",
+                false => "  ",
+            };
+            let time = match game.time_control {
+                Some(time_control) => game.clock.remaining(&player.id, &time_control, now),
+                None => game.clock.used(&player.id, now),
+            };
+            let score = game.scores.get(&player.id).copied().unwrap_or_default();
+
+            let line = Line::from(format!(
+                "{marker}{:<12} {score:>4}  {}",
+                player.name,
+                format_duration(time)
+            ));
+            match game.me == Some(player.id) {
+                true => line.bold(),
+                false => line,
+            }
+        })
+        .collect();
+    frame.render_widget(
+        Paragraph::new(score_lines).block(Block::bordered().title(" Scores ")),
+        players,
+    );
+
+    let tiles: Vec<Span> = game
+        .available()
+        .into_iter()
+        .flat_map(|x| [rack_tile(x), Span::raw(" ")])
+        .collect();
+    frame.render_widget(
+        Paragraph::new(Line::from(tiles)).block(Block::bordered().title(" Rack ")),
+        rack,
+    );
+
+    let direction = match game.direction {
+        Direction::Across => "across →",
+        Direction::Down => "down ↓",
+    };
+    let mut clock_lines = vec![Line::from(format!(
+        "Cursor {} {direction}",
+        notation::square_name(game.cursor, game.direction)
+    ))];
+    if let Some(turn_time) = game.clock.turn_time(now) {
+        clock_lines.push(Line::from(format!("Turn   {}", format_duration(turn_time))));
+    }
+    frame.render_widget(
+        Paragraph::new(clock_lines).block(Block::bordered().title(" Clock ")),
+        clock,
+    );
+}
+
+fn board_lines(game: &GameView) -> Vec<Line<'static>> {
+    let header: String = (0..BOARD_SIZE)
+        .map(|x| format!(" {} ", (b'A' + x as u8) as char))
+        .collect();
+    let mut lines = vec![Line::from(format!("   {header}")).dark_gray()];
+
+    for y in 0..BOARD_SIZE {
+        let mut spans = vec![Span::from(format!("{:>2} ", y + 1)).dark_gray()];
+
+        for x in 0..BOARD_SIZE {
+            let mut span = match (game.board.get(x, y), game.pending_at(x, y)) {
+                (Some(Tile(letter, _)), _) => Span::from(format!(" {letter} "))
+                    .fg(Color::Black)
+                    .bg(Color::Rgb(235, 210, 160))
+                    .bold(),
+                (None, Some(Tile(letter, _))) => Span::from(format!(" {letter} "))
+                    .fg(Color::Black)
+                    .bg(Color::LightGreen)
+                    .bold(),
+                (None, None) => empty_square(x, y),
+            };
+
+            if (x, y) == game.cursor {
+                span = span.add_modifier(Modifier::REVERSED);
+            }
+            spans.push(span);
+        }
+
+        lines.push(Line::from(spans));
+    }
+
+    lines
+}
+
+fn empty_square(x: usize, y: usize) -> Span<'static> {
+    if (x, y) == CENTER {
+        return Span::from(" ★ ").fg(Color::White).bg(Color::LightRed);
+    }
+
+    match Premium::at(x, y) {
+        Some(Premium::TripleWord) => Span::from("3W ").fg(Color::White).bg(Color::Red),
+        Some(Premium::DoubleWord) => Span::from("2W ").fg(Color::White).bg(Color::LightRed),
+        Some(Premium::TripleLetter) => Span::from("3L ").fg(Color::White).bg(Color::Blue),
+        Some(Premium::DoubleLetter) => Span::from("2L ").fg(Color::Black).bg(Color::Cyan),
+        None => Span::from(" · ").dark_gray(),
+    }
+}
+
+/// A tile with its value written small, like on the real thing
+fn rack_tile(Tile(letter, value): Tile) -> Span<'static> {
+    const SUBSCRIPTS: [char; 10] = ['₀', '₁', '₂', '₃', '₄', '₅', '₆', '₇', '₈', '₉'];
+    let value: String = value
+        .to_string()
+        .chars()
+        .filter_map(|x| x.to_digit(10).map(|x| SUBSCRIPTS[x as usize]))
+        .collect();
+
+    Span::from(format!(" {letter}{value} "))
+        .fg(Color::Black)
+        .bg(Color::Rgb(235, 210, 160))
+}
+
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
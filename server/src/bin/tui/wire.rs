@@ -0,0 +1,88 @@
+use scrabble_core::Play;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Version of `/protocol` this client speaks
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Body of every acknowledgement and server event
+#[derive(Deserialize, Debug, Default)]
+pub struct Reply {
+    #[serde(default)]
+    pub data: Value,
+    /// Text in the language of the socket
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+impl Reply {
+    pub fn into_result<T: for<'de> Deserialize<'de>>(self) -> Result<T, String> {
+        match self.error {
+            Some(error) => Err(error),
+            None => serde_json::from_value(self.data).map_err(|x| x.to_string()),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Deserialize, Debug)]
+pub struct Player {
+    pub id: Uuid,
+    pub name: String,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Deserialize, Debug)]
+pub struct TimeControl {
+    pub initial_seconds: u64,
+    pub increment_seconds: u64,
+}
+
+#[derive(Clone, PartialEq, Deserialize, Debug)]
+pub struct GameSummary {
+    pub uuid: Uuid,
+    pub name: String,
+    pub host: Option<String>,
+    pub players: usize,
+    pub max_players: usize,
+    pub status: String,
+    pub time_control: Option<TimeControl>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct GamePage {
+    pub games: Vec<GameSummary>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct CreatedGame {
+    pub uuid: Uuid,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct StatusUpdate {
+    pub status: String,
+    pub current_player: Option<Player>,
+    pub scores: HashMap<Uuid, i32>,
+}
+
+/// A move someone made, passes come with no tiles
+#[derive(Deserialize, Debug)]
+pub struct Turn {
+    pub player_uuid: Uuid,
+    #[serde(default)]
+    pub tiles: Vec<Play>,
+    pub score: i32,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Presence {
+    pub player: Player,
+    pub connected: bool,
+}
+
+/// The server went into or out of maintenance, `message` is ready to be shown
+#[derive(Deserialize, Debug)]
+pub struct Maintenance {
+    pub message: String,
+}
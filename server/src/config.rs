@@ -0,0 +1,555 @@
+use crate::reaper::ReaperConfig;
+use crate::settings::{DisconnectPolicy, Variant};
+use axum::http::HeaderValue;
+use clap::{Parser, ValueEnum};
+use scrabble_core::{Lexicon, MIN_PLAYERS};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Formatter;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::OnceLock;
+use std::time::Duration;
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
+use tracing::Level;
+
+/// Read when `--config` is not given, if it exists
+pub const DEFAULT_CONFIG_PATH: &str = "scrabble.toml";
+
+/// Most players a game can be configured for, the bag would run dry past that
+const MAX_SEATS: usize = 8;
+
+const MIN_ADMIN_TOKEN_LENGTH: usize = 16;
+
+/// Set once at startup, the defaults are used until then, in tests for instance
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+/// Sets the configuration of the whole process, first thing at startup
+///
+/// Panics when it was already set, or already read: whatever read it got the
+/// defaults instead.
+pub fn init(config: Config) {
+    if CONFIG.set(config).is_err() {
+        panic!("the configuration was set or read before config::init");
+    }
+}
+
+pub fn get() -> &'static Config {
+    CONFIG.get_or_init(Config::default)
+}
+
+/// Word lists of `lexicons`, read the first time they are needed
+///
+/// A list that can't be read is left out with an error in the logs, its variant
+/// takes any word then.
+pub fn lexicons() -> &'static HashMap<Variant, Lexicon> {
+    static LEXICONS: OnceLock<HashMap<Variant, Lexicon>> = OnceLock::new();
+
+    LEXICONS.get_or_init(|| {
+        get()
+            .lexicons
+            .iter()
+            .filter_map(|(variant, path)| match std::fs::read_to_string(path) {
+                Ok(text) => Some((*variant, Lexicon::parse(&text))),
+                Err(error) => {
+                    tracing::error!("Cannot read the lexicon of {variant:?}: {error}");
+                    None
+                }
+            })
+            .collect()
+    })
+}
+
+/// Everything the server reads at startup
+///
+/// Each layer overrides the previous one: built-in defaults, then the TOML file,
+/// then the `SCRABBLE_*` environment variables, then the command line.
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub network: NetworkConfig,
+    pub logging: LoggingConfig,
+    pub storage: StorageConfig,
+    /// Word list of each variant
+    ///
+    /// Words of every move are looked up in it, and it's served to clients at
+    /// `/api/v1/lexicons/{variant}` so they can check them first. Variants
+    /// without one take any word.
+    pub lexicons: HashMap<Variant, PathBuf>,
+    /// Used for the settings a game is created without
+    pub game: GameDefaults,
+    pub limits: LimitsConfig,
+    pub admin: AdminConfig,
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct NetworkConfig {
+    pub bind: SocketAddr,
+    /// Origins allowed to call the server from a browser, `*` allows them all
+    pub cors_origins: Vec<String>,
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        NetworkConfig {
+            bind: SocketAddr::from(([0, 0, 0, 0], 3000)),
+            cors_origins: vec![String::from("*")],
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct LoggingConfig {
+    /// One of `trace`, `debug`, `info`, `warn` or `error`
+    pub level: String,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        LoggingConfig {
+            level: String::from("debug"),
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Serialize, Deserialize, Debug, Default, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum StorageBackend {
+    /// Accounts, ratings and game records are saved as JSON files in `directory`
+    #[default]
+    Json,
+    /// Nothing is saved, everything is lost when the server stops
+    Memory,
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct StorageConfig {
+    pub backend: StorageBackend,
+    pub directory: PathBuf,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        StorageConfig {
+            backend: StorageBackend::default(),
+            directory: PathBuf::from("."),
+        }
+    }
+}
+
+impl StorageConfig {
+    /// Where `file_name` is saved, `None` if nothing is saved
+    pub fn path(&self, file_name: &str) -> Option<PathBuf> {
+        match self.backend {
+            StorageBackend::Json => Some(self.directory.join(file_name)),
+            StorageBackend::Memory => None,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct GameDefaults {
+    pub max_players: usize,
+    /// Seconds
+    pub disconnect_grace: u64,
+    /// Seconds
+    pub disconnect_timeout: u64,
+    pub on_disconnect: DisconnectPolicy,
+}
+
+impl Default for GameDefaults {
+    fn default() -> Self {
+        GameDefaults {
+            max_players: 4,
+            disconnect_grace: 60,
+            disconnect_timeout: 5 * 60,
+            on_disconnect: DisconnectPolicy::default(),
+        }
+    }
+}
+
+/// Durations are in seconds
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct LimitsConfig {
+    /// Most players a game can be created for
+    pub max_players: usize,
+    /// Games being played or waiting for players at the same time, archived ones aside
+    pub max_games: usize,
+    /// Games kept once over for downloads and audits, the ones that ended first
+    /// make room for the others
+    pub max_archived_games: usize,
+    /// Events waiting to be handled before the sockets have to wait
+    pub event_channel_capacity: usize,
+    /// Longest duration a client can ask for: invite lifetimes, disconnect timeouts
+    /// and clocks
+    pub max_duration: u64,
+    pub reaper_interval: u64,
+    pub empty_room_timeout: u64,
+    pub abandon_after: u64,
+    pub retention: u64,
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        let reaper = ReaperConfig::default();
+
+        LimitsConfig {
+            max_players: 4,
+            max_games: 1000,
+            max_archived_games: 1000,
+            event_channel_capacity: 32,
+            max_duration: 30 * 24 * 60 * 60,
+            reaper_interval: reaper.interval.as_secs(),
+            empty_room_timeout: reaper.empty_room_timeout.as_secs(),
+            abandon_after: reaper.abandon_after.as_secs(),
+            retention: reaper.retention.as_secs(),
+        }
+    }
+}
+
+impl LimitsConfig {
+    pub fn reaper(&self) -> ReaperConfig {
+        ReaperConfig {
+            interval: Duration::from_secs(self.reaper_interval),
+            empty_room_timeout: Duration::from_secs(self.empty_room_timeout),
+            abandon_after: Duration::from_secs(self.abandon_after),
+            retention: Duration::from_secs(self.retention),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct AdminConfig {
+    /// Bearer token of the `/admin` routes, which are left out without one
+    pub token: Option<String>,
+}
+
+/// Command line flags, each one can also be given as an environment variable
+#[derive(Parser, Debug, Default)]
+#[command(version, about = "Scrabble game server")]
+pub struct Args {
+    /// TOML configuration file [default: scrabble.toml, if it exists]
+    #[arg(short, long, env = "SCRABBLE_CONFIG")]
+    pub config: Option<PathBuf>,
+    /// Address the server listens on
+    #[arg(long, env = "SCRABBLE_BIND")]
+    pub bind: Option<SocketAddr>,
+    /// Origins allowed by CORS, comma separated, `*` for any
+    #[arg(long, env = "SCRABBLE_CORS_ORIGINS", value_delimiter = ',')]
+    pub cors_origins: Option<Vec<String>>,
+    #[arg(long, env = "SCRABBLE_LOG_LEVEL")]
+    pub log_level: Option<String>,
+    #[arg(long, env = "SCRABBLE_STORAGE")]
+    pub storage: Option<StorageBackend>,
+    /// Directory the JSON files are saved in
+    #[arg(long, env = "SCRABBLE_DATA_DIR")]
+    pub data_dir: Option<PathBuf>,
+    #[arg(long, env = "SCRABBLE_MAX_PLAYERS")]
+    pub max_players: Option<usize>,
+    #[arg(long, env = "SCRABBLE_MAX_GAMES")]
+    pub max_games: Option<usize>,
+    #[arg(long, env = "SCRABBLE_EVENT_CHANNEL_CAPACITY")]
+    pub event_channel_capacity: Option<usize>,
+    /// Bearer token of the `/admin` routes
+    #[arg(long, env = "SCRABBLE_ADMIN_TOKEN", hide_env_values = true)]
+    pub admin_token: Option<String>,
+    /// Writes the JSON Schema and TypeScript definitions of the protocol in this
+    /// directory, then exits
+    #[arg(long, value_name = "DIR")]
+    pub export_protocol: Option<PathBuf>,
+}
+
+impl Args {
+    /// Overrides whatever was given on the command line or in the environment
+    fn apply(self, config: &mut Config) {
+        if let Some(bind) = self.bind {
+            config.network.bind = bind;
+        }
+        if let Some(cors_origins) = self.cors_origins {
+            config.network.cors_origins = cors_origins;
+        }
+        if let Some(level) = self.log_level {
+            config.logging.level = level;
+        }
+        if let Some(backend) = self.storage {
+            config.storage.backend = backend;
+        }
+        if let Some(directory) = self.data_dir {
+            config.storage.directory = directory;
+        }
+        if let Some(max_players) = self.max_players {
+            config.limits.max_players = max_players;
+        }
+        if let Some(max_games) = self.max_games {
+            config.limits.max_games = max_games;
+        }
+        if let Some(capacity) = self.event_channel_capacity {
+            config.limits.event_channel_capacity = capacity;
+        }
+        if let Some(token) = self.admin_token {
+            config.admin.token = Some(token);
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Read(PathBuf, std::io::Error),
+    Parse(PathBuf, toml::de::Error),
+    /// Every problem found, not just the first one
+    Invalid(Vec<String>),
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::Read(path, error) => {
+                write!(f, "Could not read {}: {error}", path.display())
+            }
+            ConfigError::Parse(path, error) => {
+                write!(f, "Could not parse {}: {error}", path.display())
+            }
+            ConfigError::Invalid(problems) => {
+                write!(f, "Invalid configuration:")?;
+                for problem in problems {
+                    write!(f, "\n  - {problem}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl Config {
+    /// Puts the layers together, then checks the result
+    pub fn load(args: Args) -> Result<Config, ConfigError> {
+        let mut config = match &args.config {
+            Some(path) => Config::from_file(path)?,
+            None if Path::new(DEFAULT_CONFIG_PATH).exists() => {
+                Config::from_file(Path::new(DEFAULT_CONFIG_PATH))?
+            }
+            None => Config::default(),
+        };
+
+        args.apply(&mut config);
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    pub fn from_file(path: &Path) -> Result<Config, ConfigError> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|error| ConfigError::Read(path.to_path_buf(), error))?;
+
+        toml::from_str(&text).map_err(|error| ConfigError::Parse(path.to_path_buf(), error))
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let mut problems = Vec::new();
+
+        if self.log_level().is_none() {
+            problems.push(format!("unknown log level `{}`", self.logging.level));
+        }
+
+        if self.network.cors_origins.is_empty() {
+            problems.push(String::from(
+                "at least one CORS origin is needed, `*` for any",
+            ));
+        }
+        for origin in &self.network.cors_origins {
+            if origin != "*" && HeaderValue::from_str(origin).is_err() {
+                problems.push(format!("invalid CORS origin `{origin}`"));
+            }
+        }
+
+        for (variant, path) in &self.lexicons {
+            if !path.is_file() {
+                problems.push(format!(
+                    "lexicon of {variant:?} not found at {}",
+                    path.display()
+                ));
+            }
+        }
+
+        let limits = &self.limits;
+        if !(MIN_PLAYERS..=MAX_SEATS).contains(&limits.max_players) {
+            problems.push(format!(
+                "limits.max_players must be between {MIN_PLAYERS} and {MAX_SEATS}"
+            ));
+        }
+        if !(MIN_PLAYERS..=limits.max_players).contains(&self.game.max_players) {
+            problems.push(format!(
+                "game.max_players must be between {MIN_PLAYERS} and limits.max_players"
+            ));
+        }
+        if self.game.disconnect_grace > self.game.disconnect_timeout {
+            problems.push(String::from(
+                "game.disconnect_grace cannot be longer than game.disconnect_timeout",
+            ));
+        }
+
+        for (name, value) in [
+            ("limits.max_games", limits.max_games),
+            (
+                "limits.event_channel_capacity",
+                limits.event_channel_capacity,
+            ),
+            ("limits.max_duration", limits.max_duration as usize),
+            ("limits.reaper_interval", limits.reaper_interval as usize),
+        ] {
+            if value == 0 {
+                problems.push(format!("{name} must be greater than 0"));
+            }
+        }
+
+        if self
+            .admin
+            .token
+            .as_ref()
+            .is_some_and(|x| x.len() < MIN_ADMIN_TOKEN_LENGTH)
+        {
+            problems.push(format!(
+                "admin.token must be at least {MIN_ADMIN_TOKEN_LENGTH} characters long"
+            ));
+        }
+
+        match problems.is_empty() {
+            true => Ok(()),
+            false => Err(ConfigError::Invalid(problems)),
+        }
+    }
+
+    pub fn log_level(&self) -> Option<Level> {
+        Level::from_str(&self.logging.level).ok()
+    }
+
+    pub fn cors_layer(&self) -> CorsLayer {
+        if self.network.cors_origins.iter().any(|x| x == "*") {
+            return CorsLayer::permissive();
+        }
+
+        let origins = self
+            .network
+            .cors_origins
+            .iter()
+            .filter_map(|x| HeaderValue::from_str(x).ok());
+
+        CorsLayer::new()
+            .allow_origin(AllowOrigin::list(origins))
+            .allow_methods(Any)
+            .allow_headers(Any)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[should_panic(expected = "before config::init")]
+    fn config_cannot_be_set_once_read() {
+        get();
+        init(Config::default());
+    }
+
+    #[test]
+    fn defaults_are_valid() {
+        let config = Config::default();
+
+        assert!(config.validate().is_ok());
+        assert_eq!(config.log_level(), Some(Level::DEBUG));
+        assert_eq!(
+            config.limits.reaper().interval,
+            ReaperConfig::default().interval
+        );
+    }
+
+    #[test]
+    fn file_overrides_defaults() {
+        let config: Config = toml::from_str(
+            r#"
+            [network]
+            bind = "127.0.0.1:8080"
+
+            [storage]
+            backend = "memory"
+
+            [lexicons]
+            classic = "words.txt"
+
+            [game]
+            max_players = 2
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.network.bind.port(), 8080);
+        assert_eq!(config.network.cors_origins, vec!["*"]);
+        assert_eq!(config.storage.path("accounts.json"), None);
+        assert_eq!(
+            config.lexicons[&Variant::Classic],
+            PathBuf::from("words.txt")
+        );
+        assert_eq!(config.game.max_players, 2);
+        assert_eq!(config.game.disconnect_grace, 60);
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        assert!(toml::from_str::<Config>("[network]\nport = 3000").is_err());
+    }
+
+    #[test]
+    fn command_line_overrides_file() {
+        let path = std::env::temp_dir().join(format!("scrabble-{}.toml", uuid::Uuid::new_v4()));
+        std::fs::write(
+            &path,
+            "[logging]\nlevel = \"info\"\n[limits]\nmax_games = 10",
+        )
+        .unwrap();
+
+        let args = Args::try_parse_from([
+            "scrabble",
+            "--config",
+            path.to_str().unwrap(),
+            "--log-level",
+            "warn",
+            "--cors-origins",
+            "http://localhost:5173,https://example.com",
+        ])
+        .unwrap();
+        let config = Config::load(args).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(config.log_level(), Some(Level::WARN));
+        assert_eq!(config.limits.max_games, 10);
+        assert_eq!(config.network.cors_origins.len(), 2);
+    }
+
+    #[test]
+    fn every_problem_is_reported() {
+        let mut config = Config::default();
+        config.logging.level = String::from("loud");
+        config.limits.max_players = 3;
+        config.limits.event_channel_capacity = 0;
+        config
+            .lexicons
+            .insert(Variant::Classic, PathBuf::from("/nowhere/words.txt"));
+
+        let Err(ConfigError::Invalid(problems)) = config.validate() else {
+            panic!("configuration should be invalid");
+        };
+
+        // The default of 4 players per game no longer fits the limit
+        assert_eq!(problems.len(), 4);
+    }
+}
@@ -1,7 +1,43 @@
-use crate::game::GameEvent;
+use crate::actor::GameReport;
+use crate::admin::AdminEvent;
+use crate::api::ApiEvent;
 use crate::lobby::LobbyEvent;
+use std::time::Instant;
+use tokio::sync::{mpsc, oneshot};
 
 pub enum Event {
     Lobby(LobbyEvent),
-    Game(GameEvent),
+    /// Sent by the games themselves, for whatever outlives them
+    Report(GameReport),
+    Api(ApiEvent),
+    Admin(AdminEvent),
+    /// A Prometheus scrape, answered with the metrics only the event loop can see
+    Metrics(oneshot::Sender<String>),
+}
+
+/// Sending half of the event channel, events are stamped on the way in so the
+/// time they spend queued can be measured
+#[derive(Clone)]
+pub struct EventSender(mpsc::Sender<(Instant, Event)>);
+
+pub type EventReceiver = mpsc::Receiver<(Instant, Event)>;
+
+pub fn channel(buffer: usize) -> (EventSender, EventReceiver) {
+    let (sender, receiver) = mpsc::channel(buffer);
+
+    (EventSender(sender), receiver)
+}
+
+impl EventSender {
+    pub async fn send(&self, event: Event) -> Result<(), mpsc::error::SendError<Event>> {
+        self.0
+            .send((Instant::now(), event))
+            .await
+            .map_err(|mpsc::error::SendError((_, event))| mpsc::error::SendError(event))
+    }
+
+    /// Events waiting to be handled
+    pub fn depth(&self) -> usize {
+        self.0.max_capacity() - self.0.capacity()
+    }
 }
@@ -1,298 +1,852 @@
-use crate::events::Event;
-use crate::events::Event::Game;
-use crate::manager::Manager;
-use crate::player::Player;
-use crate::response::Response;
-use serde::{Deserialize, Serialize};
-use socketioxide::extract::{AckSender, Data, SocketRef};
-use socketioxide::SocketIo;
-use tokio::sync::mpsc;
+use crate::accounts::Session;
+use crate::actor::GameCommand;
+use crate::i18n::Notice;
+use crate::lobby;
+use crate::metrics::{self, MoveKind};
+use crate::presence;
+use crate::protocol::{
+    CloseRequest, InviteAction, InviteRequest, LogoutRequest, PlayRequest, RegisterRequest,
+    WhoAmIRequest,
+};
+use crate::ratings::RatingChange;
+use crate::registry::Registry;
+use crate::response::{self, Ack, Response};
+use crate::room::Room;
+use crate::Error;
+use scrabble_core::{GameStatus, Play, Player};
+use serde::Serialize;
+use socketioxide::extract::{AckSender, SocketRef, TryData};
+use socketioxide::{DecodeError, SocketIo};
+use std::collections::HashMap;
+use std::time::{Instant, SystemTime};
 use tracing::debug;
 use uuid::Uuid;
 
-#[derive(Deserialize, Serialize, Debug, Clone)]
-#[serde(rename_all = "camelCase", untagged)]
-enum GameRequest {
-    Register { game_uuid: Uuid, username: String },
-    Logout { game_uuid: Uuid, player_uuid: Uuid },
-    Id { player_uuid: Uuid },
-    PlayerList,
-    Start,
+/// Answers a registration, guests keep the token to claim their seat again
+/// after a refresh
+#[derive(Serialize, Debug)]
+struct Seat<'a> {
+    #[serde(flatten)]
+    player: Player,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reconnect_token: Option<&'a str>,
 }
 
+/// Socket requests, handled by the task of the game they are about
 pub enum GameEvent {
     Registration {
         socket_ref: SocketRef,
-        game_uuid: Uuid,
         player: Player,
-        ack_sender: AckSender,
+        invite_code: Option<String>,
+        ack_sender: Ack,
     },
     Logout {
         socket_ref: SocketRef,
-        game_uuid: Uuid,
-        player_uuid: Uuid,
-        ack: AckSender,
+        ack: Ack,
     },
     WhoAmI {
         socket_ref: SocketRef,
         player_uuid: Uuid,
-        ack_sender: AckSender,
+        reconnect_token: Option<String>,
+        ack_sender: Ack,
     },
     PlayerList {
-        game_uuid: Uuid,
-        ack_sender: AckSender,
+        ack_sender: Ack,
     },
     StartGame {
-        game_uuid: Uuid,
+        socket_ref: SocketRef,
+        ack_sender: Ack,
+    },
+    Pause {
+        socket_ref: SocketRef,
+        ack_sender: Ack,
     },
+    Resume {
+        socket_ref: SocketRef,
+        ack_sender: Ack,
+    },
+    Close {
+        socket_ref: SocketRef,
+        ack_sender: Ack,
+    },
+    Invite {
+        socket_ref: SocketRef,
+        action: InviteAction,
+        ack_sender: Ack,
+    },
+    Pass {
+        socket_ref: SocketRef,
+        ack_sender: Ack,
+    },
+    Play {
+        socket_ref: SocketRef,
+        tiles: Vec<Play>,
+        ack_sender: Ack,
+    },
+    Disconnect {
+        player: Player,
+    },
+}
+
+impl GameEvent {
+    /// Answers a request whose game could not be reached
+    fn reject(self, error: Error) {
+        let ack_sender = match self {
+            GameEvent::Registration {
+                socket_ref,
+                ack_sender,
+                ..
+            } => {
+                // The socket was bound to the player a bit too early
+                socket_ref.extensions.remove::<Player>();
+                ack_sender
+            }
+            GameEvent::Logout { ack, .. } => ack,
+            GameEvent::WhoAmI { ack_sender, .. }
+            | GameEvent::PlayerList { ack_sender }
+            | GameEvent::StartGame { ack_sender, .. }
+            | GameEvent::Pause { ack_sender, .. }
+            | GameEvent::Resume { ack_sender, .. }
+            | GameEvent::Close { ack_sender, .. }
+            | GameEvent::Invite { ack_sender, .. }
+            | GameEvent::Pass { ack_sender, .. }
+            | GameEvent::Play { ack_sender, .. } => ack_sender,
+            GameEvent::Disconnect { .. } => return,
+        };
+
+        response::reject(ack_sender, error);
+    }
+}
+
+/// Hands a request to the task of its game, or turns it down if there is no
+/// such game anymore
+async fn route(registry: &Registry, game_uuid: &Uuid, event: GameEvent) {
+    let Some(handle) = registry.get(game_uuid) else {
+        return event.reject(Error::GameNotFound);
+    };
+
+    if let Err(GameCommand::Socket(event)) = handle.send(GameCommand::Socket(event)).await {
+        event.reject(Error::GameNotFound);
+    }
 }
 
 async fn handle_registration_request(
     socket_ref: SocketRef,
-    data: GameRequest,
-    ack_sender: AckSender,
-    sender: mpsc::Sender<Event>,
+    data: Result<RegisterRequest, DecodeError>,
+    ack_sender: Ack,
+    registry: Registry,
 ) {
-    if let GameRequest::Register {
+    let Ok(RegisterRequest {
         game_uuid,
         username,
-    } = data
-    {
-        if socket_ref.extensions.get::<Player>().is_none() {
-            let uuid = Uuid::new_v4();
-            let player = Player::new(&uuid, &username);
-
-            // Associate the player to the socket for easy access
-            socket_ref.extensions.insert::<Player>(player.clone());
-
-            // Inform the manager there's a new player
-            sender
-                .send(Event::Game(GameEvent::Registration {
-                    socket_ref,
-                    game_uuid,
-                    player,
-                    ack_sender,
-                }))
-                .await
-                .unwrap();
+        invite_code,
+    }) = data
+    else {
+        return response::reject(ack_sender, Error::InvalidRequest);
+    };
 
-            debug!(?uuid, %username, "Player connected");
-        }
+    if socket_ref.extensions.get::<Player>().is_some() {
+        return response::reject(ack_sender, Error::AlreadyRegistered);
     }
+
+    // Logged in players are known under their account, guests get a new UUID
+    let player = match socket_ref.extensions.get::<Session>() {
+        Some(session) => Player::with_account(&session.account_id, &session.username),
+        None => Player::new(&Uuid::new_v4(), &username),
+    };
+    let uuid = *player.get_id();
+
+    // Associate the player to the socket for easy access
+    socket_ref.extensions.insert::<Player>(player.clone());
+
+    // Inform the game there's a new player
+    route(
+        &registry,
+        &game_uuid,
+        GameEvent::Registration {
+            socket_ref,
+            player,
+            invite_code,
+            ack_sender,
+        },
+    )
+    .await;
+
+    debug!(?uuid, %username, "Player connected");
 }
 
 async fn handle_logout_request(
     socket_ref: SocketRef,
-    data: GameRequest,
-    ack: AckSender,
-    sender: mpsc::Sender<Event>,
+    data: Result<LogoutRequest, DecodeError>,
+    ack: Ack,
+    registry: Registry,
 ) {
-    if let GameRequest::Logout {
+    let Ok(LogoutRequest {
         game_uuid,
         player_uuid,
-    } = data
-    {
-        if socket_ref.extensions.get::<Player>().is_some() {
-            socket_ref.extensions.remove::<Player>().unwrap();
-
-            sender
-                .send(Event::Game(GameEvent::Logout {
-                    socket_ref,
-                    game_uuid,
-                    player_uuid,
-                    ack,
-                }))
-                .await
-                .unwrap();
-
-            debug!(%player_uuid, "Player logged out");
-        }
+    }) = data
+    else {
+        return response::reject(ack, Error::InvalidRequest);
+    };
+
+    // Players only ever log themselves out, the player is unbound from the
+    // socket once the game agrees
+    match socket_player_uuid(&socket_ref) {
+        Ok(uuid) if uuid == player_uuid => {}
+        Ok(_) => return response::reject(ack, Error::NotYourSeat),
+        Err(error) => return response::reject(ack, error),
     }
+
+    route(&registry, &game_uuid, GameEvent::Logout { socket_ref, ack }).await;
+
+    debug!(%player_uuid, "Player logged out");
 }
 
 async fn handle_id_request(
     socket: SocketRef,
-    data: GameRequest,
-    ack_sender: AckSender,
-    sender: mpsc::Sender<Event>,
+    data: Result<WhoAmIRequest, DecodeError>,
+    ack_sender: Ack,
+    game_uuid: Uuid,
+    registry: Registry,
 ) {
-    if let GameRequest::Id { player_uuid } = data {
-        // Player shouldn't register again if already registered
-        if socket.extensions.get::<Player>().is_none() {
-            sender
-                .send(Event::Game(GameEvent::WhoAmI {
-                    socket_ref: socket,
-                    player_uuid,
-                    ack_sender,
-                }))
-                .await
-                .unwrap()
-        }
+    let Ok(WhoAmIRequest {
+        player_uuid,
+        reconnect_token,
+    }) = data
+    else {
+        return response::reject(ack_sender, Error::InvalidRequest);
+    };
+
+    // Player shouldn't register again if already registered
+    if socket.extensions.get::<Player>().is_some() {
+        return response::reject(ack_sender, Error::AlreadyRegistered);
     }
+
+    route(
+        &registry,
+        &game_uuid,
+        GameEvent::WhoAmI {
+            socket_ref: socket,
+            player_uuid,
+            reconnect_token,
+            ack_sender,
+        },
+    )
+    .await
 }
 
-async fn handle_player_list_request(
-    data: GameRequest,
-    ack_sender: AckSender,
+async fn handle_player_list_request(ack_sender: Ack, game_uuid: Uuid, registry: Registry) {
+    route(&registry, &game_uuid, GameEvent::PlayerList { ack_sender }).await
+}
+
+async fn handle_start_game_request(
+    socket_ref: SocketRef,
+    ack_sender: Ack,
     game_uuid: Uuid,
-    sender: mpsc::Sender<Event>,
+    registry: Registry,
 ) {
-    if let GameRequest::PlayerList {} = data {
-        sender
-            .send(Event::Game(GameEvent::PlayerList {
-                game_uuid,
-                ack_sender,
-            }))
-            .await
-            .unwrap()
-    }
+    route(
+        &registry,
+        &game_uuid,
+        GameEvent::StartGame {
+            socket_ref,
+            ack_sender,
+        },
+    )
+    .await
 }
 
-async fn handle_start_game_request(
-    data: GameRequest,
+async fn handle_pause_request(
+    socket_ref: SocketRef,
+    ack_sender: Ack,
+    game_uuid: Uuid,
+    registry: Registry,
+) {
+    route(
+        &registry,
+        &game_uuid,
+        GameEvent::Pause {
+            socket_ref,
+            ack_sender,
+        },
+    )
+    .await
+}
+
+async fn handle_resume_request(
+    socket_ref: SocketRef,
+    ack_sender: Ack,
+    game_uuid: Uuid,
+    registry: Registry,
+) {
+    route(
+        &registry,
+        &game_uuid,
+        GameEvent::Resume {
+            socket_ref,
+            ack_sender,
+        },
+    )
+    .await
+}
+
+async fn handle_close_request(
+    socket_ref: SocketRef,
+    data: Result<CloseRequest, DecodeError>,
+    ack_sender: Ack,
+    registry: Registry,
+) {
+    let Ok(CloseRequest { game_uuid }) = data else {
+        return response::reject(ack_sender, Error::InvalidRequest);
+    };
+
+    route(
+        &registry,
+        &game_uuid,
+        GameEvent::Close {
+            socket_ref,
+            ack_sender,
+        },
+    )
+    .await
+}
+
+async fn handle_invite_request(
+    socket_ref: SocketRef,
+    data: Result<InviteRequest, DecodeError>,
+    ack_sender: Ack,
+    registry: Registry,
+) {
+    let Ok(InviteRequest { game_uuid, action }) = data else {
+        return response::reject(ack_sender, Error::InvalidRequest);
+    };
+
+    route(
+        &registry,
+        &game_uuid,
+        GameEvent::Invite {
+            socket_ref,
+            action,
+            ack_sender,
+        },
+    )
+    .await
+}
+
+async fn handle_pass_request(
+    socket_ref: SocketRef,
+    ack_sender: Ack,
+    game_uuid: Uuid,
+    registry: Registry,
+) {
+    route(
+        &registry,
+        &game_uuid,
+        GameEvent::Pass {
+            socket_ref,
+            ack_sender,
+        },
+    )
+    .await
+}
+
+async fn handle_play_request(
+    socket_ref: SocketRef,
+    data: Result<PlayRequest, DecodeError>,
+    ack_sender: Ack,
     game_uuid: Uuid,
-    sender: mpsc::Sender<Event>,
+    registry: Registry,
 ) {
-    if let GameRequest::Start {} = data {
-        sender
-            .send(Event::Game(GameEvent::StartGame { game_uuid }))
-            .await
-            .unwrap()
+    let Ok(PlayRequest { tiles }) = data else {
+        return response::reject(ack_sender, Error::InvalidRequest);
+    };
+
+    route(
+        &registry,
+        &game_uuid,
+        GameEvent::Play {
+            socket_ref,
+            tiles,
+            ack_sender,
+        },
+    )
+    .await
+}
+
+async fn handle_disconnect(socket_ref: SocketRef, game_uuid: Uuid, registry: Registry) {
+    if let Some(player) = socket_ref.extensions.get::<Player>() {
+        route(&registry, &game_uuid, GameEvent::Disconnect { player }).await
     }
 }
 
-pub fn on_connect(socket: SocketRef, sender: mpsc::Sender<Event>, game_uuid: Uuid) {
+pub fn on_connect(socket: SocketRef, registry: Registry, game_uuid: Uuid) {
+    crate::protocol::welcome(&socket);
+
     socket.on("register_request", {
-        let sender = sender.clone();
-        |socket: SocketRef, Data::<GameRequest>(data), ack_sender: AckSender| async move {
-            handle_registration_request(socket, data, ack_sender, sender).await;
+        let registry = registry.clone();
+        |socket: SocketRef, TryData::<RegisterRequest>(data), ack_sender: AckSender| async move {
+            let ack_sender = Ack::new(&socket, ack_sender);
+            handle_registration_request(socket, data, ack_sender, registry).await;
         }
     });
 
     socket.on("logout", {
-        let sender = sender.clone();
-        |socket: SocketRef, Data::<GameRequest>(data), ack: AckSender| async move {
-            handle_logout_request(socket, data, ack, sender).await;
+        let registry = registry.clone();
+        |socket: SocketRef, TryData::<LogoutRequest>(data), ack: AckSender| async move {
+            let ack = Ack::new(&socket, ack);
+            handle_logout_request(socket, data, ack, registry).await;
         }
     });
 
     socket.on("whoami", {
-        let sender = sender.clone();
-        |socket: SocketRef, Data::<GameRequest>(message), ack: AckSender| async move {
-            handle_id_request(socket, message, ack, sender).await;
+        let registry = registry.clone();
+        move |socket: SocketRef, TryData::<WhoAmIRequest>(message), ack: AckSender| async move {
+            let ack = Ack::new(&socket, ack);
+            handle_id_request(socket, message, ack, game_uuid, registry).await;
         }
     });
 
     socket.on("player-list", {
-        let sender = sender.clone();
-        move |socket_ref: SocketRef, Data::<GameRequest>(data), ack_sender: AckSender| async move {
-            handle_player_list_request(data, ack_sender, game_uuid, sender).await;
+        let registry = registry.clone();
+        move |socket: SocketRef, ack_sender: AckSender| async move {
+            let ack_sender = Ack::new(&socket, ack_sender);
+            handle_player_list_request(ack_sender, game_uuid, registry).await;
         }
     });
 
     socket.on("start", {
-        let sender = sender.clone();
-        move |socket: SocketRef, Data::<GameRequest>(data), ack: AckSender| async move {
-            handle_start_game_request(data, game_uuid, sender).await;
+        let registry = registry.clone();
+        move |socket: SocketRef, ack_sender: AckSender| async move {
+            let ack_sender = Ack::new(&socket, ack_sender);
+            handle_start_game_request(socket, ack_sender, game_uuid, registry).await;
         }
-    })
+    });
+
+    socket.on("pause", {
+        let registry = registry.clone();
+        move |socket: SocketRef, ack_sender: AckSender| async move {
+            let ack_sender = Ack::new(&socket, ack_sender);
+            handle_pause_request(socket, ack_sender, game_uuid, registry).await;
+        }
+    });
+
+    socket.on("resume", {
+        let registry = registry.clone();
+        move |socket: SocketRef, ack_sender: AckSender| async move {
+            let ack_sender = Ack::new(&socket, ack_sender);
+            handle_resume_request(socket, ack_sender, game_uuid, registry).await;
+        }
+    });
+
+    socket.on("invite", {
+        let registry = registry.clone();
+        |socket: SocketRef, TryData::<InviteRequest>(data), ack_sender: AckSender| async move {
+            let ack_sender = Ack::new(&socket, ack_sender);
+            handle_invite_request(socket, data, ack_sender, registry).await;
+        }
+    });
+
+    socket.on("close", {
+        let registry = registry.clone();
+        |socket: SocketRef, TryData::<CloseRequest>(data), ack_sender: AckSender| async move {
+            let ack_sender = Ack::new(&socket, ack_sender);
+            handle_close_request(socket, data, ack_sender, registry).await;
+        }
+    });
+
+    socket.on("pass", {
+        let registry = registry.clone();
+        move |socket: SocketRef, ack_sender: AckSender| async move {
+            let ack_sender = Ack::new(&socket, ack_sender);
+            handle_pass_request(socket, ack_sender, game_uuid, registry).await;
+        }
+    });
+
+    socket.on("play", {
+        let registry = registry.clone();
+        move |socket: SocketRef, TryData::<PlayRequest>(data), ack_sender: AckSender| async move {
+            let ack_sender = Ack::new(&socket, ack_sender);
+            handle_play_request(socket, data, ack_sender, game_uuid, registry).await;
+        }
+    });
+
+    socket.on_disconnect({
+        let registry = registry.clone();
+        move |socket: SocketRef| async move {
+            handle_disconnect(socket, game_uuid, registry).await;
+        }
+    });
 }
 
-pub fn handle_events(event: Event, socket_io: &SocketIo, manager: &mut Manager) {
-    if let Game(event) = event {
-        match event {
-            // A player found a game and decided to play
-            GameEvent::Registration {
-                socket_ref,
-                game_uuid,
-                player,
-                ack_sender,
-            } => {
-                let player_response = match manager.register_player_to_game(&game_uuid, player) {
-                    Ok(player) => Response::from_data(player),
-                    Err(error) => Response::from_error(error),
-                };
-                ack_sender.send(&player_response).unwrap();
-
-                let players_response =
-                    Response::from_data(manager.get_players_for_game(&game_uuid));
-                socket_ref
-                    .broadcast()
-                    .emit("players-list", &players_response)
-                    .ok();
-                socket_ref.emit("players-list", &players_response).ok();
-            }
+#[derive(Serialize, Debug)]
+struct StatusUpdate<'a> {
+    status: GameStatus,
+    current_player: Option<&'a Player>,
+    scores: &'a HashMap<Uuid, i32>,
+    /// Hash of the bag's seed, from the start on
+    seed_commitment: Option<String>,
+    /// The seed itself, as a string, once the game is over
+    seed: Option<String>,
+    /// Hashed with the seed into the commitment, revealed along with it
+    seed_salt: Option<String>,
+}
 
-            // A player hit the `Log out` button
-            GameEvent::Logout {
-                socket_ref,
-                game_uuid,
-                player_uuid,
-                ack,
-            } => {
-                let player_response =
-                    match manager.remove_player_from_game(&game_uuid, &player_uuid) {
-                        Err(error) => crate::response::Response::from_error(error),
-                        Ok(_) => {
-                            let players_response =
-                                Response::from_data(manager.get_players_for_game(&game_uuid));
-                            socket_ref
-                                .broadcast()
-                                .emit("players-list", &players_response)
-                                .ok();
-                            socket_ref.emit("players-list", &players_response).ok();
-
-                            Response::from_data("Player successfully removed")
-                        }
-                    };
-                ack.send(&player_response).unwrap();
-            }
+/// Keeps the lobby list in sync with the state of a game
+fn notify_lobby(socket_io: &SocketIo, room: &Room) {
+    lobby::notify_game_updated(socket_io, &room.get_summary());
+}
 
-            // A player refreshed their page, flushing the data
-            GameEvent::WhoAmI {
-                socket_ref,
-                player_uuid,
-                ack_sender,
-            } => {
-                let response = match manager.player_from_uuid(&player_uuid) {
-                    Ok(player) => {
-                        socket_ref.extensions.insert::<Player>(player.clone());
-                        Response::from_data(player)
+/// Tells everyone in the game and in the lobby that the game changed state
+pub fn notify_status(socket_io: &SocketIo, room: &Room) {
+    let game = room.get_game();
+    let response = Response::from_data(StatusUpdate {
+        status: game.get_status(),
+        current_player: game.get_current_player(),
+        scores: game.get_scores(),
+        seed_commitment: game.get_seed_commitment(),
+        seed: game.get_revealed_seed().map(|(x, _)| x.to_string()),
+        seed_salt: game.get_revealed_seed().map(|(_, x)| x.to_string()),
+    });
+
+    if let Some(namespace) = socket_io.of(format!("/game/{}", room.get_uuid())) {
+        namespace.emit("game-status", &response).ok();
+    }
+
+    notify_lobby(socket_io, room);
+}
+
+#[derive(Serialize, Debug)]
+struct RatingUpdate<'a> {
+    player_uuid: Uuid,
+    #[serde(flatten)]
+    change: &'a RatingChange,
+}
+
+/// Tells the players of a rated game how their ratings moved
+pub fn notify_ratings(socket_io: &SocketIo, game_uuid: &Uuid, changes: &[(Uuid, RatingChange)]) {
+    let updates: Vec<_> = changes
+        .iter()
+        .map(|(player_uuid, change)| RatingUpdate {
+            player_uuid: *player_uuid,
+            change,
+        })
+        .collect();
+
+    if let Some(namespace) = socket_io.of(format!("/game/{game_uuid}")) {
+        namespace
+            .emit("ratings-updated", &Response::from_data(updates))
+            .ok();
+    }
+}
+
+/// The player bound to a socket on registration
+fn socket_player_uuid(socket_ref: &SocketRef) -> Result<Uuid, Error> {
+    socket_ref
+        .extensions
+        .get::<Player>()
+        .map(|x| *x.get_id())
+        .ok_or(Error::PlayerNotRegistered)
+}
+
+/// Runs in the task of the game, nothing else touches `room`
+pub fn handle_event(event: GameEvent, socket_io: &SocketIo, room: &mut Room) {
+    let game_uuid = *room.get_uuid();
+
+    match event {
+        // A player found a game and decided to play
+        GameEvent::Registration {
+            socket_ref,
+            player,
+            invite_code,
+            ack_sender,
+        } => {
+            let player_uuid = *player.get_id();
+            let result = match room.register_player(player, invite_code.as_deref()) {
+                // The account already has a seat, most likely from another device
+                Err(Error::DuplicatePlayerId)
+                    if socket_ref.extensions.get::<Session>().is_some() =>
+                {
+                    room.player_reconnected(&player_uuid)
+                        .and_then(|_| room.get_player(&player_uuid))
+                }
+                result => result,
+            };
+
+            let player_response = match result.cloned() {
+                Ok(player) => Response::from_data(Seat {
+                    reconnect_token: room.get_game().get_reconnect_token(&player_uuid),
+                    player,
+                }),
+                Err(error) => {
+                    // The socket was bound to the player a bit too early
+                    socket_ref.extensions.remove::<Player>();
+                    Response::from_error(error)
+                }
+            };
+            ack_sender.send(player_response);
+
+            let players_response = Response::from_data(room.get_players());
+            socket_ref
+                .broadcast()
+                .emit("players-list", &players_response)
+                .ok();
+            socket_ref.emit("players-list", &players_response).ok();
+
+            notify_lobby(socket_io, room);
+        }
+
+        // A player hit the `Log out` button
+        GameEvent::Logout { socket_ref, ack } => {
+            let result = socket_player_uuid(&socket_ref)
+                .and_then(|player_uuid| room.remove_player(&player_uuid));
+
+            let player_response = match result {
+                Err(error) => crate::response::Response::from_error(error),
+                Ok(_) => {
+                    socket_ref.extensions.remove::<Player>();
+
+                    let players_response = Response::from_data(room.get_players());
+                    socket_ref
+                        .broadcast()
+                        .emit("players-list", &players_response)
+                        .ok();
+                    socket_ref.emit("players-list", &players_response).ok();
+
+                    // Leaving mid-game may end it
+                    notify_status(socket_io, room);
+
+                    Response::from_notice(Notice::PlayerRemoved)
+                }
+            };
+            ack.send(player_response);
+        }
+
+        // A player refreshed their page, flushing the data
+        GameEvent::WhoAmI {
+            socket_ref,
+            player_uuid,
+            reconnect_token,
+            ack_sender,
+        } => {
+            let account_id = socket_ref.extensions.get::<Session>().map(|x| x.account_id);
+            let result = room.claim_seat(
+                &player_uuid,
+                account_id.as_ref(),
+                reconnect_token.as_deref(),
+            );
+
+            let response = match result.cloned() {
+                Ok(player) => {
+                    socket_ref.extensions.insert::<Player>(player.clone());
+
+                    if room.player_reconnected(&player_uuid).is_ok() {
+                        presence::notify_presence(socket_io, &game_uuid, &player, true);
                     }
-                    Err(error) => Response::from_error(error),
-                };
 
-                ack_sender.send(&response).unwrap();
-            }
+                    // Games started by the server, like tournament rounds, were dealt
+                    // before anyone connected. The rack only goes out once the seat
+                    // is known to be theirs
+                    if let Some(rack) = room
+                        .get_game()
+                        .get_player_tiles(&player_uuid)
+                        .ok()
+                        .filter(|x| !x.is_empty())
+                    {
+                        socket_ref.emit("get-tiles", rack).ok();
+                    }
 
-            // A client would like to see who's in the game lobby
-            GameEvent::PlayerList {
-                game_uuid,
-                ack_sender,
-            } => {
-                let response = Response::from_data(manager.get_players_for_game(&game_uuid));
-                ack_sender.send(&response).unwrap();
+                    Response::from_data(player)
+                }
+                Err(error) => Response::from_error(error),
+            };
+
+            ack_sender.send(response);
+        }
+
+        // A player closed their tab or lost their connection, their seat is
+        // held for a while
+        GameEvent::Disconnect { player } => {
+            if room
+                .player_disconnected(player.get_id(), SystemTime::now())
+                .is_ok()
+            {
+                presence::notify_presence(socket_io, &game_uuid, &player, false);
             }
+        }
+
+        // The current player has nothing to play
+        GameEvent::Pass {
+            socket_ref,
+            ack_sender,
+        } => {
+            let result = socket_player_uuid(&socket_ref)
+                .and_then(|player_uuid| room.pass_turn(&player_uuid));
+
+            let response = match result {
+                Ok(_) => {
+                    metrics::record_move(MoveKind::Pass);
+                    notify_status(socket_io, room);
+                    Response::from_notice(Notice::TurnPassed)
+                }
+                Err(error) => Response::from_error(error),
+            };
+
+            ack_sender.send(response);
+        }
 
-            // Game is started, tiles are given to the players
-            GameEvent::StartGame { game_uuid } => {
-                if let Ok(racks) = manager.start_game(&game_uuid) {
-                    let sockets_iter = socket_io
+        // The current player put tiles on the board
+        GameEvent::Play {
+            socket_ref,
+            tiles,
+            ack_sender,
+        } => {
+            let started_at = Instant::now();
+            let result = socket_player_uuid(&socket_ref)
+                .and_then(|player_uuid| room.play_move(&player_uuid, &tiles));
+            metrics::observe_move_evaluation(started_at.elapsed());
+
+            let response = match result {
+                Ok((turn, rack)) => {
+                    metrics::record_move(MoveKind::Play);
+                    let turn_response = Response::from_data(&turn);
+                    socket_ref
+                        .broadcast()
+                        .emit("move-played", &turn_response)
+                        .ok();
+                    socket_ref.emit("move-played", &turn_response).ok();
+                    socket_ref.emit("get-tiles", &rack).ok();
+
+                    notify_status(socket_io, room);
+
+                    Response::from_data(turn)
+                }
+                Err(error) => Response::from_error(error),
+            };
+
+            ack_sender.send(response);
+        }
+
+        // A client would like to see who's in the game lobby
+        GameEvent::PlayerList { ack_sender } => {
+            let response = Response::from_data(room.get_players());
+            ack_sender.send(response);
+        }
+
+        // Game is started, tiles are given to the players
+        GameEvent::StartGame {
+            socket_ref,
+            ack_sender,
+        } => {
+            let result = socket_player_uuid(&socket_ref).and_then(|_| room.start());
+
+            let response = match result {
+                Ok(racks) => {
+                    let sockets = socket_io
                         .of(format!("/game/{game_uuid}"))
-                        .unwrap()
-                        .sockets()
-                        .unwrap_or(Vec::new())
-                        .into_iter();
+                        .and_then(|x| x.sockets().ok())
+                        .unwrap_or_default();
 
-                    for socket in sockets_iter {
+                    for socket in sockets {
                         if let Some(player) = socket.extensions.get::<Player>() {
                             if let Some(rack) = racks.get(player.get_id()) {
-                                socket.emit("get-tiles", rack).unwrap()
+                                socket.emit("get-tiles", rack).ok();
                             }
                         }
                     }
+
+                    notify_status(socket_io, room);
+
+                    Response::from_notice(Notice::GameStarted)
                 }
-            }
+                Err(error) => Response::from_error(error),
+            };
+
+            ack_sender.send(response);
+        }
+
+        // The host needs a break
+        GameEvent::Pause {
+            socket_ref,
+            ack_sender,
+        } => {
+            let result =
+                socket_player_uuid(&socket_ref).and_then(|player_uuid| room.pause(&player_uuid));
+
+            let response = match result {
+                Ok(_) => {
+                    notify_status(socket_io, room);
+                    Response::from_notice(Notice::GamePaused)
+                }
+                Err(error) => Response::from_error(error),
+            };
+
+            ack_sender.send(response);
+        }
+
+        // The host is back
+        GameEvent::Resume {
+            socket_ref,
+            ack_sender,
+        } => {
+            let result =
+                socket_player_uuid(&socket_ref).and_then(|player_uuid| room.resume(&player_uuid));
+
+            let response = match result {
+                Ok(_) => {
+                    notify_status(socket_io, room);
+                    Response::from_notice(Notice::GameResumed)
+                }
+                Err(error) => Response::from_error(error),
+            };
+
+            ack_sender.send(response);
+        }
+
+        // The host gave up on a game before it started
+        GameEvent::Close {
+            socket_ref,
+            ack_sender,
+        } => {
+            let result =
+                socket_player_uuid(&socket_ref).and_then(|player_uuid| room.close(&player_uuid));
+
+            let response = match result {
+                Ok(_) => {
+                    socket_ref
+                        .broadcast()
+                        .emit("game-closed", &Response::from_data(game_uuid))
+                        .ok();
+
+                    lobby::notify_game_removed(socket_io, &room.get_summary());
+
+                    Response::from_notice(Notice::GameClosed)
+                }
+                Err(error) => Response::from_error(error),
+            };
+
+            ack_sender.send(response);
+        }
+
+        // The host of a private game manages who can still join
+        GameEvent::Invite {
+            socket_ref,
+            action,
+            ack_sender,
+        } => {
+            let result = socket_player_uuid(&socket_ref).and_then(|player_uuid| match action {
+                InviteAction::Show => room.get_invite(&player_uuid),
+                InviteAction::Regenerate { expires_in } => {
+                    room.regenerate_invite(&player_uuid, expires_in).map(Some)
+                }
+                InviteAction::Revoke => room.revoke_invite(&player_uuid).map(|_| None),
+            });
+
+            let response = match result {
+                Ok(invite) => Response::from_data(invite),
+                Err(error) => Response::from_error(error),
+            };
+
+            ack_sender.send(response);
         }
     }
 }
@@ -0,0 +1,389 @@
+use crate::protocol::Handshake;
+use crate::Error;
+use schemars::JsonSchema;
+use scrabble_core::GameStatus;
+use serde::{Deserialize, Serialize};
+use socketioxide::extract::{SocketRef, TryData};
+use std::collections::{BTreeMap, HashMap};
+use std::convert::Infallible;
+use std::sync::OnceLock;
+use ts_rs::TS;
+
+/// Languages players can be answered in
+#[derive(
+    Copy, Clone, PartialEq, Eq, Hash, Default, Serialize, Deserialize, Debug, JsonSchema, TS,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Locale {
+    #[default]
+    En,
+    Fr,
+    Es,
+}
+
+impl Locale {
+    pub const ALL: [Locale; 3] = [Locale::En, Locale::Fr, Locale::Es];
+
+    fn get_catalog_source(self) -> &'static str {
+        match self {
+            Locale::En => include_str!("../locales/en.toml"),
+            Locale::Fr => include_str!("../locales/fr.toml"),
+            Locale::Es => include_str!("../locales/es.toml"),
+        }
+    }
+
+    /// Locale of a language tag such as `fr-CA`, whatever the region
+    pub fn from_tag(tag: &str) -> Option<Locale> {
+        let language = tag.trim().split(['-', '_']).next()?;
+
+        match language.to_ascii_lowercase().as_str() {
+            "en" => Some(Locale::En),
+            "fr" => Some(Locale::Fr),
+            "es" => Some(Locale::Es),
+            _ => None,
+        }
+    }
+
+    /// Preferred locale of an `Accept-Language` header, among the ones we have
+    pub fn from_accept_language(header: &str) -> Option<Locale> {
+        let mut tags: Vec<(f32, &str)> = header
+            .split(',')
+            .map(|x| {
+                let mut parts = x.split(';');
+                let tag = parts.next().unwrap_or_default();
+                let weight = parts
+                    .find_map(|x| x.trim().strip_prefix("q="))
+                    .and_then(|x| x.parse().ok())
+                    .unwrap_or(1.0);
+
+                (weight, tag)
+            })
+            .collect();
+
+        // The sort is stable, so equal weights keep the order of the header
+        tags.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+        tags.into_iter()
+            .filter(|(weight, _)| *weight > 0.0)
+            .find_map(|(_, tag)| Locale::from_tag(tag))
+    }
+
+    /// Locale negotiated when the socket connected
+    pub fn of(socket_ref: &SocketRef) -> Locale {
+        socket_ref.extensions.get::<Locale>().unwrap_or_default()
+    }
+}
+
+/// Connect middleware settling the locale of a socket
+///
+/// The `locale` of the handshake wins, then the `Accept-Language` header of the
+/// connection, and English if neither is something we have.
+pub fn negotiate_locale(
+    socket_ref: SocketRef,
+    TryData(handshake): TryData<Handshake>,
+) -> Result<(), Infallible> {
+    let requested = handshake
+        .ok()
+        .and_then(|x| x.locale)
+        .and_then(|x| Locale::from_tag(&x));
+    let accepted = || {
+        socket_ref
+            .req_parts()
+            .headers
+            .get(http::header::ACCEPT_LANGUAGE)
+            .and_then(|x| x.to_str().ok())
+            .and_then(Locale::from_accept_language)
+    };
+
+    socket_ref
+        .extensions
+        .insert::<Locale>(requested.or_else(accepted).unwrap_or_default());
+
+    Ok(())
+}
+
+/// What the server tells players when a request went well
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Notice {
+    PlayerRemoved,
+    TurnPassed,
+    GameStarted,
+    GamePaused,
+    GameResumed,
+    GameClosed,
+    LoggedOut,
+    WaitingForOpponents,
+    LeftQueue,
+    TournamentBegun,
+    MaintenanceStarted,
+    MaintenanceOver,
+    ServerRestarting,
+}
+
+impl Notice {
+    fn get_key(self) -> &'static str {
+        match self {
+            Notice::PlayerRemoved => "player_removed",
+            Notice::TurnPassed => "turn_passed",
+            Notice::GameStarted => "game_started",
+            Notice::GamePaused => "game_paused",
+            Notice::GameResumed => "game_resumed",
+            Notice::GameClosed => "game_closed",
+            Notice::LoggedOut => "logged_out",
+            Notice::WaitingForOpponents => "waiting_for_opponents",
+            Notice::LeftQueue => "left_queue",
+            Notice::TournamentBegun => "tournament_begun",
+            Notice::MaintenanceStarted => "maintenance_started",
+            Notice::MaintenanceOver => "maintenance_over",
+            Notice::ServerRestarting => "server_restarting",
+        }
+    }
+}
+
+/// Value filled in a message, statuses are translated as well
+#[derive(Clone, PartialEq, Serialize, Debug, JsonSchema, TS)]
+#[serde(untagged)]
+pub enum Param {
+    Status(GameStatus),
+    Words(Vec<String>),
+}
+
+impl Param {
+    fn render(&self, locale: Locale) -> String {
+        match self {
+            Param::Status(status) => translate(locale, &format!("status.{}", status_key(*status))),
+            Param::Words(words) => words.join(", "),
+        }
+    }
+}
+
+fn status_key(status: GameStatus) -> &'static str {
+    match status {
+        GameStatus::Waiting => "waiting",
+        GameStatus::Starting => "starting",
+        GameStatus::InProgress => "in-progress",
+        GameStatus::Paused => "paused",
+        GameStatus::Finished => "finished",
+        GameStatus::Abandoned => "abandoned",
+    }
+}
+
+/// Text for a player, sent as a catalog key and parameters so clients can
+/// render it themselves, along with the text in the locale of the socket
+#[derive(Clone, PartialEq, Serialize, Debug, JsonSchema, TS)]
+pub struct Message {
+    key: &'static str,
+    params: BTreeMap<&'static str, Param>,
+}
+
+impl Message {
+    fn new(key: &'static str) -> Self {
+        Message {
+            key,
+            params: BTreeMap::new(),
+        }
+    }
+
+    fn with(mut self, name: &'static str, param: Param) -> Self {
+        self.params.insert(name, param);
+        self
+    }
+
+    pub fn render(&self, locale: Locale) -> String {
+        let mut text = translate(locale, self.key);
+
+        for (name, param) in &self.params {
+            text = text.replace(&format!("{{{name}}}"), &param.render(locale));
+        }
+
+        text
+    }
+}
+
+impl From<Notice> for Message {
+    fn from(notice: Notice) -> Self {
+        Message::new(notice.get_key())
+    }
+}
+
+impl From<&Error> for Message {
+    fn from(error: &Error) -> Self {
+        let message = Message::new(error.code());
+
+        match error {
+            Error::ActionNotAllowed(status) => message.with("status", Param::Status(*status)),
+            Error::InvalidTransition(from, to) => message
+                .with("from", Param::Status(*from))
+                .with("to", Param::Status(*to)),
+            Error::UnknownWords(words) => message.with("words", Param::Words(words.clone())),
+            _ => message,
+        }
+    }
+}
+
+type Catalog = HashMap<String, String>;
+
+/// Every catalog, keys of nested tables are joined with a dot
+fn catalogs() -> &'static HashMap<Locale, Catalog> {
+    static CATALOGS: OnceLock<HashMap<Locale, Catalog>> = OnceLock::new();
+
+    CATALOGS.get_or_init(|| {
+        Locale::ALL
+            .into_iter()
+            .map(|locale| {
+                // Catalogs are built in, a broken one is caught by the tests
+                let table = locale
+                    .get_catalog_source()
+                    .parse::<toml::Table>()
+                    .unwrap_or_default();
+
+                let mut catalog = Catalog::new();
+                flatten("", table, &mut catalog);
+
+                (locale, catalog)
+            })
+            .collect()
+    })
+}
+
+fn flatten(prefix: &str, table: toml::Table, catalog: &mut Catalog) {
+    for (key, value) in table {
+        let key = format!("{prefix}{key}");
+
+        match value {
+            toml::Value::String(text) => {
+                catalog.insert(key, text);
+            }
+            toml::Value::Table(table) => flatten(&format!("{key}."), table, catalog),
+            _ => {}
+        }
+    }
+}
+
+/// Text of a key, in English if the locale misses it, or the key itself if
+/// nobody has it
+fn translate(locale: Locale, key: &str) -> String {
+    let catalogs = catalogs();
+
+    [locale, Locale::En]
+        .iter()
+        .find_map(|x| catalogs.get(x)?.get(key))
+        .cloned()
+        .unwrap_or_else(|| key.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOTICES: [Notice; 13] = [
+        Notice::PlayerRemoved,
+        Notice::TurnPassed,
+        Notice::GameStarted,
+        Notice::GamePaused,
+        Notice::GameResumed,
+        Notice::GameClosed,
+        Notice::LoggedOut,
+        Notice::WaitingForOpponents,
+        Notice::LeftQueue,
+        Notice::TournamentBegun,
+        Notice::MaintenanceStarted,
+        Notice::MaintenanceOver,
+        Notice::ServerRestarting,
+    ];
+
+    #[test]
+    fn catalogs_have_the_same_keys() {
+        let catalogs = catalogs();
+        let english = &catalogs[&Locale::En];
+        assert!(!english.is_empty());
+
+        for locale in Locale::ALL {
+            let mut keys: Vec<_> = catalogs[&locale].keys().collect();
+            let mut expected: Vec<_> = english.keys().collect();
+            keys.sort();
+            expected.sort();
+
+            assert_eq!(keys, expected, "{locale:?}");
+        }
+    }
+
+    #[test]
+    fn every_notice_and_status_is_translated() {
+        let english = &catalogs()[&Locale::En];
+
+        for notice in NOTICES {
+            assert!(english.contains_key(notice.get_key()), "{notice:?}");
+        }
+
+        for status in [
+            GameStatus::Waiting,
+            GameStatus::Starting,
+            GameStatus::InProgress,
+            GameStatus::Paused,
+            GameStatus::Finished,
+            GameStatus::Abandoned,
+        ] {
+            assert!(english.contains_key(&format!("status.{}", status_key(status))));
+        }
+    }
+
+    #[test]
+    fn messages_are_rendered_with_their_params() {
+        let message = Message::from(&Error::ActionNotAllowed(GameStatus::InProgress));
+
+        assert_eq!(
+            message.render(Locale::En),
+            "Action not allowed while the game is in progress"
+        );
+        assert_eq!(
+            message.render(Locale::Fr),
+            "Action impossible tant que la partie est en cours"
+        );
+        assert_eq!(
+            serde_json::to_value(&message).unwrap(),
+            serde_json::json!({
+                "key": "action_not_allowed",
+                "params": { "status": "in-progress" },
+            })
+        );
+    }
+
+    #[test]
+    fn unknown_words_are_listed() {
+        let words = vec![String::from("XAT"), String::from("QI")];
+        let message = Message::from(&Error::UnknownWords(words));
+
+        assert_eq!(message.render(Locale::En), "Not in the word list: XAT, QI");
+        assert_eq!(
+            serde_json::to_value(&message).unwrap(),
+            serde_json::json!({
+                "key": "unknown_words",
+                "params": { "words": ["XAT", "QI"] },
+            })
+        );
+    }
+
+    #[test]
+    fn unknown_keys_fall_back() {
+        assert_eq!(translate(Locale::Es, "no_such_key"), "no_such_key");
+        assert_eq!(
+            Message::from(Notice::GameStarted).render(Locale::Es),
+            "Partida empezada"
+        );
+    }
+
+    #[test]
+    fn locales_are_negotiated() {
+        assert_eq!(Locale::from_tag("fr-CA"), Some(Locale::Fr));
+        assert_eq!(Locale::from_tag("ES"), Some(Locale::Es));
+        assert_eq!(Locale::from_tag("de"), None);
+
+        assert_eq!(
+            Locale::from_accept_language("de-DE, es;q=0.8, fr;q=0.9"),
+            Some(Locale::Fr)
+        );
+        assert_eq!(Locale::from_accept_language("en;q=0, es"), Some(Locale::Es));
+        assert_eq!(Locale::from_accept_language("de, it"), None);
+    }
+}
@@ -0,0 +1,101 @@
+use crate::summary::unix_timestamp;
+use crate::Error;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, SystemTime};
+
+// No 0/O or 1/I, so codes can be read out loud or copied by hand
+const INVITE_CODE_ALPHABET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+const INVITE_CODE_LENGTH: usize = 6;
+
+/// Short code a player must present to join a private game
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct Invite {
+    pub code: String,
+    /// Seconds since the UNIX epoch, `None` if the code never expires
+    pub expires_at: Option<u64>,
+}
+
+impl Invite {
+    pub fn generate(time_to_live: Option<Duration>) -> Self {
+        let mut rng = rand::thread_rng();
+        let code = (0..INVITE_CODE_LENGTH)
+            .map(|_| INVITE_CODE_ALPHABET[rng.gen_range(0..INVITE_CODE_ALPHABET.len())] as char)
+            .collect();
+
+        // Lifetimes are bounded by `limits.max_duration`, one past the end of time
+        // would be as good as never expiring
+        let expires_at = time_to_live.map(|x| {
+            SystemTime::now()
+                .checked_add(x)
+                .map_or(u64::MAX, unix_timestamp)
+        });
+
+        Invite { code, expires_at }
+    }
+
+    pub fn is_expired(&self, now: SystemTime) -> bool {
+        self.expires_at
+            .is_some_and(|expires_at| unix_timestamp(now) >= expires_at)
+    }
+
+    /// Codes are compared case-insensitively, surrounding blanks are ignored
+    pub fn check(&self, code: Option<&str>, now: SystemTime) -> Result<(), Error> {
+        match code {
+            Some(code) if code.trim().eq_ignore_ascii_case(&self.code) => {
+                if self.is_expired(now) {
+                    Err(Error::InviteCodeExpired)
+                } else {
+                    Ok(())
+                }
+            }
+            _ => Err(Error::InvalidInviteCode),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generated_code_uses_alphabet() {
+        let invite = Invite::generate(None);
+
+        assert_eq!(invite.code.len(), INVITE_CODE_LENGTH);
+        assert!(invite
+            .code
+            .bytes()
+            .all(|x| INVITE_CODE_ALPHABET.contains(&x)));
+    }
+
+    #[test]
+    fn check_accepts_matching_code() {
+        let invite = Invite::generate(None);
+        let code = invite.code.to_lowercase();
+
+        assert!(invite.check(Some(&code), SystemTime::now()).is_ok());
+        assert_eq!(
+            invite.check(Some("nope"), SystemTime::now()),
+            Err(Error::InvalidInviteCode)
+        );
+        assert_eq!(
+            invite.check(None, SystemTime::now()),
+            Err(Error::InvalidInviteCode)
+        );
+    }
+
+    #[test]
+    fn check_rejects_expired_code() {
+        let invite = Invite::generate(Some(Duration::from_secs(60)));
+        let code = invite.code.to_string();
+
+        assert!(invite.check(Some(&code), SystemTime::now()).is_ok());
+
+        let later = SystemTime::now() + Duration::from_secs(61);
+        assert_eq!(
+            invite.check(Some(&code), later),
+            Err(Error::InviteCodeExpired)
+        );
+    }
+}
@@ -0,0 +1,163 @@
+pub mod accounts;
+mod actor;
+mod admin;
+mod api;
+pub mod config;
+pub mod events;
+mod game;
+mod i18n;
+mod invite;
+mod lobby;
+pub mod maintenance;
+pub mod manager;
+mod matchmaking;
+mod metrics;
+mod presence;
+pub mod protocol;
+pub mod ratings;
+mod reaper;
+mod registry;
+mod response;
+mod room;
+mod scrabble;
+pub mod server;
+mod settings;
+pub mod stats;
+mod storage;
+mod summary;
+mod tournament;
+
+use crate::i18n::{Locale, Message};
+use scrabble_core::GameStatus;
+use serde::Serializer;
+use std::fmt::Formatter;
+
+#[derive(Debug, PartialEq)]
+pub enum Error {
+    NotEnoughPlayers,
+    TooManyPlayer,
+    DuplicatePlayerId,
+    PlayerNotRegistered,
+    NoMoreTiles,
+    PlayerHas7Tiles,
+    GameNotFound,
+    InvalidSettings,
+    NotHost,
+    ActionNotAllowed(GameStatus),
+    InvalidTransition(GameStatus, GameStatus),
+    GameNotPrivate,
+    InvalidInviteCode,
+    InviteCodeExpired,
+    NotYourTurn,
+    InvalidUsername,
+    WeakPassword,
+    UsernameTaken,
+    InvalidCredentials,
+    InvalidSession,
+    Storage,
+    AccountRequired,
+    InvalidPlacement,
+    TileNotInRack,
+    UnknownWords(Vec<String>),
+    AlreadyQueued,
+    NotQueued,
+    TournamentNotFound,
+    TournamentStarted,
+    NotDirector,
+    AccountNotFound,
+    TooManyGames,
+    Maintenance,
+    InvalidRequest,
+    AlreadyRegistered,
+    NotYourSeat,
+    ServerUnavailable,
+    UnsupportedProtocol,
+    Internal,
+}
+
+impl Error {
+    /// Machine-readable name of the error, sent along with the message so clients
+    /// can react to it without parsing text
+    ///
+    /// Clients rely on these, so a code never changes once released.
+    fn code(&self) -> &'static str {
+        match self {
+            Error::NotEnoughPlayers => "not_enough_players",
+            Error::TooManyPlayer => "too_many_players",
+            Error::DuplicatePlayerId => "duplicate_player_id",
+            Error::PlayerNotRegistered => "player_not_registered",
+            Error::NoMoreTiles => "no_more_tiles",
+            Error::PlayerHas7Tiles => "player_has_7_tiles",
+            Error::GameNotFound => "game_not_found",
+            Error::InvalidSettings => "invalid_settings",
+            Error::NotHost => "not_host",
+            Error::ActionNotAllowed(_) => "action_not_allowed",
+            Error::InvalidTransition(_, _) => "invalid_transition",
+            Error::GameNotPrivate => "game_not_private",
+            Error::InvalidInviteCode => "invalid_invite_code",
+            Error::InviteCodeExpired => "invite_code_expired",
+            Error::NotYourTurn => "not_your_turn",
+            Error::InvalidUsername => "invalid_username",
+            Error::WeakPassword => "weak_password",
+            Error::UsernameTaken => "username_taken",
+            Error::InvalidCredentials => "invalid_credentials",
+            Error::InvalidSession => "invalid_session",
+            Error::Storage => "storage",
+            Error::AccountRequired => "account_required",
+            Error::InvalidPlacement => "invalid_placement",
+            Error::TileNotInRack => "tile_not_in_rack",
+            Error::UnknownWords(_) => "unknown_words",
+            Error::AlreadyQueued => "already_queued",
+            Error::NotQueued => "not_queued",
+            Error::TournamentNotFound => "tournament_not_found",
+            Error::TournamentStarted => "tournament_started",
+            Error::NotDirector => "not_director",
+            Error::AccountNotFound => "account_not_found",
+            Error::TooManyGames => "too_many_games",
+            Error::Maintenance => "maintenance",
+            Error::InvalidRequest => "invalid_request",
+            Error::AlreadyRegistered => "already_registered",
+            Error::NotYourSeat => "not_your_seat",
+            Error::ServerUnavailable => "server_unavailable",
+            Error::UnsupportedProtocol => "unsupported_protocol",
+            Error::Internal => "internal",
+        }
+    }
+}
+
+impl serde::Serialize for Error {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&format!("{}", self))
+    }
+}
+
+/// Errors read in English, players get them in their language from the catalogs
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", Message::from(self).render(Locale::En))
+    }
+}
+
+/// Every refusal of the rules has an error of its own here, with the same name
+impl From<scrabble_core::Error> for Error {
+    fn from(error: scrabble_core::Error) -> Self {
+        use scrabble_core::Error as Rules;
+
+        match error {
+            Rules::NotEnoughPlayers => Error::NotEnoughPlayers,
+            Rules::DuplicatePlayerId => Error::DuplicatePlayerId,
+            Rules::PlayerNotRegistered => Error::PlayerNotRegistered,
+            Rules::NoMoreTiles => Error::NoMoreTiles,
+            Rules::PlayerHas7Tiles => Error::PlayerHas7Tiles,
+            Rules::ActionNotAllowed(status) => Error::ActionNotAllowed(status),
+            Rules::InvalidTransition(from, to) => Error::InvalidTransition(from, to),
+            Rules::NotYourTurn => Error::NotYourTurn,
+            Rules::InvalidPlacement => Error::InvalidPlacement,
+            Rules::TileNotInRack => Error::TileNotInRack,
+            Rules::UnknownWords(words) => Error::UnknownWords(words),
+        }
+    }
+}
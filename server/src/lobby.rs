@@ -1,53 +1,870 @@
-use crate::events::Event;
+use crate::accounts::{self, Session, SharedAccounts};
 use crate::events::Event::Lobby;
-use crate::manager::Manager;
-use crate::response::Response;
-use serde::{Deserialize, Serialize};
-use socketioxide::extract::{AckSender, Data, SocketRef};
-use tokio::sync::mpsc;
+use crate::events::{Event, EventSender};
+use crate::i18n::Notice;
+use crate::manager::{CreatedGame, Manager, Seating};
+use crate::matchmaking::QueuePreferences;
+use crate::protocol::{
+    CreateGameRequest, CreateTournamentRequest, CredentialsRequest, JoinQueueRequest,
+    LeaderboardRequest, ListGamesRequest, PlayerStatsRequest, RatingsRequest, TournamentRequest,
+};
+use crate::response::{self, Ack, Response};
+use crate::settings::{GameSettings, Variant};
+use crate::stats::{LeaderboardKind, StatsFilter};
+use crate::summary::{GameFilter, GameSummary, Pagination};
+use crate::tournament::TournamentSettings;
+use crate::Error;
+use scrabble_core::Player;
+use serde::Serialize;
+use socketioxide::extract::{AckSender, SocketRef, TryData};
+use socketioxide::{DecodeError, SocketIo};
+use std::sync::PoisonError;
+use std::time::SystemTime;
+use tokio::sync::mpsc::error::SendError;
+use tracing::debug;
+use uuid::Uuid;
 
-#[derive(Deserialize, Serialize, Debug, Clone)]
-#[serde(rename_all = "camelCase", untagged)]
-enum LobbyRequest {
-    ListGames,
+/// Tells a queued player where their game is, they are already registered in it
+#[derive(Serialize, Debug)]
+struct MatchFound<'a> {
+    game_uuid: Uuid,
+    namespace: String,
+    player: &'a Player,
+    players: &'a [Player],
+    /// Only for guests, to claim their seat with `whoami`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reconnect_token: Option<&'a str>,
+}
+
+/// Tells a tournament player where their game of the round is
+#[derive(Serialize, Debug)]
+struct TournamentGame<'a> {
+    tournament_uuid: Uuid,
+    round: usize,
+    game_uuid: Uuid,
+    namespace: String,
+    /// Only for guests, to claim their seat with `whoami`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reconnect_token: Option<&'a str>,
 }
 
 pub enum LobbyEvent {
-    ListGames { ack_sender: AckSender },
+    ListGames {
+        filter: GameFilter,
+        pagination: Pagination,
+        ack_sender: Ack,
+    },
+    CreateGame {
+        settings: GameSettings,
+        ack_sender: Ack,
+    },
+    Ratings {
+        account_id: Uuid,
+        variant: Option<Variant>,
+        ack_sender: Ack,
+    },
+    PlayerStats {
+        account_id: Uuid,
+        filter: StatsFilter,
+        ack_sender: Ack,
+    },
+    Leaderboard {
+        kind: LeaderboardKind,
+        filter: StatsFilter,
+        limit: Option<usize>,
+        ack_sender: Ack,
+    },
+    JoinQueue {
+        player: Player,
+        preferences: QueuePreferences,
+        ack_sender: Ack,
+    },
+    LeaveQueue {
+        player_uuid: Uuid,
+        ack_sender: Ack,
+    },
+    Disconnect {
+        player_uuid: Uuid,
+    },
+    CreateTournament {
+        settings: TournamentSettings,
+        director: Player,
+        ack_sender: Ack,
+    },
+    JoinTournament {
+        tournament_uuid: Uuid,
+        player: Player,
+        ack_sender: Ack,
+    },
+    StartTournament {
+        tournament_uuid: Uuid,
+        player: Player,
+        ack_sender: Ack,
+    },
+    Tournament {
+        tournament_uuid: Uuid,
+        ack_sender: Ack,
+    },
+    ListTournaments {
+        ack_sender: Ack,
+    },
+}
+
+impl LobbyEvent {
+    /// Answers a request the lobby loop could not take
+    fn reject(self, error: Error) {
+        let ack_sender = match self {
+            LobbyEvent::ListGames { ack_sender, .. }
+            | LobbyEvent::CreateGame { ack_sender, .. }
+            | LobbyEvent::Ratings { ack_sender, .. }
+            | LobbyEvent::PlayerStats { ack_sender, .. }
+            | LobbyEvent::Leaderboard { ack_sender, .. }
+            | LobbyEvent::JoinQueue { ack_sender, .. }
+            | LobbyEvent::LeaveQueue { ack_sender, .. }
+            | LobbyEvent::CreateTournament { ack_sender, .. }
+            | LobbyEvent::JoinTournament { ack_sender, .. }
+            | LobbyEvent::StartTournament { ack_sender, .. }
+            | LobbyEvent::Tournament { ack_sender, .. }
+            | LobbyEvent::ListTournaments { ack_sender } => ack_sender,
+            LobbyEvent::Disconnect { .. } => return,
+        };
+
+        response::reject(ack_sender, error);
+    }
+}
+
+/// Hands a request to the lobby loop, which is only gone once the server shuts down
+async fn forward(sender: &EventSender, event: LobbyEvent) {
+    if let Err(SendError(Lobby(event))) = sender.send(Lobby(event)).await {
+        event.reject(Error::ServerUnavailable);
+    }
 }
 
 async fn handle_list_games_request(
-    message: LobbyRequest,
-    ack_sender: AckSender,
-    sender: mpsc::Sender<Event>,
+    message: Result<ListGamesRequest, DecodeError>,
+    ack_sender: Ack,
+    sender: EventSender,
 ) {
-    if let LobbyRequest::ListGames = message {
-        sender
-            .send(Event::Lobby(LobbyEvent::ListGames { ack_sender }))
-            .await
-            .unwrap();
-    }
+    let Ok(ListGamesRequest { filter, pagination }) = message else {
+        return response::reject(ack_sender, Error::InvalidRequest);
+    };
+
+    let event = LobbyEvent::ListGames {
+        filter,
+        pagination,
+        ack_sender,
+    };
+    forward(&sender, event).await;
+}
+
+async fn handle_create_game_request(
+    message: Result<CreateGameRequest, DecodeError>,
+    ack_sender: Ack,
+    sender: EventSender,
+) {
+    let Ok(CreateGameRequest { settings }) = message else {
+        return response::reject(ack_sender, Error::InvalidRequest);
+    };
+
+    let event = LobbyEvent::CreateGame {
+        settings,
+        ack_sender,
+    };
+    forward(&sender, event).await;
+}
+
+async fn handle_ratings_request(
+    message: Result<RatingsRequest, DecodeError>,
+    ack_sender: Ack,
+    sender: EventSender,
+) {
+    let Ok(RatingsRequest {
+        account_id,
+        variant,
+    }) = message
+    else {
+        return response::reject(ack_sender, Error::InvalidRequest);
+    };
+
+    let event = LobbyEvent::Ratings {
+        account_id,
+        variant,
+        ack_sender,
+    };
+    forward(&sender, event).await;
+}
+
+async fn handle_player_stats_request(
+    message: Result<PlayerStatsRequest, DecodeError>,
+    ack_sender: Ack,
+    sender: EventSender,
+) {
+    let Ok(PlayerStatsRequest { account_id, filter }) = message else {
+        return response::reject(ack_sender, Error::InvalidRequest);
+    };
+
+    let event = LobbyEvent::PlayerStats {
+        account_id,
+        filter,
+        ack_sender,
+    };
+    forward(&sender, event).await;
+}
+
+async fn handle_leaderboard_request(
+    message: Result<LeaderboardRequest, DecodeError>,
+    ack_sender: Ack,
+    sender: EventSender,
+) {
+    let Ok(LeaderboardRequest {
+        leaderboard,
+        filter,
+        limit,
+    }) = message
+    else {
+        return response::reject(ack_sender, Error::InvalidRequest);
+    };
+
+    let event = LobbyEvent::Leaderboard {
+        kind: leaderboard,
+        filter,
+        limit,
+        ack_sender,
+    };
+    forward(&sender, event).await;
+}
+
+/// Room of the lobby sockets of a queued player, to reach them once matched
+fn player_room(player_uuid: &Uuid) -> String {
+    format!("player/{player_uuid}")
+}
+
+/// Who a lobby socket plays as, logged in players play under their account
+///
+/// Guests keep the same UUID for as long as their socket stays connected. Either
+/// way the socket joins the player's room so it hears about their games.
+fn lobby_player(socket_ref: &SocketRef, username: Option<&str>) -> Player {
+    let player = match socket_ref.extensions.get::<Session>() {
+        Some(session) => Player::with_account(&session.account_id, &session.username),
+        None => socket_ref
+            .extensions
+            .get::<Player>()
+            .filter(|x| !x.is_registered())
+            .unwrap_or_else(|| Player::new(&Uuid::new_v4(), username.unwrap_or("Guest"))),
+    };
+
+    socket_ref.extensions.insert::<Player>(player.clone());
+    socket_ref.join(player_room(player.get_id())).ok();
+
+    player
 }
 
-pub fn on_connect(socket: SocketRef, sender: mpsc::Sender<Event>) {
-    let sender_clone = sender.clone();
+async fn handle_join_queue_request(
+    socket_ref: SocketRef,
+    message: Result<JoinQueueRequest, DecodeError>,
+    ack_sender: Ack,
+    sender: EventSender,
+) {
+    let Ok(JoinQueueRequest {
+        preferences,
+        username,
+    }) = message
+    else {
+        return response::reject(ack_sender, Error::InvalidRequest);
+    };
+
+    let event = LobbyEvent::JoinQueue {
+        player: lobby_player(&socket_ref, username.as_deref()),
+        preferences,
+        ack_sender,
+    };
+    forward(&sender, event).await;
+}
+
+async fn handle_leave_queue_request(socket_ref: SocketRef, ack_sender: Ack, sender: EventSender) {
+    let Some(player) = socket_ref.extensions.get::<Player>() else {
+        return response::reject(ack_sender, Error::NotQueued);
+    };
+
+    let event = LobbyEvent::LeaveQueue {
+        player_uuid: *player.get_id(),
+        ack_sender,
+    };
+    forward(&sender, event).await;
+}
 
-    socket.on(
-        "list-games",
-        |socket_ref: SocketRef, Data::<LobbyRequest>(message), ack_sender: AckSender| async move {
-            handle_list_games_request(message, ack_sender, sender_clone).await;
+async fn handle_create_tournament_request(
+    socket_ref: SocketRef,
+    message: Result<CreateTournamentRequest, DecodeError>,
+    ack_sender: Ack,
+    sender: EventSender,
+) {
+    let Ok(CreateTournamentRequest {
+        tournament,
+        username,
+    }) = message
+    else {
+        return response::reject(ack_sender, Error::InvalidRequest);
+    };
+
+    let event = LobbyEvent::CreateTournament {
+        settings: tournament,
+        director: lobby_player(&socket_ref, username.as_deref()),
+        ack_sender,
+    };
+    forward(&sender, event).await;
+}
+
+#[derive(Clone, Copy)]
+enum TournamentAction {
+    Join,
+    Start,
+    Show,
+}
+
+async fn handle_tournament_request(
+    socket_ref: SocketRef,
+    message: Result<TournamentRequest, DecodeError>,
+    ack_sender: Ack,
+    action: TournamentAction,
+    sender: EventSender,
+) {
+    let Ok(TournamentRequest {
+        tournament_uuid,
+        username,
+    }) = message
+    else {
+        return response::reject(ack_sender, Error::InvalidRequest);
+    };
+
+    let event = match action {
+        TournamentAction::Join => LobbyEvent::JoinTournament {
+            tournament_uuid,
+            player: lobby_player(&socket_ref, username.as_deref()),
+            ack_sender,
         },
-    );
+        TournamentAction::Start => LobbyEvent::StartTournament {
+            tournament_uuid,
+            player: lobby_player(&socket_ref, username.as_deref()),
+            ack_sender,
+        },
+        TournamentAction::Show => LobbyEvent::Tournament {
+            tournament_uuid,
+            ack_sender,
+        },
+    };
+    forward(&sender, event).await;
+}
+
+async fn handle_list_tournaments_request(ack_sender: Ack, sender: EventSender) {
+    forward(&sender, LobbyEvent::ListTournaments { ack_sender }).await;
+}
+
+async fn handle_disconnect(socket_ref: SocketRef, sender: EventSender) {
+    if let Some(player) = socket_ref.extensions.get::<Player>() {
+        let event = LobbyEvent::Disconnect {
+            player_uuid: *player.get_id(),
+        };
+        forward(&sender, event).await;
+    }
+}
+
+// Accounts are not part of any game, so these requests never go through the manager
+
+#[derive(Clone, Copy)]
+enum CredentialsAction {
+    SignUp,
+    LogIn,
 }
 
-pub fn handle_events(event: Event, manager: &mut Manager) {
+async fn handle_credentials_request(
+    socket_ref: SocketRef,
+    message: Result<CredentialsRequest, DecodeError>,
+    ack_sender: Ack,
+    action: CredentialsAction,
+    accounts: SharedAccounts,
+) {
+    let Ok(CredentialsRequest { username, password }) = message else {
+        return response::reject(ack_sender, Error::InvalidRequest);
+    };
+
+    // Hashing passwords is slow on purpose, keep it away from the sockets
+    let result = tokio::task::spawn_blocking(move || match action {
+        CredentialsAction::SignUp => accounts::sign_up(&accounts, &username, &password),
+        CredentialsAction::LogIn => accounts::log_in(&accounts, &username, &password),
+    })
+    .await
+    // Only a panic while hashing ends up here
+    .unwrap_or(Err(Error::Internal));
+
+    let response = match result {
+        Ok(session) => {
+            debug!(account_id = %session.account_id, "Player logged in");
+
+            // Game namespaces pick the session up from the handshake token
+            socket_ref.extensions.insert::<Session>(session.clone());
+            Response::from_data(session)
+        }
+        Err(error) => Response::from_error(error),
+    };
+
+    ack_sender.send(response);
+}
+
+async fn handle_log_out_request(socket_ref: SocketRef, ack_sender: Ack, accounts: SharedAccounts) {
+    let response = match socket_ref.extensions.remove::<Session>() {
+        Some(session) => {
+            accounts
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .log_out(&session.token);
+            Response::from_notice(Notice::LoggedOut)
+        }
+        None => Response::from_error(Error::InvalidSession),
+    };
+
+    ack_sender.send(response);
+}
+
+pub fn on_connect(socket: SocketRef, sender: EventSender, accounts: SharedAccounts) {
+    crate::protocol::welcome(&socket);
+
+    socket.on("ratings", {
+        let sender = sender.clone();
+        |socket: SocketRef, TryData::<RatingsRequest>(message), ack_sender: AckSender| async move {
+            let ack_sender = Ack::new(&socket, ack_sender);
+            handle_ratings_request(message, ack_sender, sender).await;
+        }
+    });
+
+    socket.on("player-stats", {
+        let sender = sender.clone();
+        |socket: SocketRef, TryData::<PlayerStatsRequest>(message), ack_sender: AckSender| async move {
+            let ack_sender = Ack::new(&socket, ack_sender);
+            handle_player_stats_request(message, ack_sender, sender).await;
+        }
+    });
+
+    socket.on("leaderboard", {
+        let sender = sender.clone();
+        |socket: SocketRef, TryData::<LeaderboardRequest>(message), ack_sender: AckSender| async move {
+            let ack_sender = Ack::new(&socket, ack_sender);
+            handle_leaderboard_request(message, ack_sender, sender).await;
+        }
+    });
+
+    socket.on("sign-up", {
+        let accounts = accounts.clone();
+        |socket: SocketRef, TryData::<CredentialsRequest>(message), ack_sender: AckSender| async move {
+            let ack_sender = Ack::new(&socket, ack_sender);
+            handle_credentials_request(
+                socket,
+                message,
+                ack_sender,
+                CredentialsAction::SignUp,
+                accounts,
+            )
+            .await;
+        }
+    });
+
+    socket.on("log-in", {
+        let accounts = accounts.clone();
+        |socket: SocketRef, TryData::<CredentialsRequest>(message), ack_sender: AckSender| async move {
+            let ack_sender = Ack::new(&socket, ack_sender);
+            handle_credentials_request(
+                socket,
+                message,
+                ack_sender,
+                CredentialsAction::LogIn,
+                accounts,
+            )
+            .await;
+        }
+    });
+
+    socket.on("log-out", {
+        let accounts = accounts.clone();
+        |socket: SocketRef, ack_sender: AckSender| async move {
+            let ack_sender = Ack::new(&socket, ack_sender);
+            handle_log_out_request(socket, ack_sender, accounts).await;
+        }
+    });
+
+    socket.on("list-games", {
+        let sender = sender.clone();
+        |socket: SocketRef, TryData::<ListGamesRequest>(message), ack_sender: AckSender| async move {
+            let ack_sender = Ack::new(&socket, ack_sender);
+            handle_list_games_request(message, ack_sender, sender).await;
+        }
+    });
+
+    socket.on("create-game", {
+        let sender = sender.clone();
+        |socket: SocketRef, TryData::<CreateGameRequest>(message), ack_sender: AckSender| async move {
+            let ack_sender = Ack::new(&socket, ack_sender);
+            handle_create_game_request(message, ack_sender, sender).await;
+        }
+    });
+
+    socket.on("join-queue", {
+        let sender = sender.clone();
+        |socket: SocketRef, TryData::<JoinQueueRequest>(message), ack_sender: AckSender| async move {
+            let ack_sender = Ack::new(&socket, ack_sender);
+            handle_join_queue_request(socket, message, ack_sender, sender).await;
+        }
+    });
+
+    socket.on("leave-queue", {
+        let sender = sender.clone();
+        |socket: SocketRef, ack_sender: AckSender| async move {
+            let ack_sender = Ack::new(&socket, ack_sender);
+            handle_leave_queue_request(socket, ack_sender, sender).await;
+        }
+    });
+
+    socket.on("create-tournament", {
+        let sender = sender.clone();
+        |socket: SocketRef, TryData::<CreateTournamentRequest>(message), ack_sender: AckSender| async move {
+            let ack_sender = Ack::new(&socket, ack_sender);
+            handle_create_tournament_request(socket, message, ack_sender, sender).await;
+        }
+    });
+
+    for (event, action) in [
+        ("join-tournament", TournamentAction::Join),
+        ("start-tournament", TournamentAction::Start),
+        ("tournament", TournamentAction::Show),
+    ] {
+        let sender = sender.clone();
+        socket.on(
+            event,
+            move |socket: SocketRef,
+                  TryData::<TournamentRequest>(message),
+                  ack_sender: AckSender| async move {
+                let ack_sender = Ack::new(&socket, ack_sender);
+                handle_tournament_request(socket, message, ack_sender, action, sender).await;
+            },
+        );
+    }
+
+    socket.on("list-tournaments", {
+        let sender = sender.clone();
+        |socket: SocketRef, ack_sender: AckSender| async move {
+            let ack_sender = Ack::new(&socket, ack_sender);
+            handle_list_tournaments_request(ack_sender, sender).await;
+        }
+    });
+
+    socket.on_disconnect({
+        let sender = sender.clone();
+        move |socket: SocketRef| async move {
+            handle_disconnect(socket, sender).await;
+        }
+    });
+}
+
+/// Tells every lobby socket that a game can now be found in the list, unless it is private
+pub fn notify_game_added(socket_io: &SocketIo, summary: &GameSummary) {
+    if !summary.private {
+        socket_io
+            .emit("game-added", &Response::from_data(summary))
+            .ok();
+    }
+}
+
+/// Tells every lobby socket that a game of the list changed
+pub fn notify_game_updated(socket_io: &SocketIo, summary: &GameSummary) {
+    if !summary.private {
+        socket_io
+            .emit("game-updated", &Response::from_data(summary))
+            .ok();
+    }
+}
+
+/// Tells every lobby socket that a game is gone from the list
+pub fn notify_game_removed(socket_io: &SocketIo, summary: &GameSummary) {
+    if !summary.private {
+        socket_io
+            .emit("game-removed", &Response::from_data(summary.uuid))
+            .ok();
+    }
+}
+
+/// Creates a game and shows it in the lobby, whether it was asked for from a
+/// socket or over HTTP
+pub fn create_game(
+    socket_io: &SocketIo,
+    manager: &mut Manager,
+    settings: GameSettings,
+) -> Result<CreatedGame, Error> {
+    let created = manager.create_game(settings)?;
+    debug!(game_uuid = %created.uuid, "Game created");
+
+    if let Ok(summary) = manager.get_game_summary(&created.uuid) {
+        notify_game_added(socket_io, &summary);
+    }
+
+    Ok(created)
+}
+
+/// Creates the games of the players the queue put together, and tells each of
+/// them which namespace to join
+pub fn make_matches(socket_io: &SocketIo, manager: &mut Manager) {
+    for seating in manager.create_matches(SystemTime::now()) {
+        let game_uuid = seating.game_uuid;
+        debug!(%game_uuid, players = seating.players.len(), "Match found");
+
+        if let Ok(summary) = manager.get_game_summary(&game_uuid) {
+            notify_game_added(socket_io, &summary);
+        }
+
+        for player in &seating.players {
+            let found = MatchFound {
+                game_uuid,
+                namespace: format!("/game/{game_uuid}"),
+                player,
+                players: &seating.players,
+                reconnect_token: seating
+                    .reconnect_tokens
+                    .get(player.get_id())
+                    .map(String::as_str),
+            };
+
+            socket_io
+                .within(player_room(player.get_id()))
+                .emit("match-found", &Response::from_data(found))
+                .ok();
+        }
+    }
+}
+
+/// Tells every lobby socket how a tournament is going, and the players of the
+/// games that just started where to play
+fn notify_tournament(
+    socket_io: &SocketIo,
+    manager: &Manager,
+    tournament_uuid: &Uuid,
+    games: &[Seating],
+) {
+    let Ok(tournament) = manager.get_tournament(tournament_uuid) else {
+        return;
+    };
+    let summary = tournament.get_summary();
+
+    for seating in games {
+        let game_uuid = seating.game_uuid;
+        if let Ok(game_summary) = manager.get_game_summary(&game_uuid) {
+            notify_game_added(socket_io, &game_summary);
+        }
+
+        for player_uuid in tournament
+            .get_game_players(&game_uuid)
+            .into_iter()
+            .flatten()
+        {
+            let game = TournamentGame {
+                tournament_uuid: *tournament_uuid,
+                round: summary.round,
+                game_uuid,
+                namespace: format!("/game/{game_uuid}"),
+                reconnect_token: seating
+                    .reconnect_tokens
+                    .get(&player_uuid)
+                    .map(String::as_str),
+            };
+
+            socket_io
+                .within(player_room(&player_uuid))
+                .emit("tournament-game", &Response::from_data(&game))
+                .ok();
+        }
+    }
+
+    socket_io
+        .emit("tournament-updated", &Response::from_data(summary))
+        .ok();
+}
+
+/// Keeps tournaments going once the games of a round are over
+pub fn advance_tournaments(socket_io: &SocketIo, manager: &mut Manager) {
+    for (tournament_uuid, games) in manager.advance_tournaments(SystemTime::now()) {
+        debug!(%tournament_uuid, games = games.len(), "Tournament advanced");
+
+        notify_tournament(socket_io, manager, &tournament_uuid, &games);
+    }
+}
+
+pub fn handle_events(event: Event, socket_io: &SocketIo, manager: &mut Manager) {
     if let Lobby(event) = event {
         match event {
             // A player asked to see the games list
-            LobbyEvent::ListGames { ack_sender } => {
-                let response = Response::from_data(manager.get_game_list());
+            LobbyEvent::ListGames {
+                filter,
+                pagination,
+                ack_sender,
+            } => {
+                let response = Response::from_data(manager.get_game_list(&filter, pagination));
+
+                ack_sender.send(response);
+            }
+
+            // A player wants to host a new game
+            LobbyEvent::CreateGame {
+                settings,
+                ack_sender,
+            } => {
+                let response = match create_game(socket_io, manager, settings) {
+                    Ok(created) => Response::from_data(created),
+                    Err(error) => Response::from_error(error),
+                };
+
+                ack_sender.send(response);
+            }
+
+            // Someone wants to know how good a player is, and how they got there
+            LobbyEvent::Ratings {
+                account_id,
+                variant,
+                ack_sender,
+            } => {
+                let response = Response::from_data(manager.get_ratings(&account_id, variant));
+
+                ack_sender.send(response);
+            }
+
+            // Someone is curious about how a player usually does
+            LobbyEvent::PlayerStats {
+                account_id,
+                filter,
+                ack_sender,
+            } => {
+                let response = Response::from_data(manager.get_player_stats(&account_id, &filter));
+
+                ack_sender.send(response);
+            }
+
+            // Who's the best around
+            LobbyEvent::Leaderboard {
+                kind,
+                filter,
+                limit,
+                ack_sender,
+            } => {
+                let response = Response::from_data(manager.get_leaderboard(kind, &filter, limit));
+
+                ack_sender.send(response);
+            }
+
+            // A player wants to play now, against whoever is around
+            LobbyEvent::JoinQueue {
+                player,
+                preferences,
+                ack_sender,
+            } => {
+                let response = match manager.join_queue(player, preferences, SystemTime::now()) {
+                    Ok(()) => Response::from_notice(Notice::WaitingForOpponents),
+                    Err(error) => Response::from_error(error),
+                };
+
+                ack_sender.send(response);
+
+                // Someone may have been waiting for just this player
+                make_matches(socket_io, manager);
+            }
+
+            // A player got tired of waiting
+            LobbyEvent::LeaveQueue {
+                player_uuid,
+                ack_sender,
+            } => {
+                let response = match manager.leave_queue(&player_uuid) {
+                    Ok(()) => Response::from_notice(Notice::LeftQueue),
+                    Err(error) => Response::from_error(error),
+                };
+
+                ack_sender.send(response);
+            }
+
+            // Nobody would be there to hear about the match
+            LobbyEvent::Disconnect { player_uuid } => {
+                manager.leave_queue(&player_uuid).ok();
+            }
+
+            // A club director sets up a tournament, players can now sign up
+            LobbyEvent::CreateTournament {
+                settings,
+                director,
+                ack_sender,
+            } => {
+                let response = match manager.create_tournament(settings, director.get_id()) {
+                    Ok(tournament_uuid) => {
+                        debug!(%tournament_uuid, "Tournament created");
+                        notify_tournament(socket_io, manager, &tournament_uuid, &[]);
+
+                        Response::from_data(tournament_uuid)
+                    }
+                    Err(error) => Response::from_error(error),
+                };
+
+                ack_sender.send(response);
+            }
+
+            // A player signs up for a tournament
+            LobbyEvent::JoinTournament {
+                tournament_uuid,
+                player,
+                ack_sender,
+            } => {
+                let response = match manager.join_tournament(&tournament_uuid, player.clone()) {
+                    Ok(()) => {
+                        notify_tournament(socket_io, manager, &tournament_uuid, &[]);
+                        Response::from_data(player)
+                    }
+                    Err(error) => Response::from_error(error),
+                };
+
+                ack_sender.send(response);
+            }
+
+            // Registration is over, the first round begins
+            LobbyEvent::StartTournament {
+                tournament_uuid,
+                player,
+                ack_sender,
+            } => {
+                let result =
+                    manager.start_tournament(&tournament_uuid, player.get_id(), SystemTime::now());
+
+                let response = match result {
+                    Ok(games) => {
+                        notify_tournament(socket_io, manager, &tournament_uuid, &games);
+                        Response::from_notice(Notice::TournamentBegun)
+                    }
+                    Err(error) => Response::from_error(error),
+                };
+
+                ack_sender.send(response);
+            }
+
+            // Someone wants the pairings and standings of a tournament
+            LobbyEvent::Tournament {
+                tournament_uuid,
+                ack_sender,
+            } => {
+                let response = match manager.get_tournament(&tournament_uuid) {
+                    Ok(tournament) => Response::from_data(tournament.get_details()),
+                    Err(error) => Response::from_error(error),
+                };
+
+                ack_sender.send(response);
+            }
+
+            // A player is looking for a tournament to enter
+            LobbyEvent::ListTournaments { ack_sender } => {
+                let response = Response::from_data(manager.get_tournament_list());
 
-                ack_sender.send(&response).unwrap();
+                ack_sender.send(response);
             }
         }
     }
@@ -1,119 +1,90 @@
-mod events;
-mod game;
-mod lobby;
-mod manager;
-mod player;
-mod response;
-mod scrabble;
-
-use crate::events::Event;
-use crate::manager::Manager;
-use axum::routing::get;
-use axum::Router;
-use serde::{Deserialize, Serialize, Serializer};
-use socketioxide::extract::SocketRef;
-use socketioxide::SocketIo;
-use std::fmt::Formatter;
-use std::str::FromStr;
-use tokio::sync::mpsc;
-use tower_http::cors::CorsLayer;
-use tracing::{debug, Level};
+use clap::Parser;
+use scrabble::accounts::Accounts;
+use scrabble::config::{Args, Config};
+use scrabble::manager::Manager;
+use scrabble::ratings::Ratings;
+use scrabble::stats::Statistics;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+use tokio::sync::oneshot;
 use tracing_subscriber::FmtSubscriber;
-use uuid::Uuid;
-
-#[derive(Copy, Clone, PartialEq, Serialize, Deserialize, Debug)]
-struct Tile(char, usize);
-
-#[derive(Debug, PartialEq)]
-enum Error {
-    NotEnoughPlayers,
-    TooManyPlayer,
-    DuplicatePlayerId,
-    PlayerNotRegistered,
-    NoMoreTiles,
-    PlayerHas7Tiles,
-    GameNotFound,
-}
 
-impl serde::Serialize for Error {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-    where
-        S: Serializer,
-    {
-        serializer.serialize_str(&format!("{}", self))
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+    if let Some(directory) = &args.export_protocol {
+        scrabble::protocol::export(directory)?;
+        return Ok(());
     }
-}
 
-impl std::fmt::Display for Error {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Error::NotEnoughPlayers => write!(f, "Not enough players"),
-            Error::TooManyPlayer => write!(f, "Too many players"),
-            Error::DuplicatePlayerId => write!(f, "Duplicate player UUID"),
-            Error::PlayerNotRegistered => write!(f, "Player is not registered in this game"),
-            Error::NoMoreTiles => write!(f, "No more tiles in the bag"),
-            Error::GameNotFound => write!(f, "Game not found with this UUID"),
-            Error::PlayerHas7Tiles => write!(f, "Player already has 7 tiles"),
+    match Config::load(args) {
+        Ok(config) => scrabble::config::init(config),
+        Err(error) => {
+            eprintln!("{error}");
+            std::process::exit(2);
         }
-    }
-}
+    };
+    let config = scrabble::config::get();
 
-struct Play {
-    tile: Tile,
-    x: usize,
-    y: usize,
-}
-
-fn on_game_namespace_connect(socket: SocketRef, sender: mpsc::Sender<Event>) {
-    debug!("Connecting game namespace");
-}
-
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let subscriber = FmtSubscriber::builder()
-        .with_max_level(Level::DEBUG)
+        .with_max_level(config.log_level().unwrap_or(tracing::Level::INFO))
         .finish();
 
     tracing::subscriber::set_global_default(subscriber)?;
 
-    let mut manager = Manager::new();
-
-    let (tx, mut rx) = mpsc::channel::<Event>(32);
+    let storage = &config.storage;
+    let mut manager = Manager::with_records(
+        match storage.path(scrabble::ratings::RATINGS_PATH) {
+            Some(path) => Ratings::load(&path)?,
+            None => Ratings::default(),
+        },
+        match storage.path(scrabble::stats::STATS_PATH) {
+            Some(path) => Statistics::load(&path)?,
+            None => Statistics::default(),
+        },
+    );
+    let accounts = Arc::new(Mutex::new(
+        match storage.path(scrabble::accounts::ACCOUNTS_PATH) {
+            Some(path) => Accounts::load(&path)?,
+            None => Accounts::default(),
+        },
+    ));
+
+    // Games left by the last shutdown pick up where they were
+    let snapshot_path = storage.path(scrabble::maintenance::SNAPSHOT_PATH);
+    if let Some(path) = &snapshot_path {
+        manager.restore(
+            scrabble::maintenance::load_snapshot(path)?,
+            SystemTime::now(),
+        );
+    }
 
-    let (layer, io) = SocketIo::new_layer();
+    let (tx, rx) = scrabble::events::channel(config.limits.event_channel_capacity);
+    let registry = manager.get_registry().clone();
+    let (app, io) = scrabble::server::app(config, &tx, &registry, &accounts)?;
 
-    io.ns("/", {
-        let sender = tx.clone();
-        move |socket| crate::lobby::on_connect(socket, sender)
-    });
-    io.dyn_ns("/game/{*game_uuid}", {
-        let sender = tx.clone();
-        move |socket_ref: SocketRef| {
-            let ns = socket_ref.ns();
-            let game_uuid = Uuid::from_str(ns.split("/").last().unwrap()).unwrap();
+    let listener = tokio::net::TcpListener::bind(config.network.bind).await?;
 
-            return crate::game::on_connect(socket_ref, sender, game_uuid);
-        }
-    })
-    .unwrap();
-
-    let app = Router::new()
-        .route("/", get(|| async { "Hello, World!" }))
-        .layer(layer)
-        .layer(CorsLayer::permissive());
+    // Read now rather than in the event loop on the first move
+    for (variant, lexicon) in scrabble::config::lexicons() {
+        tracing::info!("{} words in the lexicon of {variant:?}", lexicon.len());
+    }
 
-    let listener = tokio::net::TcpListener::bind("0.0.0.0:3000").await.unwrap();
+    let (stopped_tx, stopped_rx) = oneshot::channel();
 
     tokio::spawn(async move {
-        while let Some(event) = rx.recv().await {
-            match event {
-                Event::Game(_) => crate::game::handle_events(event, &io, &mut manager),
-                Event::Lobby(_) => crate::lobby::handle_events(event, &mut manager),
-            }
-        }
+        let shutdown = scrabble::maintenance::shutdown_signal();
+        let (manager, games) = scrabble::server::run(io, manager, rx, tx, registry, shutdown).await;
+
+        scrabble::maintenance::save_snapshot(&manager, games, snapshot_path.as_deref());
+        stopped_tx.send(()).ok();
     });
 
-    axum::serve(listener, app).await.unwrap();
+    axum::serve(listener, app)
+        .with_graceful_shutdown(async {
+            stopped_rx.await.ok();
+        })
+        .await?;
 
     Ok(())
 }
@@ -0,0 +1,243 @@
+use crate::i18n::{Locale, Message, Notice};
+use crate::manager::{Manager, Snapshot};
+use crate::response::Response;
+use crate::scrabble::Scrabble;
+use crate::storage;
+use serde::Serialize;
+use socketioxide::SocketIo;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::path::Path;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// Games and tournaments still going when the server stopped
+pub const SNAPSHOT_PATH: &str = "snapshot.json";
+
+/// Sent as "maintenance" to the lobby and to every game
+#[derive(Clone, Debug)]
+pub struct MaintenanceNotice {
+    /// No new games can be created
+    pub enabled: bool,
+    /// The server is going down right now, sockets are about to be closed
+    pub shutdown: bool,
+    /// Written by an admin and sent as is, players otherwise get a stock
+    /// message in their language
+    pub message: Option<String>,
+}
+
+/// The notice as a socket gets it
+#[derive(Serialize, Debug)]
+struct LocalizedNotice<'a> {
+    enabled: bool,
+    shutdown: bool,
+    message: Cow<'a, str>,
+}
+
+impl MaintenanceNotice {
+    pub fn shutdown() -> Self {
+        MaintenanceNotice {
+            enabled: true,
+            shutdown: true,
+            message: None,
+        }
+    }
+
+    fn localized(&self, locale: Locale) -> LocalizedNotice<'_> {
+        let stock = match (self.shutdown, self.enabled) {
+            (true, _) => Notice::ServerRestarting,
+            (false, true) => Notice::MaintenanceStarted,
+            (false, false) => Notice::MaintenanceOver,
+        };
+
+        LocalizedNotice {
+            enabled: self.enabled,
+            shutdown: self.shutdown,
+            message: match &self.message {
+                Some(message) => Cow::Borrowed(message),
+                None => Cow::Owned(Message::from(stock).render(locale)),
+            },
+        }
+    }
+}
+
+/// Tells every socket, in its own language
+pub fn notify_all(socket_io: &SocketIo, manager: &Manager, notice: &MaintenanceNotice) {
+    let games = manager
+        .get_game_uuids()
+        .into_iter()
+        .map(|x| format!("/game/{x}"));
+
+    for path in std::iter::once(String::from("/")).chain(games) {
+        let sockets = socket_io
+            .of(path)
+            .and_then(|x| x.sockets().ok())
+            .unwrap_or_default();
+
+        for socket in sockets {
+            let response = Response::from_data(notice.localized(Locale::of(&socket)));
+            socket.emit("maintenance", &response).ok();
+        }
+    }
+}
+
+/// Resolves on SIGINT, or SIGTERM where there is such a thing
+pub async fn shutdown_signal() {
+    let interrupt = async {
+        if let Err(error) = tokio::signal::ctrl_c().await {
+            warn!(%error, "Could not listen for SIGINT");
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                terminate.recv().await;
+            }
+            Err(error) => {
+                warn!(%error, "Could not listen for SIGTERM");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = interrupt => {},
+        _ = terminate => {},
+    }
+}
+
+/// Saves the games handed back by their tasks along with the tournaments, `path`
+/// is `None` when nothing is ever saved
+pub fn save_snapshot(manager: &Manager, games: HashMap<Uuid, Scrabble>, path: Option<&Path>) {
+    let Some(path) = path else {
+        warn!("Nothing is saved with this storage backend, the games are lost");
+        return;
+    };
+
+    if storage::save(path, &manager.snapshot(games)).is_ok() {
+        info!(path = %path.display(), "Games saved");
+    }
+}
+
+/// Reads the snapshot left by the last shutdown, and deletes it so a crash
+/// later on never brings the same games back twice
+pub fn load_snapshot(path: &Path) -> std::io::Result<Snapshot> {
+    let snapshot = storage::load(path)?;
+
+    match std::fs::remove_file(path) {
+        Err(error) if error.kind() != std::io::ErrorKind::NotFound => Err(error),
+        _ => Ok(snapshot),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::room::Room;
+    use crate::settings::GameSettings;
+    use scrabble_core::Player;
+    use std::time::{Duration, SystemTime};
+
+    #[test]
+    fn games_survive_a_restart() {
+        let path = std::env::temp_dir().join(format!("snapshot-{}.json", Uuid::new_v4()));
+        let (host, guest) = (
+            Player::new(&Uuid::new_v4(), "host"),
+            Player::new(&Uuid::new_v4(), "guest"),
+        );
+        let before = SystemTime::now() - Duration::from_secs(3600);
+
+        let game_uuid = Uuid::new_v4();
+        let mut room = Room::new(game_uuid, Scrabble::with_settings(GameSettings::default()));
+        for player in [&host, &guest] {
+            room.register_player(player.clone(), None).unwrap();
+        }
+        room.start().unwrap();
+        room.player_disconnected(host.get_id(), before).unwrap();
+        let rack = room
+            .get_game()
+            .get_player_tiles(guest.get_id())
+            .unwrap()
+            .clone();
+
+        // Games are handed over by their tasks once stopped
+        let games = HashMap::from([(game_uuid, room.into_game())]);
+        save_snapshot(&Manager::new(), games, Some(&path));
+
+        let now = SystemTime::now();
+        let mut restarted = Manager::new();
+        restarted.restore(load_snapshot(&path).unwrap(), now);
+        assert!(!path.exists());
+        assert_eq!(restarted.get_game_uuids(), vec![game_uuid]);
+
+        let mut actors = restarted.take_new_games();
+        let room = actors[0].get_room_mut();
+        let game = room.get_game();
+        assert_eq!(game.get_status(), scrabble_core::GameStatus::InProgress);
+        assert_eq!(game.get_player_tiles(guest.get_id()), Ok(&rack));
+        // Everyone gets a fresh grace period, whenever they left
+        assert_eq!(game.get_disconnected_since(host.get_id()), Some(now));
+        assert_eq!(game.get_disconnected_since(guest.get_id()), Some(now));
+        assert!(room.get_players().contains(&host));
+    }
+
+    #[test]
+    fn archived_games_survive_a_restart() {
+        let path = std::env::temp_dir().join(format!("snapshot-{}.json", Uuid::new_v4()));
+        let game_uuid = Uuid::new_v4();
+        let mut game = Scrabble::new();
+        game.abandon().unwrap();
+
+        let mut manager = Manager::new();
+        manager.game_removed(&game_uuid, Some(game));
+        save_snapshot(&manager, HashMap::new(), Some(&path));
+
+        let mut restarted = Manager::new();
+        restarted.restore(load_snapshot(&path).unwrap(), SystemTime::now());
+        assert!(restarted.get_game_uuids().is_empty());
+        let archived = restarted.get_archived_game(&game_uuid).unwrap();
+        assert!(archived.get_bag_record().is_some());
+    }
+
+    #[test]
+    fn maintenance_blocks_new_games_only() {
+        let mut manager = Manager::new();
+        manager.create_game(GameSettings::default()).unwrap();
+
+        manager.set_maintenance(true);
+        assert_eq!(
+            manager.create_game(GameSettings::default()).unwrap_err(),
+            crate::Error::Maintenance
+        );
+        // The game already there is still going
+        assert_eq!(manager.count_running_games(), 1);
+
+        manager.set_maintenance(false);
+        assert!(manager.create_game(GameSettings::default()).is_ok());
+    }
+
+    #[test]
+    fn notices_speak_the_language_of_the_socket() {
+        let notice = MaintenanceNotice::shutdown();
+        assert_eq!(
+            notice.localized(Locale::Fr).message,
+            "Le serveur redémarre, les parties reprendront là où elles en étaient"
+        );
+
+        // What an admin wrote is sent as is
+        let notice = MaintenanceNotice {
+            enabled: true,
+            shutdown: false,
+            message: Some(String::from("Back at 5")),
+        };
+        assert_eq!(notice.localized(Locale::Es).message, "Back at 5");
+    }
+}
@@ -1,356 +1,861 @@
-use crate::player::Player;
+use crate::actor::{self, GameActor};
+use crate::config;
+use crate::invite::Invite;
+use crate::matchmaking::{Matchmaking, QueuePreferences};
+use crate::ratings::{Rating, RatingChange, Ratings};
+use crate::registry::Registry;
+use crate::room::Room;
 use crate::scrabble::Scrabble;
-use crate::{Error, Tile};
+use crate::settings::{GameSettings, Variant};
+use crate::stats::{LeaderboardEntry, LeaderboardKind, PlayerStats, Statistics, StatsFilter};
+use crate::summary::{GameFilter, GamePage, GameSummary, Pagination};
+use crate::tournament::{
+    Pairing, Tournament, TournamentSettings, TournamentStatus, TournamentSummary,
+};
+use crate::Error;
+use scrabble_core::{GameStatus, Player};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::time::SystemTime;
 use uuid::Uuid;
 
+/// Everything that spans several games: the list of games, ratings, statistics,
+/// the queue and the tournaments
+///
+/// Games themselves run in their own tasks, the manager only creates them and
+/// hears back from them once they are over.
 pub struct Manager {
-    game_map: HashMap<Uuid, Scrabble>,
-    player_to_game: HashMap<Uuid, Uuid>,
+    registry: Registry,
+    /// Games created since the lobby loop last started their tasks
+    new_games: Vec<GameActor>,
+    /// Games that are over and whose task the reaper ended, still listed,
+    /// downloadable and auditable, up to `limits.max_archived_games`
+    archive: HashMap<Uuid, Scrabble>,
+    ratings: Ratings,
+    statistics: Statistics,
+    matchmaking: Matchmaking,
+    tournaments: HashMap<Uuid, Tournament>,
+    /// No new games while set, the ones already there are played to the end
+    maintenance: bool,
+}
+
+/// Games, archived games and tournaments saved on shutdown, to be picked up on
+/// the next start
+#[derive(Serialize, Deserialize, Default)]
+pub struct Snapshot {
+    games: HashMap<Uuid, Scrabble>,
+    tournaments: HashMap<Uuid, Tournament>,
+    /// Left out of snapshots saved before games were archived whole
+    #[serde(default)]
+    archive: HashMap<Uuid, Scrabble>,
+}
+
+/// A game the server seated players in, with the reconnect tokens of the guests
+/// among them, only they get told
+#[derive(Debug)]
+pub struct Seating {
+    pub game_uuid: Uuid,
+    pub players: Vec<Player>,
+    pub reconnect_tokens: HashMap<Uuid, String>,
+}
+
+impl Seating {
+    fn new(room: &Room) -> Self {
+        let game = room.get_game();
+        let players = game.get_players();
+        let reconnect_tokens = players
+            .iter()
+            .filter_map(|x| {
+                let token = game.get_reconnect_token(x.get_id())?;
+                Some((*x.get_id(), token.to_owned()))
+            })
+            .collect();
+
+        Seating {
+            game_uuid: *room.get_uuid(),
+            players,
+            reconnect_tokens,
+        }
+    }
+}
+
+/// Sent back to whoever created a game, private games come with their invite
+#[derive(Serialize, Debug)]
+pub struct CreatedGame {
+    pub uuid: Uuid,
+    pub invite: Option<Invite>,
+}
+
+impl Default for Manager {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Manager {
     pub fn new() -> Self {
-        let mut result = Self {
-            game_map: HashMap::new(),
-            player_to_game: HashMap::new(),
+        Self::with_records(Ratings::default(), Statistics::default())
+    }
+
+    /// Ratings and statistics outlive the games, they are loaded from storage
+    pub fn with_records(ratings: Ratings, statistics: Statistics) -> Self {
+        Self {
+            registry: Registry::default(),
+            new_games: Vec::new(),
+            archive: HashMap::new(),
+            ratings,
+            statistics,
+            matchmaking: Matchmaking::default(),
+            tournaments: HashMap::new(),
+            maintenance: false,
+        }
+    }
+
+    /// Where sockets and the API find the games without going through the manager
+    pub fn get_registry(&self) -> &Registry {
+        &self.registry
+    }
+
+    pub fn create_game(&mut self, settings: GameSettings) -> Result<CreatedGame, Error> {
+        if self.maintenance {
+            return Err(Error::Maintenance);
+        }
+
+        let room = self.new_room(settings)?;
+        let created = CreatedGame {
+            uuid: *room.get_uuid(),
+            invite: room.get_game().get_invite().cloned(),
         };
+        self.insert_room(room);
 
-        // For testing purpose
-        // let uuid = Uuid::parse_str("550e8400-e29b-41d4-a716-446655440000").unwrap();
-        // result.game_map.insert(uuid, Scrabble::new());
+        Ok(created)
+    }
+
+    /// Sets a game up even during maintenance, for the tournaments already running
+    fn new_room(&self, settings: GameSettings) -> Result<Room, Error> {
+        settings.validate()?;
 
-        result
+        // Games over are only waiting for the reaper, they do not count
+        if self.count_running_games() >= config::get().limits.max_games {
+            return Err(Error::TooManyGames);
+        }
+
+        Ok(Room::new(Uuid::new_v4(), Scrabble::with_settings(settings)))
     }
 
-    pub fn create_game(&mut self) -> Uuid {
-        let uuid = Uuid::new_v4();
-        self.game_map.insert(uuid, Scrabble::new());
+    /// Registers a game, its task is started by the lobby loop
+    fn insert_room(&mut self, room: Room) {
+        let uuid = *room.get_uuid();
+        let (handle, actor) = actor::new(room);
 
-        uuid
+        self.registry.insert(uuid, handle);
+        self.new_games.push(actor);
     }
 
-    pub fn register_player_to_game(
-        &mut self,
-        game_uuid: &Uuid,
-        player: Player,
-    ) -> Result<&Player, Error> {
-        match self.game_map.get_mut(game_uuid) {
-            Some(game) => {
-                let registered_player = game.register_player(player)?;
-                self.player_to_game
-                    .insert(registered_player.get_id().clone(), game_uuid.clone());
-
-                Ok(registered_player)
-            }
-            None => Err(Error::GameNotFound),
+    /// Games whose task has yet to be started
+    pub fn take_new_games(&mut self) -> Vec<GameActor> {
+        std::mem::take(&mut self.new_games)
+    }
+
+    /// The task of a game ended, archived games are kept as they ended
+    pub fn game_removed(&mut self, game_uuid: &Uuid, archived: Option<Scrabble>) {
+        self.registry.remove(game_uuid);
+
+        if let Some(game) = archived {
+            self.archive.insert(*game_uuid, game);
+            self.trim_archive(config::get().limits.max_archived_games);
         }
     }
 
-    pub fn remove_player_from_game(
-        &mut self,
-        game_uuid: &Uuid,
-        player_uuid: &Uuid,
-    ) -> Result<(), Error> {
-        match self.game_map.get_mut(game_uuid) {
-            Some(game) => game.remove_player(player_uuid),
-            None => Err(Error::GameNotFound),
+    /// Drops the games that ended first until the archive is back within
+    /// `max_archived_games`
+    fn trim_archive(&mut self, max_archived_games: usize) {
+        while self.archive.len() > max_archived_games {
+            let oldest = self
+                .archive
+                .iter()
+                .min_by_key(|(_, x)| x.get_ended_at())
+                .map(|(x, _)| *x);
+
+            if let Some(game_uuid) = oldest {
+                self.archive.remove(&game_uuid);
+            }
         }
     }
 
-    pub fn get_game_list(&self) -> Vec<&Uuid> {
-        self.game_map.keys().collect()
+    /// A game whose task the reaper ended once it was over
+    pub fn get_archived_game(&self, game_uuid: &Uuid) -> Option<&Scrabble> {
+        self.archive.get(game_uuid)
+    }
+
+    pub fn get_game_list(&self, filter: &GameFilter, pagination: Pagination) -> GamePage {
+        let summaries = self
+            .registry
+            .get_summaries()
+            .into_iter()
+            .filter(|x| !x.private && filter.matches(x))
+            .collect();
+
+        GamePage::new(summaries, pagination)
+    }
+
+    /// Games still running in a task, the archived ones are left out
+    pub fn get_game_uuids(&self) -> Vec<Uuid> {
+        self.registry.get_uuids()
     }
 
-    pub fn player_from_uuid(&self, player_uuid: &Uuid) -> Result<&Player, Error> {
-        match self.player_to_game.get(player_uuid) {
-            Some(game_uuid) => match self.game_map.get(game_uuid) {
-                Some(game) => Ok(game.get_player(player_uuid)?),
-                None => Err(Error::GameNotFound),
-            },
-            None => Err(Error::PlayerNotRegistered),
+    /// How many games are in each state, archived games included
+    pub fn get_game_counts(&self) -> HashMap<GameStatus, usize> {
+        let mut counts = HashMap::new();
+        let statuses = self
+            .registry
+            .get_summaries()
+            .into_iter()
+            .map(|x| x.status)
+            .chain(self.archive.values().map(|x| x.get_status()));
+
+        for status in statuses {
+            *counts.entry(status).or_default() += 1;
         }
+
+        counts
     }
 
-    pub fn get_players_for_game(&self, game_uuid: &Uuid) -> Vec<Player> {
-        match self.game_map.get(game_uuid) {
-            Some(game) => game.get_players(),
-            None => Vec::new(),
+    /// Archived games can still be summarized
+    pub fn get_game_summary(&self, game_uuid: &Uuid) -> Result<GameSummary, Error> {
+        match self.registry.get(game_uuid) {
+            Some(handle) => Ok(handle.get_summary()),
+            None => self
+                .archive
+                .get(game_uuid)
+                .map(|x| GameSummary::new(game_uuid, x))
+                .ok_or(Error::GameNotFound),
         }
     }
 
-    pub fn start_game(&mut self, game_uuid: &Uuid) -> Result<HashMap<Uuid, Vec<Tile>>, Error> {
-        match self.game_map.get_mut(game_uuid) {
-            Some(game) => game.start(),
-            None => Err(Error::GameNotFound),
+    /// Records the statistics of a game that just ended, and of its tournament if
+    /// it was part of one
+    ///
+    /// Gives back how the ratings moved when the game was rated.
+    pub fn game_over(
+        &mut self,
+        game_uuid: &Uuid,
+        game: &Scrabble,
+        now: SystemTime,
+    ) -> Option<Vec<(Uuid, RatingChange)>> {
+        if !self.statistics.is_recorded(game_uuid) {
+            self.statistics.record_game(game_uuid, game, now);
+        }
+
+        // Abandoned games have no final scores, both players lose their pairing
+        let final_scores = game.get_final_scores();
+        for tournament in self.tournaments.values_mut() {
+            if tournament.get_pending_games().contains(game_uuid) {
+                tournament.record_result(game_uuid, final_scores.as_deref());
+            }
+        }
+
+        if !game.get_settings().rated || self.ratings.is_settled(game_uuid) {
+            return None;
         }
+
+        let variant = game.get_settings().variant;
+        final_scores.map(|x| self.ratings.settle(game_uuid, variant, &x, now))
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::ops::Deref;
+    pub fn get_player_stats(&self, account_id: &Uuid, filter: &StatsFilter) -> PlayerStats {
+        self.statistics
+            .get_player_stats(account_id, filter, SystemTime::now())
+    }
 
-    fn create_player() -> Player {
-        let player_id = Uuid::new_v4();
-        let player_name = format!("Player_{player_id}");
+    pub fn get_leaderboard(
+        &self,
+        kind: LeaderboardKind,
+        filter: &StatsFilter,
+        limit: Option<usize>,
+    ) -> Vec<LeaderboardEntry> {
+        self.statistics
+            .get_leaderboard(kind, filter, limit, &self.ratings, SystemTime::now())
+    }
 
-        Player::new(&player_id, &player_name)
+    /// Ratings of an account, in a single variant or in every variant it played
+    pub fn get_ratings(&self, account_id: &Uuid, variant: Option<Variant>) -> Vec<Rating> {
+        match variant {
+            Some(variant) => vec![self.ratings.get_rating(account_id, variant)],
+            None => self.ratings.get_ratings(account_id),
+        }
     }
 
-    #[test]
-    fn game_is_added_on_creation() {
-        let mut manager = Manager::new();
+    /// Puts a player in the quick game queue, they are matched on their rating
+    /// in the variant they asked for
+    pub fn join_queue(
+        &mut self,
+        player: Player,
+        preferences: QueuePreferences,
+        now: SystemTime,
+    ) -> Result<(), Error> {
+        if self.maintenance {
+            return Err(Error::Maintenance);
+        }
 
-        assert_eq!(manager.game_map.len(), 0);
+        let rating = self
+            .ratings
+            .get_rating(player.get_id(), preferences.variant)
+            .rating;
 
-        let game = manager.create_game();
+        self.matchmaking.join(player, preferences, rating, now)
+    }
 
-        assert_eq!(manager.game_map.len(), 1);
+    pub fn leave_queue(&mut self, player_uuid: &Uuid) -> Result<(), Error> {
+        self.matchmaking.leave(player_uuid)
     }
 
-    #[test]
-    fn register_player_to_game__good_id() {
-        let mut manager = Manager::new();
+    /// Creates a game for every group of queued players that can play together
+    /// and registers them in it, the longest waiting player hosts
+    pub fn create_matches(&mut self, now: SystemTime) -> Vec<Seating> {
+        let mut created = Vec::new();
 
-        let game_uuid = manager.create_game();
-        let player = create_player();
+        // Players already queued keep waiting until maintenance is over
+        if self.maintenance {
+            return created;
+        }
+
+        for found in self.matchmaking.pair(now) {
+            let Ok(mut room) = self.new_room(found.preferences.game_settings()) else {
+                continue;
+            };
+
+            for player in found.players {
+                room.register_player(player, None).ok();
+            }
+
+            created.push(Seating::new(&room));
+            self.insert_room(room);
+        }
 
-        let result = manager.register_player_to_game(&game_uuid, player.clone());
-        assert!(result.is_ok());
-        assert_eq!(player, result.unwrap().deref().clone());
-        assert_eq!(manager.get_players_for_game(&game_uuid), vec![player]);
+        created
     }
 
-    #[test]
-    fn register_player_to_game__bad_id() {
-        let mut manager = Manager::new();
+    pub fn create_tournament(
+        &mut self,
+        settings: TournamentSettings,
+        director: &Uuid,
+    ) -> Result<Uuid, Error> {
+        settings.validate()?;
 
-        let game_uuid = manager.create_game();
-        let player = create_player();
-        let random_id = Uuid::new_v4();
+        let uuid = Uuid::new_v4();
+        self.tournaments
+            .insert(uuid, Tournament::new(uuid, settings, *director));
 
-        let result = manager.register_player_to_game(&random_id, player.clone());
-        assert!(result.is_err());
-        assert_eq!(result.unwrap_err(), Error::GameNotFound);
-        assert_eq!(manager.get_players_for_game(&game_uuid), vec![]);
+        Ok(uuid)
     }
 
-    #[test]
-    fn register_player_to_game__no_games() {
-        let mut manager = Manager::new();
+    pub fn get_tournament(&self, tournament_uuid: &Uuid) -> Result<&Tournament, Error> {
+        self.tournaments
+            .get(tournament_uuid)
+            .ok_or(Error::TournamentNotFound)
+    }
 
-        let player = create_player();
-        let random_id = Uuid::new_v4();
+    /// Every tournament, whatever its status
+    pub fn get_tournament_list(&self) -> Vec<TournamentSummary> {
+        self.tournaments.values().map(|x| x.get_summary()).collect()
+    }
 
-        let result = manager.register_player_to_game(&random_id, player.clone());
-        assert!(result.is_err());
-        assert_eq!(result.unwrap_err(), Error::GameNotFound);
+    pub fn join_tournament(&mut self, tournament_uuid: &Uuid, player: Player) -> Result<(), Error> {
+        self.tournaments
+            .get_mut(tournament_uuid)
+            .ok_or(Error::TournamentNotFound)?
+            .join(player)
     }
 
-    #[test]
-    fn remove_player_from_game__good_id() {
-        let mut manager = Manager::new();
+    /// Closes registration and starts the first round, on behalf of the director
+    pub fn start_tournament(
+        &mut self,
+        tournament_uuid: &Uuid,
+        player_uuid: &Uuid,
+        now: SystemTime,
+    ) -> Result<Vec<Seating>, Error> {
+        if self.maintenance {
+            return Err(Error::Maintenance);
+        }
 
-        let game_uuid = manager.create_game();
-        let player = create_player();
+        self.tournaments
+            .get_mut(tournament_uuid)
+            .ok_or(Error::TournamentNotFound)?
+            .start(player_uuid)?;
 
-        manager
-            .register_player_to_game(&game_uuid, player.clone())
-            .unwrap();
-        let result = manager.remove_player_from_game(&game_uuid, player.get_id());
-        assert!(result.is_ok());
-        assert_eq!(manager.get_players_for_game(&game_uuid), vec![]);
+        self.start_round(tournament_uuid, now)
     }
 
-    #[test]
-    fn remove_player_from_game__bad_ids() {
-        {
-            let mut manager = Manager::new();
+    /// Creates and starts the games of the next round, and gives back who sits
+    /// where
+    ///
+    /// Players are seated as disconnected, so the ones who never show up are
+    /// dealt with like any player who left.
+    fn start_round(
+        &mut self,
+        tournament_uuid: &Uuid,
+        now: SystemTime,
+    ) -> Result<Vec<Seating>, Error> {
+        let tournament = self.get_tournament(tournament_uuid)?;
+        let number = tournament.get_summary().round + 1;
+        let settings = tournament.game_settings(number);
+        let mut seats = Vec::new();
+
+        for (a, b) in tournament.get_next_pairings() {
+            let b = b.map(|x| tournament.get_player(&x).cloned()).transpose()?;
+            seats.push((tournament.get_player(&a)?.clone(), b));
+        }
 
-            let game_uuid = manager.create_game();
-            let player = create_player();
-            let random_id = Uuid::new_v4();
+        let mut pairings = Vec::new();
+        let mut rooms = Vec::new();
 
-            manager
-                .register_player_to_game(&game_uuid, player.clone())
-                .unwrap();
-            let result = manager.remove_player_from_game(&random_id, player.get_id());
-            assert!(result.is_err());
-            assert_eq!(result.unwrap_err(), Error::GameNotFound);
-            assert_eq!(manager.get_players_for_game(&game_uuid), vec![player]);
+        for (a, b) in seats {
+            let Some(b) = b else {
+                pairings.push(Pairing::Bye {
+                    player_uuid: *a.get_id(),
+                });
+                continue;
+            };
+
+            let mut room = self.new_room(settings.clone())?;
+            let players = [*a.get_id(), *b.get_id()];
+
+            for player in [a, b] {
+                room.register_player(player, None)?;
+            }
+            room.start()?;
+            room.reset_presence(now);
+
+            pairings.push(Pairing::Game {
+                game_uuid: *room.get_uuid(),
+                players,
+                results: None,
+            });
+            rooms.push(room);
         }
 
-        {
-            let mut manager = Manager::new();
+        // Nothing is registered until every game of the round could be set up
+        let games = rooms.iter().map(Seating::new).collect();
+        for room in rooms {
+            self.insert_room(room);
+        }
 
-            let game_uuid = manager.create_game();
-            let player = create_player();
-            let random_id = Uuid::new_v4();
+        if let Some(tournament) = self.tournaments.get_mut(tournament_uuid) {
+            tournament.add_round(pairings);
+        }
 
-            manager
-                .register_player_to_game(&game_uuid, player.clone())
-                .unwrap();
-            let result = manager.remove_player_from_game(&game_uuid, &random_id);
-            assert!(result.is_err());
-            assert_eq!(result.unwrap_err(), Error::PlayerNotRegistered);
-            assert_eq!(manager.get_players_for_game(&game_uuid), vec![player]);
+        Ok(games)
+    }
+
+    /// Starts the next round of the tournaments whose games are all over, or
+    /// finishes them after their last round
+    ///
+    /// Gives back the tournaments that changed, with the games they just started.
+    pub fn advance_tournaments(&mut self, now: SystemTime) -> Vec<(Uuid, Vec<Seating>)> {
+        let ready: Vec<_> = self
+            .tournaments
+            .iter()
+            .filter(|(_, x)| {
+                x.get_status() == TournamentStatus::InProgress && x.get_pending_games().is_empty()
+            })
+            .map(|(x, _)| *x)
+            .collect();
+
+        let mut progress = Vec::new();
+
+        for tournament_uuid in ready {
+            let Some(tournament) = self.tournaments.get_mut(&tournament_uuid) else {
+                continue;
+            };
+
+            if !tournament.has_more_rounds() {
+                tournament.finish();
+                progress.push((tournament_uuid, Vec::new()));
+            } else if let Ok(games) = self.start_round(&tournament_uuid, now) {
+                progress.push((tournament_uuid, games));
+            }
         }
 
-        {
-            let mut manager = Manager::new();
+        progress
+    }
 
-            let game_uuid = manager.create_game();
-            let player = create_player();
-            let random_id = Uuid::new_v4();
+    pub fn set_maintenance(&mut self, maintenance: bool) {
+        self.maintenance = maintenance;
+    }
 
-            manager
-                .register_player_to_game(&game_uuid, player.clone())
-                .unwrap();
-            let result = manager.remove_player_from_game(&random_id, &random_id);
-            assert!(result.is_err());
-            assert_eq!(result.unwrap_err(), Error::GameNotFound);
-            assert_eq!(manager.get_players_for_game(&game_uuid), vec![player]);
+    pub fn is_in_maintenance(&self) -> bool {
+        self.maintenance
+    }
+
+    /// Games not over yet, the ones maintenance waits for
+    pub fn count_running_games(&self) -> usize {
+        self.registry
+            .get_summaries()
+            .iter()
+            .filter(|x| !x.status.is_over())
+            .count()
+    }
+
+    /// Puts the games handed back by their stopped tasks next to the tournaments
+    /// and the archive
+    pub fn snapshot(&self, games: HashMap<Uuid, Scrabble>) -> Snapshot {
+        Snapshot {
+            games,
+            tournaments: self.tournaments.clone(),
+            archive: self.archive.clone(),
         }
     }
 
+    /// Puts back the games, archived games and tournaments of a snapshot
+    ///
+    /// Nobody is connected yet, so every player of a game still going is marked
+    /// disconnected as of `now` and gets the usual grace period to come back.
+    pub fn restore(&mut self, snapshot: Snapshot, now: SystemTime) {
+        for (tournament_uuid, mut tournament) in snapshot.tournaments {
+            // Games saved as they ended, or not saved at all, are settled right away
+            for game_uuid in tournament.get_pending_games() {
+                match snapshot.games.get(&game_uuid) {
+                    Some(game) if !game.get_status().is_over() => {}
+                    game => {
+                        let final_scores = game.and_then(|x| x.get_final_scores());
+                        tournament.record_result(&game_uuid, final_scores.as_deref());
+                    }
+                }
+            }
+
+            self.tournaments.insert(tournament_uuid, tournament);
+        }
+
+        self.archive.extend(snapshot.archive);
+        self.trim_archive(config::get().limits.max_archived_games);
+
+        for (game_uuid, game) in snapshot.games {
+            let mut room = Room::new(game_uuid, game);
+
+            // The downtime does not count against anyone
+            room.reset_presence(now);
+            self.insert_room(room);
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(non_snake_case)]
+mod tests {
+    use super::*;
+
+    fn create_player() -> Player {
+        let player_id = Uuid::new_v4();
+        let player_name = format!("Player_{player_id}");
+
+        Player::new(&player_id, &player_name)
+    }
+
+    /// The room of a game whose task hasn't been started yet
+    fn new_room_mut<'a>(manager: &'a mut Manager, game_uuid: &Uuid) -> &'a mut Room {
+        manager
+            .new_games
+            .iter_mut()
+            .map(|x| x.get_room_mut())
+            .find(|x| x.get_uuid() == game_uuid)
+            .unwrap()
+    }
+
     #[test]
-    fn player_from_uuid__good_id() {
+    fn game_is_added_on_creation() {
         let mut manager = Manager::new();
 
-        let game_uuid = manager.create_game();
-        let player = create_player();
+        assert_eq!(manager.get_game_uuids().len(), 0);
 
-        manager
-            .register_player_to_game(&game_uuid, player.clone())
-            .unwrap();
-        let result = manager.player_from_uuid(player.get_id());
-        assert!(result.is_ok());
-        assert_eq!(player, result.unwrap().deref().clone());
+        let created = manager.create_game(GameSettings::default()).unwrap();
+
+        assert_eq!(manager.get_game_uuids(), vec![created.uuid]);
+        assert_eq!(manager.take_new_games().len(), 1);
+        assert!(manager.take_new_games().is_empty());
     }
 
     #[test]
-    fn player_from_uuid__bad_id() {
+    fn create_game__invalid_settings() {
         let mut manager = Manager::new();
 
-        let game_uuid = manager.create_game();
-        let player = create_player();
-        let random_id = Uuid::new_v4();
+        let settings = GameSettings {
+            max_players: 8,
+            ..GameSettings::default()
+        };
 
-        manager
-            .register_player_to_game(&game_uuid, player.clone())
-            .unwrap();
-        let result = manager.player_from_uuid(&random_id);
+        let result = manager.create_game(settings);
         assert!(result.is_err());
-        assert_eq!(result.unwrap_err(), Error::PlayerNotRegistered);
+        assert_eq!(result.unwrap_err(), Error::InvalidSettings);
+        assert_eq!(manager.get_game_uuids().len(), 0);
     }
 
     #[test]
-    fn get_players_for_game__good_id() {
+    fn create_game__private_game_comes_with_its_invite() {
         let mut manager = Manager::new();
 
-        let game_uuid = manager.create_game();
-        let player = create_player();
+        let created = manager.create_game(GameSettings::default()).unwrap();
+        assert!(created.invite.is_none());
 
-        manager
-            .register_player_to_game(&game_uuid, player.clone())
+        let created = manager
+            .create_game(GameSettings {
+                private: true,
+                ..GameSettings::default()
+            })
             .unwrap();
-        let result = manager.get_players_for_game(&game_uuid);
-
-        assert_eq!(result.len(), 1);
-        assert_eq!(result, vec![player]);
+        assert!(created.invite.is_some());
     }
 
     #[test]
-    fn get_players_for_game__bad_id() {
+    fn game_removed__only_archived_games_are_kept() {
         let mut manager = Manager::new();
 
-        let game_uuid = manager.create_game();
-        let player = create_player();
-        let random_id = Uuid::new_v4();
+        let expired = manager.create_game(GameSettings::default()).unwrap().uuid;
+        let archived = manager.create_game(GameSettings::default()).unwrap().uuid;
 
-        manager
-            .register_player_to_game(&game_uuid, player.clone())
-            .unwrap();
-        let result = manager.get_players_for_game(&random_id);
+        for (game_uuid, keep) in [(expired, false), (archived, true)] {
+            manager.game_removed(&game_uuid, keep.then(Scrabble::new));
+        }
 
-        assert_eq!(result.len(), 0);
+        assert!(manager.get_game_uuids().is_empty());
+        assert_eq!(
+            manager.get_game_summary(&expired).unwrap_err(),
+            Error::GameNotFound
+        );
+        assert_eq!(manager.get_game_summary(&archived).unwrap().uuid, archived);
+        assert!(manager.get_archived_game(&archived).is_some());
+        assert_eq!(manager.get_game_counts()[&GameStatus::Waiting], 1);
     }
 
     #[test]
-    fn start_game__good_id() {
+    fn trim_archive__drops_the_games_that_ended_first() {
         let mut manager = Manager::new();
+        let uuids: Vec<_> = (0..3).map(|_| Uuid::new_v4()).collect();
 
-        let game_uuid = manager.create_game();
+        for game_uuid in &uuids {
+            let mut game = Scrabble::new();
+            game.abandon().unwrap();
+            manager.game_removed(game_uuid, Some(game));
+            std::thread::sleep(std::time::Duration::from_millis(2));
+        }
 
-        let player_1 = create_player();
-        let player_2 = create_player();
+        manager.trim_archive(2);
+        assert!(manager.get_archived_game(&uuids[0]).is_none());
+        assert!(manager.get_archived_game(&uuids[1]).is_some());
+        assert!(manager.get_archived_game(&uuids[2]).is_some());
+    }
 
+    #[test]
+    fn get_game_list__hides_private_games() {
+        let mut manager = Manager::new();
+
+        manager.create_game(GameSettings::default()).unwrap();
         manager
-            .register_player_to_game(&game_uuid, player_1.clone())
-            .unwrap();
-        manager
-            .register_player_to_game(&game_uuid, player_2.clone())
+            .create_game(GameSettings {
+                private: true,
+                ..GameSettings::default()
+            })
             .unwrap();
-        let result = manager.start_game(&game_uuid);
 
-        assert!(result.is_ok());
+        let page = manager.get_game_list(&GameFilter::default(), Pagination::default());
+        assert_eq!(page.total, 1);
+        assert!(!page.games[0].private);
+    }
+
+    #[test]
+    fn get_game_list__filter_and_pagination() {
+        let mut manager = Manager::new();
+
+        for i in 0..5 {
+            let settings = GameSettings {
+                name: format!("Game {i}"),
+                ..GameSettings::default()
+            };
+            manager.create_game(settings).unwrap();
+        }
+
+        // Summaries are published by the game task, the room is filled before it starts
+        let mut room = manager
+            .new_room(GameSettings {
+                name: String::from("Full game"),
+                max_players: 2,
+                ..GameSettings::default()
+            })
+            .unwrap();
+        room.register_player(create_player(), None).unwrap();
+        room.register_player(create_player(), None).unwrap();
+        let full_game = *room.get_uuid();
+        manager.insert_room(room);
+
+        let page = manager.get_game_list(&GameFilter::default(), Pagination::default());
+        assert_eq!(page.total, 6);
+        assert_eq!(page.games.len(), 6);
+
+        let filter = GameFilter {
+            joinable: true,
+            ..GameFilter::default()
+        };
+        let page = manager.get_game_list(&filter, Pagination::default());
+        assert_eq!(page.total, 5);
+        assert!(page.games.iter().all(|x| x.uuid != full_game));
 
-        let map = result.unwrap();
-        assert_eq!(map.len(), 2);
-        assert_eq!(map.get(player_1.get_id()).unwrap().len(), 7);
-        assert_eq!(map.get(player_2.get_id()).unwrap().len(), 7);
+        let filter = GameFilter {
+            search: Some(String::from("full")),
+            ..GameFilter::default()
+        };
+        let page = manager.get_game_list(&filter, Pagination::default());
+        assert_eq!(page.total, 1);
+        assert_eq!(page.games[0].uuid, full_game);
+        assert_eq!(page.games[0].players, 2);
+
+        let pagination = Pagination {
+            offset: 4,
+            limit: 4,
+        };
+        let page = manager.get_game_list(&GameFilter::default(), pagination);
+        assert_eq!(page.total, 6);
+        assert_eq!(page.games.len(), 2);
     }
 
     #[test]
-    fn start_game__not_enough_players() {
-        {
-            let mut manager = Manager::new();
-            let game_uuid = manager.create_game();
+    fn game_over__rated_games_only() {
+        let mut manager = Manager::new();
+        let rated = GameSettings {
+            rated: true,
+            ..GameSettings::default()
+        };
 
-            let result = manager.start_game(&game_uuid);
+        let game_uuid = manager.create_game(rated).unwrap().uuid;
+        let players: Vec<_> = (0..3)
+            .map(|i| Player::with_account(&Uuid::new_v4(), &format!("Player{i}")))
+            .collect();
+        let room = new_room_mut(&mut manager, &game_uuid);
+        for player in &players {
+            room.register_player(player.clone(), None).unwrap();
+        }
+        room.start().unwrap();
 
-            assert!(result.is_err());
-            assert_eq!(result.unwrap_err(), Error::NotEnoughPlayers);
+        // Logging out of a rated game forfeits it
+        for player in &players[..2] {
+            room.remove_player(player.get_id()).unwrap();
         }
+        let game = room.get_game().clone();
 
-        {
-            let mut manager = Manager::new();
-            let game_uuid = manager.create_game();
+        let casual_game = manager.create_game(GameSettings::default()).unwrap().uuid;
+        let room = new_room_mut(&mut manager, &casual_game);
+        let casual_players = [create_player(), create_player()];
+        for player in &casual_players {
+            room.register_player(player.clone(), None).unwrap();
+        }
+        room.start().unwrap();
+        room.remove_player(casual_players[0].get_id()).unwrap();
+        let casual = room.get_game().clone();
+
+        let now = SystemTime::now();
+        assert!(manager.game_over(&casual_game, &casual, now).is_none());
+        assert_eq!(manager.game_over(&game_uuid, &game, now).unwrap().len(), 3);
+        assert!(manager.game_over(&game_uuid, &game, now).is_none());
+
+        let winner = manager.get_ratings(players[2].get_id(), Some(Variant::Classic));
+        let loser = manager.get_ratings(players[0].get_id(), None);
+        assert!(winner[0].rating > crate::ratings::INITIAL_RATING);
+        assert!(loser[0].rating < crate::ratings::INITIAL_RATING);
+        assert_eq!(loser[0].history[0].game_uuid, game_uuid);
+    }
 
-            let player = create_player();
+    #[test]
+    fn create_matches__registers_everyone() {
+        let mut manager = Manager::new();
+        let preferences = QueuePreferences {
+            variant: Variant::Classic,
+            time_control: None,
+            rated: false,
+            opponents: 1,
+        };
 
+        let now = SystemTime::now();
+        let players = [create_player(), create_player()];
+        for player in &players {
             manager
-                .register_player_to_game(&game_uuid, player.clone())
+                .join_queue(player.clone(), preferences, now)
                 .unwrap();
-            let result = manager.start_game(&game_uuid);
-
-            assert!(result.is_err());
-            assert_eq!(result.unwrap_err(), Error::NotEnoughPlayers);
         }
+
+        let created = manager.create_matches(now);
+        assert_eq!(created.len(), 1);
+
+        let Seating {
+            game_uuid,
+            players: matched,
+            reconnect_tokens,
+        } = &created[0];
+        assert_eq!(matched, &players.to_vec());
+        assert!(players
+            .iter()
+            .all(|x| reconnect_tokens.contains_key(x.get_id())));
+
+        let summary = manager.get_game_summary(game_uuid).unwrap();
+        assert_eq!(summary.players, 2);
+        assert_eq!(summary.max_players, 2);
+        assert_eq!(
+            new_room_mut(&mut manager, game_uuid).get_players(),
+            players.to_vec()
+        );
+        assert_eq!(
+            manager.leave_queue(players[0].get_id()),
+            Err(Error::NotQueued)
+        );
     }
 
     #[test]
-    fn start_game__bad_id() {
+    fn advance_tournaments__next_round_after_the_last_game() {
         let mut manager = Manager::new();
+        let director = Uuid::new_v4();
+        let settings = TournamentSettings {
+            name: String::from("Club night"),
+            pairing: crate::tournament::PairingSystem::RoundRobin,
+            rounds: 1,
+            king_of_the_hill: false,
+            variant: Variant::Classic,
+            time_control: None,
+            rated: false,
+            seed: None,
+        };
 
-        let game_uuid = manager.create_game();
-
-        let player_1 = create_player();
-        let player_2 = create_player();
+        let tournament_uuid = manager.create_tournament(settings, &director).unwrap();
+        let players: Vec<_> = (0..3).map(|_| create_player()).collect();
+        for player in &players {
+            manager
+                .join_tournament(&tournament_uuid, player.clone())
+                .unwrap();
+        }
 
-        manager
-            .register_player_to_game(&game_uuid, player_1.clone())
-            .unwrap();
-        manager
-            .register_player_to_game(&game_uuid, player_2.clone())
+        let now = SystemTime::now();
+        let games = manager
+            .start_tournament(&tournament_uuid, &director, now)
             .unwrap();
-
-        let random_uuid = Uuid::new_v4();
-        let result = manager.start_game(&random_uuid);
-
-        assert!(result.is_err());
-        assert_eq!(result.unwrap_err(), Error::GameNotFound);
+        assert_eq!(games.len(), 1);
+        let game_uuid = games[0].game_uuid;
+        assert_eq!(
+            manager.get_game_summary(&game_uuid).unwrap().status,
+            GameStatus::InProgress
+        );
+        assert!(manager.advance_tournaments(now).is_empty());
+
+        // Someone leaving ends the game, which ends the round
+        let room = new_room_mut(&mut manager, &game_uuid);
+        let leaver = room.get_players()[0].clone();
+        room.remove_player(leaver.get_id()).unwrap();
+        let game = room.get_game().clone();
+        manager.game_over(&game_uuid, &game, now);
+
+        let progress = manager.advance_tournaments(now);
+        assert_eq!(progress.len(), 1);
+        assert_eq!(progress[0].1.len(), 1);
+        assert!(manager.advance_tournaments(now).is_empty());
+
+        let tournament = manager.get_tournament(&tournament_uuid).unwrap();
+        assert_eq!(tournament.get_summary().round, 2);
+        assert_eq!(tournament.get_standings()[0].points, 1.0);
     }
 }
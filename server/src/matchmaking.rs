@@ -0,0 +1,329 @@
+use crate::config;
+use crate::settings::{GameSettings, TimeControl, Variant};
+use crate::Error;
+use schemars::JsonSchema;
+use scrabble_core::{Player, MIN_PLAYERS};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, SystemTime};
+use ts_rs::TS;
+use uuid::Uuid;
+
+/// How often the queue looks for players to put together
+pub const MATCHMAKING_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Rating gap accepted right after joining the queue
+const INITIAL_WINDOW: f64 = 100.0;
+/// How much the gap widens for every second spent waiting
+const WINDOW_GROWTH: f64 = 10.0;
+/// After about a minute and a half anyone will do
+const MAX_WINDOW: f64 = 1000.0;
+
+/// What a player is looking for, only players with the very same preferences are matched
+#[derive(Copy, Clone, PartialEq, Eq, Serialize, Deserialize, Debug, JsonSchema, TS)]
+pub struct QueuePreferences {
+    #[serde(default)]
+    pub variant: Variant,
+    #[serde(default)]
+    pub time_control: Option<TimeControl>,
+    #[serde(default)]
+    pub rated: bool,
+    #[serde(default = "default_opponents")]
+    pub opponents: usize,
+}
+
+fn default_opponents() -> usize {
+    1
+}
+
+impl QueuePreferences {
+    pub fn validate(&self) -> Result<(), Error> {
+        if !(MIN_PLAYERS..=config::get().limits.max_players).contains(&(self.opponents + 1)) {
+            return Err(Error::InvalidSettings);
+        }
+
+        self.time_control.map_or(Ok(()), |x| x.validate())
+    }
+
+    /// Settings of the game created for the players matched with these preferences
+    pub fn game_settings(&self) -> GameSettings {
+        GameSettings {
+            name: String::from("Quick game"),
+            max_players: self.opponents + 1,
+            variant: self.variant,
+            time_control: self.time_control,
+            rated: self.rated,
+            ..GameSettings::default()
+        }
+    }
+}
+
+struct Ticket {
+    player: Player,
+    preferences: QueuePreferences,
+    rating: f64,
+    joined_at: SystemTime,
+}
+
+impl Ticket {
+    /// Widest rating gap this player accepts after waiting until `now`
+    fn window(&self, now: SystemTime) -> f64 {
+        let waited = now
+            .duration_since(self.joined_at)
+            .unwrap_or_default()
+            .as_secs_f64();
+
+        (INITIAL_WINDOW + WINDOW_GROWTH * waited).min(MAX_WINDOW)
+    }
+
+    fn accepts(&self, other: &Ticket, now: SystemTime) -> bool {
+        self.preferences == other.preferences
+            && (self.rating - other.rating).abs() <= self.window(now).min(other.window(now))
+    }
+}
+
+/// Players put together by the queue, the one who waited the longest comes first
+#[derive(Debug)]
+pub struct Match {
+    pub preferences: QueuePreferences,
+    pub players: Vec<Player>,
+}
+
+/// Players waiting for a quick game, oldest first
+#[derive(Default)]
+pub struct Matchmaking {
+    tickets: Vec<Ticket>,
+}
+
+impl Matchmaking {
+    /// `rating` is the player's rating in the variant they asked for
+    pub fn join(
+        &mut self,
+        player: Player,
+        preferences: QueuePreferences,
+        rating: f64,
+        now: SystemTime,
+    ) -> Result<(), Error> {
+        preferences.validate()?;
+
+        if preferences.rated && !player.is_registered() {
+            return Err(Error::AccountRequired);
+        }
+
+        if self.is_queued(player.get_id()) {
+            return Err(Error::AlreadyQueued);
+        }
+
+        self.tickets.push(Ticket {
+            player,
+            preferences,
+            rating,
+            joined_at: now,
+        });
+
+        Ok(())
+    }
+
+    pub fn leave(&mut self, player_uuid: &Uuid) -> Result<(), Error> {
+        let position = self
+            .tickets
+            .iter()
+            .position(|x| x.player.get_id() == player_uuid)
+            .ok_or(Error::NotQueued)?;
+
+        self.tickets.remove(position);
+
+        Ok(())
+    }
+
+    pub fn is_queued(&self, player_uuid: &Uuid) -> bool {
+        self.tickets
+            .iter()
+            .any(|x| x.player.get_id() == player_uuid)
+    }
+
+    /// Takes out of the queue every group of players that can play together
+    ///
+    /// The longest waiting players are served first, with the closest ratings
+    /// within what both sides accept. Everyone in a group must accept everyone
+    /// else, so a match never spans more than the narrowest window.
+    pub fn pair(&mut self, now: SystemTime) -> Vec<Match> {
+        let mut matches = Vec::new();
+        let mut i = 0;
+
+        while i < self.tickets.len() {
+            let anchor = &self.tickets[i];
+            let size = anchor.preferences.opponents + 1;
+
+            let mut candidates: Vec<usize> = (i + 1..self.tickets.len())
+                .filter(|&j| anchor.accepts(&self.tickets[j], now))
+                .collect();
+            candidates.sort_by(|&a, &b| {
+                let gap = |j: usize| (self.tickets[j].rating - anchor.rating).abs();
+                gap(a).total_cmp(&gap(b))
+            });
+
+            let mut group = vec![i];
+            for j in candidates {
+                if group.len() == size {
+                    break;
+                }
+
+                if group
+                    .iter()
+                    .all(|&k| self.tickets[k].accepts(&self.tickets[j], now))
+                {
+                    group.push(j);
+                }
+            }
+
+            if group.len() < size {
+                i += 1;
+                continue;
+            }
+
+            let preferences = anchor.preferences;
+            // Removing from the back keeps the other indices valid
+            group.sort_unstable_by(|a, b| b.cmp(a));
+            let mut players: Vec<Player> = group
+                .into_iter()
+                .map(|j| self.tickets.remove(j).player)
+                .collect();
+            players.reverse();
+
+            matches.push(Match {
+                preferences,
+                players,
+            });
+        }
+
+        matches
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn player() -> Player {
+        Player::with_account(&Uuid::new_v4(), "player")
+    }
+
+    fn preferences() -> QueuePreferences {
+        QueuePreferences {
+            variant: Variant::Classic,
+            time_control: None,
+            rated: true,
+            opponents: 1,
+        }
+    }
+
+    #[test]
+    fn close_ratings_are_paired() {
+        let mut matchmaking = Matchmaking::default();
+        let now = SystemTime::now();
+        let (first, second) = (player(), player());
+
+        matchmaking
+            .join(first.clone(), preferences(), 1500.0, now)
+            .unwrap();
+        matchmaking
+            .join(player(), preferences(), 1800.0, now)
+            .unwrap();
+        matchmaking
+            .join(second.clone(), preferences(), 1550.0, now)
+            .unwrap();
+
+        let matches = matchmaking.pair(now);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].players, vec![first, second]);
+        assert_eq!(matchmaking.tickets.len(), 1);
+    }
+
+    #[test]
+    fn window_widens_while_waiting() {
+        let mut matchmaking = Matchmaking::default();
+        let now = SystemTime::now();
+
+        matchmaking
+            .join(player(), preferences(), 1500.0, now)
+            .unwrap();
+        matchmaking
+            .join(player(), preferences(), 1800.0, now)
+            .unwrap();
+
+        assert!(matchmaking.pair(now).is_empty());
+        assert!(matchmaking.pair(now + Duration::from_secs(10)).is_empty());
+        assert_eq!(matchmaking.pair(now + Duration::from_secs(30)).len(), 1);
+    }
+
+    #[test]
+    fn only_same_preferences_are_paired() {
+        let mut matchmaking = Matchmaking::default();
+        let now = SystemTime::now();
+        let casual = QueuePreferences {
+            rated: false,
+            ..preferences()
+        };
+
+        matchmaking
+            .join(player(), preferences(), 1500.0, now)
+            .unwrap();
+        matchmaking.join(player(), casual, 1500.0, now).unwrap();
+
+        assert!(matchmaking.pair(now + Duration::from_secs(600)).is_empty());
+    }
+
+    #[test]
+    fn groups_wait_until_full() {
+        let mut matchmaking = Matchmaking::default();
+        let now = SystemTime::now();
+        let three_players = QueuePreferences {
+            opponents: 2,
+            ..preferences()
+        };
+
+        matchmaking
+            .join(player(), three_players, 1500.0, now)
+            .unwrap();
+        matchmaking
+            .join(player(), three_players, 1500.0, now)
+            .unwrap();
+        assert!(matchmaking.pair(now).is_empty());
+
+        matchmaking
+            .join(player(), three_players, 1500.0, now)
+            .unwrap();
+        assert_eq!(matchmaking.pair(now)[0].players.len(), 3);
+    }
+
+    #[test]
+    fn join_checks_the_player() {
+        let mut matchmaking = Matchmaking::default();
+        let now = SystemTime::now();
+        let registered = player();
+
+        assert_eq!(
+            matchmaking.join(
+                Player::new(&Uuid::new_v4(), "guest"),
+                preferences(),
+                1500.0,
+                now
+            ),
+            Err(Error::AccountRequired)
+        );
+
+        matchmaking
+            .join(registered.clone(), preferences(), 1500.0, now)
+            .unwrap();
+        assert_eq!(
+            matchmaking.join(registered.clone(), preferences(), 1500.0, now),
+            Err(Error::AlreadyQueued)
+        );
+
+        assert_eq!(matchmaking.leave(registered.get_id()), Ok(()));
+        assert_eq!(
+            matchmaking.leave(registered.get_id()),
+            Err(Error::NotQueued)
+        );
+    }
+}
@@ -0,0 +1,269 @@
+use crate::events::{Event, EventSender};
+use crate::manager::Manager;
+use crate::Error;
+use axum::extract::State;
+use axum::http::{header, StatusCode};
+use axum::response::IntoResponse;
+use scrabble_core::GameStatus;
+use socketioxide::SocketIo;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+use tokio::sync::oneshot;
+
+/// Upper bounds of the histogram buckets, in seconds
+const BUCKETS: [f64; 10] = [0.0001, 0.0005, 0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0];
+
+const STATUSES: [GameStatus; 6] = [
+    GameStatus::Waiting,
+    GameStatus::Starting,
+    GameStatus::InProgress,
+    GameStatus::Paused,
+    GameStatus::Finished,
+    GameStatus::Abandoned,
+];
+
+/// Socket handlers, game tasks and the lobby loop record into the same metrics,
+/// wherever they run
+static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::default);
+
+#[derive(Default)]
+struct Histogram {
+    /// One count per bucket, not cumulative
+    buckets: [AtomicU64; BUCKETS.len()],
+    count: AtomicU64,
+    sum_nanos: AtomicU64,
+}
+
+impl Histogram {
+    fn observe(&self, duration: Duration) {
+        let seconds = duration.as_secs_f64();
+
+        if let Some(bucket) = BUCKETS.iter().position(|x| seconds <= *x) {
+            self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        }
+
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum_nanos
+            .fetch_add(duration.as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    fn render(&self, out: &mut String, name: &str, help: &str) {
+        describe(out, name, "histogram", help);
+
+        let mut cumulative = 0;
+        for (bound, bucket) in BUCKETS.iter().zip(&self.buckets) {
+            cumulative += bucket.load(Ordering::Relaxed);
+            writeln!(out, "{name}_bucket{{le=\"{bound}\"}} {cumulative}").ok();
+        }
+
+        let count = self.count.load(Ordering::Relaxed);
+        let sum = self.sum_nanos.load(Ordering::Relaxed) as f64 / 1e9;
+        writeln!(out, "{name}_bucket{{le=\"+Inf\"}} {count}").ok();
+        writeln!(out, "{name}_sum {sum}").ok();
+        writeln!(out, "{name}_count {count}").ok();
+    }
+}
+
+#[derive(Default)]
+struct Metrics {
+    plays: AtomicU64,
+    passes: AtomicU64,
+    errors: Mutex<BTreeMap<String, u64>>,
+    event_wait: Histogram,
+    event_handling: Histogram,
+    move_evaluation: Histogram,
+}
+
+#[derive(Clone, Copy)]
+pub enum MoveKind {
+    Play,
+    Pass,
+}
+
+pub fn record_move(kind: MoveKind) {
+    match kind {
+        MoveKind::Play => &METRICS.plays,
+        MoveKind::Pass => &METRICS.passes,
+    }
+    .fetch_add(1, Ordering::Relaxed);
+}
+
+/// Counted once per error sent back to a client, by variant
+pub fn record_error(error: &Error) {
+    // Variants carrying data are counted together, whatever the data
+    let name = format!("{error:?}");
+    let name = name.split('(').next().unwrap_or_default().to_string();
+
+    *METRICS.errors.lock().unwrap().entry(name).or_default() += 1;
+}
+
+/// How long an event sat in the channel, then how long it took to handle
+pub fn observe_event(wait: Duration, handling: Duration) {
+    METRICS.event_wait.observe(wait);
+    METRICS.event_handling.observe(handling);
+}
+
+/// Checking, scoring and applying a move
+pub fn observe_move_evaluation(duration: Duration) {
+    METRICS.move_evaluation.observe(duration);
+}
+
+fn describe(out: &mut String, name: &str, kind: &str, help: &str) {
+    writeln!(out, "# HELP {name} {help}").ok();
+    writeln!(out, "# TYPE {name} {kind}").ok();
+}
+
+fn status_label(status: GameStatus) -> String {
+    serde_json::to_value(status)
+        .ok()
+        .and_then(|x| x.as_str().map(String::from))
+        .unwrap_or_default()
+}
+
+/// Everything the event loop owns, rendered in the Prometheus text format
+///
+/// Game namespaces are summed into `/game` so the number of series stays bounded.
+pub fn render(socket_io: &SocketIo, manager: &Manager) -> String {
+    let mut out = String::new();
+    let count_sockets = |namespace: &str| {
+        socket_io
+            .of(namespace)
+            .and_then(|x| x.sockets().ok())
+            .map_or(0, |x| x.len())
+    };
+
+    let lobby_sockets = count_sockets("/");
+    let game_sockets: usize = manager
+        .get_game_uuids()
+        .iter()
+        .map(|x| count_sockets(&format!("/game/{x}")))
+        .sum();
+
+    describe(
+        &mut out,
+        "scrabble_sockets",
+        "gauge",
+        "Connected sockets per namespace",
+    );
+    writeln!(out, "scrabble_sockets{{namespace=\"/\"}} {lobby_sockets}").ok();
+    writeln!(
+        out,
+        "scrabble_sockets{{namespace=\"/game\"}} {game_sockets}"
+    )
+    .ok();
+
+    let counts = manager.get_game_counts();
+    describe(
+        &mut out,
+        "scrabble_games",
+        "gauge",
+        "Games per lifecycle state, archived ones included",
+    );
+    for status in STATUSES {
+        let count = counts.get(&status).copied().unwrap_or_default();
+        writeln!(
+            out,
+            "scrabble_games{{status=\"{}\"}} {count}",
+            status_label(status)
+        )
+        .ok();
+    }
+
+    describe(
+        &mut out,
+        "scrabble_moves_total",
+        "counter",
+        "Moves played by the players",
+    );
+    for (kind, counter) in [("play", &METRICS.plays), ("pass", &METRICS.passes)] {
+        let count = counter.load(Ordering::Relaxed);
+        writeln!(out, "scrabble_moves_total{{kind=\"{kind}\"}} {count}").ok();
+    }
+
+    describe(
+        &mut out,
+        "scrabble_errors_total",
+        "counter",
+        "Errors sent back to clients per variant",
+    );
+    for (error, count) in METRICS.errors.lock().unwrap().iter() {
+        writeln!(out, "scrabble_errors_total{{error=\"{error}\"}} {count}").ok();
+    }
+
+    METRICS.event_wait.render(
+        &mut out,
+        "scrabble_event_wait_seconds",
+        "Time events spend in the channel before being handled",
+    );
+    METRICS.event_handling.render(
+        &mut out,
+        "scrabble_event_handling_seconds",
+        "Time taken to handle an event",
+    );
+    METRICS.move_evaluation.render(
+        &mut out,
+        "scrabble_move_evaluation_seconds",
+        "Time taken to check, score and apply a move",
+    );
+
+    out
+}
+
+/// `/metrics`, scraped by Prometheus
+pub async fn serve(State(sender): State<EventSender>) -> axum::response::Response {
+    // Read before queuing the scrape, which would count itself otherwise
+    let depth = sender.depth();
+    let (reply, response) = oneshot::channel();
+
+    if sender.send(Event::Metrics(reply)).await.is_err() {
+        return StatusCode::SERVICE_UNAVAILABLE.into_response();
+    }
+
+    let Ok(mut out) = response.await else {
+        return StatusCode::SERVICE_UNAVAILABLE.into_response();
+    };
+
+    describe(
+        &mut out,
+        "scrabble_event_channel_depth",
+        "gauge",
+        "Events waiting to be handled",
+    );
+    writeln!(out, "scrabble_event_channel_depth {depth}").ok();
+
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], out).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn histogram_buckets_are_cumulative() {
+        let histogram = Histogram::default();
+        histogram.observe(Duration::from_micros(50));
+        histogram.observe(Duration::from_millis(3));
+        histogram.observe(Duration::from_secs(10));
+
+        let mut out = String::new();
+        histogram.render(&mut out, "test", "Test");
+
+        assert!(out.contains("test_bucket{le=\"0.0001\"} 1\n"));
+        assert!(out.contains("test_bucket{le=\"0.005\"} 2\n"));
+        assert!(out.contains("test_bucket{le=\"5\"} 2\n"));
+        assert!(out.contains("test_bucket{le=\"+Inf\"} 3\n"));
+        assert!(out.contains("test_count 3\n"));
+    }
+
+    #[test]
+    fn errors_are_counted_per_variant() {
+        record_error(&Error::ActionNotAllowed(GameStatus::Waiting));
+        record_error(&Error::ActionNotAllowed(GameStatus::Finished));
+
+        let errors = METRICS.errors.lock().unwrap();
+        assert!(errors["ActionNotAllowed"] >= 2);
+    }
+}
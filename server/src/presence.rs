@@ -0,0 +1,73 @@
+use crate::game;
+use crate::response::Response;
+use crate::room::{DisconnectAction, Room};
+use scrabble_core::Player;
+use serde::Serialize;
+use socketioxide::SocketIo;
+use std::time::{Duration, SystemTime};
+use tracing::debug;
+use uuid::Uuid;
+
+/// How often disconnected players are checked on
+pub const PRESENCE_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Serialize, Debug)]
+struct Presence<'a> {
+    player: &'a Player,
+    connected: bool,
+}
+
+/// Tells the other players of a game that someone left or came back
+pub fn notify_presence(socket_io: &SocketIo, game_uuid: &Uuid, player: &Player, connected: bool) {
+    if let Some(namespace) = socket_io.of(format!("/game/{game_uuid}")) {
+        namespace
+            .emit(
+                "presence",
+                &Response::from_data(Presence { player, connected }),
+            )
+            .ok();
+    }
+}
+
+/// Deals with the players of a game who have been away for too long
+pub fn enforce(socket_io: &SocketIo, room: &mut Room) {
+    let actions = room.enforce_disconnects(SystemTime::now());
+    let game_uuid = room.get_uuid();
+
+    for action in &actions {
+        let namespace = socket_io.of(format!("/game/{game_uuid}"));
+
+        match action {
+            DisconnectAction::TurnPassed(player_uuid) => {
+                debug!(%game_uuid, %player_uuid, "Turn passed for away player");
+
+                if let Some(namespace) = namespace {
+                    namespace
+                        .emit("turn-passed", &Response::from_data(player_uuid))
+                        .ok();
+                }
+            }
+            DisconnectAction::Forfeited(player_uuid) => {
+                debug!(%game_uuid, %player_uuid, "Away player forfeited");
+
+                if let Some(namespace) = namespace {
+                    namespace
+                        .emit("player-forfeited", &Response::from_data(player_uuid))
+                        .ok();
+                }
+            }
+            DisconnectAction::Removed(player_uuid) => {
+                debug!(%game_uuid, %player_uuid, "Away player removed");
+
+                if let Some(namespace) = namespace {
+                    let players_response = Response::from_data(room.get_players());
+                    namespace.emit("players-list", &players_response).ok();
+                }
+            }
+        }
+    }
+
+    if !actions.is_empty() {
+        game::notify_status(socket_io, room);
+    }
+}
@@ -0,0 +1,405 @@
+use crate::i18n::{Locale, Message};
+use crate::matchmaking::QueuePreferences;
+use crate::response::Response;
+use crate::settings::{GameSettings, Variant};
+use crate::stats::{LeaderboardKind, StatsFilter};
+use crate::summary::{GameFilter, Pagination};
+use crate::tournament::TournamentSettings;
+use crate::Error;
+use schemars::gen::SchemaSettings;
+use schemars::JsonSchema;
+use scrabble_core::Play;
+use serde::{Deserialize, Serialize};
+use socketioxide::extract::{SocketRef, TryData};
+use std::path::Path;
+use ts_rs::TS;
+use uuid::Uuid;
+
+/// Bumped whenever a change would break existing clients
+pub const PROTOCOL_VERSION: u32 = 1;
+
+pub const SCHEMA_FILE: &str = "protocol.schema.json";
+pub const TYPESCRIPT_FILE: &str = "protocol.d.ts";
+
+/// Sent by clients as the socket.io `auth` payload of every namespace
+#[derive(Deserialize, Serialize, Debug, Default, JsonSchema, TS)]
+pub struct Handshake {
+    /// The version of the protocol the client speaks, it must be `PROTOCOL_VERSION`
+    #[ts(type = "number")]
+    pub protocol: Option<u32>,
+    /// Session token of a logged in player, guests send none
+    #[serde(default)]
+    #[ts(optional = nullable)]
+    pub token: Option<String>,
+    /// Language tag such as `fr-CA`, the `Accept-Language` header is used otherwise
+    #[serde(default)]
+    #[ts(optional = nullable)]
+    pub locale: Option<String>,
+}
+
+/// Sent as "welcome" once a socket is connected
+#[derive(Serialize, Debug, JsonSchema, TS)]
+pub struct Welcome {
+    pub protocol: u32,
+    /// Language errors and notices are sent in
+    pub locale: Locale,
+}
+
+/// Shape of every acknowledgement and server event, see `Response`
+#[derive(Serialize, Debug, JsonSchema, TS)]
+pub struct Reply {
+    #[ts(type = "unknown")]
+    pub data: Option<serde_json::Value>,
+    /// Error text in the locale of the socket
+    pub error: Option<String>,
+    /// Stable name of the error
+    pub code: Option<String>,
+    /// What `error`, or `data` for notices, was rendered from
+    pub message: Option<Message>,
+}
+
+/// Connect middleware turning away clients that speak another version
+pub fn check_version(TryData(handshake): TryData<Handshake>) -> Result<(), Error> {
+    let protocol = handshake.ok().and_then(|x| x.protocol);
+
+    match protocol == Some(PROTOCOL_VERSION) {
+        true => Ok(()),
+        false => Err(Error::UnsupportedProtocol),
+    }
+}
+
+/// Confirms the version to a socket that was let in
+pub fn welcome(socket_ref: &SocketRef) {
+    let welcome = Welcome {
+        protocol: PROTOCOL_VERSION,
+        locale: Locale::of(socket_ref),
+    };
+
+    socket_ref
+        .emit("welcome", &Response::from_data(welcome))
+        .ok();
+}
+
+// Lobby requests
+
+#[derive(Deserialize, Serialize, Debug, Clone, JsonSchema, TS)]
+pub struct ListGamesRequest {
+    #[serde(default)]
+    pub filter: GameFilter,
+    #[serde(default)]
+    pub pagination: Pagination,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, JsonSchema, TS)]
+pub struct CreateGameRequest {
+    pub settings: GameSettings,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, JsonSchema, TS)]
+pub struct RatingsRequest {
+    pub account_id: Uuid,
+    /// Every variant the player is rated in if omitted
+    #[serde(default)]
+    pub variant: Option<Variant>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, JsonSchema, TS)]
+pub struct PlayerStatsRequest {
+    pub account_id: Uuid,
+    #[serde(flatten)]
+    pub filter: StatsFilter,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, JsonSchema, TS)]
+pub struct LeaderboardRequest {
+    pub leaderboard: LeaderboardKind,
+    #[serde(flatten)]
+    pub filter: StatsFilter,
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+/// Sent to sign up as well as to log in
+#[derive(Deserialize, Serialize, Debug, Clone, JsonSchema, TS)]
+pub struct CredentialsRequest {
+    pub username: String,
+    pub password: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, JsonSchema, TS)]
+pub struct JoinQueueRequest {
+    pub preferences: QueuePreferences,
+    /// Guests pick a name, logged in players play under their username
+    #[serde(default)]
+    pub username: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, JsonSchema, TS)]
+pub struct CreateTournamentRequest {
+    pub tournament: TournamentSettings,
+    #[serde(default)]
+    pub username: Option<String>,
+}
+
+/// Sent to join, start or look at a tournament
+#[derive(Deserialize, Serialize, Debug, Clone, JsonSchema, TS)]
+pub struct TournamentRequest {
+    pub tournament_uuid: Uuid,
+    #[serde(default)]
+    pub username: Option<String>,
+}
+
+// Game requests
+
+#[derive(Deserialize, Serialize, Debug, Clone, JsonSchema, TS)]
+pub struct RegisterRequest {
+    pub game_uuid: Uuid,
+    pub username: String,
+    #[serde(default)]
+    pub invite_code: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, JsonSchema, TS)]
+pub struct LogoutRequest {
+    pub game_uuid: Uuid,
+    pub player_uuid: Uuid,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, JsonSchema, TS)]
+pub struct WhoAmIRequest {
+    pub player_uuid: Uuid,
+    /// Given to guests with their seat, players with an account are known by
+    /// their session
+    #[serde(default)]
+    pub reconnect_token: Option<String>,
+}
+
+/// What the host of a private game wants to do with its invite code
+#[derive(Deserialize, Serialize, Debug, Clone, JsonSchema, TS)]
+#[serde(rename_all = "kebab-case")]
+pub enum InviteAction {
+    Show,
+    Regenerate {
+        /// Lifetime of the new code in seconds, never expires if omitted
+        #[serde(default)]
+        #[ts(type = "number | null")]
+        expires_in: Option<u64>,
+    },
+    Revoke,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, JsonSchema, TS)]
+pub struct InviteRequest {
+    pub game_uuid: Uuid,
+    pub action: InviteAction,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, JsonSchema, TS)]
+pub struct CloseRequest {
+    pub game_uuid: Uuid,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, JsonSchema, TS)]
+pub struct PlayRequest {
+    pub tiles: Vec<Play>,
+}
+
+/// Every event a client can send, `event` is the socket.io event name and
+/// `data` its payload, events without one take no payload at all
+///
+/// Sockets get each payload on its own, this ties them to their event names for
+/// the JSON Schema and the TypeScript definitions in `/protocol`.
+#[derive(Deserialize, Serialize, Debug, Clone, JsonSchema, TS)]
+#[serde(tag = "event", content = "data", rename_all = "kebab-case")]
+pub enum ClientMessage {
+    // On "/"
+    ListGames(ListGamesRequest),
+    CreateGame(CreateGameRequest),
+    Ratings(RatingsRequest),
+    PlayerStats(PlayerStatsRequest),
+    Leaderboard(LeaderboardRequest),
+    SignUp(CredentialsRequest),
+    LogIn(CredentialsRequest),
+    LogOut,
+    JoinQueue(JoinQueueRequest),
+    LeaveQueue,
+    CreateTournament(CreateTournamentRequest),
+    JoinTournament(TournamentRequest),
+    StartTournament(TournamentRequest),
+    Tournament(TournamentRequest),
+    ListTournaments,
+
+    // On "/game/{game_uuid}"
+    #[serde(rename = "register_request")]
+    Register(RegisterRequest),
+    Logout(LogoutRequest),
+    #[serde(rename = "whoami")]
+    WhoAmI(WhoAmIRequest),
+    PlayerList,
+    Start,
+    Pause,
+    Resume,
+    Invite(InviteRequest),
+    Close(CloseRequest),
+    Pass,
+    Play(PlayRequest),
+}
+
+/// Declares a type and every type it is made of in TypeScript, once each
+macro_rules! declarations {
+    ($($type:ty),* $(,)?) => {
+        [$(
+            format!(
+                "{}export {}\n",
+                <$type as TS>::DOCS.unwrap_or_default(),
+                <$type as TS>::decl()
+            ),
+        )*]
+    };
+}
+
+/// JSON Schema of the messages, the root being `ClientMessage`
+pub fn json_schema() -> String {
+    let mut generator = SchemaSettings::draft07().into_generator();
+    generator.subschema_for::<Handshake>();
+    generator.subschema_for::<Welcome>();
+    generator.subschema_for::<Reply>();
+    let schema = generator.into_root_schema_for::<ClientMessage>();
+
+    // Serializing a schema can't fail, it is plain JSON
+    serde_json::to_string_pretty(&schema).unwrap_or_default() + "\n"
+}
+
+/// TypeScript definitions of the messages and of every type they use
+pub fn typescript() -> String {
+    use crate::i18n::Param;
+    use crate::settings::{DisconnectPolicy, TimeControl};
+    use crate::stats::Period;
+    use crate::tournament::PairingSystem;
+    use scrabble_core::{GameStatus, Tile};
+
+    let declarations = declarations![
+        Handshake,
+        Welcome,
+        Reply,
+        Message,
+        Param,
+        Locale,
+        ClientMessage,
+        ListGamesRequest,
+        CreateGameRequest,
+        RatingsRequest,
+        PlayerStatsRequest,
+        LeaderboardRequest,
+        CredentialsRequest,
+        JoinQueueRequest,
+        CreateTournamentRequest,
+        TournamentRequest,
+        RegisterRequest,
+        LogoutRequest,
+        WhoAmIRequest,
+        InviteAction,
+        InviteRequest,
+        CloseRequest,
+        PlayRequest,
+        Play,
+        Tile,
+        GameFilter,
+        Pagination,
+        GameStatus,
+        GameSettings,
+        Variant,
+        TimeControl,
+        DisconnectPolicy,
+        QueuePreferences,
+        TournamentSettings,
+        PairingSystem,
+        StatsFilter,
+        Period,
+        LeaderboardKind,
+    ];
+
+    format!(
+        "// Generated by `scrabble --export-protocol`, do not edit\n\
+         \n\
+         export const PROTOCOL_VERSION = {PROTOCOL_VERSION};\n\
+         \n\
+         {}",
+        declarations.join("\n")
+    )
+}
+
+/// Writes the JSON Schema and the TypeScript definitions in `directory`, as
+/// `scrabble --export-protocol ../protocol` does whenever the messages change
+pub fn export(directory: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(directory)?;
+    std::fs::write(directory.join(SCHEMA_FILE), json_schema())?;
+    std::fs::write(directory.join(TYPESCRIPT_FILE), typescript())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn unit_events_are_told_apart() {
+        let message: ClientMessage =
+            serde_json::from_value(json!({"event": "player-list"})).unwrap();
+        assert!(matches!(message, ClientMessage::PlayerList));
+
+        let message: ClientMessage = serde_json::from_value(json!({"event": "start"})).unwrap();
+        assert!(matches!(message, ClientMessage::Start));
+
+        let message: ClientMessage = serde_json::from_value(json!({
+            "event": "whoami",
+            "data": {"player_uuid": Uuid::nil()},
+        }))
+        .unwrap();
+        assert!(matches!(message, ClientMessage::WhoAmI(_)));
+
+        // A payload only matches the event it is sent with
+        let message = serde_json::from_value::<ClientMessage>(json!({
+            "event": "close",
+            "data": {"player_uuid": Uuid::nil()},
+        }));
+        assert!(message.is_err());
+    }
+
+    #[test]
+    fn only_the_current_version_is_let_in() {
+        let handshake = |protocol| {
+            Ok(Handshake {
+                protocol,
+                ..Handshake::default()
+            })
+        };
+
+        assert!(check_version(TryData(handshake(Some(PROTOCOL_VERSION)))).is_ok());
+        assert_eq!(
+            check_version(TryData(handshake(Some(PROTOCOL_VERSION + 1)))),
+            Err(Error::UnsupportedProtocol)
+        );
+        assert_eq!(
+            check_version(TryData(handshake(None))),
+            Err(Error::UnsupportedProtocol)
+        );
+    }
+
+    #[test]
+    fn generated_files_are_up_to_date() {
+        let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("../protocol");
+
+        for (file, generated) in [
+            (SCHEMA_FILE, json_schema()),
+            (TYPESCRIPT_FILE, typescript()),
+        ] {
+            let committed = std::fs::read_to_string(directory.join(file)).unwrap_or_default();
+
+            assert!(
+                committed == generated,
+                "{file} is out of date, run `cargo run -- --export-protocol ../protocol`"
+            );
+        }
+    }
+}
@@ -0,0 +1,377 @@
+use crate::settings::Variant;
+use crate::storage;
+use crate::summary::unix_timestamp;
+use crate::Error;
+use scrabble_core::FinalScore;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use uuid::Uuid;
+
+/// Where ratings are kept between restarts
+pub const RATINGS_PATH: &str = "ratings.json";
+
+pub const INITIAL_RATING: f64 = 1500.0;
+
+/// How many points a single game can move a rating, newcomers move faster
+const K_FACTOR: f64 = 32.0;
+const PROVISIONAL_K_FACTOR: f64 = 48.0;
+const PROVISIONAL_GAMES: u32 = 10;
+
+/// A win by that many points counts about 35% more than a narrow one
+const SPREAD_SCALE: f64 = 100.0;
+
+/// How a rating moved after a game
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct RatingChange {
+    pub game_uuid: Uuid,
+    pub before: f64,
+    pub after: f64,
+    /// Seconds since the UNIX epoch
+    pub at: u64,
+}
+
+/// Rating of an account in one variant
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct Rating {
+    pub variant: Variant,
+    pub rating: f64,
+    pub games: u32,
+    /// Oldest first
+    pub history: Vec<RatingChange>,
+}
+
+impl Rating {
+    fn new(variant: Variant) -> Self {
+        Rating {
+            variant,
+            rating: INITIAL_RATING,
+            games: 0,
+            history: Vec::new(),
+        }
+    }
+
+    fn k_factor(&self) -> f64 {
+        if self.games < PROVISIONAL_GAMES {
+            PROVISIONAL_K_FACTOR
+        } else {
+            K_FACTOR
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct RatingRecord {
+    account_id: Uuid,
+    #[serde(flatten)]
+    rating: Rating,
+}
+
+/// Elo ratings of every account, one per variant
+#[derive(Default)]
+pub struct Ratings {
+    ratings: HashMap<(Uuid, Variant), Rating>,
+    /// Games already accounted for, so none is counted twice
+    settled: HashSet<Uuid>,
+    /// In memory only when `None`
+    path: Option<PathBuf>,
+}
+
+impl Ratings {
+    /// Reads the ratings saved at `path`, starts from scratch if there is no file yet
+    pub fn load(path: &Path) -> std::io::Result<Self> {
+        let records: Vec<RatingRecord> = storage::load(path)?;
+
+        let settled = records
+            .iter()
+            .flat_map(|x| x.rating.history.iter().map(|x| x.game_uuid))
+            .collect();
+        let ratings = records
+            .into_iter()
+            .map(|x| ((x.account_id, x.rating.variant), x.rating))
+            .collect();
+
+        Ok(Ratings {
+            ratings,
+            settled,
+            path: Some(path.to_path_buf()),
+        })
+    }
+
+    fn save(&self) -> Result<(), Error> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        let records: Vec<RatingRecord> = self
+            .ratings
+            .iter()
+            .map(|((account_id, _), rating)| RatingRecord {
+                account_id: *account_id,
+                rating: rating.clone(),
+            })
+            .collect();
+
+        storage::save(path, &records)
+    }
+
+    /// Accounts that never played the variant have the initial rating
+    pub fn get_rating(&self, account_id: &Uuid, variant: Variant) -> Rating {
+        self.ratings
+            .get(&(*account_id, variant))
+            .cloned()
+            .unwrap_or_else(|| Rating::new(variant))
+    }
+
+    /// Ratings of an account in every variant it played
+    pub fn get_ratings(&self, account_id: &Uuid) -> Vec<Rating> {
+        self.ratings
+            .iter()
+            .filter(|((x, _), _)| x == account_id)
+            .map(|(_, x)| x.clone())
+            .collect()
+    }
+
+    pub fn get_variant_ratings(&self, variant: Variant) -> impl Iterator<Item = (&Uuid, &Rating)> {
+        self.ratings
+            .iter()
+            .filter(move |((_, x), _)| *x == variant)
+            .map(|((account_id, _), rating)| (account_id, rating))
+    }
+
+    pub fn is_settled(&self, game_uuid: &Uuid) -> bool {
+        self.settled.contains(game_uuid)
+    }
+
+    /// Updates the ratings of everyone who took part in a finished game
+    ///
+    /// Every player is compared with every other one: higher score wins, equal
+    /// scores draw and forfeiting loses to anyone who didn't. The spread scales how
+    /// much each of these results counts, and the K factor is shared between
+    /// opponents so bigger games don't move ratings more.
+    pub fn settle(
+        &mut self,
+        game_uuid: &Uuid,
+        variant: Variant,
+        final_scores: &[FinalScore],
+        now: SystemTime,
+    ) -> Vec<(Uuid, RatingChange)> {
+        if final_scores.len() < 2 || !self.settled.insert(*game_uuid) {
+            return Vec::new();
+        }
+
+        let before: Vec<Rating> = final_scores
+            .iter()
+            .map(|x| self.get_rating(&x.player_uuid, variant))
+            .collect();
+        let opponents = (final_scores.len() - 1) as f64;
+        let mut changes = Vec::new();
+
+        for (i, player) in final_scores.iter().enumerate() {
+            let delta: f64 = final_scores
+                .iter()
+                .enumerate()
+                .filter(|(j, _)| *j != i)
+                .map(|(j, opponent)| {
+                    let expected = expected_result(before[i].rating, before[j].rating);
+                    spread_factor(player, opponent) * (result(player, opponent) - expected)
+                })
+                .sum();
+
+            let mut rating = before[i].clone();
+            let change = RatingChange {
+                game_uuid: *game_uuid,
+                before: rating.rating,
+                after: rating.rating + rating.k_factor() * delta / opponents,
+                at: unix_timestamp(now),
+            };
+
+            rating.rating = change.after;
+            rating.games += 1;
+            rating.history.push(change.clone());
+
+            self.ratings.insert((player.player_uuid, variant), rating);
+            changes.push((player.player_uuid, change));
+        }
+
+        // Ratings stay right in memory, the next game will try saving again
+        self.save().ok();
+
+        changes
+    }
+}
+
+/// 1 for a win, 0.5 for a draw and 0 for a loss
+pub fn result(player: &FinalScore, opponent: &FinalScore) -> f64 {
+    match (player.forfeited, opponent.forfeited) {
+        (false, true) => 1.0,
+        (true, false) => 0.0,
+        _ => match player.score.cmp(&opponent.score) {
+            std::cmp::Ordering::Greater => 1.0,
+            std::cmp::Ordering::Equal => 0.5,
+            std::cmp::Ordering::Less => 0.0,
+        },
+    }
+}
+
+fn expected_result(rating: f64, opponent_rating: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf((opponent_rating - rating) / 400.0))
+}
+
+/// Grows slowly with the spread, forfeits count as narrow results
+fn spread_factor(player: &FinalScore, opponent: &FinalScore) -> f64 {
+    if player.forfeited || opponent.forfeited {
+        return 1.0;
+    }
+
+    let spread = (player.score - opponent.score).unsigned_abs() as f64;
+    1.0 + (spread / SPREAD_SCALE).ln_1p() / 2.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn final_score(score: i32) -> FinalScore {
+        FinalScore {
+            player_uuid: Uuid::new_v4(),
+            score,
+            forfeited: false,
+        }
+    }
+
+    #[test]
+    fn winner_gains_what_loser_loses() {
+        let mut ratings = Ratings::default();
+        let winner = final_score(400);
+        let loser = final_score(350);
+
+        let changes = ratings.settle(
+            &Uuid::new_v4(),
+            Variant::Classic,
+            &[winner, loser],
+            SystemTime::now(),
+        );
+        assert_eq!(changes.len(), 2);
+
+        let winner_rating = ratings.get_rating(&winner.player_uuid, Variant::Classic);
+        let loser_rating = ratings.get_rating(&loser.player_uuid, Variant::Classic);
+        assert!(winner_rating.rating > INITIAL_RATING);
+        assert!(
+            (winner_rating.rating - INITIAL_RATING - (INITIAL_RATING - loser_rating.rating)).abs()
+                < 1e-9
+        );
+        assert_eq!(winner_rating.games, 1);
+        assert_eq!(winner_rating.history.len(), 1);
+    }
+
+    #[test]
+    fn bigger_spread_moves_ratings_more() {
+        let mut ratings = Ratings::default();
+        let narrow = [final_score(401), final_score(400)];
+        let blowout = [final_score(500), final_score(200)];
+
+        ratings.settle(
+            &Uuid::new_v4(),
+            Variant::Classic,
+            &narrow,
+            SystemTime::now(),
+        );
+        ratings.settle(
+            &Uuid::new_v4(),
+            Variant::Classic,
+            &blowout,
+            SystemTime::now(),
+        );
+
+        let narrow_gain = ratings
+            .get_rating(&narrow[0].player_uuid, Variant::Classic)
+            .rating
+            - INITIAL_RATING;
+        let blowout_gain = ratings
+            .get_rating(&blowout[0].player_uuid, Variant::Classic)
+            .rating
+            - INITIAL_RATING;
+        assert!(blowout_gain > narrow_gain);
+    }
+
+    #[test]
+    fn draw_between_equals_changes_nothing() {
+        let mut ratings = Ratings::default();
+        let players = [final_score(300), final_score(300)];
+
+        let changes = ratings.settle(
+            &Uuid::new_v4(),
+            Variant::Classic,
+            &players,
+            SystemTime::now(),
+        );
+
+        for (_, change) in changes {
+            assert_eq!(change.before, change.after);
+        }
+    }
+
+    #[test]
+    fn forfeit_loses_whatever_the_score() {
+        let mut ratings = Ratings::default();
+        let quitter = FinalScore {
+            forfeited: true,
+            ..final_score(300)
+        };
+        let player = final_score(10);
+
+        ratings.settle(
+            &Uuid::new_v4(),
+            Variant::Classic,
+            &[quitter, player],
+            SystemTime::now(),
+        );
+
+        assert!(
+            ratings
+                .get_rating(&quitter.player_uuid, Variant::Classic)
+                .rating
+                < INITIAL_RATING
+        );
+    }
+
+    #[test]
+    fn games_are_settled_once() {
+        let mut ratings = Ratings::default();
+        let game_uuid = Uuid::new_v4();
+        let players = [final_score(300), final_score(200)];
+
+        ratings.settle(&game_uuid, Variant::Classic, &players, SystemTime::now());
+        let rating = ratings.get_rating(&players[0].player_uuid, Variant::Classic);
+
+        assert!(ratings.is_settled(&game_uuid));
+        assert!(ratings
+            .settle(&game_uuid, Variant::Classic, &players, SystemTime::now())
+            .is_empty());
+        assert_eq!(
+            ratings.get_rating(&players[0].player_uuid, Variant::Classic),
+            rating
+        );
+    }
+
+    #[test]
+    fn ratings_survive_a_restart() {
+        let path = std::env::temp_dir().join(format!("ratings-{}.json", Uuid::new_v4()));
+        let game_uuid = Uuid::new_v4();
+        let players = [final_score(300), final_score(200)];
+
+        let rating = {
+            let mut ratings = Ratings::load(&path).unwrap();
+            ratings.settle(&game_uuid, Variant::Classic, &players, SystemTime::now());
+            ratings.get_rating(&players[0].player_uuid, Variant::Classic)
+        };
+
+        let ratings = Ratings::load(&path).unwrap();
+        assert!(ratings.is_settled(&game_uuid));
+        assert_eq!(ratings.get_ratings(&players[0].player_uuid), vec![rating]);
+
+        std::fs::remove_file(&path).ok();
+    }
+}
@@ -0,0 +1,53 @@
+use crate::room::{Reaped, Room};
+use crate::{game, lobby};
+use socketioxide::SocketIo;
+use std::time::{Duration, SystemTime};
+use tracing::debug;
+
+/// How long games are kept around before the reaper steps in
+#[derive(Clone, Debug)]
+pub struct ReaperConfig {
+    /// How often the reaper goes through the games
+    pub interval: Duration,
+    /// Waiting rooms nobody joined for that long are removed
+    pub empty_room_timeout: Duration,
+    /// Games where every player is disconnected for that long are abandoned
+    pub abandon_after: Duration,
+    /// Finished and abandoned games stay visible for that long, then are archived
+    pub retention: Duration,
+}
+
+impl Default for ReaperConfig {
+    fn default() -> Self {
+        ReaperConfig {
+            interval: Duration::from_secs(30),
+            empty_room_timeout: Duration::from_secs(10 * 60),
+            abandon_after: Duration::from_secs(5 * 60),
+            retention: Duration::from_secs(30 * 60),
+        }
+    }
+}
+
+/// Cleans up a game and tells the affected sockets about it
+///
+/// Expired and archived games are done for, their task ends right after.
+pub fn reap(socket_io: &SocketIo, room: &mut Room, config: &ReaperConfig) -> Option<Reaped> {
+    let reaped = room.reap(config, SystemTime::now());
+    let game_uuid = room.get_uuid();
+
+    match reaped {
+        Some(Reaped::Abandoned) => {
+            debug!(%game_uuid, "Game abandoned");
+
+            game::notify_status(socket_io, room);
+        }
+        Some(Reaped::Expired | Reaped::Archived) => {
+            debug!(%game_uuid, "Game removed");
+
+            lobby::notify_game_removed(socket_io, &room.get_summary());
+        }
+        None => {}
+    }
+
+    reaped
+}
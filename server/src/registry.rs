@@ -0,0 +1,77 @@
+use crate::actor::GameCommand;
+use crate::summary::GameSummary;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::Instant;
+use tokio::sync::{mpsc, watch};
+use uuid::Uuid;
+
+/// How the task running a game is reached, cheap to clone
+#[derive(Clone)]
+pub struct GameHandle {
+    commands: mpsc::Sender<(Instant, GameCommand)>,
+    summary: watch::Receiver<GameSummary>,
+}
+
+impl GameHandle {
+    pub fn new(
+        commands: mpsc::Sender<(Instant, GameCommand)>,
+        summary: watch::Receiver<GameSummary>,
+    ) -> Self {
+        GameHandle { commands, summary }
+    }
+
+    /// Queues a command for the game, and gives it back if the game is gone
+    pub async fn send(&self, command: GameCommand) -> Result<(), GameCommand> {
+        self.commands
+            .send((Instant::now(), command))
+            .await
+            .map_err(|mpsc::error::SendError((_, command))| command)
+    }
+
+    /// The game as the lobby sees it, as of its last change
+    pub fn get_summary(&self) -> GameSummary {
+        self.summary.borrow().clone()
+    }
+}
+
+/// Every game still running, shared between the sockets, the API and the lobby
+/// loop so nobody has to go through the lobby to reach a game
+#[derive(Clone, Default)]
+pub struct Registry(Arc<RwLock<HashMap<Uuid, GameHandle>>>);
+
+impl Registry {
+    pub fn get(&self, game_uuid: &Uuid) -> Option<GameHandle> {
+        self.0.read().unwrap().get(game_uuid).cloned()
+    }
+
+    pub fn insert(&self, game_uuid: Uuid, handle: GameHandle) {
+        self.0.write().unwrap().insert(game_uuid, handle);
+    }
+
+    pub fn remove(&self, game_uuid: &Uuid) -> Option<GameHandle> {
+        self.0.write().unwrap().remove(game_uuid)
+    }
+
+    pub fn get_uuids(&self) -> Vec<Uuid> {
+        self.0.read().unwrap().keys().copied().collect()
+    }
+
+    pub fn get_summaries(&self) -> Vec<GameSummary> {
+        self.0
+            .read()
+            .unwrap()
+            .values()
+            .map(|x| x.get_summary())
+            .collect()
+    }
+
+    pub fn get_handles(&self) -> Vec<(Uuid, GameHandle)> {
+        self.0
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(uuid, handle)| (*uuid, handle.clone()))
+            .collect()
+    }
+}
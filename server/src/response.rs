@@ -1,11 +1,19 @@
+use crate::i18n::{Locale, Message, Notice};
 use crate::Error;
-use serde::Serialize;
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+use socketioxide::extract::{AckSender, SocketRef};
 
-#[derive(Serialize, Debug)]
-#[serde(rename_all = "camelCase")]
+/// Body of every acknowledgement and notification
+///
+/// Errors and notices are sent as text in `locale`, along with the `message`
+/// they were rendered from.
+#[derive(Debug)]
 pub struct Response<T> {
     data: Option<T>,
     error: Option<Error>,
+    notice: Option<Notice>,
+    locale: Locale,
 }
 
 impl<T: Serialize> Response<T> {
@@ -13,13 +21,134 @@ impl<T: Serialize> Response<T> {
         Response {
             data: Some(data),
             error: None,
+            notice: None,
+            locale: Locale::default(),
         }
     }
 
     pub fn from_error(error: Error) -> Self {
+        crate::metrics::record_error(&error);
+
         Response {
             data: None,
             error: Some(error),
+            notice: None,
+            locale: Locale::default(),
+        }
+    }
+
+    /// Renders the text of the response in another language
+    pub fn localized(self, locale: Locale) -> Self {
+        Response { locale, ..self }
+    }
+}
+
+impl Response<()> {
+    /// A request went well, and there is nothing more to say than that
+    pub fn from_notice(notice: Notice) -> Self {
+        Response {
+            data: None,
+            error: None,
+            notice: Some(notice),
+            locale: Locale::default(),
         }
     }
 }
+
+impl<T: Serialize> Serialize for Response<T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let message = match (&self.error, self.notice) {
+            (Some(error), _) => Some(Message::from(error)),
+            (None, Some(notice)) => Some(Message::from(notice)),
+            (None, None) => None,
+        };
+        let text = message.as_ref().map(|x| x.render(self.locale));
+
+        let mut state = serializer.serialize_struct("Response", 4)?;
+        match self.notice {
+            Some(_) => state.serialize_field("data", &text)?,
+            None => state.serialize_field("data", &self.data)?,
+        }
+        match self.error {
+            Some(_) => state.serialize_field("error", &text)?,
+            None => state.serialize_field("error", &None::<String>)?,
+        }
+        // Stable name of the error, for clients that act on it
+        state.serialize_field("code", &self.error.as_ref().map(|x| x.code()))?;
+        state.serialize_field("message", &message)?;
+        state.end()
+    }
+}
+
+/// Answers a socket request in the locale of the socket
+pub struct Ack {
+    sender: AckSender,
+    locale: Locale,
+}
+
+impl Ack {
+    pub fn new(socket_ref: &SocketRef, sender: AckSender) -> Self {
+        Ack {
+            sender,
+            locale: Locale::of(socket_ref),
+        }
+    }
+
+    /// Sends the answer, whether or not the client waits for one
+    pub fn send<T: Serialize>(self, response: Response<T>) {
+        self.sender.send(&response.localized(self.locale)).ok();
+    }
+}
+
+/// Turns a socket request down
+pub fn reject(ack_sender: Ack, error: Error) {
+    ack_sender.send(Response::<()>::from_error(error));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use scrabble_core::GameStatus;
+
+    #[test]
+    fn errors_carry_their_code() {
+        let response = Response::<()>::from_error(Error::ActionNotAllowed(GameStatus::Waiting));
+        let value = serde_json::to_value(&response).unwrap();
+
+        assert_eq!(value["code"], "action_not_allowed");
+        assert_eq!(
+            value["error"],
+            Error::ActionNotAllowed(GameStatus::Waiting).to_string()
+        );
+        assert!(value["data"].is_null());
+    }
+
+    #[test]
+    fn data_has_no_code() {
+        let value = serde_json::to_value(Response::from_data(42)).unwrap();
+
+        assert_eq!(value["data"], 42);
+        assert!(value["code"].is_null());
+        assert!(value["error"].is_null());
+        assert!(value["message"].is_null());
+    }
+
+    #[test]
+    fn text_follows_the_locale() {
+        let response = Response::<()>::from_error(Error::NotYourTurn).localized(Locale::Fr);
+        let value = serde_json::to_value(&response).unwrap();
+
+        assert_eq!(value["error"], "Ce n'est pas votre tour");
+        assert_eq!(value["message"]["key"], "not_your_turn");
+
+        let response = Response::from_notice(Notice::GameStarted).localized(Locale::Es);
+        let value = serde_json::to_value(&response).unwrap();
+
+        assert_eq!(value["data"], "Partida empezada");
+        assert_eq!(value["message"]["key"], "game_started");
+        assert!(value["code"].is_null());
+    }
+}
@@ -0,0 +1,709 @@
+use crate::invite::Invite;
+use crate::reaper::ReaperConfig;
+use crate::scrabble::Scrabble;
+use crate::settings::DisconnectPolicy;
+use crate::summary::GameSummary;
+use crate::Error;
+use scrabble_core::{GameStatus, Play, Player, Tile, Turn};
+use std::collections::HashMap;
+use std::time::SystemTime;
+use uuid::Uuid;
+
+/// What was done to a player who stayed disconnected for too long
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum DisconnectAction {
+    TurnPassed(Uuid),
+    Forfeited(Uuid),
+    Removed(Uuid),
+}
+
+/// What the reaper did to a game
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Reaped {
+    /// Everybody left a game still going, it stays around for a while
+    Abandoned,
+    /// Nobody joined the waiting room in time, it is gone
+    Expired,
+    /// The game has been over for long enough, only its summary is kept
+    Archived,
+}
+
+/// A single game and everything around it that is not Scrabble itself: who
+/// hosts it, who may join it, and what happens to the players who leave
+pub struct Room {
+    uuid: Uuid,
+    game: Scrabble,
+    /// Set once the host closed the waiting room
+    closed: bool,
+}
+
+impl Room {
+    pub fn new(uuid: Uuid, game: Scrabble) -> Self {
+        Room {
+            uuid,
+            game,
+            closed: false,
+        }
+    }
+
+    pub fn get_uuid(&self) -> &Uuid {
+        &self.uuid
+    }
+
+    pub fn get_game(&self) -> &Scrabble {
+        &self.game
+    }
+
+    pub fn into_game(self) -> Scrabble {
+        self.game
+    }
+
+    pub fn get_summary(&self) -> GameSummary {
+        GameSummary::new(&self.uuid, &self.game)
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    /// Private games require `invite_code` to match their current invite
+    pub fn register_player(
+        &mut self,
+        player: Player,
+        invite_code: Option<&str>,
+    ) -> Result<&Player, Error> {
+        self.game.check_invite(invite_code)?;
+        self.game.register_player(player)
+    }
+
+    /// Gives access to the game only if `player_uuid` is hosting it
+    fn get_hosted_game(&mut self, player_uuid: &Uuid) -> Result<&mut Scrabble, Error> {
+        if self.game.get_host().map(|x| x.get_id()) != Some(player_uuid) {
+            return Err(Error::NotHost);
+        }
+
+        Ok(&mut self.game)
+    }
+
+    /// Gives up on a game that hasn't started yet, on behalf of its host
+    pub fn close(&mut self, player_uuid: &Uuid) -> Result<(), Error> {
+        let game = self.get_hosted_game(player_uuid)?;
+
+        if game.get_status() != GameStatus::Waiting {
+            return Err(Error::ActionNotAllowed(game.get_status()));
+        }
+
+        game.abandon()?;
+        self.closed = true;
+
+        Ok(())
+    }
+
+    pub fn pause(&mut self, player_uuid: &Uuid) -> Result<(), Error> {
+        self.get_hosted_game(player_uuid)?.pause()
+    }
+
+    pub fn resume(&mut self, player_uuid: &Uuid) -> Result<(), Error> {
+        self.get_hosted_game(player_uuid)?.resume()
+    }
+
+    pub fn get_invite(&mut self, player_uuid: &Uuid) -> Result<Option<Invite>, Error> {
+        let game = self.get_hosted_game(player_uuid)?;
+
+        if !game.get_settings().private {
+            return Err(Error::GameNotPrivate);
+        }
+
+        Ok(game.get_invite().cloned())
+    }
+
+    pub fn regenerate_invite(
+        &mut self,
+        player_uuid: &Uuid,
+        expires_in: Option<u64>,
+    ) -> Result<Invite, Error> {
+        self.get_hosted_game(player_uuid)?
+            .regenerate_invite(expires_in)
+            .cloned()
+    }
+
+    pub fn revoke_invite(&mut self, player_uuid: &Uuid) -> Result<(), Error> {
+        self.get_hosted_game(player_uuid)?.revoke_invite()
+    }
+
+    pub fn remove_player(&mut self, player_uuid: &Uuid) -> Result<(), Error> {
+        self.game.remove_player(player_uuid)
+    }
+
+    pub fn get_player(&self, player_uuid: &Uuid) -> Result<&Player, Error> {
+        self.game.get_player(player_uuid)
+    }
+
+    pub fn claim_seat(
+        &self,
+        player_uuid: &Uuid,
+        account_id: Option<&Uuid>,
+        reconnect_token: Option<&str>,
+    ) -> Result<&Player, Error> {
+        self.game
+            .claim_seat(player_uuid, account_id, reconnect_token)
+    }
+
+    pub fn get_players(&self) -> Vec<Player> {
+        self.game.get_players()
+    }
+
+    pub fn player_disconnected(
+        &mut self,
+        player_uuid: &Uuid,
+        now: SystemTime,
+    ) -> Result<(), Error> {
+        self.game.mark_disconnected(player_uuid, now)
+    }
+
+    pub fn player_reconnected(&mut self, player_uuid: &Uuid) -> Result<(), Error> {
+        self.game.mark_connected(player_uuid)
+    }
+
+    /// Gives back the turn played and the player's rack once refilled
+    pub fn play_move(
+        &mut self,
+        player_uuid: &Uuid,
+        plays: &[Play],
+    ) -> Result<(Turn, Vec<Tile>), Error> {
+        let turn = self.game.play(player_uuid, plays)?.clone();
+
+        Ok((turn, self.game.get_player_tiles(player_uuid)?.clone()))
+    }
+
+    pub fn pass_turn(&mut self, player_uuid: &Uuid) -> Result<(), Error> {
+        self.game.pass(player_uuid)
+    }
+
+    pub fn start(&mut self) -> Result<HashMap<Uuid, Vec<Tile>>, Error> {
+        self.game.start()
+    }
+
+    /// Everybody starts disconnected as of `now`, for games brought back after a
+    /// restart or started by the server before anyone showed up
+    pub fn reset_presence(&mut self, now: SystemTime) {
+        if self.game.get_status().is_over() {
+            return;
+        }
+
+        for player in self.game.get_players() {
+            self.game.mark_connected(player.get_id()).ok();
+            self.game.mark_disconnected(player.get_id(), now).ok();
+        }
+    }
+
+    /// Passes the turns of players who are away past the grace period, then forfeits
+    /// or removes the ones away past the timeout, as the game settings say
+    ///
+    /// Started games are left alone once everybody is away, the reaper abandons them.
+    pub fn enforce_disconnects(&mut self, now: SystemTime) -> Vec<DisconnectAction> {
+        let mut actions = Vec::new();
+        let game = &mut self.game;
+        let away_for = |game: &Scrabble, player_uuid: &Uuid| {
+            game.get_disconnected_since(player_uuid)
+                .and_then(|x| now.duration_since(x).ok())
+        };
+
+        let status = game.get_status();
+        let someone_connected = game
+            .get_players()
+            .iter()
+            .any(|x| !game.is_forfeited(x.get_id()) && away_for(game, x.get_id()).is_none());
+
+        if status.is_over() || (status != GameStatus::Waiting && !someone_connected) {
+            return actions;
+        }
+
+        let settings = game.get_settings().clone();
+
+        for player in game.get_players() {
+            let player_uuid = player.get_id();
+
+            if game.is_forfeited(player_uuid)
+                || away_for(game, player_uuid).is_none_or(|x| x < settings.disconnect_timeout())
+            {
+                continue;
+            }
+
+            // Nobody can forfeit a game that hasn't started, and nobody leaves a
+            // rated game without losing it
+            let action = match (game.get_status(), settings.on_disconnect) {
+                (GameStatus::Waiting, _) => game
+                    .remove_player(player_uuid)
+                    .map(|_| DisconnectAction::Removed(*player_uuid)),
+                (_, DisconnectPolicy::Remove) if !settings.rated => game
+                    .remove_player(player_uuid)
+                    .map(|_| DisconnectAction::Removed(*player_uuid)),
+                _ => game
+                    .forfeit(player_uuid)
+                    .map(|_| DisconnectAction::Forfeited(*player_uuid)),
+            };
+
+            if let Ok(action) = action {
+                actions.push(action);
+            }
+        }
+
+        // Everybody gets at most one turn passed per round
+        for _ in 0..game.get_players().len() {
+            let Some(current_player) = game.get_current_player().cloned() else {
+                break;
+            };
+
+            if game.get_status() != GameStatus::InProgress
+                || away_for(game, current_player.get_id())
+                    .is_none_or(|x| x < settings.disconnect_grace())
+                || game.pass(current_player.get_id()).is_err()
+            {
+                break;
+            }
+
+            actions.push(DisconnectAction::TurnPassed(*current_player.get_id()));
+        }
+
+        actions
+    }
+
+    /// Abandons the game once nobody is connected to it anymore, and tells when
+    /// an empty waiting room or a game over for long enough should go away
+    pub fn reap(&mut self, config: &ReaperConfig, now: SystemTime) -> Option<Reaped> {
+        let elapsed = |since: SystemTime, timeout| {
+            now.duration_since(since)
+                .is_ok_and(|elapsed| elapsed >= timeout)
+        };
+        let game = &mut self.game;
+
+        if game.get_status() == GameStatus::Waiting
+            && game
+                .get_empty_since()
+                .is_some_and(|x| elapsed(x, config.empty_room_timeout))
+        {
+            Some(Reaped::Expired)
+        } else if !game.get_status().is_over()
+            && game
+                .get_everyone_disconnected_since()
+                .is_some_and(|x| elapsed(x, config.abandon_after))
+            && game.abandon().is_ok()
+        {
+            Some(Reaped::Abandoned)
+        } else if game
+            .get_ended_at()
+            .is_some_and(|x| elapsed(x, config.retention))
+        {
+            Some(Reaped::Archived)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(non_snake_case)]
+mod tests {
+    use super::*;
+    use crate::settings::GameSettings;
+    use std::time::Duration;
+
+    fn create_player() -> Player {
+        let player_id = Uuid::new_v4();
+        let player_name = format!("Player_{player_id}");
+
+        Player::new(&player_id, &player_name)
+    }
+
+    fn create_room(settings: GameSettings) -> Room {
+        Room::new(Uuid::new_v4(), Scrabble::with_settings(settings))
+    }
+
+    #[test]
+    fn close__host_only() {
+        let mut room = create_room(GameSettings::default());
+        let host = create_player();
+        let guest = create_player();
+
+        room.register_player(host.clone(), None).unwrap();
+        room.register_player(guest.clone(), None).unwrap();
+
+        let result = room.close(guest.get_id());
+        assert_eq!(result.unwrap_err(), Error::NotHost);
+        assert!(!room.is_closed());
+
+        assert!(room.close(host.get_id()).is_ok());
+        assert!(room.is_closed());
+    }
+
+    #[test]
+    fn close__already_started() {
+        let mut room = create_room(GameSettings::default());
+        let host = create_player();
+
+        room.register_player(host.clone(), None).unwrap();
+        room.register_player(create_player(), None).unwrap();
+        room.start().unwrap();
+
+        let result = room.close(host.get_id());
+        assert_eq!(
+            result.unwrap_err(),
+            Error::ActionNotAllowed(GameStatus::InProgress)
+        );
+        assert!(!room.is_closed());
+    }
+
+    #[test]
+    fn register_player__private_game() {
+        let mut room = create_room(GameSettings {
+            private: true,
+            ..GameSettings::default()
+        });
+        let code = room.get_game().get_invite().unwrap().code.clone();
+
+        let result = room.register_player(create_player(), None);
+        assert_eq!(result.unwrap_err(), Error::InvalidInviteCode);
+
+        let host = create_player();
+        let result = room.register_player(host.clone(), Some(&code));
+        assert!(result.is_ok());
+
+        let guest = create_player();
+        let result = room.get_invite(guest.get_id());
+        assert_eq!(result.unwrap_err(), Error::NotHost);
+
+        room.revoke_invite(host.get_id()).unwrap();
+        let result = room.register_player(guest.clone(), Some(&code));
+        assert_eq!(result.unwrap_err(), Error::InvalidInviteCode);
+
+        let invite = room.regenerate_invite(host.get_id(), None).unwrap();
+        assert_eq!(room.get_invite(host.get_id()), Ok(Some(invite.clone())));
+
+        let result = room.register_player(guest, Some(&invite.code));
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn get_summary__host_is_first_player() {
+        let mut room = create_room(GameSettings::default());
+        let player_1 = create_player();
+        let player_2 = create_player();
+
+        assert_eq!(room.get_summary().host, None);
+
+        room.register_player(player_1.clone(), None).unwrap();
+        room.register_player(player_2.clone(), None).unwrap();
+
+        let summary = room.get_summary();
+        assert_eq!(summary.host, Some(player_1.get_name().to_string()));
+        assert_eq!(summary.players, 2);
+
+        room.remove_player(player_1.get_id()).unwrap();
+
+        let summary = room.get_summary();
+        assert_eq!(summary.host, Some(player_2.get_name().to_string()));
+    }
+
+    #[test]
+    fn register_player__good_id() {
+        let mut room = create_room(GameSettings::default());
+        let player = create_player();
+
+        let result = room.register_player(player.clone(), None);
+        assert!(result.is_ok());
+        assert_eq!(player, result.unwrap().clone());
+        assert_eq!(room.get_players(), vec![player.clone()]);
+        assert_eq!(room.get_player(player.get_id()), Ok(&player));
+    }
+
+    #[test]
+    fn remove_player__good_id() {
+        let mut room = create_room(GameSettings::default());
+        let player = create_player();
+
+        room.register_player(player.clone(), None).unwrap();
+        assert!(room.remove_player(player.get_id()).is_ok());
+        assert_eq!(room.get_players(), vec![]);
+        assert_eq!(
+            room.get_player(player.get_id()).unwrap_err(),
+            Error::PlayerNotRegistered
+        );
+    }
+
+    #[test]
+    fn remove_player__bad_id() {
+        let mut room = create_room(GameSettings::default());
+        let player = create_player();
+
+        room.register_player(player.clone(), None).unwrap();
+        let result = room.remove_player(&Uuid::new_v4());
+        assert_eq!(result.unwrap_err(), Error::PlayerNotRegistered);
+        assert_eq!(room.get_players(), vec![player]);
+    }
+
+    #[test]
+    fn start__good_id() {
+        let mut room = create_room(GameSettings::default());
+        let player_1 = create_player();
+        let player_2 = create_player();
+
+        room.register_player(player_1.clone(), None).unwrap();
+        room.register_player(player_2.clone(), None).unwrap();
+
+        let map = room.start().unwrap();
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get(player_1.get_id()).unwrap().len(), 7);
+        assert_eq!(map.get(player_2.get_id()).unwrap().len(), 7);
+    }
+
+    #[test]
+    fn start__not_enough_players() {
+        let mut room = create_room(GameSettings::default());
+        assert_eq!(room.start().unwrap_err(), Error::NotEnoughPlayers);
+
+        room.register_player(create_player(), None).unwrap();
+        assert_eq!(room.start().unwrap_err(), Error::NotEnoughPlayers);
+    }
+
+    #[test]
+    fn start__twice() {
+        let mut room = create_room(GameSettings::default());
+
+        room.register_player(create_player(), None).unwrap();
+        room.register_player(create_player(), None).unwrap();
+
+        assert!(room.start().is_ok());
+        assert_eq!(
+            room.start().unwrap_err(),
+            Error::ActionNotAllowed(GameStatus::InProgress)
+        );
+
+        let result = room.register_player(create_player(), None);
+        assert_eq!(
+            result.unwrap_err(),
+            Error::ActionNotAllowed(GameStatus::InProgress)
+        );
+    }
+
+    #[test]
+    fn pause__host_only() {
+        let mut room = create_room(GameSettings::default());
+        let host = create_player();
+        let guest = create_player();
+
+        room.register_player(host.clone(), None).unwrap();
+        room.register_player(guest.clone(), None).unwrap();
+
+        let result = room.pause(host.get_id());
+        assert_eq!(
+            result.unwrap_err(),
+            Error::ActionNotAllowed(GameStatus::Waiting)
+        );
+
+        room.start().unwrap();
+
+        assert_eq!(room.pause(guest.get_id()).unwrap_err(), Error::NotHost);
+
+        assert!(room.pause(host.get_id()).is_ok());
+        assert_eq!(room.get_game().get_status(), GameStatus::Paused);
+
+        assert!(room.resume(host.get_id()).is_ok());
+        assert_eq!(room.get_game().get_status(), GameStatus::InProgress);
+    }
+
+    #[test]
+    fn remove_player__mid_game() {
+        let mut room = create_room(GameSettings::default());
+        let player_1 = create_player();
+        let player_2 = create_player();
+
+        room.register_player(player_1.clone(), None).unwrap();
+        room.register_player(player_2.clone(), None).unwrap();
+        room.start().unwrap();
+
+        assert!(room.remove_player(player_1.get_id()).is_ok());
+        assert_eq!(
+            room.get_player(player_1.get_id()).unwrap_err(),
+            Error::PlayerNotRegistered
+        );
+        assert_eq!(room.get_game().get_status(), GameStatus::Finished);
+    }
+
+    #[test]
+    fn player_disconnected__not_registered() {
+        let mut room = create_room(GameSettings::default());
+
+        let result = room.player_disconnected(&Uuid::new_v4(), SystemTime::now());
+        assert_eq!(result.unwrap_err(), Error::PlayerNotRegistered);
+    }
+
+    fn reaper_config() -> ReaperConfig {
+        ReaperConfig {
+            interval: Duration::from_secs(1),
+            empty_room_timeout: Duration::from_secs(60),
+            abandon_after: Duration::from_secs(60),
+            retention: Duration::from_secs(60),
+        }
+    }
+
+    #[test]
+    fn reap__expires_empty_waiting_rooms() {
+        let mut empty_room = create_room(GameSettings::default());
+        let mut busy_room = create_room(GameSettings::default());
+        busy_room.register_player(create_player(), None).unwrap();
+
+        assert_eq!(empty_room.reap(&reaper_config(), SystemTime::now()), None);
+
+        let later = SystemTime::now() + Duration::from_secs(61);
+        assert_eq!(
+            empty_room.reap(&reaper_config(), later),
+            Some(Reaped::Expired)
+        );
+        assert_eq!(busy_room.reap(&reaper_config(), later), None);
+    }
+
+    #[test]
+    fn reap__abandons_then_archives_disconnected_games() {
+        let mut room = create_room(GameSettings::default());
+        let player_1 = create_player();
+        let player_2 = create_player();
+
+        room.register_player(player_1.clone(), None).unwrap();
+        room.register_player(player_2.clone(), None).unwrap();
+        room.start().unwrap();
+
+        let now = SystemTime::now();
+        room.player_disconnected(player_1.get_id(), now).unwrap();
+        room.player_disconnected(player_2.get_id(), now).unwrap();
+        room.player_reconnected(player_2.get_id()).unwrap();
+
+        let later = now + Duration::from_secs(61);
+        assert_eq!(room.reap(&reaper_config(), later), None);
+
+        room.player_disconnected(player_2.get_id(), now).unwrap();
+
+        assert_eq!(room.reap(&reaper_config(), later), Some(Reaped::Abandoned));
+        assert_eq!(room.get_game().get_status(), GameStatus::Abandoned);
+
+        // Abandoned games are kept for a while before being archived
+        assert_eq!(room.reap(&reaper_config(), SystemTime::now()), None);
+
+        let much_later = SystemTime::now() + Duration::from_secs(61);
+        assert_eq!(
+            room.reap(&reaper_config(), much_later),
+            Some(Reaped::Archived)
+        );
+    }
+
+    fn create_started_room(settings: GameSettings) -> (Room, Vec<Player>) {
+        let mut room = create_room(settings);
+        let players = vec![create_player(), create_player(), create_player()];
+
+        for player in &players {
+            room.register_player(player.clone(), None).unwrap();
+        }
+        room.start().unwrap();
+
+        (room, players)
+    }
+
+    #[test]
+    fn enforce_disconnects__passes_then_forfeits() {
+        let (mut room, players) = create_started_room(GameSettings {
+            disconnect_grace: 10,
+            disconnect_timeout: 60,
+            ..GameSettings::default()
+        });
+
+        let now = SystemTime::now();
+        room.player_disconnected(players[0].get_id(), now).unwrap();
+
+        // Still within the grace period
+        let actions = room.enforce_disconnects(now + Duration::from_secs(5));
+        assert!(actions.is_empty());
+
+        let actions = room.enforce_disconnects(now + Duration::from_secs(11));
+        assert_eq!(
+            actions,
+            vec![DisconnectAction::TurnPassed(*players[0].get_id())]
+        );
+
+        let actions = room.enforce_disconnects(now + Duration::from_secs(61));
+        assert_eq!(
+            actions,
+            vec![DisconnectAction::Forfeited(*players[0].get_id())]
+        );
+
+        let game = room.get_game();
+        assert!(game.is_forfeited(players[0].get_id()));
+        assert_eq!(game.get_status(), GameStatus::InProgress);
+    }
+
+    #[test]
+    fn enforce_disconnects__removes_when_configured() {
+        let (mut room, players) = create_started_room(GameSettings {
+            disconnect_grace: 10,
+            disconnect_timeout: 60,
+            on_disconnect: DisconnectPolicy::Remove,
+            ..GameSettings::default()
+        });
+
+        let now = SystemTime::now();
+        room.player_disconnected(players[1].get_id(), now).unwrap();
+
+        let actions = room.enforce_disconnects(now + Duration::from_secs(61));
+        assert_eq!(
+            actions,
+            vec![DisconnectAction::Removed(*players[1].get_id())]
+        );
+        assert_eq!(room.get_players().len(), 2);
+        assert_eq!(
+            room.get_player(players[1].get_id()).unwrap_err(),
+            Error::PlayerNotRegistered
+        );
+    }
+
+    #[test]
+    fn enforce_disconnects__waits_when_everyone_is_away() {
+        let (mut room, players) = create_started_room(GameSettings::default());
+
+        let now = SystemTime::now();
+        for player in &players {
+            room.player_disconnected(player.get_id(), now).unwrap();
+        }
+
+        let actions = room.enforce_disconnects(now + Duration::from_secs(3600));
+        assert!(actions.is_empty());
+    }
+
+    #[test]
+    fn enforce_disconnects__reconnection_keeps_the_seat() {
+        let (mut room, players) = create_started_room(GameSettings::default());
+
+        let now = SystemTime::now();
+        room.player_disconnected(players[0].get_id(), now).unwrap();
+        room.player_reconnected(players[0].get_id()).unwrap();
+
+        let actions = room.enforce_disconnects(now + Duration::from_secs(3600));
+        assert!(actions.is_empty());
+    }
+
+    #[test]
+    fn reset_presence__fresh_grace_period() {
+        let (mut room, players) = create_started_room(GameSettings::default());
+        let before = SystemTime::now() - Duration::from_secs(3600);
+        room.player_disconnected(players[0].get_id(), before)
+            .unwrap();
+
+        let now = SystemTime::now();
+        room.reset_presence(now);
+
+        for player in &players {
+            assert_eq!(
+                room.get_game().get_disconnected_since(player.get_id()),
+                Some(now)
+            );
+        }
+    }
+}
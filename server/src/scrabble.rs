@@ -1,197 +1,370 @@
-use crate::player::Player;
-use crate::{Error, Tile};
-use rand::prelude::SliceRandom;
+use crate::accounts;
+use crate::config;
+use crate::invite::Invite;
+use crate::settings::{client_duration, GameSettings};
+use crate::Error;
+use scrabble_core::{BagRecord, Board, FinalScore, Game, GameStatus, Play, Player, Tile, Turn};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::time::SystemTime;
 use uuid::Uuid;
 
-const TILE_BAG: [(Tile, usize); 26] = [
-    (Tile('A', 1), 9),
-    (Tile('B', 3), 2),
-    (Tile('C', 3), 2),
-    (Tile('D', 2), 4),
-    (Tile('E', 1), 12),
-    (Tile('F', 4), 2),
-    (Tile('G', 2), 3),
-    (Tile('H', 4), 2),
-    (Tile('I', 1), 9),
-    (Tile('J', 8), 1),
-    (Tile('K', 5), 1),
-    (Tile('L', 1), 4),
-    (Tile('M', 3), 2),
-    (Tile('N', 1), 6),
-    (Tile('O', 1), 8),
-    (Tile('P', 3), 2),
-    (Tile('Q', 10), 1),
-    (Tile('R', 1), 6),
-    (Tile('S', 1), 4),
-    (Tile('T', 1), 6),
-    (Tile('U', 1), 4),
-    (Tile('V', 4), 2),
-    (Tile('W', 4), 2),
-    (Tile('X', 8), 1),
-    (Tile('Y', 4), 2),
-    (Tile('Z', 10), 1),
-];
-
-const BOARD_SIZE: usize = 15;
-
+/// A game as the server hosts it: the rules are those of `scrabble_core::Game`,
+/// this adds the settings it was created with, its invite, and who is connected
+///
+/// Serialized only to survive a restart.
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Scrabble {
-    board: [[char; BOARD_SIZE]; BOARD_SIZE],
-    tile_bag: Vec<Tile>,
-    racks: HashMap<Uuid, Vec<Tile>>,
-    players: Vec<Player>,
-    current_player_index: usize,
+    /// Flattened, snapshots read the same as when the rules were part of the server
+    #[serde(flatten)]
+    game: Game,
+    settings: GameSettings,
+    created_at: SystemTime,
+    /// Only private games have one, `None` once revoked
+    invite: Option<Invite>,
+    /// Since when nobody is registered, `None` while someone is
+    empty_since: Option<SystemTime>,
+    /// When the game became `Finished` or `Abandoned`
+    ended_at: Option<SystemTime>,
+    /// Players whose socket went away, and since when
+    disconnected: HashMap<Uuid, SystemTime>,
+    /// What guests show to get their seat back, players with an account show
+    /// their session instead
+    #[serde(default)]
+    reconnect_tokens: HashMap<Uuid, String>,
 }
 
 impl Scrabble {
+    /// A public game with the default settings, as the tests set them up
+    #[cfg(test)]
     pub fn new() -> Self {
-        let mut game = Scrabble {
-            board: [[' ' as char; BOARD_SIZE]; BOARD_SIZE],
-            tile_bag: Vec::new(),
-            racks: HashMap::new(),
-            players: Vec::new(),
-            current_player_index: 0,
+        Self::with_settings(GameSettings::default())
+    }
+
+    pub fn with_settings(settings: GameSettings) -> Self {
+        let invite = match settings.private {
+            true => Some(Invite::generate(settings.invite_time_to_live())),
+            false => None,
         };
 
-        game.init_tile_bag();
-        game
+        let created_at = SystemTime::now();
+
+        Scrabble {
+            game: settings.seed.map_or_else(Game::new, Game::with_seed),
+            settings,
+            created_at,
+            invite,
+            empty_since: Some(created_at),
+            ended_at: None,
+            disconnected: HashMap::new(),
+            reconnect_tokens: HashMap::new(),
+        }
     }
 
-    fn init_tile_bag(&mut self) {
-        for &(tile, amount) in &TILE_BAG {
-            for _ in 0..amount {
-                self.tile_bag.push(tile);
-            }
+    /// Notes when the game ended, whichever action ended it
+    fn record_end(&mut self) {
+        if self.ended_at.is_none() && self.game.get_status().is_over() {
+            self.ended_at = Some(SystemTime::now());
         }
+    }
 
-        let mut rng = rand::thread_rng();
-        self.tile_bag.shuffle(&mut rng);
+    fn require_not_over(&self) -> Result<(), Error> {
+        match self.game.get_status() {
+            status if status.is_over() => Err(Error::ActionNotAllowed(status)),
+            _ => Ok(()),
+        }
     }
 
+    /// No more players than the game settings allow, and only players with an
+    /// account in rated games
     pub fn register_player(&mut self, player: Player) -> Result<&Player, Error> {
-        // No more than 4 players
-        if self.players.len() >= 4 {
-            return Err(Error::TooManyPlayer);
-        } else if self.players.iter().any(|x| x.get_id() == player.get_id()) {
-            return Err(Error::DuplicatePlayerId);
+        if self.game.get_status() == GameStatus::Waiting {
+            if self.game.get_players().len() >= self.settings.max_players {
+                return Err(Error::TooManyPlayer);
+            } else if self.settings.rated && !player.is_registered() {
+                return Err(Error::AccountRequired);
+            }
+        }
+
+        let player = self.game.register_player(player)?;
+        self.empty_since = None;
+
+        if !player.is_registered() {
+            self.reconnect_tokens
+                .insert(*player.get_id(), accounts::random_token());
         }
 
-        self.racks.insert(*player.get_id(), Vec::new());
-        self.players.push(player);
+        Ok(player)
+    }
+
+    /// Given to a guest along with their seat, see `claim_seat`
+    pub fn get_reconnect_token(&self, player_uuid: &Uuid) -> Option<&str> {
+        self.reconnect_tokens.get(player_uuid).map(String::as_str)
+    }
+
+    /// The seat of `player_uuid`, as long as whoever asks proves it is theirs:
+    /// with the session of its account, or with its reconnect token for guests
+    pub fn claim_seat(
+        &self,
+        player_uuid: &Uuid,
+        account_id: Option<&Uuid>,
+        reconnect_token: Option<&str>,
+    ) -> Result<&Player, Error> {
+        let player = self.get_player(player_uuid)?;
+
+        let owned = match player.is_registered() {
+            true => account_id == Some(player_uuid),
+            false => {
+                reconnect_token.is_some()
+                    && reconnect_token == self.get_reconnect_token(player_uuid)
+            }
+        };
 
-        Ok(self.players.last().unwrap())
+        match owned {
+            true => Ok(player),
+            false => Err(Error::NotYourSeat),
+        }
     }
 
+    /// Leaving a rated game that started is a forfeit, so nobody can dodge a loss
     pub fn remove_player(&mut self, player_uuid: &Uuid) -> Result<(), Error> {
-        if self.racks.contains_key(player_uuid) {
-            self.players.retain(|x| x.get_id() != player_uuid);
-            self.racks.remove(player_uuid);
+        if self.settings.rated && self.game.get_status() != GameStatus::Waiting {
+            return self.forfeit(player_uuid);
+        }
 
-            Ok(())
-        } else {
-            Err(Error::PlayerNotRegistered)
+        self.game.remove_player(player_uuid)?;
+        self.disconnected.remove(player_uuid);
+        self.reconnect_tokens.remove(player_uuid);
+
+        if self.game.get_players().is_empty() {
+            self.empty_since = Some(SystemTime::now());
         }
+
+        self.record_end();
+        Ok(())
+    }
+
+    pub fn forfeit(&mut self, player_uuid: &Uuid) -> Result<(), Error> {
+        self.game.forfeit(player_uuid)?;
+        self.record_end();
+        Ok(())
     }
 
-    fn is_player_registered(&self, player: &Player) -> bool {
-        self.players.contains(player)
+    pub fn is_forfeited(&self, player_uuid: &Uuid) -> bool {
+        self.game.is_forfeited(player_uuid)
     }
 
-    fn are_there_tiles_remaining(&self) -> bool {
-        self.tile_bag.is_empty()
+    pub fn pass(&mut self, player_uuid: &Uuid) -> Result<(), Error> {
+        self.game.pass(player_uuid)?;
+        self.record_end();
+        Ok(())
     }
 
-    fn get_player_ids(&self) -> Vec<Uuid> {
-        self.players.iter().map(|x| x.get_id().clone()).collect()
+    pub fn play(&mut self, player_uuid: &Uuid, plays: &[Play]) -> Result<&Turn, Error> {
+        let lexicon = config::lexicons().get(&self.settings.variant);
+        self.game.play_checked(player_uuid, plays, lexicon)?;
+        self.record_end();
+
+        Ok(self.game.get_turns().last().unwrap())
     }
 
     pub fn get_players(&self) -> Vec<Player> {
-        self.players.clone()
+        self.game.get_players().to_vec()
     }
 
-    fn give_tile(&mut self, player_id: &Uuid) -> Result<(), Error> {
-        let tile = self.tile_bag.pop().ok_or(Error::NoMoreTiles)?;
+    pub fn get_host(&self) -> Option<&Player> {
+        self.game.get_host()
+    }
 
-        match self.racks.get_mut(player_id) {
-            Some(rack) => {
-                if rack.len() == 7 {
-                    return Err(Error::PlayerHas7Tiles);
-                }
+    pub fn get_settings(&self) -> &GameSettings {
+        &self.settings
+    }
 
-                rack.push(tile);
-                Ok(())
-            }
-            None => {
-                self.tile_bag.push(tile);
-                Err(Error::PlayerNotRegistered)
-            }
-        }
+    pub fn get_status(&self) -> GameStatus {
+        self.game.get_status()
     }
 
-    pub fn get_player(&self, player_id: &Uuid) -> Result<&Player, Error> {
-        self.players
+    pub fn get_created_at(&self) -> SystemTime {
+        self.created_at
+    }
+
+    pub fn get_current_player(&self) -> Option<&Player> {
+        self.game.get_current_player()
+    }
+
+    pub fn get_empty_since(&self) -> Option<SystemTime> {
+        self.empty_since
+    }
+
+    pub fn get_ended_at(&self) -> Option<SystemTime> {
+        self.ended_at
+    }
+
+    pub fn mark_disconnected(&mut self, player_uuid: &Uuid, now: SystemTime) -> Result<(), Error> {
+        self.game.get_player(player_uuid)?;
+        self.disconnected.entry(*player_uuid).or_insert(now);
+
+        Ok(())
+    }
+
+    pub fn get_disconnected_since(&self, player_uuid: &Uuid) -> Option<SystemTime> {
+        self.disconnected.get(player_uuid).copied()
+    }
+
+    pub fn mark_connected(&mut self, player_uuid: &Uuid) -> Result<(), Error> {
+        self.game.get_player(player_uuid)?;
+        self.disconnected.remove(player_uuid);
+
+        Ok(())
+    }
+
+    /// When the last connected player went away, `None` if anyone is still there
+    pub fn get_everyone_disconnected_since(&self) -> Option<SystemTime> {
+        let players = self.game.get_players();
+        if players.is_empty() {
+            return None;
+        }
+
+        players
             .iter()
-            .find(|x| x.get_id() == player_id)
-            .ok_or(Error::PlayerNotRegistered)
+            .map(|x| self.disconnected.get(x.get_id()).copied())
+            .collect::<Option<Vec<_>>>()?
+            .into_iter()
+            .max()
+    }
+
+    pub fn get_final_scores(&self) -> Option<Vec<FinalScore>> {
+        self.game.get_final_scores()
     }
 
-    fn get_player_tiles(&self, player_uuid: &Uuid) -> Result<&Vec<Tile>, Error> {
-        match self.racks.get(player_uuid) {
-            Some(rack) => Ok(rack),
-            None => Err(Error::PlayerNotRegistered),
+    /// Published once the game starts, nobody sees the seed itself before it's over
+    pub fn get_seed_commitment(&self) -> Option<String> {
+        match self.game.get_status() {
+            GameStatus::Waiting | GameStatus::Starting => None,
+            _ => Some(self.game.get_seed_commitment()),
         }
     }
 
-    pub fn start(&mut self) -> Result<HashMap<Uuid, Vec<Tile>>, Error> {
-        if self.players.len() < 2 {
-            return Err(Error::NotEnoughPlayers);
-        } else if self.players.len() > 4 {
-            return Err(Error::TooManyPlayer);
+    /// The seed behind the commitment and its salt, once the game is over
+    pub fn get_revealed_seed(&self) -> Option<(u64, &str)> {
+        self.game
+            .get_status()
+            .is_over()
+            .then(|| (self.game.get_seed(), self.game.get_seed_salt()))
+    }
+
+    pub fn get_bag_record(&self) -> Option<BagRecord> {
+        self.game.get_bag_record()
+    }
+
+    pub fn get_invite(&self) -> Option<&Invite> {
+        self.invite.as_ref()
+    }
+
+    /// Public games let anyone in, private ones need their current invite code
+    pub fn check_invite(&self, code: Option<&str>) -> Result<(), Error> {
+        if !self.settings.private {
+            return Ok(());
         }
 
-        let player_ids = self.get_player_ids();
+        match &self.invite {
+            Some(invite) => invite.check(code, SystemTime::now()),
+            None => Err(Error::InvalidInviteCode),
+        }
+    }
 
-        for player_id in player_ids {
-            for _ in 0..7 {
-                self.give_tile(&player_id)?
-            }
+    /// Replaces the invite code, the previous one can't be used anymore
+    ///
+    /// The new code expires after `expires_in` seconds, or never.
+    pub fn regenerate_invite(&mut self, expires_in: Option<u64>) -> Result<&Invite, Error> {
+        self.require_not_over()?;
+
+        if !self.settings.private {
+            return Err(Error::GameNotPrivate);
         }
 
-        Ok(self.racks.clone())
+        let time_to_live = match expires_in {
+            Some(seconds) => Some(client_duration(seconds).ok_or(Error::InvalidRequest)?),
+            None => None,
+        };
+
+        Ok(self.invite.insert(Invite::generate(time_to_live)))
     }
 
-    pub fn display_board(&self) {
-        println!("Initial board:");
-        for row in self.board {
-            print!("|");
-            for cell in row {
-                print!("{} ", cell);
-            }
-            println!("|");
+    /// Nobody can join the game until a new code is generated
+    pub fn revoke_invite(&mut self) -> Result<(), Error> {
+        self.require_not_over()?;
+
+        if !self.settings.private {
+            return Err(Error::GameNotPrivate);
         }
+
+        self.invite = None;
+        Ok(())
     }
 
-    pub fn next_turn(&mut self) -> usize {
-        self.current_player_index = (self.current_player_index + 1) % self.players.len();
+    pub fn get_player(&self, player_id: &Uuid) -> Result<&Player, Error> {
+        Ok(self.game.get_player(player_id)?)
+    }
 
-        self.current_player_index
+    pub fn get_player_tiles(&self, player_uuid: &Uuid) -> Result<&Vec<Tile>, Error> {
+        Ok(self.game.get_player_tiles(player_uuid)?)
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::{Player, Scrabble};
-    use uuid::Uuid;
+    pub fn start(&mut self) -> Result<HashMap<Uuid, Vec<Tile>>, Error> {
+        let status = self.game.get_status();
+        if status == GameStatus::Waiting
+            && self.game.get_players().len() > self.settings.max_players
+        {
+            return Err(Error::TooManyPlayer);
+        }
 
-    // === Game.new()
+        Ok(self.game.start()?)
+    }
 
-    #[test]
-    fn test_new_game_tile_bag_initialized() {
-        let game = Scrabble::new();
-        assert_eq!(game.tile_bag.len(), 98); // Official rules
+    pub fn pause(&mut self) -> Result<(), Error> {
+        Ok(self.game.pause()?)
+    }
+
+    pub fn resume(&mut self) -> Result<(), Error> {
+        Ok(self.game.resume()?)
+    }
+
+    pub fn abandon(&mut self) -> Result<(), Error> {
+        self.game.abandon()?;
+        self.record_end();
+        Ok(())
+    }
+
+    #[cfg(test)]
+    pub fn set_rack(&mut self, player_uuid: &Uuid, rack: Vec<Tile>) {
+        self.game.set_rack(player_uuid, rack);
     }
 
-    // ===
+    pub fn get_scores(&self) -> &HashMap<Uuid, i32> {
+        self.game.get_scores()
+    }
+
+    pub fn get_board(&self) -> &Board {
+        self.game.get_board()
+    }
+
+    pub fn get_tiles_left(&self) -> usize {
+        self.game.get_tiles_left()
+    }
+
+    pub fn get_turns(&self) -> &[Turn] {
+        self.game.get_turns()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{GameStatus, Player, Scrabble};
+    use crate::settings::GameSettings;
+    use crate::Error;
+    use std::time::{Duration, SystemTime};
+    use uuid::Uuid;
 
     #[test]
     fn game_cannot_have_more_than_4_players() {
@@ -207,120 +380,278 @@ mod tests {
     }
 
     #[test]
-    fn game_cannot_have_multiple_players_with_same_id() {
-        let mut game = Scrabble::new();
+    fn game_respects_max_players_setting() {
+        let mut game = Scrabble::with_settings(GameSettings {
+            max_players: 2,
+            ..GameSettings::default()
+        });
 
-        let uuid = Uuid::new_v4();
-        assert!(game.register_player(Player::new(&uuid, "Player")).is_ok());
+        for _ in 0..2 {
+            let uuid = Uuid::new_v4();
+            assert!(game.register_player(Player::new(&uuid, "Player")).is_ok());
+        }
 
         let uuid = Uuid::new_v4();
-        assert!(game.register_player(Player::new(&uuid, "Player")).is_ok());
         assert!(game.register_player(Player::new(&uuid, "Player")).is_err());
     }
 
     #[test]
-    fn game_cannot_start_without_at_least_2_players() {
-        {
-            let mut game = Scrabble::new();
+    fn game_is_shuffled_from_the_settings_seed() {
+        let uuids = [Uuid::new_v4(), Uuid::new_v4()];
+        let mut games = [1, 2].map(|_| {
+            Scrabble::with_settings(GameSettings {
+                seed: Some(42),
+                ..GameSettings::default()
+            })
+        });
+
+        for game in &mut games {
+            for uuid in &uuids {
+                game.register_player(Player::new(uuid, "Player")).unwrap();
+            }
+            game.start().unwrap();
+        }
 
-            // Zero players
-            assert!(game.start().is_err());
+        assert_eq!(games[0].game.get_seed(), 42);
+        for uuid in &uuids {
+            assert_eq!(
+                games[0].get_player_tiles(uuid).unwrap(),
+                games[1].get_player_tiles(uuid).unwrap()
+            );
         }
+    }
 
-        {
-            let mut game = Scrabble::new();
+    #[test]
+    fn private_game_requires_invite_code() {
+        let mut game = Scrabble::with_settings(GameSettings {
+            private: true,
+            ..GameSettings::default()
+        });
+
+        let code = game.get_invite().unwrap().code.clone();
+
+        assert_eq!(game.check_invite(None), Err(Error::InvalidInviteCode));
+        assert_eq!(
+            game.check_invite(Some("WRONG")),
+            Err(Error::InvalidInviteCode)
+        );
+        assert!(game.check_invite(Some(&code)).is_ok());
+
+        let new_code = game.regenerate_invite(None).unwrap().code.clone();
+        assert!(game.check_invite(Some(&new_code)).is_ok());
+
+        game.revoke_invite().unwrap();
+        assert_eq!(
+            game.check_invite(Some(&new_code)),
+            Err(Error::InvalidInviteCode)
+        );
+    }
 
-            // One player
-            let uuid = Uuid::new_v4();
-            game.register_player(Player::new(&uuid, "Player")).unwrap();
-            assert!(game.start().is_err());
-        }
+    #[test]
+    fn invite_lifetime_is_bounded() {
+        let mut game = Scrabble::with_settings(GameSettings {
+            private: true,
+            ..GameSettings::default()
+        });
+
+        assert_eq!(
+            game.regenerate_invite(Some(u64::MAX)).unwrap_err(),
+            Error::InvalidRequest
+        );
+        assert!(game
+            .regenerate_invite(Some(60))
+            .unwrap()
+            .expires_at
+            .is_some());
+    }
 
-        // >= 2 players
-        for n_players in 2..4 {
-            let mut game = Scrabble::new();
+    #[test]
+    fn public_game_has_no_invite() {
+        let mut game = Scrabble::new();
 
-            let uuid = Uuid::new_v4();
-            game.register_player(Player::new(&uuid, "Player")).unwrap();
+        assert!(game.get_invite().is_none());
+        assert!(game.check_invite(None).is_ok());
+        assert_eq!(game.revoke_invite(), Err(Error::GameNotPrivate));
+        assert_eq!(
+            game.regenerate_invite(None).unwrap_err(),
+            Error::GameNotPrivate
+        );
+    }
 
-            for _ in 1..n_players {
-                let uuid = Uuid::new_v4();
-                game.register_player(Player::new(&uuid, "Player")).unwrap();
-            }
+    fn started_game(n_players: usize) -> (Scrabble, Vec<Uuid>) {
+        let mut game = Scrabble::new();
+        let mut uuids = Vec::new();
 
-            assert!(game.start().is_ok());
+        for i in 0..n_players {
+            let uuid = Uuid::new_v4();
+            game.register_player(Player::new(&uuid, &format!("Player{i}")))
+                .unwrap();
+            uuids.push(uuid);
         }
+
+        game.start().unwrap();
+        (game, uuids)
     }
 
     #[test]
-    fn game_can_give_a_registered_player() {
-        let mut game = Scrabble::new();
+    fn disconnections_are_tracked_per_player() {
+        let (mut game, uuids) = started_game(2);
+        let now = SystemTime::now();
+        let later = now + Duration::from_secs(30);
 
-        let uuid_0 = Uuid::new_v4();
-        let uuid_1 = Uuid::new_v4();
+        game.mark_disconnected(&uuids[0], now).unwrap();
+        assert_eq!(game.get_everyone_disconnected_since(), None);
 
-        assert!(game
-            .register_player(Player::new(&uuid_0, "Player0"))
-            .is_ok());
-        assert!(game
-            .register_player(Player::new(&uuid_1, "Player1"))
-            .is_ok());
+        game.mark_disconnected(&uuids[1], later).unwrap();
+        assert_eq!(game.get_everyone_disconnected_since(), Some(later));
 
-        assert!(game.get_player(&uuid_0).is_ok());
-        assert_eq!(*game.get_player(&uuid_0).unwrap().get_id(), uuid_0);
-        assert_eq!(game.get_player(&uuid_0).unwrap().get_name(), "Player0");
+        game.mark_connected(&uuids[1]).unwrap();
+        assert_eq!(game.get_everyone_disconnected_since(), None);
 
-        assert!(game.get_player(&uuid_1).is_ok());
-        assert_eq!(*game.get_player(&uuid_1).unwrap().get_id(), uuid_1);
-        assert_eq!(game.get_player(&uuid_1).unwrap().get_name(), "Player1");
+        let unknown = Uuid::new_v4();
+        assert_eq!(
+            game.mark_disconnected(&unknown, now),
+            Err(Error::PlayerNotRegistered)
+        );
     }
 
     #[test]
-    fn game_cannot_give_player_not_registered() {
+    fn empty_since_follows_registrations() {
         let mut game = Scrabble::new();
+        assert!(game.get_empty_since().is_some());
 
         let uuid = Uuid::new_v4();
-        assert!(game.register_player(Player::new(&uuid, "Player0")).is_ok());
+        game.register_player(Player::new(&uuid, "Player")).unwrap();
+        assert!(game.get_empty_since().is_none());
 
-        let uuid = Uuid::new_v4();
-        assert!(game.register_player(Player::new(&uuid, "Player1")).is_ok());
+        game.remove_player(&uuid).unwrap();
+        assert!(game.get_empty_since().is_some());
+    }
 
-        let uuid = Uuid::new_v4();
-        assert!(game.get_player(&uuid).is_err()); // Unknown player ID
+    #[test]
+    fn seed_is_committed_at_start_and_revealed_at_the_end() {
+        let mut game = Scrabble::with_settings(GameSettings {
+            seed: Some(42),
+            ..GameSettings::default()
+        });
+        for _ in 0..2 {
+            game.register_player(Player::new(&Uuid::new_v4(), "Player"))
+                .unwrap();
+        }
+        assert_eq!(game.get_seed_commitment(), None);
+
+        game.start().unwrap();
+        let commitment = game.get_seed_commitment().unwrap();
+        let salt = game.game.get_seed_salt().to_string();
+        assert_eq!(commitment, scrabble_core::commit_seed(42, &salt));
+        assert_eq!(game.get_revealed_seed(), None);
+        assert!(game.get_bag_record().is_none());
+
+        game.abandon().unwrap();
+        assert_eq!(game.get_revealed_seed(), Some((42, salt.as_str())));
+        let record = game.get_bag_record().unwrap();
+        assert_eq!(record.commitment, commitment);
+        assert_eq!(record.verify().unwrap().racks.len(), 2);
     }
 
     #[test]
-    fn game_must_give_7_tiles_on_start() {
-        let mut game = Scrabble::new();
+    fn abandoned_game_cannot_be_resumed() {
+        let (mut game, _) = started_game(2);
+
+        game.pause().unwrap();
+        game.abandon().unwrap();
+
+        assert_eq!(game.get_status(), GameStatus::Abandoned);
+        assert!(game.get_ended_at().is_some());
+        assert_eq!(
+            game.resume().unwrap_err(),
+            Error::ActionNotAllowed(GameStatus::Abandoned)
+        );
+        assert_eq!(
+            game.abandon().unwrap_err(),
+            Error::ActionNotAllowed(GameStatus::Abandoned)
+        );
+    }
 
-        let uuid_0 = Uuid::new_v4();
-        let uuid_1 = Uuid::new_v4();
+    fn rated_game(n_players: usize) -> (Scrabble, Vec<Uuid>) {
+        let mut game = Scrabble::with_settings(GameSettings {
+            rated: true,
+            ..GameSettings::default()
+        });
+        let mut uuids = Vec::new();
 
-        game.register_player(Player::new(&uuid_0, "Player0"))
-            .unwrap();
-        game.register_player(Player::new(&uuid_1, "Player1"))
-            .unwrap();
+        for i in 0..n_players {
+            let uuid = Uuid::new_v4();
+            game.register_player(Player::with_account(&uuid, &format!("Player{i}")))
+                .unwrap();
+            uuids.push(uuid);
+        }
 
-        game.start().unwrap();
+        (game, uuids)
+    }
 
-        assert_eq!(game.get_player_tiles(&uuid_0).unwrap().len(), 7);
-        assert_eq!(game.get_player_tiles(&uuid_1).unwrap().len(), 7);
+    #[test]
+    fn rated_game_is_for_registered_players() {
+        let (mut game, _) = rated_game(1);
+
+        assert_eq!(
+            game.register_player(Player::new(&Uuid::new_v4(), "Guest"))
+                .unwrap_err(),
+            Error::AccountRequired
+        );
     }
 
     #[test]
-    fn next_turn_works() {
-        let mut game = Scrabble::new();
+    fn leaving_rated_game_is_a_forfeit() {
+        let (mut game, uuids) = rated_game(3);
+        game.start().unwrap();
 
-        let uuid_0 = Uuid::new_v4();
-        let uuid_1 = Uuid::new_v4();
+        game.remove_player(&uuids[0]).unwrap();
+        assert_eq!(game.get_players().len(), 3);
+        assert!(game.is_forfeited(&uuids[0]));
+        assert!(game.get_final_scores().is_none());
 
-        game.register_player(Player::new(&uuid_0, "Player0"))
-            .unwrap();
-        game.register_player(Player::new(&uuid_1, "Player1"))
+        game.remove_player(&uuids[1]).unwrap();
+        assert_eq!(game.get_status(), GameStatus::Finished);
+
+        let final_scores = game.get_final_scores().unwrap();
+        assert_eq!(final_scores.len(), 3);
+        assert!(final_scores[0].forfeited && final_scores[1].forfeited);
+        assert!(!final_scores[2].forfeited);
+    }
+
+    #[test]
+    fn seats_are_claimed_with_proof() {
+        let mut game = Scrabble::new();
+        let guest = Uuid::new_v4();
+        let member = Uuid::new_v4();
+        game.register_player(Player::new(&guest, "Guest")).unwrap();
+        game.register_player(Player::with_account(&member, "Member"))
             .unwrap();
 
-        assert_eq!(game.current_player_index, 0);
-        assert_eq!(game.next_turn(), 1);
-        assert_eq!(game.next_turn(), 0);
+        // Only guests get a token, members have their session
+        let token = game.get_reconnect_token(&guest).unwrap().to_owned();
+        assert!(game.get_reconnect_token(&member).is_none());
+
+        assert!(game.claim_seat(&guest, None, Some(&token)).is_ok());
+        assert_eq!(
+            game.claim_seat(&guest, None, None).unwrap_err(),
+            Error::NotYourSeat
+        );
+        assert_eq!(
+            game.claim_seat(&guest, None, Some("guess")).unwrap_err(),
+            Error::NotYourSeat
+        );
+
+        assert!(game.claim_seat(&member, Some(&member), None).is_ok());
+        assert_eq!(
+            game.claim_seat(&member, Some(&guest), Some(&token))
+                .unwrap_err(),
+            Error::NotYourSeat
+        );
+
+        // A guest who logged out has no seat left to claim
+        game.remove_player(&guest).unwrap();
+        assert!(game.get_reconnect_token(&guest).is_none());
     }
 }
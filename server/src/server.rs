@@ -0,0 +1,508 @@
+use crate::accounts::SharedAccounts;
+use crate::config::Config;
+use crate::events::{Event, EventReceiver, EventSender};
+use crate::i18n::Locale;
+use crate::maintenance::MaintenanceNotice;
+use crate::manager::Manager;
+use crate::registry::Registry;
+use crate::response::Response;
+use crate::scrabble::Scrabble;
+use crate::Error;
+use axum::routing::get;
+use axum::Router;
+use socketioxide::extract::SocketRef;
+use socketioxide::handler::ConnectHandler;
+use socketioxide::{NsInsertError, SocketIo};
+use std::collections::HashMap;
+use std::future::Future;
+use std::str::FromStr;
+use std::time::Instant;
+use tracing::info;
+use uuid::Uuid;
+
+/// Routes and socket namespaces of the server, events they receive go to `sender`
+///
+/// Served by `main`, and by the tests that talk to the server over real sockets.
+pub fn app(
+    config: &Config,
+    sender: &EventSender,
+    registry: &Registry,
+    accounts: &SharedAccounts,
+) -> Result<(Router, SocketIo), NsInsertError> {
+    let (layer, io) = SocketIo::new_layer();
+
+    // Sockets speaking another version of the protocol are turned away, the others
+    // authenticate once on connect, with the token given when logging in, and
+    // settle on the language they are answered in
+    io.ns("/", {
+        let sender = sender.clone();
+        let authenticate = crate::accounts::authenticate_socket(accounts.clone());
+        let accounts = accounts.clone();
+        let on_connect =
+            move |socket: SocketRef| crate::lobby::on_connect(socket, sender, accounts);
+
+        on_connect
+            .with(authenticate)
+            .with(crate::i18n::negotiate_locale)
+            .with(crate::protocol::check_version)
+    });
+    io.dyn_ns("/game/{*game_uuid}", {
+        let registry = registry.clone();
+        let on_connect = move |socket_ref: SocketRef| {
+            let game_uuid = socket_ref
+                .ns()
+                .strip_prefix("/game/")
+                .and_then(|x| Uuid::from_str(x).ok());
+
+            match game_uuid {
+                Some(game_uuid) => crate::game::on_connect(socket_ref, registry, game_uuid),
+                // No game could ever live there, the client is told before being let go
+                None => {
+                    let response = Response::<()>::from_error(Error::GameNotFound)
+                        .localized(Locale::of(&socket_ref));
+                    socket_ref.emit("error", &response).ok();
+                    socket_ref.disconnect().ok();
+                }
+            }
+        };
+
+        on_connect
+            .with(crate::accounts::authenticate_socket(accounts.clone()))
+            .with(crate::i18n::negotiate_locale)
+            .with(crate::protocol::check_version)
+    })?;
+
+    let mut app = Router::new()
+        .route("/", get(|| async { "Hello, World!" }))
+        .nest(
+            "/api/v1",
+            crate::api::router(sender.clone(), registry.clone(), accounts.clone()),
+        )
+        .route(
+            "/metrics",
+            get(crate::metrics::serve).with_state(sender.clone()),
+        );
+    if let Some(token) = &config.admin.token {
+        app = app.nest(
+            "/admin",
+            crate::admin::router(sender.clone(), token.clone()),
+        );
+    }
+
+    Ok((app.layer(layer).layer(config.cors_layer()), io))
+}
+
+/// Handles every event sent to the manager until `shutdown` resolves, then stops
+/// the games and hands them back with the manager, to be saved
+pub async fn run(
+    io: SocketIo,
+    mut manager: Manager,
+    mut rx: EventReceiver,
+    tx: EventSender,
+    registry: Registry,
+    shutdown: impl Future<Output = ()>,
+) -> (Manager, HashMap<Uuid, Scrabble>) {
+    let mut matchmaking_interval = tokio::time::interval(crate::matchmaking::MATCHMAKING_INTERVAL);
+    tokio::pin!(shutdown);
+
+    // Games restored from the snapshot are running again
+    crate::actor::spawn_new_games(&io, &mut manager, &tx);
+
+    loop {
+        tokio::select! {
+            event = rx.recv() => {
+                let Some((sent_at, event)) = event else {
+                    break;
+                };
+                let received_at = Instant::now();
+
+                handle_event(event, &io, &mut manager);
+
+                crate::metrics::observe_event(received_at - sent_at, received_at.elapsed());
+            },
+            // Rating windows widen as players wait, so the queue is looked at again
+            _ = matchmaking_interval.tick() => crate::lobby::make_matches(&io, &mut manager),
+            _ = &mut shutdown => break,
+        }
+
+        // A game that just ended may have ended a tournament round
+        crate::lobby::advance_tournaments(&io, &mut manager);
+        crate::actor::spawn_new_games(&io, &mut manager, &tx);
+    }
+
+    info!("Shutting down");
+    manager.set_maintenance(true);
+    crate::maintenance::notify_all(&io, &manager, &MaintenanceNotice::shutdown());
+
+    // Sockets send their last events while they are being closed
+    let close = io.close();
+    tokio::pin!(close);
+    loop {
+        tokio::select! {
+            _ = &mut close => break,
+            Some((_, event)) = rx.recv() => handle_event(event, &io, &mut manager),
+        }
+    }
+
+    // Games report to the lobby loop until their very last command
+    let stop = crate::actor::stop_all(&registry);
+    tokio::pin!(stop);
+    let games = loop {
+        tokio::select! {
+            games = &mut stop => break games,
+            Some((_, event)) = rx.recv() => handle_event(event, &io, &mut manager),
+        }
+    };
+
+    rx.close();
+    while let Ok((_, event)) = rx.try_recv() {
+        handle_event(event, &io, &mut manager);
+    }
+
+    (manager, games)
+}
+
+fn handle_event(event: Event, io: &SocketIo, manager: &mut Manager) {
+    match event {
+        event @ Event::Report(_) => crate::actor::handle_events(event, io, manager),
+        event @ Event::Lobby(_) => crate::lobby::handle_events(event, io, manager),
+        event @ Event::Api(_) => crate::api::handle_events(event, io, manager),
+        event @ Event::Admin(_) => crate::admin::handle_events(event, io, manager),
+        Event::Metrics(reply) => {
+            reply.send(crate::metrics::render(io, manager)).ok();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::FutureExt;
+    use rust_socketio::asynchronous::{Client, ClientBuilder};
+    use rust_socketio::Payload;
+    use serde_json::{json, Value};
+    use std::net::SocketAddr;
+    use std::time::Duration;
+    use tokio::sync::{mpsc, oneshot};
+
+    /// Longest a test waits for an answer or an event
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    /// The whole server on an ephemeral port, with nothing saved anywhere
+    async fn serve() -> SocketAddr {
+        let manager = Manager::new();
+        let registry = manager.get_registry().clone();
+        let (tx, rx) = crate::events::channel(64);
+        let (app, io) = app(
+            crate::config::get(),
+            &tx,
+            &registry,
+            &SharedAccounts::default(),
+        )
+        .unwrap();
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        tokio::spawn(run(io, manager, rx, tx, registry, std::future::pending()));
+
+        address
+    }
+
+    /// A scripted socket, keeping every event it gets for the test to look at
+    struct TestClient {
+        socket: Client,
+        events: mpsc::UnboundedReceiver<(String, Value)>,
+    }
+
+    impl TestClient {
+        /// Connects to `namespace`, once the server welcomed the socket
+        async fn connect(address: SocketAddr, namespace: &str) -> TestClient {
+            let (sender, events) = mpsc::unbounded_channel();
+
+            let socket = ClientBuilder::new(format!("http://{address}"))
+                .namespace(namespace)
+                .auth(json!({ "protocol": crate::protocol::PROTOCOL_VERSION }))
+                .reconnect(false)
+                .on_any(move |event, payload, _| {
+                    sender
+                        .send((event.as_str().to_string(), first_value(payload)))
+                        .ok();
+                    async {}.boxed()
+                })
+                .connect()
+                .await
+                .unwrap();
+
+            let mut client = TestClient { socket, events };
+            client.expect("welcome").await;
+            client
+        }
+
+        /// Sends a request and waits for its acknowledgement
+        async fn request(&self, event: &str, data: Value) -> Value {
+            let (reply, answer) = oneshot::channel();
+            let reply = std::sync::Mutex::new(Some(reply));
+            let payload = match data {
+                Value::Null => Payload::Text(Vec::new()),
+                data => Payload::from(data),
+            };
+
+            self.socket
+                .emit_with_ack(event, payload, TIMEOUT, move |payload, _| {
+                    // Acknowledgements come as the list of their arguments
+                    let value = match first_value(payload) {
+                        Value::Array(mut values) if !values.is_empty() => values.swap_remove(0),
+                        value => value,
+                    };
+                    if let Some(reply) = reply.lock().unwrap().take() {
+                        reply.send(value).ok();
+                    }
+                    async {}.boxed()
+                })
+                .await
+                .unwrap();
+
+            tokio::time::timeout(TIMEOUT, answer)
+                .await
+                .unwrap_or_else(|_| panic!("no answer to {event}"))
+                .unwrap()
+        }
+
+        /// Payload of the next `event`, the events before it are skipped
+        async fn expect(&mut self, event: &str) -> Value {
+            let next = async {
+                loop {
+                    match self.events.recv().await {
+                        Some((name, data)) if name == event => return data,
+                        Some(_) => continue,
+                        None => panic!("socket closed while waiting for {event}"),
+                    }
+                }
+            };
+
+            tokio::time::timeout(TIMEOUT, next)
+                .await
+                .unwrap_or_else(|_| panic!("no {event} event"))
+        }
+
+        async fn close(self) {
+            self.socket.disconnect().await.unwrap();
+        }
+    }
+
+    /// Server events and acknowledgements carry a single argument
+    fn first_value(payload: Payload) -> Value {
+        match payload {
+            Payload::Text(mut values) if !values.is_empty() => values.swap_remove(0),
+            _ => Value::Null,
+        }
+    }
+
+    /// A game created from the lobby, and a registered player for each name
+    async fn seated_game(
+        address: SocketAddr,
+        names: &[&str],
+    ) -> (String, Vec<(TestClient, Value)>) {
+        let lobby = TestClient::connect(address, "/").await;
+        let created = lobby
+            .request("create-game", json!({ "settings": { "name": "Harness" } }))
+            .await;
+        let game_uuid = created["data"]["uuid"].as_str().unwrap().to_string();
+        lobby.close().await;
+
+        let mut players = Vec::new();
+        for name in names {
+            let client = TestClient::connect(address, &format!("/game/{game_uuid}")).await;
+            let player = client
+                .request(
+                    "register_request",
+                    json!({ "game_uuid": game_uuid, "username": name }),
+                )
+                .await;
+            assert!(player["error"].is_null(), "{player}");
+            players.push((client, player["data"].clone()));
+        }
+
+        (game_uuid, players)
+    }
+
+    #[tokio::test]
+    async fn a_game_is_played_over_sockets() {
+        let address = serve().await;
+        let (_, mut players) = seated_game(address, &["Alice", "Bob"]).await;
+
+        // Alice hears about her own registration, then about Bob's
+        let alice = &mut players[0].0;
+        let players_list = alice.expect("players-list").await;
+        assert_eq!(players_list["data"].as_array().unwrap().len(), 1);
+        let players_list = alice.expect("players-list").await;
+        assert_eq!(players_list["data"][1]["name"], "Bob");
+
+        let started = players[0].0.request("start", Value::Null).await;
+        assert!(started["error"].is_null(), "{started}");
+
+        let mut racks = Vec::new();
+        for (client, _) in &mut players {
+            let rack = client.expect("get-tiles").await;
+            assert_eq!(rack.as_array().unwrap().len(), 7);
+            racks.push(rack);
+
+            let status = client.expect("game-status").await;
+            assert_eq!(status["data"]["status"], "in-progress");
+            assert!(status["data"]["seed_commitment"].is_string());
+        }
+
+        // Any two tiles go across the center, words are not checked
+        let alice_uuid = players[0].1["id"].clone();
+        let tiles = json!([
+            { "tile": racks[0][0], "x": 7, "y": 7 },
+            { "tile": racks[0][1], "x": 8, "y": 7 },
+        ]);
+        let turn = players[0]
+            .0
+            .request("play", json!({ "tiles": tiles }))
+            .await;
+        assert!(turn["error"].is_null(), "{turn}");
+        assert_eq!(turn["data"]["player_uuid"], alice_uuid);
+
+        let (bob, bob_seat) = &mut players[1];
+        let bob_uuid = bob_seat["id"].clone();
+        let played = bob.expect("move-played").await;
+        assert_eq!(played["data"]["player_uuid"], alice_uuid);
+        assert_eq!(played["data"]["score"], turn["data"]["score"]);
+        let status = bob.expect("game-status").await;
+        assert_eq!(status["data"]["current_player"]["id"], bob_uuid);
+
+        // Alice draws back up to a full rack
+        let alice = &mut players[0].0;
+        assert_eq!(alice.expect("get-tiles").await.as_array().unwrap().len(), 7);
+        let status = alice.expect("game-status").await;
+        assert_ne!(status["data"]["current_player"]["id"], alice_uuid);
+
+        // Alice can't play twice in a row
+        let refused = players[0].0.request("pass", Value::Null).await;
+        assert_eq!(refused["code"], "not_your_turn");
+
+        let passed = players[1].0.request("pass", Value::Null).await;
+        assert!(passed["error"].is_null(), "{passed}");
+        let status = players[0].0.expect("game-status").await;
+        assert_eq!(status["data"]["current_player"]["id"], alice_uuid);
+    }
+
+    #[tokio::test]
+    async fn a_player_reconnects_to_their_seat() {
+        let address = serve().await;
+        let (game_uuid, mut players) = seated_game(address, &["Alice", "Bob"]).await;
+
+        let started = players[0].0.request("start", Value::Null).await;
+        assert!(started["error"].is_null(), "{started}");
+
+        let (bob, seat) = players.pop().unwrap();
+        let bob_uuid = seat["id"].as_str().unwrap();
+        let rack = {
+            let mut bob = bob;
+            let rack = bob.expect("get-tiles").await;
+            bob.close().await;
+            rack
+        };
+
+        let (alice, _) = &mut players[0];
+        let presence = alice.expect("presence").await;
+        assert_eq!(presence["data"]["player"]["id"], bob_uuid);
+        assert_eq!(presence["data"]["connected"], false);
+
+        // A refreshed page only knows who it was, and the token that proves it
+        let mut bob = TestClient::connect(address, &format!("/game/{game_uuid}")).await;
+        let whoami = bob
+            .request("whoami", json!({ "player_uuid": bob_uuid }))
+            .await;
+        assert_eq!(whoami["code"], "not_your_seat");
+        let whoami = bob
+            .request(
+                "whoami",
+                json!({ "player_uuid": bob_uuid, "reconnect_token": seat["reconnect_token"] }),
+            )
+            .await;
+        assert_eq!(whoami["data"]["name"], "Bob");
+        assert_eq!(bob.expect("get-tiles").await, rack);
+
+        let presence = alice.expect("presence").await;
+        assert_eq!(presence["data"]["player"]["id"], bob_uuid);
+        assert_eq!(presence["data"]["connected"], true);
+
+        // The new socket plays for Bob
+        let passed = players[0].0.request("pass", Value::Null).await;
+        assert!(passed["error"].is_null(), "{passed}");
+        let passed = bob.request("pass", Value::Null).await;
+        assert!(passed["error"].is_null(), "{passed}");
+    }
+
+    #[tokio::test]
+    async fn someone_elses_seat_stays_theirs() {
+        let address = serve().await;
+        let (game_uuid, players) = seated_game(address, &["Alice", "Bob"]).await;
+        let bob_uuid = players[1].1["id"].clone();
+
+        let started = players[0].0.request("start", Value::Null).await;
+        assert!(started["error"].is_null(), "{started}");
+
+        // Knowing Bob's UUID is not enough, nor is a made up token
+        let mut intruder = TestClient::connect(address, &format!("/game/{game_uuid}")).await;
+        for request in [
+            json!({ "player_uuid": bob_uuid }),
+            json!({ "player_uuid": bob_uuid, "reconnect_token": "guess" }),
+        ] {
+            let whoami = intruder.request("whoami", request).await;
+            assert_eq!(whoami["code"], "not_your_seat");
+        }
+
+        // Neither Bob's rack nor his turns
+        let passed = intruder.request("pass", Value::Null).await;
+        assert_eq!(passed["code"], "player_not_registered");
+        while let Ok((event, _)) = intruder.events.try_recv() {
+            assert_ne!(event, "get-tiles");
+        }
+    }
+
+    #[tokio::test]
+    async fn players_only_log_themselves_out() {
+        let address = serve().await;
+        let (game_uuid, mut players) = seated_game(address, &["Alice", "Bob"]).await;
+        let bob_uuid = players[1].1["id"].clone();
+
+        let (alice, _) = &mut players[0];
+        let answer = alice
+            .request(
+                "logout",
+                json!({ "game_uuid": game_uuid, "player_uuid": bob_uuid }),
+            )
+            .await;
+        assert_eq!(answer["code"], "not_your_seat");
+
+        // Bob is still seated, and Alice still plays for herself
+        let list = alice.request("player-list", Value::Null).await;
+        assert_eq!(list["data"].as_array().unwrap().len(), 2);
+        let started = alice.request("start", Value::Null).await;
+        assert!(started["error"].is_null(), "{started}");
+    }
+
+    #[tokio::test]
+    async fn requests_about_missing_games_are_answered() {
+        let address = serve().await;
+        let game_uuid = Uuid::new_v4();
+
+        let client = TestClient::connect(address, &format!("/game/{game_uuid}")).await;
+        let answer = client
+            .request(
+                "register_request",
+                json!({ "game_uuid": game_uuid, "username": "Alice" }),
+            )
+            .await;
+        assert_eq!(answer["code"], "game_not_found");
+
+        let answer = client.request("play", json!({ "tiles": "none" })).await;
+        assert_eq!(answer["code"], "invalid_request");
+    }
+}
@@ -0,0 +1,257 @@
+use crate::config;
+use crate::Error;
+use schemars::JsonSchema;
+use scrabble_core::MIN_PLAYERS;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use ts_rs::TS;
+
+#[derive(
+    Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Debug, Default, JsonSchema, TS,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum Variant {
+    #[default]
+    Classic,
+}
+
+/// What happens to a player who stays disconnected past the timeout
+#[derive(Copy, Clone, PartialEq, Eq, Serialize, Deserialize, Debug, Default, JsonSchema, TS)]
+#[serde(rename_all = "kebab-case")]
+pub enum DisconnectPolicy {
+    /// The player loses but keeps their seat in the game record
+    #[default]
+    Forfeit,
+    /// The player is removed from the game as if they had logged out
+    Remove,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Serialize, Deserialize, Debug, JsonSchema, TS)]
+pub struct TimeControl {
+    #[ts(type = "number")]
+    pub initial_seconds: u64,
+    #[ts(type = "number")]
+    pub increment_seconds: u64,
+}
+
+/// Options chosen by whoever creates a game
+#[derive(Clone, Serialize, Deserialize, Debug, JsonSchema, TS)]
+pub struct GameSettings {
+    pub name: String,
+    #[serde(default = "default_max_players")]
+    pub max_players: usize,
+    #[serde(default)]
+    pub variant: Variant,
+    #[serde(default)]
+    pub time_control: Option<TimeControl>,
+    /// Private games are hidden from the lobby and need an invite code to join
+    #[serde(default)]
+    pub private: bool,
+    /// Lifetime of invite codes in seconds, `None` for codes that never expire
+    #[serde(default)]
+    #[ts(type = "number | null")]
+    pub invite_expires_in: Option<u64>,
+    /// Seconds a disconnected player's seat is held before their turns are passed
+    #[serde(default = "default_disconnect_grace")]
+    #[ts(type = "number")]
+    pub disconnect_grace: u64,
+    /// Seconds after which a disconnected player is dealt with `on_disconnect`
+    #[serde(default = "default_disconnect_timeout")]
+    #[ts(type = "number")]
+    pub disconnect_timeout: u64,
+    #[serde(default = "default_on_disconnect")]
+    pub on_disconnect: DisconnectPolicy,
+    /// Rated games are for logged in players only and update their ratings at game over
+    #[serde(default)]
+    pub rated: bool,
+    /// Seed the bag is shuffled from, drawn from the OS when `None`
+    ///
+    /// Only ever set by the server, never read from or sent to clients: whoever
+    /// picks the seed knows every draw in advance.
+    #[serde(skip)]
+    pub seed: Option<u64>,
+}
+
+fn default_max_players() -> usize {
+    config::get().game.max_players
+}
+
+fn default_disconnect_grace() -> u64 {
+    config::get().game.disconnect_grace
+}
+
+fn default_disconnect_timeout() -> u64 {
+    config::get().game.disconnect_timeout
+}
+
+fn default_on_disconnect() -> DisconnectPolicy {
+    config::get().game.on_disconnect
+}
+
+impl Default for GameSettings {
+    fn default() -> Self {
+        GameSettings {
+            name: String::from("Scrabble"),
+            max_players: default_max_players(),
+            variant: Variant::default(),
+            time_control: None,
+            private: false,
+            invite_expires_in: None,
+            disconnect_grace: default_disconnect_grace(),
+            disconnect_timeout: default_disconnect_timeout(),
+            on_disconnect: default_on_disconnect(),
+            rated: false,
+            seed: None,
+        }
+    }
+}
+
+impl TimeControl {
+    pub fn validate(&self) -> Result<(), Error> {
+        if client_duration(self.initial_seconds).is_none()
+            || client_duration(self.increment_seconds).is_none()
+        {
+            return Err(Error::InvalidSettings);
+        }
+
+        Ok(())
+    }
+}
+
+/// A duration sent by a client, `None` past `limits.max_duration` so that adding
+/// it to the current time can't overflow
+pub fn client_duration(seconds: u64) -> Option<Duration> {
+    (seconds <= config::get().limits.max_duration).then(|| Duration::from_secs(seconds))
+}
+
+impl GameSettings {
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.name.trim().is_empty() {
+            return Err(Error::InvalidSettings);
+        }
+
+        if !(MIN_PLAYERS..=config::get().limits.max_players).contains(&self.max_players) {
+            return Err(Error::InvalidSettings);
+        }
+
+        if self.disconnect_grace > self.disconnect_timeout {
+            return Err(Error::InvalidSettings);
+        }
+
+        let durations = [
+            self.invite_expires_in,
+            Some(self.disconnect_grace),
+            Some(self.disconnect_timeout),
+        ];
+        if durations
+            .into_iter()
+            .flatten()
+            .any(|x| client_duration(x).is_none())
+        {
+            return Err(Error::InvalidSettings);
+        }
+
+        self.time_control.map_or(Ok(()), |x| x.validate())
+    }
+
+    pub fn invite_time_to_live(&self) -> Option<Duration> {
+        self.invite_expires_in.map(Duration::from_secs)
+    }
+
+    pub fn disconnect_grace(&self) -> Duration {
+        Duration::from_secs(self.disconnect_grace)
+    }
+
+    pub fn disconnect_timeout(&self) -> Duration {
+        Duration::from_secs(self.disconnect_timeout)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_settings_are_valid() {
+        assert!(GameSettings::default().validate().is_ok());
+    }
+
+    #[test]
+    fn settings_reject_bad_player_count() {
+        for max_players in [1, 5] {
+            let settings = GameSettings {
+                max_players,
+                ..GameSettings::default()
+            };
+
+            assert_eq!(settings.validate(), Err(Error::InvalidSettings));
+        }
+    }
+
+    #[test]
+    fn settings_reject_grace_longer_than_timeout() {
+        let settings = GameSettings {
+            disconnect_grace: 120,
+            disconnect_timeout: 60,
+            ..GameSettings::default()
+        };
+
+        assert_eq!(settings.validate(), Err(Error::InvalidSettings));
+    }
+
+    #[test]
+    fn settings_reject_endless_invite() {
+        let settings = GameSettings {
+            private: true,
+            invite_expires_in: Some(u64::MAX),
+            ..GameSettings::default()
+        };
+
+        assert_eq!(settings.validate(), Err(Error::InvalidSettings));
+    }
+
+    #[test]
+    fn settings_reject_durations_past_the_limit() {
+        let too_long = config::get().limits.max_duration + 1;
+        let settings = [
+            GameSettings {
+                disconnect_grace: too_long,
+                disconnect_timeout: too_long,
+                ..GameSettings::default()
+            },
+            GameSettings {
+                disconnect_timeout: too_long,
+                ..GameSettings::default()
+            },
+            GameSettings {
+                time_control: Some(TimeControl {
+                    initial_seconds: 600,
+                    increment_seconds: too_long,
+                }),
+                ..GameSettings::default()
+            },
+        ];
+
+        for settings in settings {
+            assert_eq!(settings.validate(), Err(Error::InvalidSettings));
+        }
+    }
+
+    #[test]
+    fn clients_cannot_pick_the_seed() {
+        let settings: GameSettings =
+            serde_json::from_str(r#"{ "name": "Rigged", "seed": 42 }"#).unwrap();
+
+        assert_eq!(settings.seed, None);
+    }
+
+    #[test]
+    fn settings_reject_empty_name() {
+        let settings = GameSettings {
+            name: String::from("  "),
+            ..GameSettings::default()
+        };
+
+        assert_eq!(settings.validate(), Err(Error::InvalidSettings));
+    }
+}
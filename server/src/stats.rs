@@ -0,0 +1,592 @@
+use crate::ratings::Ratings;
+use crate::scrabble::Scrabble;
+use crate::settings::Variant;
+use crate::storage;
+use crate::summary::unix_timestamp;
+use crate::Error;
+use schemars::JsonSchema;
+use scrabble_core::TurnAction;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use ts_rs::TS;
+use uuid::Uuid;
+
+/// Where finished games are kept between restarts
+pub const STATS_PATH: &str = "stats.json";
+
+/// Players need that many games in the period to appear on the win rate leaderboard
+const MIN_GAMES_FOR_WIN_RATE: usize = 5;
+
+const DEFAULT_LEADERBOARD_SIZE: usize = 20;
+const MAX_LEADERBOARD_SIZE: usize = 100;
+
+/// How far back statistics go
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Debug, Default, JsonSchema, TS)]
+#[serde(rename_all = "kebab-case")]
+pub enum Period {
+    Day,
+    Week,
+    Month,
+    Year,
+    #[default]
+    AllTime,
+}
+
+impl Period {
+    /// Seconds since the UNIX epoch at which the period starts
+    fn since(self, now: SystemTime) -> u64 {
+        let days = match self {
+            Period::Day => 1,
+            Period::Week => 7,
+            Period::Month => 30,
+            Period::Year => 365,
+            Period::AllTime => return 0,
+        };
+
+        unix_timestamp(now - Duration::from_secs(days * 24 * 60 * 60))
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, Default, JsonSchema, TS)]
+pub struct StatsFilter {
+    #[serde(default)]
+    pub variant: Option<Variant>,
+    #[serde(default)]
+    pub period: Period,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Debug, JsonSchema, TS)]
+#[serde(rename_all = "kebab-case")]
+pub enum LeaderboardKind {
+    Rating,
+    WinRate,
+    BestMove,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct BestMove {
+    pub game_uuid: Uuid,
+    pub score: i32,
+    /// The word along the placed tiles comes first
+    pub words: Vec<String>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct BestWord {
+    pub word: String,
+    pub score: u32,
+}
+
+/// What a registered player did in one finished game
+#[derive(Clone, Serialize, Deserialize, Debug)]
+struct PlayerRecord {
+    account_id: Uuid,
+    username: String,
+    /// Final score, leftover tiles accounted for
+    score: i32,
+    won: bool,
+    turns: usize,
+    /// Points scored by moves alone
+    points: i32,
+    bingos: usize,
+    best_move: Option<BestMove>,
+    best_word: Option<BestWord>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+struct GameRecord {
+    game_uuid: Uuid,
+    variant: Variant,
+    /// Seconds since the UNIX epoch
+    ended_at: u64,
+    players: Vec<PlayerRecord>,
+}
+
+impl GameRecord {
+    /// Guests are left out, `None` if nobody in the game had an account
+    fn new(game_uuid: &Uuid, game: &Scrabble, now: SystemTime) -> Option<Self> {
+        let final_scores = game.get_final_scores()?;
+
+        // A draw for first place is not a win
+        let top_score = final_scores
+            .iter()
+            .filter(|x| !x.forfeited)
+            .map(|x| x.score)
+            .max();
+        let winners: Vec<_> = final_scores
+            .iter()
+            .filter(|x| !x.forfeited && Some(x.score) == top_score)
+            .collect();
+
+        let players: Vec<PlayerRecord> = final_scores
+            .iter()
+            .filter_map(|final_score| {
+                let player = game.get_player(&final_score.player_uuid).ok()?;
+                if !player.is_registered() {
+                    return None;
+                }
+
+                let mut record = PlayerRecord {
+                    account_id: final_score.player_uuid,
+                    username: player.get_name().to_string(),
+                    score: final_score.score,
+                    won: winners.len() == 1 && winners[0].player_uuid == final_score.player_uuid,
+                    turns: 0,
+                    points: 0,
+                    bingos: 0,
+                    best_move: None,
+                    best_word: None,
+                };
+
+                for turn in game
+                    .get_turns()
+                    .iter()
+                    .filter(|x| x.player_uuid == final_score.player_uuid)
+                {
+                    record.turns += 1;
+                    record.points += turn.score;
+
+                    let TurnAction::Play { words, bingo, .. } = &turn.action else {
+                        continue;
+                    };
+
+                    record.bingos += *bingo as usize;
+
+                    if record
+                        .best_move
+                        .as_ref()
+                        .is_none_or(|x| turn.score > x.score)
+                    {
+                        record.best_move = Some(BestMove {
+                            game_uuid: *game_uuid,
+                            score: turn.score,
+                            words: words.iter().map(|x| x.word.clone()).collect(),
+                        });
+                    }
+
+                    for word in words {
+                        if record
+                            .best_word
+                            .as_ref()
+                            .is_none_or(|x| word.score > x.score)
+                        {
+                            record.best_word = Some(BestWord {
+                                word: word.word.clone(),
+                                score: word.score,
+                            });
+                        }
+                    }
+                }
+
+                Some(record)
+            })
+            .collect();
+
+        if players.is_empty() {
+            return None;
+        }
+
+        Some(GameRecord {
+            game_uuid: *game_uuid,
+            variant: game.get_settings().variant,
+            ended_at: unix_timestamp(game.get_ended_at().unwrap_or(now)),
+            players,
+        })
+    }
+}
+
+/// How a player did over the games matching a filter
+#[derive(Clone, Serialize, Debug, PartialEq, Default)]
+pub struct PlayerStats {
+    pub games_played: usize,
+    pub wins: usize,
+    pub win_rate: f64,
+    pub average_score: f64,
+    pub average_points_per_turn: f64,
+    pub bingos_per_game: f64,
+    pub best_move: Option<BestMove>,
+    pub best_word: Option<BestWord>,
+}
+
+#[derive(Clone, Serialize, Debug, PartialEq)]
+pub struct LeaderboardEntry {
+    pub rank: usize,
+    pub account_id: Uuid,
+    pub username: String,
+    pub games_played: usize,
+    /// Rating, win rate between 0 and 1 or score of the best move, depending on the leaderboard
+    pub value: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub best_move: Option<BestMove>,
+}
+
+/// Registered players' results in every finished game
+#[derive(Default)]
+pub struct Statistics {
+    records: Vec<GameRecord>,
+    /// Finished games already looked at, recorded or not
+    seen: HashSet<Uuid>,
+    /// In memory only when `None`
+    path: Option<PathBuf>,
+}
+
+impl Statistics {
+    /// Reads the games saved at `path`, starts from scratch if there is no file yet
+    pub fn load(path: &Path) -> std::io::Result<Self> {
+        let records: Vec<GameRecord> = storage::load(path)?;
+
+        Ok(Statistics {
+            seen: records.iter().map(|x| x.game_uuid).collect(),
+            records,
+            path: Some(path.to_path_buf()),
+        })
+    }
+
+    fn save(&self) -> Result<(), Error> {
+        match &self.path {
+            Some(path) => storage::save(path, &self.records),
+            None => Ok(()),
+        }
+    }
+
+    /// Keeps the results of a finished game, games are only recorded once
+    pub fn record_game(&mut self, game_uuid: &Uuid, game: &Scrabble, now: SystemTime) {
+        if game.get_final_scores().is_none() || !self.seen.insert(*game_uuid) {
+            return;
+        }
+
+        if let Some(record) = GameRecord::new(game_uuid, game, now) {
+            self.records.push(record);
+
+            // Records stay right in memory, the next game will try saving again
+            self.save().ok();
+        }
+    }
+
+    pub fn is_recorded(&self, game_uuid: &Uuid) -> bool {
+        self.seen.contains(game_uuid)
+    }
+
+    fn player_records<'a>(
+        &'a self,
+        filter: &'a StatsFilter,
+        now: SystemTime,
+    ) -> impl Iterator<Item = &'a PlayerRecord> {
+        let since = filter.period.since(now);
+
+        self.records
+            .iter()
+            .filter(move |x| x.ended_at >= since && filter.variant.is_none_or(|v| v == x.variant))
+            .flat_map(|x| x.players.iter())
+    }
+
+    fn aggregate<'a>(records: impl Iterator<Item = &'a PlayerRecord>) -> PlayerStats {
+        let mut stats = PlayerStats::default();
+        let (mut turns, mut points, mut score, mut bingos) = (0, 0, 0, 0);
+
+        for record in records {
+            stats.games_played += 1;
+            stats.wins += record.won as usize;
+            turns += record.turns;
+            points += record.points;
+            score += record.score;
+            bingos += record.bingos;
+
+            if let Some(best_move) = &record.best_move {
+                if stats
+                    .best_move
+                    .as_ref()
+                    .is_none_or(|x| best_move.score > x.score)
+                {
+                    stats.best_move = Some(best_move.clone());
+                }
+            }
+
+            if let Some(best_word) = &record.best_word {
+                if stats
+                    .best_word
+                    .as_ref()
+                    .is_none_or(|x| best_word.score > x.score)
+                {
+                    stats.best_word = Some(best_word.clone());
+                }
+            }
+        }
+
+        if stats.games_played > 0 {
+            let games = stats.games_played as f64;
+
+            stats.win_rate = stats.wins as f64 / games;
+            stats.average_score = score as f64 / games;
+            stats.bingos_per_game = bingos as f64 / games;
+        }
+
+        if turns > 0 {
+            stats.average_points_per_turn = points as f64 / turns as f64;
+        }
+
+        stats
+    }
+
+    pub fn get_player_stats(
+        &self,
+        account_id: &Uuid,
+        filter: &StatsFilter,
+        now: SystemTime,
+    ) -> PlayerStats {
+        Self::aggregate(
+            self.player_records(filter, now)
+                .filter(|x| x.account_id == *account_id),
+        )
+    }
+
+    /// The rating leaderboard is for a single variant, the default one unless the
+    /// filter says otherwise, and only keeps players who played rated games in the period
+    pub fn get_leaderboard(
+        &self,
+        kind: LeaderboardKind,
+        filter: &StatsFilter,
+        limit: Option<usize>,
+        ratings: &Ratings,
+        now: SystemTime,
+    ) -> Vec<LeaderboardEntry> {
+        let mut by_account: HashMap<Uuid, Vec<&PlayerRecord>> = HashMap::new();
+        for record in self.player_records(filter, now) {
+            by_account
+                .entry(record.account_id)
+                .or_default()
+                .push(record);
+        }
+
+        // Usernames come from the latest game played
+        let username = |account_id: &Uuid| {
+            self.records
+                .iter()
+                .rev()
+                .flat_map(|x| x.players.iter())
+                .find(|x| x.account_id == *account_id)
+                .map(|x| x.username.clone())
+        };
+
+        let mut entries: Vec<LeaderboardEntry> = match kind {
+            LeaderboardKind::Rating => {
+                let since = filter.period.since(now);
+
+                ratings
+                    .get_variant_ratings(filter.variant.unwrap_or_default())
+                    .filter_map(|(account_id, rating)| {
+                        let games_played = rating.history.iter().filter(|x| x.at >= since).count();
+
+                        (games_played > 0).then(|| LeaderboardEntry {
+                            rank: 0,
+                            account_id: *account_id,
+                            username: username(account_id).unwrap_or_default(),
+                            games_played,
+                            value: rating.rating,
+                            best_move: None,
+                        })
+                    })
+                    .collect()
+            }
+            LeaderboardKind::WinRate => by_account
+                .iter()
+                .filter(|(_, records)| records.len() >= MIN_GAMES_FOR_WIN_RATE)
+                .map(|(account_id, records)| {
+                    let stats = Self::aggregate(records.iter().copied());
+
+                    LeaderboardEntry {
+                        rank: 0,
+                        account_id: *account_id,
+                        username: username(account_id).unwrap_or_default(),
+                        games_played: stats.games_played,
+                        value: stats.win_rate,
+                        best_move: None,
+                    }
+                })
+                .collect(),
+            LeaderboardKind::BestMove => by_account
+                .iter()
+                .filter_map(|(account_id, records)| {
+                    let stats = Self::aggregate(records.iter().copied());
+                    let best_move = stats.best_move?;
+
+                    Some(LeaderboardEntry {
+                        rank: 0,
+                        account_id: *account_id,
+                        username: username(account_id).unwrap_or_default(),
+                        games_played: stats.games_played,
+                        value: best_move.score as f64,
+                        best_move: Some(best_move),
+                    })
+                })
+                .collect(),
+        };
+
+        entries.sort_by(|a, b| {
+            b.value
+                .total_cmp(&a.value)
+                .then(b.games_played.cmp(&a.games_played))
+                .then(a.username.cmp(&b.username))
+        });
+        entries.truncate(
+            limit
+                .unwrap_or(DEFAULT_LEADERBOARD_SIZE)
+                .clamp(1, MAX_LEADERBOARD_SIZE),
+        );
+
+        for (i, entry) in entries.iter_mut().enumerate() {
+            entry.rank = i + 1;
+        }
+
+        entries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::GameSettings;
+    use scrabble_core::{Play, Player, Tile};
+
+    fn word(word: &str, x: usize, y: usize) -> Vec<Play> {
+        word.chars()
+            .enumerate()
+            .map(|(i, letter)| Play {
+                tile: Tile(letter, 1),
+                x: x + i,
+                y,
+            })
+            .collect()
+    }
+
+    /// Two registered players, the first one plays a word then both pass until the end
+    fn finished_game() -> (Uuid, Scrabble, Vec<Uuid>) {
+        let mut game = Scrabble::with_settings(GameSettings::default());
+        let uuids = vec![Uuid::new_v4(), Uuid::new_v4()];
+
+        for (i, uuid) in uuids.iter().enumerate() {
+            game.register_player(Player::with_account(uuid, &format!("Player{i}")))
+                .unwrap();
+        }
+        game.start().unwrap();
+
+        game.set_rack(
+            &uuids[0],
+            word("CAT", 0, 0).iter().map(|x| x.tile).collect(),
+        );
+        game.play(&uuids[0], &word("CAT", 6, 7)).unwrap();
+
+        // Leftover tiles decide the final scores too
+        game.set_rack(&uuids[0], Vec::new());
+        game.set_rack(&uuids[1], vec![Tile('Q', 10)]);
+
+        while game.get_final_scores().is_none() {
+            let current_player = *game.get_current_player().unwrap().get_id();
+            game.pass(&current_player).unwrap();
+        }
+
+        (Uuid::new_v4(), game, uuids)
+    }
+
+    #[test]
+    fn player_stats_come_from_finished_games() {
+        let mut statistics = Statistics::default();
+        let (game_uuid, game, uuids) = finished_game();
+
+        statistics.record_game(&game_uuid, &game, SystemTime::now());
+        statistics.record_game(&game_uuid, &game, SystemTime::now());
+        assert!(statistics.is_recorded(&game_uuid));
+
+        let stats =
+            statistics.get_player_stats(&uuids[0], &StatsFilter::default(), SystemTime::now());
+        assert_eq!(stats.games_played, 1);
+        assert_eq!(stats.wins, 1);
+        assert_eq!(stats.win_rate, 1.0);
+        assert_eq!(stats.best_move.unwrap().words, vec!["CAT"]);
+        assert_eq!(stats.best_word.unwrap().score, 6);
+        assert_eq!(stats.average_points_per_turn, 6.0 / 3.0);
+
+        let stats =
+            statistics.get_player_stats(&uuids[1], &StatsFilter::default(), SystemTime::now());
+        assert_eq!(stats.games_played, 1);
+        assert_eq!(stats.wins, 0);
+        assert_eq!(stats.best_move, None);
+    }
+
+    #[test]
+    fn guests_are_not_recorded() {
+        let mut statistics = Statistics::default();
+        let mut game = Scrabble::with_settings(GameSettings::default());
+
+        for i in 0..2 {
+            game.register_player(Player::new(&Uuid::new_v4(), &format!("Guest{i}")))
+                .unwrap();
+        }
+        game.start().unwrap();
+        while game.get_final_scores().is_none() {
+            let current_player = *game.get_current_player().unwrap().get_id();
+            game.pass(&current_player).unwrap();
+        }
+
+        statistics.record_game(&Uuid::new_v4(), &game, SystemTime::now());
+        assert!(statistics.records.is_empty());
+    }
+
+    #[test]
+    fn filters_by_variant_and_period() {
+        let mut statistics = Statistics::default();
+        let (game_uuid, game, uuids) = finished_game();
+        statistics.record_game(&game_uuid, &game, SystemTime::now());
+
+        let next_week = SystemTime::now() + Duration::from_secs(8 * 24 * 60 * 60);
+        let this_week = StatsFilter {
+            variant: Some(Variant::Classic),
+            period: Period::Week,
+        };
+
+        assert_eq!(
+            statistics
+                .get_player_stats(&uuids[0], &this_week, SystemTime::now())
+                .games_played,
+            1
+        );
+        assert_eq!(
+            statistics
+                .get_player_stats(&uuids[0], &this_week, next_week)
+                .games_played,
+            0
+        );
+    }
+
+    #[test]
+    fn best_move_leaderboard_is_ranked() {
+        let mut statistics = Statistics::default();
+        let (game_uuid, game, uuids) = finished_game();
+        statistics.record_game(&game_uuid, &game, SystemTime::now());
+
+        let leaderboard = statistics.get_leaderboard(
+            LeaderboardKind::BestMove,
+            &StatsFilter::default(),
+            None,
+            &Ratings::default(),
+            SystemTime::now(),
+        );
+
+        assert_eq!(leaderboard.len(), 1);
+        assert_eq!(leaderboard[0].rank, 1);
+        assert_eq!(leaderboard[0].account_id, uuids[0]);
+        assert_eq!(leaderboard[0].value, 6.0);
+
+        // Not enough games to be ranked on win rate
+        let leaderboard = statistics.get_leaderboard(
+            LeaderboardKind::WinRate,
+            &StatsFilter::default(),
+            None,
+            &Ratings::default(),
+            SystemTime::now(),
+        );
+        assert!(leaderboard.is_empty());
+    }
+}
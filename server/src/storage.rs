@@ -0,0 +1,27 @@
+use crate::Error;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::path::Path;
+use tracing::warn;
+
+/// Reads a JSON file, a missing file gives the default value
+pub fn load<T: DeserializeOwned + Default>(path: &Path) -> std::io::Result<T> {
+    match std::fs::read(path) {
+        Ok(bytes) => Ok(serde_json::from_slice(&bytes)?),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(T::default()),
+        Err(error) => Err(error),
+    }
+}
+
+/// Writes a JSON file, next to it first so a crash never leaves a truncated file behind
+pub fn save<T: Serialize + ?Sized>(path: &Path, value: &T) -> Result<(), Error> {
+    let bytes = serde_json::to_vec_pretty(value).map_err(|_| Error::Storage)?;
+    let tmp_path = path.with_extension("tmp");
+
+    std::fs::write(&tmp_path, bytes)
+        .and_then(|_| std::fs::rename(&tmp_path, path))
+        .map_err(|error| {
+            warn!(%error, path = %path.display(), "Could not save data");
+            Error::Storage
+        })
+}
@@ -0,0 +1,157 @@
+use crate::scrabble::Scrabble;
+use crate::settings::{TimeControl, Variant};
+use schemars::JsonSchema;
+use scrabble_core::GameStatus;
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+use ts_rs::TS;
+use uuid::Uuid;
+
+const DEFAULT_PAGE_SIZE: usize = 20;
+const MAX_PAGE_SIZE: usize = 100;
+
+/// Seconds since the UNIX epoch, as sent to the clients
+pub fn unix_timestamp(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|x| x.as_secs())
+        .unwrap_or_default()
+}
+
+/// What the lobby needs to know about a game to display it
+#[derive(Clone, Serialize, Debug, PartialEq)]
+pub struct GameSummary {
+    pub uuid: Uuid,
+    pub name: String,
+    pub host: Option<String>,
+    pub players: usize,
+    pub max_players: usize,
+    pub status: GameStatus,
+    pub variant: Variant,
+    pub time_control: Option<TimeControl>,
+    /// Private games are never shown in the lobby
+    pub private: bool,
+    pub rated: bool,
+    /// Seconds since the UNIX epoch
+    pub created_at: u64,
+}
+
+impl GameSummary {
+    pub fn new(uuid: &Uuid, game: &Scrabble) -> Self {
+        let settings = game.get_settings();
+
+        GameSummary {
+            uuid: *uuid,
+            name: settings.name.clone(),
+            host: game.get_host().map(|x| x.get_name().to_string()),
+            players: game.get_players().len(),
+            max_players: settings.max_players,
+            status: game.get_status(),
+            variant: settings.variant,
+            time_control: settings.time_control,
+            private: settings.private,
+            rated: settings.rated,
+            created_at: unix_timestamp(game.get_created_at()),
+        }
+    }
+
+    fn has_free_seat(&self) -> bool {
+        self.players < self.max_players
+    }
+}
+
+/// Criteria a lobby client can use to narrow down the games list
+#[derive(Clone, Serialize, Deserialize, Debug, Default, JsonSchema, TS)]
+pub struct GameFilter {
+    #[serde(default)]
+    pub status: Option<GameStatus>,
+    #[serde(default)]
+    pub variant: Option<Variant>,
+    #[serde(default)]
+    pub rated: Option<bool>,
+    /// Only keep games with at least one free seat
+    #[serde(default)]
+    pub joinable: bool,
+    /// Case-insensitive search on the game name
+    #[serde(default)]
+    pub search: Option<String>,
+}
+
+impl GameFilter {
+    pub fn matches(&self, summary: &GameSummary) -> bool {
+        if self.status.is_some_and(|x| x != summary.status) {
+            return false;
+        }
+
+        if self.variant.is_some_and(|x| x != summary.variant) {
+            return false;
+        }
+
+        if self.rated.is_some_and(|x| x != summary.rated) {
+            return false;
+        }
+
+        if self.joinable && !summary.has_free_seat() {
+            return false;
+        }
+
+        match &self.search {
+            Some(search) => summary.name.to_lowercase().contains(&search.to_lowercase()),
+            None => true,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, JsonSchema, TS)]
+pub struct Pagination {
+    #[serde(default)]
+    pub offset: usize,
+    #[serde(default = "default_page_size")]
+    pub limit: usize,
+}
+
+fn default_page_size() -> usize {
+    DEFAULT_PAGE_SIZE
+}
+
+impl Default for Pagination {
+    fn default() -> Self {
+        Pagination {
+            offset: 0,
+            limit: DEFAULT_PAGE_SIZE,
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct GamePage {
+    pub games: Vec<GameSummary>,
+    pub total: usize,
+    pub offset: usize,
+    pub limit: usize,
+}
+
+impl GamePage {
+    /// Sorts the summaries newest first, then cuts the requested page
+    pub fn new(mut summaries: Vec<GameSummary>, pagination: Pagination) -> Self {
+        summaries.sort_by(|a, b| {
+            b.created_at
+                .cmp(&a.created_at)
+                .then_with(|| a.uuid.cmp(&b.uuid))
+        });
+
+        let limit = pagination.limit.clamp(1, MAX_PAGE_SIZE);
+        let total = summaries.len();
+        let games = summaries
+            .into_iter()
+            .skip(pagination.offset)
+            .take(limit)
+            .collect();
+
+        GamePage {
+            games,
+            total,
+            offset: pagination.offset,
+            limit,
+        }
+    }
+}
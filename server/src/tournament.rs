@@ -0,0 +1,746 @@
+use crate::ratings;
+use crate::settings::{GameSettings, TimeControl, Variant};
+use crate::Error;
+use schemars::JsonSchema;
+use scrabble_core::{FinalScore, Player, MIN_PLAYERS};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+use uuid::Uuid;
+
+/// A bye, or an opponent's forfeit, counts as a win by that many points
+pub const BYE_SPREAD: i32 = 50;
+
+const MAX_SWISS_ROUNDS: usize = 20;
+
+#[derive(Copy, Clone, PartialEq, Eq, Serialize, Deserialize, Debug, Default, JsonSchema, TS)]
+#[serde(rename_all = "kebab-case")]
+pub enum PairingSystem {
+    /// Everyone plays everyone once
+    #[default]
+    RoundRobin,
+    /// Players with similar standings meet, avoiding rematches
+    Swiss,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum TournamentStatus {
+    Registration,
+    InProgress,
+    Finished,
+}
+
+/// Options chosen by whoever runs a tournament
+#[derive(Clone, Serialize, Deserialize, Debug, JsonSchema, TS)]
+pub struct TournamentSettings {
+    pub name: String,
+    #[serde(default)]
+    pub pairing: PairingSystem,
+    /// Number of Swiss rounds, round robins take as many as needed
+    #[serde(default = "default_rounds")]
+    pub rounds: usize,
+    /// Adds a final round pairing first against second, third against fourth and so on
+    #[serde(default)]
+    pub king_of_the_hill: bool,
+    #[serde(default)]
+    pub variant: Variant,
+    #[serde(default)]
+    pub time_control: Option<TimeControl>,
+    #[serde(default)]
+    pub rated: bool,
+    /// Every game of a round draws from the same bag order, seeded from this and
+    /// the round number, drawn from the OS for each game when `None`
+    ///
+    /// Only ever set by the server, like the seed of a game, and not kept in
+    /// snapshots: rounds paired after a restart draw from the OS.
+    #[serde(skip)]
+    pub seed: Option<u64>,
+}
+
+fn default_rounds() -> usize {
+    5
+}
+
+impl TournamentSettings {
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.name.trim().is_empty() {
+            return Err(Error::InvalidSettings);
+        }
+
+        if self.pairing == PairingSystem::Swiss && !(1..=MAX_SWISS_ROUNDS).contains(&self.rounds) {
+            return Err(Error::InvalidSettings);
+        }
+
+        self.time_control.map_or(Ok(()), |x| x.validate())
+    }
+}
+
+/// How a player did in a single pairing
+#[derive(Copy, Clone, PartialEq, Serialize, Deserialize, Debug)]
+pub struct PairingResult {
+    /// 1 for a win, 0.5 for a draw and 0 for a loss
+    pub points: f64,
+    pub spread: i32,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum Pairing {
+    Game {
+        game_uuid: Uuid,
+        players: [Uuid; 2],
+        /// In the order of `players`, once the game is over
+        results: Option<[PairingResult; 2]>,
+    },
+    Bye {
+        player_uuid: Uuid,
+    },
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct Round {
+    pub number: usize,
+    pub king_of_the_hill: bool,
+    pub pairings: Vec<Pairing>,
+}
+
+#[derive(Clone, Serialize, Debug)]
+pub struct Standing {
+    pub rank: usize,
+    pub player: Player,
+    pub points: f64,
+    /// Tiebreak between players with the same points
+    pub spread: i32,
+    pub played: usize,
+}
+
+/// A series of two player games, paired round after round
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct Tournament {
+    uuid: Uuid,
+    settings: TournamentSettings,
+    /// Whoever created the tournament, only they can start it
+    director: Uuid,
+    status: TournamentStatus,
+    players: Vec<Player>,
+    rounds: Vec<Round>,
+}
+
+/// Everything about a tournament, as sent to the clients
+#[derive(Serialize, Debug)]
+pub struct TournamentDetails<'a> {
+    #[serde(flatten)]
+    tournament: &'a Tournament,
+    standings: Vec<Standing>,
+}
+
+/// A tournament as shown in a list
+#[derive(Serialize, Debug)]
+pub struct TournamentSummary {
+    pub uuid: Uuid,
+    pub name: String,
+    pub pairing: PairingSystem,
+    pub status: TournamentStatus,
+    pub players: usize,
+    pub round: usize,
+    pub total_rounds: usize,
+}
+
+impl Tournament {
+    pub fn new(uuid: Uuid, settings: TournamentSettings, director: Uuid) -> Self {
+        Tournament {
+            uuid,
+            settings,
+            director,
+            status: TournamentStatus::Registration,
+            players: Vec::new(),
+            rounds: Vec::new(),
+        }
+    }
+
+    pub fn get_status(&self) -> TournamentStatus {
+        self.status
+    }
+
+    pub fn get_player(&self, player_uuid: &Uuid) -> Result<&Player, Error> {
+        self.players
+            .iter()
+            .find(|x| x.get_id() == player_uuid)
+            .ok_or(Error::PlayerNotRegistered)
+    }
+
+    pub fn get_details(&self) -> TournamentDetails<'_> {
+        TournamentDetails {
+            tournament: self,
+            standings: self.get_standings(),
+        }
+    }
+
+    pub fn get_summary(&self) -> TournamentSummary {
+        TournamentSummary {
+            uuid: self.uuid,
+            name: self.settings.name.clone(),
+            pairing: self.settings.pairing,
+            status: self.status,
+            players: self.players.len(),
+            round: self.rounds.len(),
+            total_rounds: self.get_total_rounds(),
+        }
+    }
+
+    pub fn join(&mut self, player: Player) -> Result<(), Error> {
+        if self.status != TournamentStatus::Registration {
+            return Err(Error::TournamentStarted);
+        } else if self.players.iter().any(|x| x.get_id() == player.get_id()) {
+            return Err(Error::DuplicatePlayerId);
+        } else if self.settings.rated && !player.is_registered() {
+            return Err(Error::AccountRequired);
+        }
+
+        self.players.push(player);
+
+        Ok(())
+    }
+
+    /// Closes registration, the first round is then paired with `get_next_pairings`
+    pub fn start(&mut self, player_uuid: &Uuid) -> Result<(), Error> {
+        if *player_uuid != self.director {
+            return Err(Error::NotDirector);
+        } else if self.status != TournamentStatus::Registration {
+            return Err(Error::TournamentStarted);
+        } else if self.players.len() < MIN_PLAYERS {
+            return Err(Error::NotEnoughPlayers);
+        }
+
+        self.status = TournamentStatus::InProgress;
+
+        Ok(())
+    }
+
+    /// Round robins need one round per opponent, and one more for the byes of
+    /// an odd number of players
+    pub fn get_total_rounds(&self) -> usize {
+        let rounds = match self.settings.pairing {
+            PairingSystem::RoundRobin => {
+                let players = self.players.len();
+                players.saturating_sub(1) + players % 2
+            }
+            PairingSystem::Swiss => self.settings.rounds,
+        };
+
+        rounds + usize::from(self.settings.king_of_the_hill)
+    }
+
+    pub fn has_more_rounds(&self) -> bool {
+        self.rounds.len() < self.get_total_rounds()
+    }
+
+    fn is_king_of_the_hill(&self, number: usize) -> bool {
+        self.settings.king_of_the_hill && number == self.get_total_rounds()
+    }
+
+    /// Settings of the games of a round, only two players sit at each table
+    pub fn game_settings(&self, number: usize) -> GameSettings {
+        GameSettings {
+            name: format!("{} - Round {number}", self.settings.name),
+            max_players: 2,
+            variant: self.settings.variant,
+            time_control: self.settings.time_control,
+            rated: self.settings.rated,
+            seed: self.settings.seed.map(|x| x.wrapping_add(number as u64)),
+            ..GameSettings::default()
+        }
+    }
+
+    /// Who plays whom in the next round, a missing opponent is a bye
+    pub fn get_next_pairings(&self) -> Vec<(Uuid, Option<Uuid>)> {
+        let number = self.rounds.len() + 1;
+
+        if self.is_king_of_the_hill(number) {
+            self.pair_by_standings(false)
+        } else {
+            match self.settings.pairing {
+                PairingSystem::RoundRobin => self.pair_round_robin(number - 1),
+                PairingSystem::Swiss => self.pair_by_standings(true),
+            }
+        }
+    }
+
+    /// Circle method: the first player stays put while the others rotate
+    fn pair_round_robin(&self, round: usize) -> Vec<(Uuid, Option<Uuid>)> {
+        let mut slots: Vec<Option<Uuid>> = self.players.iter().map(|x| Some(*x.get_id())).collect();
+        if slots.len() % 2 == 1 {
+            slots.push(None);
+        }
+
+        let mut rest = slots.split_off(1);
+        let turns = round % rest.len();
+        rest.rotate_right(turns);
+        slots.extend(rest);
+
+        (0..slots.len() / 2)
+            .filter_map(|i| match (slots[i], slots[slots.len() - 1 - i]) {
+                (Some(a), b) => Some((a, b)),
+                (None, Some(b)) => Some((b, None)),
+                (None, None) => None,
+            })
+            .collect()
+    }
+
+    /// Goes down the standings, the lowest player without a bye yet sits out
+    /// when the count is odd
+    fn pair_by_standings(&self, avoid_rematches: bool) -> Vec<(Uuid, Option<Uuid>)> {
+        let mut unpaired: Vec<Uuid> = self
+            .get_standings()
+            .into_iter()
+            .map(|x| *x.player.get_id())
+            .collect();
+        let mut pairings = Vec::new();
+
+        if unpaired.len() % 2 == 1 {
+            let index = unpaired
+                .iter()
+                .rposition(|x| !self.had_bye(x))
+                .unwrap_or(unpaired.len() - 1);
+
+            pairings.push((unpaired.remove(index), None));
+        }
+
+        while !unpaired.is_empty() {
+            let player = unpaired.remove(0);
+            let index = match avoid_rematches {
+                true => unpaired
+                    .iter()
+                    .position(|x| !self.have_played(&player, x))
+                    .unwrap_or(0),
+                false => 0,
+            };
+
+            pairings.push((player, Some(unpaired.remove(index))));
+        }
+
+        pairings
+    }
+
+    fn had_bye(&self, player_uuid: &Uuid) -> bool {
+        self.rounds
+            .iter()
+            .flat_map(|x| &x.pairings)
+            .any(|x| matches!(x, Pairing::Bye { player_uuid: x } if x == player_uuid))
+    }
+
+    fn have_played(&self, a: &Uuid, b: &Uuid) -> bool {
+        self.rounds.iter().flat_map(|x| &x.pairings).any(|x| {
+            matches!(x, Pairing::Game { players, .. } if players.contains(a) && players.contains(b))
+        })
+    }
+
+    pub fn add_round(&mut self, pairings: Vec<Pairing>) {
+        let number = self.rounds.len() + 1;
+
+        self.rounds.push(Round {
+            number,
+            king_of_the_hill: self.is_king_of_the_hill(number),
+            pairings,
+        });
+    }
+
+    /// Games of the current round that are still being played
+    pub fn get_pending_games(&self) -> Vec<Uuid> {
+        self.rounds
+            .last()
+            .map(|x| &x.pairings)
+            .into_iter()
+            .flatten()
+            .filter_map(|x| match x {
+                Pairing::Game {
+                    game_uuid,
+                    results: None,
+                    ..
+                } => Some(*game_uuid),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Players seated in a game of the current round
+    pub fn get_game_players(&self, game_uuid: &Uuid) -> Option<[Uuid; 2]> {
+        self.rounds.last()?.pairings.iter().find_map(|x| match x {
+            Pairing::Game {
+                game_uuid: x,
+                players,
+                ..
+            } if x == game_uuid => Some(*players),
+            _ => None,
+        })
+    }
+
+    /// Records how a game of the current round ended, `None` for a game that
+    /// was abandoned and counts as a loss for both players
+    ///
+    /// A player missing from the final scores left the game, which is a forfeit.
+    pub fn record_result(&mut self, game_uuid: &Uuid, final_scores: Option<&[FinalScore]>) {
+        let Some(Pairing::Game {
+            players, results, ..
+        }) = self.rounds.last_mut().and_then(|x| {
+            x.pairings
+                .iter_mut()
+                .find(|x| matches!(x, Pairing::Game { game_uuid: x, .. } if x == game_uuid))
+        })
+        else {
+            return;
+        };
+
+        let Some(final_scores) = final_scores else {
+            *results = Some(
+                [PairingResult {
+                    points: 0.0,
+                    spread: 0,
+                }; 2],
+            );
+            return;
+        };
+
+        let [a, b] = players.map(|player_uuid| {
+            final_scores
+                .iter()
+                .find(|x| x.player_uuid == player_uuid)
+                .copied()
+                .unwrap_or(FinalScore {
+                    player_uuid,
+                    score: 0,
+                    forfeited: true,
+                })
+        });
+        let spread = match (a.forfeited, b.forfeited) {
+            (false, true) => BYE_SPREAD,
+            (true, false) => -BYE_SPREAD,
+            _ => a.score - b.score,
+        };
+
+        *results = Some([
+            PairingResult {
+                points: ratings::result(&a, &b),
+                spread,
+            },
+            PairingResult {
+                points: ratings::result(&b, &a),
+                spread: -spread,
+            },
+        ]);
+    }
+
+    pub fn finish(&mut self) {
+        self.status = TournamentStatus::Finished;
+    }
+
+    /// Most points first, spread breaking ties, then whoever registered first
+    pub fn get_standings(&self) -> Vec<Standing> {
+        let mut standings: Vec<Standing> = self
+            .players
+            .iter()
+            .map(|player| Standing {
+                rank: 0,
+                player: player.clone(),
+                points: 0.0,
+                spread: 0,
+                played: 0,
+            })
+            .collect();
+
+        let mut add = |player_uuid: &Uuid, result: PairingResult| {
+            if let Some(standing) = standings
+                .iter_mut()
+                .find(|x| x.player.get_id() == player_uuid)
+            {
+                standing.points += result.points;
+                standing.spread += result.spread;
+                standing.played += 1;
+            }
+        };
+
+        for pairing in self.rounds.iter().flat_map(|x| &x.pairings) {
+            match pairing {
+                Pairing::Game {
+                    players,
+                    results: Some(results),
+                    ..
+                } => {
+                    add(&players[0], results[0]);
+                    add(&players[1], results[1]);
+                }
+                Pairing::Bye { player_uuid } => add(
+                    player_uuid,
+                    PairingResult {
+                        points: 1.0,
+                        spread: BYE_SPREAD,
+                    },
+                ),
+                _ => (),
+            }
+        }
+
+        standings.sort_by(|a, b| {
+            b.points
+                .total_cmp(&a.points)
+                .then_with(|| b.spread.cmp(&a.spread))
+        });
+
+        for (i, standing) in standings.iter_mut().enumerate() {
+            standing.rank = i + 1;
+        }
+
+        standings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    fn tournament(pairing: PairingSystem, players: usize) -> Tournament {
+        let director = Uuid::new_v4();
+        let settings = TournamentSettings {
+            name: String::from("Club night"),
+            pairing,
+            rounds: 3,
+            king_of_the_hill: false,
+            variant: Variant::Classic,
+            time_control: None,
+            rated: false,
+            seed: None,
+        };
+
+        let mut tournament = Tournament::new(Uuid::new_v4(), settings, director);
+        for i in 0..players {
+            tournament
+                .join(Player::new(&Uuid::new_v4(), &format!("Player{i}")))
+                .unwrap();
+        }
+        tournament.start(&director).unwrap();
+
+        tournament
+    }
+
+    /// Plays the next round, the first player of each pairing winning by `spread`
+    fn play_round(tournament: &mut Tournament, spread: i32) {
+        let pairings = tournament
+            .get_next_pairings()
+            .into_iter()
+            .map(|(a, b)| match b {
+                Some(b) => Pairing::Game {
+                    game_uuid: Uuid::new_v4(),
+                    players: [a, b],
+                    results: None,
+                },
+                None => Pairing::Bye { player_uuid: a },
+            })
+            .collect::<Vec<_>>();
+        let scores: Vec<_> = pairings
+            .iter()
+            .filter_map(|x| match x {
+                Pairing::Game {
+                    game_uuid, players, ..
+                } => Some((*game_uuid, *players)),
+                _ => None,
+            })
+            .collect();
+
+        tournament.add_round(pairings);
+
+        for (game_uuid, [a, b]) in scores {
+            let final_scores = [
+                FinalScore {
+                    player_uuid: a,
+                    score: 300 + spread,
+                    forfeited: false,
+                },
+                FinalScore {
+                    player_uuid: b,
+                    score: 300,
+                    forfeited: false,
+                },
+            ];
+            tournament.record_result(&game_uuid, Some(&final_scores));
+        }
+    }
+
+    #[test]
+    fn round_robin_plays_everyone_once() {
+        let mut tournament = tournament(PairingSystem::RoundRobin, 5);
+        assert_eq!(tournament.get_total_rounds(), 5);
+
+        let mut games = HashSet::new();
+        let mut byes = HashSet::new();
+        while tournament.has_more_rounds() {
+            for (a, b) in tournament.get_next_pairings() {
+                match b {
+                    Some(b) => assert!(games.insert(if a < b { (a, b) } else { (b, a) })),
+                    None => assert!(byes.insert(a)),
+                }
+            }
+            play_round(&mut tournament, 10);
+        }
+
+        assert_eq!(games.len(), 5 * 4 / 2);
+        assert_eq!(byes.len(), 5);
+    }
+
+    #[test]
+    fn seeded_rounds_share_a_bag_order() {
+        let mut tournament = tournament(PairingSystem::Swiss, 4);
+        assert_eq!(tournament.game_settings(1).seed, None);
+
+        tournament.settings.seed = Some(1000);
+        assert_eq!(tournament.game_settings(1).seed, Some(1001));
+        assert_eq!(tournament.game_settings(2).seed, Some(1002));
+    }
+
+    #[test]
+    fn swiss_avoids_rematches_and_repeated_byes() {
+        let mut tournament = tournament(PairingSystem::Swiss, 5);
+
+        play_round(&mut tournament, 10);
+        play_round(&mut tournament, 10);
+
+        let pairings = tournament.get_next_pairings();
+        for (a, b) in pairings {
+            match b {
+                Some(b) => assert!(!tournament.have_played(&a, &b)),
+                None => assert!(!tournament.had_bye(&a)),
+            }
+        }
+    }
+
+    #[test]
+    fn standings_break_ties_on_spread() {
+        let mut tournament = tournament(PairingSystem::Swiss, 4);
+        play_round(&mut tournament, 100);
+        let Pairing::Game {
+            game_uuid, players, ..
+        } = tournament.rounds[0].pairings[1].clone()
+        else {
+            unreachable!()
+        };
+
+        // The second game ended with a narrower win than recorded
+        tournament.record_result(
+            &game_uuid,
+            Some(&[
+                FinalScore {
+                    player_uuid: players[0],
+                    score: 310,
+                    forfeited: false,
+                },
+                FinalScore {
+                    player_uuid: players[1],
+                    score: 300,
+                    forfeited: false,
+                },
+            ]),
+        );
+
+        let standings = tournament.get_standings();
+        assert_eq!(standings[0].points, 1.0);
+        assert_eq!(standings[1].points, 1.0);
+        assert!(standings[0].spread > standings[1].spread);
+        assert_eq!(standings[3].points, 0.0);
+    }
+
+    #[test]
+    fn forfeits_and_abandoned_games_are_losses() {
+        let mut tournament = tournament(PairingSystem::RoundRobin, 4);
+        let pairings: Vec<_> = tournament
+            .get_next_pairings()
+            .into_iter()
+            .map(|(a, b)| Pairing::Game {
+                game_uuid: Uuid::new_v4(),
+                players: [a, b.unwrap()],
+                results: None,
+            })
+            .collect();
+        tournament.add_round(pairings);
+
+        let games = tournament.get_pending_games();
+        let Pairing::Game { players, .. } = tournament.rounds[0].pairings[0].clone() else {
+            unreachable!()
+        };
+
+        // The second player left the game
+        tournament.record_result(
+            &games[0],
+            Some(&[FinalScore {
+                player_uuid: players[0],
+                score: 10,
+                forfeited: false,
+            }]),
+        );
+        tournament.record_result(&games[1], None);
+        assert!(tournament.get_pending_games().is_empty());
+
+        let standings = tournament.get_standings();
+        assert_eq!(standings[0].player.get_id(), &players[0]);
+        assert_eq!(standings[0].spread, BYE_SPREAD);
+        assert_eq!(
+            standings.iter().map(|x| x.points).sum::<f64>(),
+            1.0,
+            "Nobody wins an abandoned game"
+        );
+    }
+
+    #[test]
+    fn king_of_the_hill_pairs_the_leaders() {
+        let mut tournament = tournament(PairingSystem::Swiss, 4);
+        tournament.settings.king_of_the_hill = true;
+        tournament.settings.rounds = 1;
+
+        play_round(&mut tournament, 10);
+        assert!(tournament.has_more_rounds());
+
+        let standings = tournament.get_standings();
+        let pairings = tournament.get_next_pairings();
+        assert_eq!(
+            pairings[0],
+            (
+                *standings[0].player.get_id(),
+                Some(*standings[1].player.get_id())
+            )
+        );
+
+        play_round(&mut tournament, 10);
+        assert!(tournament.rounds[1].king_of_the_hill);
+        assert!(!tournament.has_more_rounds());
+    }
+
+    #[test]
+    fn only_the_director_starts() {
+        let settings = TournamentSettings {
+            name: String::from("Club night"),
+            pairing: PairingSystem::RoundRobin,
+            rounds: 1,
+            king_of_the_hill: false,
+            variant: Variant::Classic,
+            time_control: None,
+            rated: false,
+            seed: None,
+        };
+        let director = Uuid::new_v4();
+        let mut tournament = Tournament::new(Uuid::new_v4(), settings, director);
+
+        tournament
+            .join(Player::new(&Uuid::new_v4(), "Player"))
+            .unwrap();
+        assert_eq!(tournament.start(&Uuid::new_v4()), Err(Error::NotDirector));
+        assert_eq!(tournament.start(&director), Err(Error::NotEnoughPlayers));
+
+        tournament
+            .join(Player::new(&Uuid::new_v4(), "Other"))
+            .unwrap();
+        tournament.start(&director).unwrap();
+        assert_eq!(
+            tournament.join(Player::new(&Uuid::new_v4(), "Late")),
+            Err(Error::TournamentStarted)
+        );
+    }
+}
@@ -0,0 +1,73 @@
+//! WebAssembly bindings of `scrabble-core`, so browsers check and score moves
+//! with the rules the server plays by
+//!
+//! Built with
+//!
+//! ```text
+//! cargo build -p scrabble-wasm --target wasm32-unknown-unknown --release
+//! wasm-bindgen --target web --out-dir ../client/assets/js/wasm \
+//!     target/wasm32-unknown-unknown/release/scrabble_wasm.wasm
+//! ```
+//!
+//! Boards are the `board` rows of `/api/v1/games/{uuid}`, tiles are the `tiles` of
+//! a `play` request, bag records are what `/api/v1/games/{uuid}/bag` answers.
+
+use scrabble_core::{BagRecord, Board, Play, ScoredMove, Tile, BOARD_SIZE};
+use serde::de::DeserializeOwned;
+use wasm_bindgen::prelude::*;
+
+type Rows = [[Option<Tile>; BOARD_SIZE]; BOARD_SIZE];
+
+/// Throws the reason the tiles can't go there, if they can't
+#[wasm_bindgen(js_name = validatePlacement)]
+pub fn validate_placement(board: JsValue, tiles: JsValue) -> Result<(), JsError> {
+    evaluate(board, tiles).map(|_| ())
+}
+
+/// Words the tiles would form and what the move would score, as
+/// `{words: [{word, score}], bingo, score}`
+#[wasm_bindgen(js_name = scoreMove)]
+pub fn score_move(board: JsValue, tiles: JsValue) -> Result<JsValue, JsError> {
+    let scored = evaluate(board, tiles)?;
+    Ok(serde_wasm_bindgen::to_value(&scored)?)
+}
+
+/// Deals a game that is over again from its revealed seed, and gives back every
+/// draw as `{racks, turns}`, throws if the server's draws don't match
+#[wasm_bindgen(js_name = verifyBag)]
+pub fn verify_bag(record: JsValue) -> Result<JsValue, JsError> {
+    let draws = from_js::<BagRecord>(record)?.verify()?;
+    Ok(serde_wasm_bindgen::to_value(&draws)?)
+}
+
+fn evaluate(board: JsValue, tiles: JsValue) -> Result<ScoredMove, JsError> {
+    let board = Board::from_rows(from_js::<Rows>(board)?);
+    let plays = from_js::<Vec<Play>>(tiles)?;
+
+    Ok(board.evaluate(&plays)?)
+}
+
+fn from_js<T: DeserializeOwned>(value: JsValue) -> Result<T, JsError> {
+    Ok(serde_wasm_bindgen::from_value(value)?)
+}
+
+/// Word list the words of a move are checked against
+///
+/// Made from the text of `/api/v1/lexicons/{variant}`, so the browser knows the
+/// same words as the server.
+#[wasm_bindgen]
+pub struct Lexicon(scrabble_core::Lexicon);
+
+#[wasm_bindgen]
+impl Lexicon {
+    #[wasm_bindgen(constructor)]
+    pub fn new(text: &str) -> Lexicon {
+        Lexicon(scrabble_core::Lexicon::parse(text))
+    }
+
+    /// Words that are not in the list, in the order they were given
+    #[wasm_bindgen(js_name = checkWords)]
+    pub fn check_words(&self, words: Vec<String>) -> Vec<String> {
+        words.into_iter().filter(|x| !self.0.contains(x)).collect()
+    }
+}
@@ -0,0 +1,89 @@
+//! The bindings as JavaScript calls them, run in Node with
+//!
+//! ```text
+//! CARGO_TARGET_WASM32_UNKNOWN_UNKNOWN_RUNNER=wasm-bindgen-test-runner \
+//!     cargo test -p scrabble-wasm --target wasm32-unknown-unknown
+//! ```
+
+#![cfg(target_arch = "wasm32")]
+
+use scrabble_core::{Play, Tile, Word, BOARD_SIZE};
+use scrabble_wasm::{score_move, validate_placement, Lexicon};
+use serde::Deserialize;
+use wasm_bindgen::JsValue;
+use wasm_bindgen_test::wasm_bindgen_test;
+
+#[derive(Deserialize)]
+struct Scored {
+    words: Vec<Word>,
+    bingo: bool,
+    score: u32,
+}
+
+fn board(tiles: &[Play]) -> JsValue {
+    let mut rows = [[None::<Tile>; BOARD_SIZE]; BOARD_SIZE];
+    for play in tiles {
+        rows[play.y][play.x] = Some(play.tile);
+    }
+
+    serde_wasm_bindgen::to_value(&rows).unwrap()
+}
+
+fn word(word: &str, x: usize, y: usize) -> Vec<Play> {
+    word.chars()
+        .enumerate()
+        .map(|(i, letter)| Play {
+            tile: Tile(letter, 1),
+            x: x + i,
+            y,
+        })
+        .collect()
+}
+
+fn tiles(plays: &[Play]) -> JsValue {
+    serde_wasm_bindgen::to_value(plays).unwrap()
+}
+
+#[wasm_bindgen_test]
+fn placements_follow_the_rules() {
+    assert!(validate_placement(board(&[]), tiles(&word("CAT", 6, 7))).is_ok());
+    assert!(validate_placement(board(&[]), tiles(&word("CAT", 0, 0))).is_err());
+
+    // Connected to CAT, then apart from it
+    let cat = word("CAT", 6, 7);
+    assert!(validate_placement(board(&cat), tiles(&word("S", 9, 7))).is_ok());
+    assert!(validate_placement(board(&cat), tiles(&word("DOG", 0, 0))).is_err());
+}
+
+#[wasm_bindgen_test]
+fn malformed_input_is_refused() {
+    assert!(validate_placement(JsValue::from_str("board"), tiles(&word("CAT", 6, 7))).is_err());
+    assert!(validate_placement(board(&[]), JsValue::NULL).is_err());
+}
+
+#[wasm_bindgen_test]
+fn moves_are_scored_like_the_server_does() {
+    let scored = score_move(board(&[]), tiles(&word("CAT", 6, 7))).unwrap();
+    let scored: Scored = serde_wasm_bindgen::from_value(scored).unwrap();
+
+    // The center doubles the word
+    assert_eq!(
+        scored.words,
+        vec![Word {
+            word: String::from("CAT"),
+            score: 6,
+        }]
+    );
+    assert!(!scored.bingo);
+    assert_eq!(scored.score, 6);
+
+    assert!(score_move(board(&[]), tiles(&word("CAT", 0, 0))).is_err());
+}
+
+#[wasm_bindgen_test]
+fn unknown_words_are_given_back() {
+    let lexicon = Lexicon::new("cat\nat\n");
+    let words = vec![String::from("CAT"), String::from("XAT"), String::from("At")];
+
+    assert_eq!(lexicon.check_words(words), vec![String::from("XAT")]);
+}